@@ -0,0 +1,445 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tonic::{
+    body::BoxBody,
+    metadata::MetadataValue,
+    server::NamedService,
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::Body,
+    Request, Status,
+};
+use tower::Service;
+use tracing::warn;
+
+use crate::telemetry::TracedChannel;
+
+/// scope of the admin methods, e.g. `RawQuery` of user-stat
+pub const ADMIN_SCOPE: &str = "admin";
+
+/// the key of `secrets/dev-auth.key` the configs of the repo read, known to anyone
+pub const DEV_KEY: &str = "camp-dev-only-secret";
+
+/// a token is minted again once less than this is left of its lifetime
+const REFRESH_BEFORE_SECS: u64 = 30;
+
+#[derive(Debug, Error)]
+pub enum AuthErr {
+    #[error("Invalid auth config: {0}")]
+    Invalid(String),
+
+    #[error("Jwt error: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyAlgorithm {
+    /// the key is a secret shared by the services
+    #[default]
+    Hs256,
+    /// the keys are PEM Ed25519 keys
+    EdDsa,
+}
+
+impl From<KeyAlgorithm> for Algorithm {
+    fn from(algorithm: KeyAlgorithm) -> Self {
+        match algorithm {
+            KeyAlgorithm::Hs256 => Algorithm::HS256,
+            KeyAlgorithm::EdDsa => Algorithm::EdDSA,
+        }
+    }
+}
+
+/// Bearer tokens a grpc server requires, see [`Authenticated`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub algorithm: KeyAlgorithm,
+    /// key the tokens are verified with, the shared secret or the public key, `{file: path}` to
+    /// read it from a file
+    pub pk: String,
+    /// tokens of other issuers are rejected if set
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// scope required by a method, e.g. `/user_stat.UserStat/RawQuery`, or by every method of a
+    /// service, e.g. `notification.NotificationAdmin`. Any valid token may call other methods
+    #[serde(default)]
+    pub scopes: HashMap<String, String>,
+}
+
+/// Bearer tokens a grpc client attaches, minted with `sk` or given as `token`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClientAuthConfig {
+    #[serde(default)]
+    pub algorithm: KeyAlgorithm,
+    /// key the tokens are signed with, the shared secret or the private key, `{file: path}` to
+    /// read it from a file
+    #[serde(default)]
+    pub sk: Option<String>,
+    /// a token minted elsewhere, attached as is
+    #[serde(default)]
+    pub token: Option<String>,
+    /// the caller, e.g. `crm`
+    pub subject: String,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_ttl_secs() -> u64 {
+    300
+}
+
+/// Claims of the tokens, `scope` is space separated like in OAuth.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default)]
+    pub scope: String,
+}
+
+impl Claims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split_whitespace().any(|s| s == scope)
+    }
+}
+
+fn warn_dev_key(key: &str, role: &str) {
+    if key == DEV_KEY {
+        warn!(
+            "tokens are {} with the development key, anyone can mint them: mount the key of \
+             the deployment over secrets/dev-auth.key",
+            role
+        );
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Verifies the bearer token of a request and the scope its method requires.
+#[derive(Clone)]
+pub struct Verifier {
+    key: DecodingKey,
+    validation: Validation,
+    scopes: HashMap<String, String>,
+}
+
+impl Verifier {
+    pub fn try_new(config: &AuthConfig) -> Result<Self, AuthErr> {
+        warn_dev_key(&config.pk, "verified");
+        let key = match config.algorithm {
+            KeyAlgorithm::Hs256 => DecodingKey::from_secret(config.pk.as_bytes()),
+            KeyAlgorithm::EdDsa => DecodingKey::from_ed_pem(config.pk.as_bytes())?,
+        };
+        let mut validation = Validation::new(config.algorithm.into());
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        Ok(Self {
+            key,
+            validation,
+            scopes: config.scopes.clone(),
+        })
+    }
+
+    /// `method` requires tokens with `scope`, see [`AuthConfig::scopes`]
+    pub fn require_scope(&mut self, method: &str, scope: &str) {
+        self.scopes.insert(method.to_string(), scope.to_string());
+    }
+
+    fn required_scope(&self, path: &str) -> Option<&str> {
+        let service = path.trim_start_matches('/').split('/').next()?;
+        self.scopes
+            .get(path)
+            .or_else(|| self.scopes.get(service))
+            .map(String::as_str)
+    }
+
+    /// the claims of the token of a request to `path`, e.g. `/user_stat.UserStat/Query`
+    #[allow(clippy::result_large_err)]
+    pub fn verify(&self, path: &str, headers: &http::HeaderMap) -> Result<Claims, Status> {
+        let token = headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("a bearer token is required"))?;
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.key, &self.validation)
+            .map_err(|e| Status::unauthenticated(format!("invalid token: {}", e)))?
+            .claims;
+        match self.required_scope(path) {
+            Some(scope) if !claims.has_scope(scope) => Err(Status::permission_denied(format!(
+                "{} requires the {} scope",
+                path, scope
+            ))),
+            _ => Ok(claims),
+        }
+    }
+}
+
+/// A grpc service only answering requests with a valid bearer token, the [`Claims`] are added to
+/// the extensions of the request. Every request is answered if there is no verifier.
+///
+/// A tonic interceptor does not see the method called, so the scopes are checked around the
+/// service instead.
+#[derive(Clone)]
+pub struct Authenticated<S> {
+    inner: S,
+    verifier: Option<Arc<Verifier>>,
+}
+
+impl<S> Authenticated<S> {
+    pub fn new(inner: S, verifier: Option<Arc<Verifier>>) -> Self {
+        Self { inner, verifier }
+    }
+}
+
+impl<S: NamedService> NamedService for Authenticated<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for Authenticated<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<Body>) -> Self::Future {
+        if let Some(verifier) = &self.verifier {
+            match verifier.verify(request.uri().path(), request.headers()) {
+                Ok(claims) => {
+                    request.extensions_mut().insert(claims);
+                }
+                Err(status) => return Box::pin(async move { Ok(status.to_http()) }),
+            }
+        }
+        // the clone may not be ready, call the one which is
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(inner.call(request))
+    }
+}
+
+struct Minter {
+    key: EncodingKey,
+    header: Header,
+    config: ClientAuthConfig,
+    cached: Mutex<Option<(String, u64)>>,
+}
+
+impl Minter {
+    fn token(&self) -> Result<String, AuthErr> {
+        let now = now_secs();
+        let mut cached = self.cached.lock().expect("cached token poisoned");
+        if let Some((token, exp)) = cached.as_ref() {
+            if *exp > now + REFRESH_BEFORE_SECS {
+                return Ok(token.clone());
+            }
+        }
+        let claims = Claims {
+            sub: self.config.subject.clone(),
+            exp: now + self.config.ttl_secs,
+            iss: self.config.issuer.clone(),
+            scope: self.config.scopes.join(" "),
+        };
+        let token = jsonwebtoken::encode(&self.header, &claims, &self.key)?;
+        *cached = Some((token.clone(), claims.exp));
+        Ok(token)
+    }
+}
+
+#[derive(Clone)]
+enum Credentials {
+    Token(Arc<str>),
+    Minted(Arc<Minter>),
+}
+
+/// a channel whose requests carry the token of a [`ClientAuth`] and the trace context
+pub type AuthChannel = InterceptedService<TracedChannel, ClientAuth>;
+
+/// Attaches a bearer token to every request of a client, nothing if not configured.
+#[derive(Clone, Default)]
+pub struct ClientAuth {
+    credentials: Option<Credentials>,
+}
+
+impl ClientAuth {
+    pub fn try_new(config: &ClientAuthConfig) -> Result<Self, AuthErr> {
+        let credentials = match (&config.sk, &config.token) {
+            (Some(sk), None) => {
+                warn_dev_key(sk, "signed");
+                let key = match config.algorithm {
+                    KeyAlgorithm::Hs256 => EncodingKey::from_secret(sk.as_bytes()),
+                    KeyAlgorithm::EdDsa => EncodingKey::from_ed_pem(sk.as_bytes())?,
+                };
+                Credentials::Minted(Arc::new(Minter {
+                    key,
+                    header: Header::new(config.algorithm.into()),
+                    config: config.clone(),
+                    cached: Mutex::new(None),
+                }))
+            }
+            (None, Some(token)) => Credentials::Token(token.as_str().into()),
+            _ => {
+                return Err(AuthErr::Invalid(
+                    "exactly one of sk and token must be set".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            credentials: Some(credentials),
+        })
+    }
+
+    /// attaches nothing if `config` is unset
+    pub fn from_config(config: Option<&ClientAuthConfig>) -> Result<Self, AuthErr> {
+        config.map_or_else(|| Ok(Self::default()), Self::try_new)
+    }
+}
+
+impl Interceptor for ClientAuth {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let token = match &self.credentials {
+            None => return Ok(request),
+            Some(Credentials::Token(token)) => token.to_string(),
+            Some(Credentials::Minted(minter)) => minter
+                .token()
+                .map_err(|e| Status::internal(format!("failed to mint a token: {}", e)))?,
+        };
+        let value = MetadataValue::try_from(format!("Bearer {}", token))
+            .map_err(|_| Status::internal("the token is not a valid header value"))?;
+        request.metadata_mut().insert("authorization", value);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server(scopes: &[(&str, &str)]) -> Verifier {
+        let mut verifier = Verifier::try_new(&AuthConfig {
+            algorithm: KeyAlgorithm::Hs256,
+            pk: "secret".to_string(),
+            issuer: Some("camp".to_string()),
+            scopes: HashMap::new(),
+        })
+        .unwrap();
+        for (method, scope) in scopes {
+            verifier.require_scope(method, scope);
+        }
+        verifier
+    }
+
+    fn headers(sk: &str, scopes: &[&str]) -> http::HeaderMap {
+        let mut client = ClientAuth::try_new(&ClientAuthConfig {
+            algorithm: KeyAlgorithm::Hs256,
+            sk: Some(sk.to_string()),
+            token: None,
+            subject: "crm".to_string(),
+            issuer: Some("camp".to_string()),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            ttl_secs: 60,
+        })
+        .unwrap();
+        client
+            .call(Request::new(()))
+            .unwrap()
+            .metadata()
+            .clone()
+            .into_headers()
+    }
+
+    #[test]
+    fn test_dev_key_is_the_one_of_the_repo() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../secrets/dev-auth.key");
+        let key = std::fs::read_to_string(path).unwrap();
+        assert_eq!(key.trim_end(), DEV_KEY);
+    }
+
+    #[test]
+    fn test_verify_tokens_and_scopes() {
+        let verifier = server(&[
+            ("/user_stat.UserStat/RawQuery", ADMIN_SCOPE),
+            ("notification.NotificationAdmin", ADMIN_SCOPE),
+        ]);
+        let query = "/user_stat.UserStat/Query";
+        let raw_query = "/user_stat.UserStat/RawQuery";
+        let replay = "/notification.NotificationAdmin/ReplayDeadLetters";
+
+        let claims = verifier.verify(query, &headers("secret", &[])).unwrap();
+        assert_eq!(claims.sub, "crm");
+
+        let status = verifier.verify(query, &http::HeaderMap::new()).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let status = verifier.verify(query, &headers("other", &[])).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let user = headers("secret", &["campaign"]);
+        for method in [raw_query, replay] {
+            let status = verifier.verify(method, &user).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        }
+        let admin = headers("secret", &["campaign", ADMIN_SCOPE]);
+        for method in [raw_query, replay] {
+            assert!(verifier.verify(method, &admin).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_minted_token_is_reused() {
+        let config = ClientAuthConfig {
+            algorithm: KeyAlgorithm::Hs256,
+            sk: Some("secret".to_string()),
+            token: None,
+            subject: "crm".to_string(),
+            issuer: None,
+            scopes: vec![],
+            ttl_secs: 60,
+        };
+        let mut client = ClientAuth::try_new(&config).unwrap();
+        let first = client.call(Request::new(())).unwrap();
+        let second = client.call(Request::new(())).unwrap();
+        assert_eq!(
+            first.metadata().get("authorization"),
+            second.metadata().get("authorization")
+        );
+
+        let both = ClientAuthConfig {
+            token: Some("token".to_string()),
+            ..config
+        };
+        assert!(ClientAuth::try_new(&both).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    future::Future,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tonic::{transport::Channel, Code, Request, Status};
+use tracing::{info, warn};
+
+use crate::{
+    discovery::{balance, Endpoints},
+    error::is_retryable,
+    metrics::{counter_vec, gauge_vec, IntCounterVec, IntGaugeVec},
+    tls::{endpoint, ClientTlsConfig, TlsErr},
+};
+
+/// Settings of the calls to another service, every setting has a default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// connections are made on the first call and made again once lost, a connection not made
+    /// in time fails the call
+    pub connect_timeout_ms: u64,
+    /// a call not answered in time fails with deadline exceeded, the server is told the deadline
+    pub deadline_ms: u64,
+    /// idempotent calls failing with a retryable status are sent again up to this many times
+    pub retries: u32,
+    /// wait before the first retry, doubled for every further one
+    pub backoff_ms: u64,
+    /// consecutive failures opening the circuit, calls then fail at once
+    pub breaker_failures: u32,
+    /// the circuit lets a single call through after this long, it is closed if that one succeeds
+    pub breaker_open_ms: u64,
+    /// the endpoints of a balanced service not serving are no longer called, see [`Endpoints`]
+    pub health_check_ms: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 1000,
+            deadline_ms: 10_000,
+            retries: 2,
+            backoff_ms: 100,
+            breaker_failures: 5,
+            breaker_open_ms: 10_000,
+            health_check_ms: 5000,
+        }
+    }
+}
+
+/// a channel to `endpoints` connecting on its first call, over TLS if `tls` is given
+pub fn channel(
+    endpoints: &Endpoints,
+    tls: Option<&ClientTlsConfig>,
+    config: &ClientConfig,
+) -> Result<Channel, TlsErr> {
+    match endpoints.single() {
+        Some(url) => Ok(endpoint(url, tls)?
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .connect_lazy()),
+        None => balance(endpoints, tls, config),
+    }
+}
+
+static CIRCUIT_OPEN: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    gauge_vec(
+        "grpc_client_circuit_open",
+        "1 while the circuit of a service called is open, by service",
+        &["service"],
+    )
+});
+
+static CIRCUIT_REJECTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "grpc_client_circuit_rejected_total",
+        "calls failed at once as the circuit of the service was open, by service",
+        &["service"],
+    )
+});
+
+static RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "grpc_client_retries_total",
+        "calls sent again after a retryable failure, by service",
+        &["service"],
+    )
+});
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// the open period is over, a single call probes the service until `probe_until`, its
+    /// result closes or opens the circuit, another call probes it if it is dropped unanswered
+    HalfOpen {
+        probe_until: Instant,
+    },
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: Mutex<State>,
+    closed: watch::Sender<bool>,
+}
+
+/// The calls to one service, with a deadline, retries and a circuit breaker.
+///
+/// Failures with a retryable status, see [`is_retryable`], count towards opening the circuit;
+/// the service answering anything else, even an error, closes it.
+#[derive(Debug, Clone)]
+pub struct Circuit {
+    name: String,
+    config: ClientConfig,
+    inner: Arc<Inner>,
+}
+
+impl Circuit {
+    /// the calls to the service `name`, as it is logged and labelled in the metrics
+    pub fn new(name: &str, config: &ClientConfig) -> Self {
+        CIRCUIT_OPEN.with_label_values(&[name]).set(0);
+        Self {
+            name: name.to_string(),
+            config: config.clone(),
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::Closed { failures: 0 }),
+                closed: watch::channel(true).0,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_open(&self) -> bool {
+        !*self.inner.closed.borrow()
+    }
+
+    /// true while the circuit is closed
+    pub fn watch(&self) -> watch::Receiver<bool> {
+        self.inner.closed.subscribe()
+    }
+
+    /// `message` with the deadline of the calls
+    pub fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        request.set_timeout(self.deadline());
+        request
+    }
+
+    /// Make a call, `call` is called again for every retry, only `idempotent` calls are retried.
+    ///
+    /// Every attempt not answered within the deadline fails with deadline exceeded. Fails with
+    /// unavailable without calling while the circuit is open, or probed by another call.
+    pub async fn call<T, F, Fut>(&self, idempotent: bool, mut call: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let mut attempt = 0;
+        loop {
+            if !self.allow() {
+                CIRCUIT_REJECTED.with_label_values(&[&self.name]).inc();
+                return Err(Status::unavailable(format!(
+                    "the circuit of {} is open",
+                    self.name
+                )));
+            }
+            let started = Instant::now();
+            let status = match tokio::time::timeout(self.deadline(), call()).await {
+                Ok(Ok(response)) => {
+                    self.record(true);
+                    return Ok(response);
+                }
+                // the server cancels a call once the deadline it was told is over, which may
+                // be answered before the timer fires
+                Ok(Err(status))
+                    if status.code() == Code::Cancelled && started.elapsed() >= self.deadline() =>
+                {
+                    self.expired()
+                }
+                Ok(Err(status)) => status,
+                Err(_) => self.expired(),
+            };
+            let retryable = is_retryable(&status);
+            self.record(!retryable);
+            if !idempotent || !retryable || attempt >= self.config.retries {
+                return Err(status);
+            }
+            let backoff = Duration::from_millis(
+                self.config
+                    .backoff_ms
+                    .saturating_mul(2u64.saturating_pow(attempt)),
+            );
+            warn!(
+                "call to {} failed, retry {} in {:?}: {}",
+                self.name,
+                attempt + 1,
+                backoff,
+                status
+            );
+            RETRIES.with_label_values(&[&self.name]).inc();
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    fn expired(&self) -> Status {
+        Status::deadline_exceeded(format!(
+            "{} did not answer within {}ms",
+            self.name, self.config.deadline_ms
+        ))
+    }
+
+    fn deadline(&self) -> Duration {
+        Duration::from_millis(self.config.deadline_ms)
+    }
+
+    fn allow(&self) -> bool {
+        let mut state = self.inner.state.lock().expect("circuit state poisoned");
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::HalfOpen { probe_until: until } if now < until => false,
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen {
+                    probe_until: now + self.deadline(),
+                };
+                true
+            }
+        }
+    }
+
+    /// the service answered if `ok`, it failed with a retryable status otherwise
+    fn record(&self, ok: bool) {
+        let mut state = self.inner.state.lock().expect("circuit state poisoned");
+        *state = match *state {
+            _ if ok => State::Closed { failures: 0 },
+            State::Closed { failures } if failures + 1 < self.config.breaker_failures => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            State::Open { until } => State::Open { until },
+            _ => State::Open {
+                until: Instant::now() + Duration::from_millis(self.config.breaker_open_ms),
+            },
+        };
+        let closed = !matches!(*state, State::Open { .. });
+        drop(state);
+
+        let changed = self.inner.closed.send_if_modified(|current| {
+            let changed = *current != closed;
+            *current = closed;
+            changed
+        });
+        if changed {
+            CIRCUIT_OPEN
+                .with_label_values(&[&self.name])
+                .set(i64::from(!closed));
+            if closed {
+                info!("circuit of {} is closed", self.name);
+            } else {
+                warn!("circuit of {} is open", self.name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::metrics::gather;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn config() -> ClientConfig {
+        ClientConfig {
+            retries: 2,
+            backoff_ms: 1,
+            breaker_failures: 2,
+            breaker_open_ms: 50,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_idempotent_calls() {
+        let circuit = Circuit::new("test_retry", &config());
+        let calls = AtomicU32::new(0);
+        let answer = circuit
+            .call(true, || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(Status::unavailable("starting")),
+                    _ => Ok("answer"),
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(answer, "answer");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // neither a call which is not idempotent nor a client error is sent again
+        calls.store(0, Ordering::SeqCst);
+        let unavailable = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Status::unavailable("down"))
+        };
+        circuit.call(false, unavailable).await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let invalid = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Status::invalid_argument("bad"))
+        };
+        circuit.call(true, invalid).await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(!circuit.is_open());
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_and_closes() {
+        let circuit = Circuit::new("test_breaker", &config());
+        let closed = circuit.watch();
+        let calls = AtomicU32::new(0);
+        let unavailable = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Status::unavailable("down"))
+        };
+        // the first failure is retried, the second one opens the circuit
+        circuit.call(true, unavailable).await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(circuit.is_open());
+        assert!(!*closed.borrow());
+        assert!(gather().contains(r#"grpc_client_circuit_open{service="test_breaker"} 1"#));
+
+        let status = circuit.call(true, unavailable).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        circuit.call(true, || async { Ok(()) }).await.unwrap();
+        assert!(!circuit.is_open());
+        assert!(*closed.borrow());
+        assert!(gather().contains(r#"grpc_client_circuit_open{service="test_breaker"} 0"#));
+    }
+
+    #[tokio::test]
+    async fn test_calls_fail_past_their_deadline() {
+        let circuit = Circuit::new(
+            "test_deadline",
+            &ClientConfig {
+                deadline_ms: 50,
+                retries: 1,
+                ..config()
+            },
+        );
+        let calls = AtomicU32::new(0);
+        let started = Instant::now();
+        let status = circuit
+            .call(true, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                std::future::pending::<Result<(), Status>>().await
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        // the attempt past its deadline is retried
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        // the server cancelling the call once the deadline is over, before the timer fires
+        let circuit = Circuit::new(
+            "test_cancelled",
+            &ClientConfig {
+                deadline_ms: 50,
+                ..config()
+            },
+        );
+        let cancelled = |wait: u64| async move {
+            std::thread::sleep(Duration::from_millis(wait));
+            Err::<(), _>(Status::cancelled("cancelled"))
+        };
+        let status = circuit.call(false, || cancelled(0)).await.unwrap_err();
+        assert_eq!(status.code(), Code::Cancelled);
+        let status = circuit.call(false, || cancelled(60)).await.unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_circuit_lets_a_single_call_through() {
+        let circuit = Circuit::new(
+            "test_probe",
+            &ClientConfig {
+                deadline_ms: 200,
+                retries: 0,
+                breaker_failures: 1,
+                ..config()
+            },
+        );
+        circuit
+            .call(true, || async { Err::<(), _>(Status::unavailable("down")) })
+            .await
+            .unwrap_err();
+        assert!(circuit.is_open());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let probe = circuit.call(true, || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        });
+        let other = async {
+            // called once the probe is on its way
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            circuit.call(true, || async { Ok(()) }).await
+        };
+        let (probe, other) = tokio::join!(probe, other);
+        probe.unwrap();
+        assert_eq!(other.unwrap_err().code(), Code::Unavailable);
+        assert!(!circuit.is_open());
+        circuit.call(true, || async { Ok(()) }).await.unwrap();
+    }
+}
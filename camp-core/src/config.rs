@@ -1,28 +1,786 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde_yaml::{Mapping, Value};
 use thiserror::Error;
-use tracing::info;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// environment variable naming the environment, e.g. `staging` loads `notification.staging.yml`
+pub const ENV_VAR: &str = "CAMP_ENV";
+/// key of the mapping standing for the content of a file, e.g. `password: {file: /run/pw}`
+const FILE_KEY: &str = "file";
+/// how often the files of a watched config are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Error)]
 pub enum ConfigErr {
     #[error("Open config error: {0}")]
     NotExists(String),
 
-    #[error("Parse config error: {0}")]
-    Parse(#[from] serde_yaml::Error),
+    #[error("Read config error: {source_name}: {message}")]
+    Read {
+        source_name: String,
+        message: String,
+    },
+
+    #[error("Invalid config key `{key}` from {source_name}: {message}")]
+    Invalid {
+        key: String,
+        source_name: String,
+        message: String,
+    },
+}
+
+/// Configuration merged from, in increasing precedence:
+///
+/// 1. built-in defaults
+/// 2. the first base file found, e.g. `notification.yml`
+/// 3. the file of the environment next to it, e.g. `notification.staging.yml`
+/// 4. environment variables, e.g. `CAMP_NOTIFICATION__DB__HOST`
+/// 5. command line flags, e.g. `--set db.host=localhost`
+///
+/// Mappings are merged key by key, any other value replaces the one of a lower layer.
+/// Values of environment variables and flags are read as yaml, quote them to keep a number a
+/// string. A mapping holding only a `file` key, e.g. `password: {file: /run/secrets/db}`, is
+/// replaced by the content of the file it names, a relative path is next to the config file
+/// setting it.
+///
+/// Environment variables and flags are only read after [`ConfigLoader::from_process`], or given
+/// with [`ConfigLoader::vars`] and [`ConfigLoader::args`].
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    paths: Vec<PathBuf>,
+    env_prefix: String,
+    environment: Option<String>,
+    defaults: Option<String>,
+    vars: Vec<(String, String)>,
+    args: Vec<String>,
+    interval: Duration,
+}
+
+/// A config which can be loaded again while the service runs, see [`ConfigLoader::watch`].
+pub trait Reload: Clone + Send + Sync + 'static {
+    /// reject a config which loaded but can not be used, e.g. an empty time window
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// put back the settings of `current` which only change on restart, e.g. ports and database
+    /// urls, returning the keys of the ones which were changed
+    fn keep_fixed(&mut self, current: &Self) -> Vec<&'static str>;
 }
 
-pub fn config_load<T>(paths: Vec<String>) -> Result<T, ConfigErr>
+/// keep the `current` value of a setting which only changes on restart, recording `key` in
+/// `changed` if the reloaded value differs
+pub fn keep_fixed<T: PartialEq + Clone>(
+    key: &'static str,
+    value: &mut T,
+    current: &T,
+    changed: &mut Vec<&'static str>,
+) {
+    if value != current {
+        changed.push(key);
+        *value = current.clone();
+    }
+}
+
+/// a receiver of one section of a watched config, updated whenever the config is
+pub fn section<C, S>(config: &watch::Receiver<C>, section: fn(&C) -> S) -> watch::Receiver<S>
 where
-    T: serde::de::DeserializeOwned,
+    C: Send + Sync + 'static,
+    S: Send + Sync + 'static,
 {
-    for path in &paths {
-        match std::fs::File::open(path) {
-            Ok(file) => {
-                let config: T = serde_yaml::from_reader(file)?;
-                return Ok(config);
+    let mut config = config.clone();
+    let (tx, rx) = watch::channel(section(&config.borrow_and_update()));
+    tokio::spawn(async move {
+        while config.changed().await.is_ok() {
+            let value = section(&config.borrow_and_update());
+            if tx.send(value).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// the merged value and where each of its keys came from
+#[derive(Debug, Default)]
+struct Layers {
+    value: Value,
+    sources: BTreeMap<String, String>,
+    /// the config files merged, a relative secret file is next to the one naming it
+    files: Vec<PathBuf>,
+}
+
+impl ConfigLoader {
+    /// config of the service `name`, e.g. `user_stat` reads `CAMP_USER_STAT__*` variables
+    pub fn new(name: &str) -> Self {
+        Self {
+            paths: vec![],
+            env_prefix: format!("CAMP_{}__", name.to_uppercase().replace('-', "_")),
+            environment: None,
+            defaults: None,
+            vars: vec![],
+            args: vec![],
+            interval: WATCH_INTERVAL,
+        }
+    }
+
+    /// read the environment variables and command line arguments of the process, and the
+    /// environment named by `CAMP_ENV`
+    pub fn from_process(self) -> Self {
+        let environment = std::env::var(ENV_VAR).ok().filter(|e| !e.is_empty());
+        self.environment(environment.as_deref())
+            .vars(std::env::vars())
+            .args(std::env::args().skip(1))
+    }
+
+    /// base files to look for, the first one found is read
+    pub fn paths<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// yaml of the values used unless a file, variable or flag sets them
+    pub fn defaults(mut self, yaml: &str) -> Self {
+        self.defaults = Some(yaml.to_string());
+        self
+    }
+
+    pub fn environment(mut self, environment: Option<&str>) -> Self {
+        self.environment = environment.map(str::to_string);
+        self
+    }
+
+    /// environment variables to read instead of the ones of the process
+    pub fn vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.vars = vars.into_iter().collect();
+        self
+    }
+
+    /// command line arguments to read instead of the ones of the process
+    pub fn args(mut self, args: impl IntoIterator<Item = String>) -> Self {
+        self.args = args.into_iter().collect();
+        self
+    }
+
+    /// how often [`ConfigLoader::watch`] checks the files for changes
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn load<T>(&self) -> Result<T, ConfigErr>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut layers = self.layers()?;
+        layers.read_secrets()?;
+        layers.deserialize()
+    }
+
+    /// every layer merged, before the secrets are read
+    fn layers(&self) -> Result<Layers, ConfigErr> {
+        let mut layers = Layers::default();
+        if let Some(defaults) = &self.defaults {
+            layers.merge(parse("defaults", defaults)?, "defaults");
+        }
+        let base = self.base_file()?;
+        layers.merge(read_file(&base)?, &base.display().to_string());
+        layers.files.push(base.clone());
+        if let Some(environment) = &self.environment {
+            let path = environment_file(&base, environment);
+            if path.exists() {
+                layers.merge(read_file(&path)?, &path.display().to_string());
+                layers.files.push(path);
+            } else {
+                info!(
+                    "Config of environment {} not exists: {:?}",
+                    environment, path
+                );
+            }
+        }
+        for (name, key, value) in self.env_overrides() {
+            let source = format!("environment variable {}", name);
+            layers.set(&key, parse(&source, value)?, &source);
+        }
+        for (key, value) in self.flag_overrides()? {
+            let source = format!("flag --set {}", key);
+            layers.set(&key, parse(&source, &value)?, &source);
+        }
+        Ok(layers)
+    }
+
+    /// the config loaded again, with the settings of `current` which only change on restart.
+    /// `None` if it is invalid, the error is logged
+    pub fn reload<T>(&self, current: &T) -> Option<T>
+    where
+        T: serde::de::DeserializeOwned + Reload,
+    {
+        let mut config: T = match self.load() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Config not reloaded, keeping the current one: {}", e);
+                return None;
+            }
+        };
+        if let Err(e) = config.validate() {
+            warn!("Config not reloaded, keeping the current one: {}", e);
+            return None;
+        }
+        for key in config.keep_fixed(current) {
+            warn!(
+                "Config {} only changes on restart, keeping the current value",
+                key
+            );
+        }
+        Some(config)
+    }
+
+    /// publish `initial`, then the config loaded again whenever its files change, the secrets
+    /// read from files included. Variables and flags are the ones given when watching started.
+    /// Stops once every receiver is dropped
+    pub fn watch<T>(self, initial: T) -> watch::Receiver<T>
+    where
+        T: serde::de::DeserializeOwned + Reload,
+    {
+        let (tx, rx) = watch::channel(initial);
+        let mut modified = self.modified();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = tx.closed() => break,
+                }
+                let now = self.modified();
+                if now == modified {
+                    continue;
+                }
+                modified = now;
+                let current = tx.borrow().clone();
+                if let Some(config) = self.reload(&current) {
+                    info!("Config reloaded");
+                    tx.send_replace(config);
+                }
+            }
+        });
+        rx
+    }
+
+    /// when and how big the files read were last changed, to notice a change
+    fn modified(&self) -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+        self.files()
+            .into_iter()
+            .map(|path| {
+                let metadata = std::fs::metadata(&path)
+                    .ok()
+                    .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+                (path, metadata)
+            })
+            .collect()
+    }
+
+    /// the files a load reads, the base one, the one of the environment and the secrets
+    fn files(&self) -> Vec<PathBuf> {
+        let Ok(base) = self.base_file() else {
+            return vec![];
+        };
+        let mut files = vec![base.clone()];
+        if let Some(environment) = &self.environment {
+            files.push(environment_file(&base, environment));
+        }
+        if let Ok(layers) = self.layers() {
+            layers.secret_files(&layers.value, "", &mut files);
+        }
+        files
+    }
+
+    fn base_file(&self) -> Result<PathBuf, ConfigErr> {
+        match self.paths.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(path.clone()),
+            None => {
+                let path_string = format!("{:?} not exists", self.paths);
+                info!("Config not exists: {}", path_string);
+                Err(ConfigErr::NotExists(path_string))
+            }
+        }
+    }
+
+    /// `CAMP_NOTIFICATION__DB__HOST` sets `db.host`
+    fn env_overrides(&self) -> Vec<(&str, String, &str)> {
+        let mut overrides: Vec<_> = self
+            .vars
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(&self.env_prefix)?;
+                let key = key.to_lowercase().replace("__", ".");
+                Some((name.as_str(), key, value.as_str()))
+            })
+            .collect();
+        // parents before their children, so a child is not replaced by its parent
+        overrides.sort_by(|a, b| a.1.cmp(&b.1));
+        overrides
+    }
+
+    /// `--set db.host=localhost` or `--set=db.host=localhost`, other arguments are left alone
+    fn flag_overrides(&self) -> Result<Vec<(String, String)>, ConfigErr> {
+        let mut overrides = vec![];
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            let assignment = match arg.strip_prefix("--set") {
+                Some("") => args.next().cloned().unwrap_or_default(),
+                Some(rest) if rest.starts_with('=') => rest[1..].to_string(),
+                _ => continue,
+            };
+            let Some((key, value)) = assignment.split_once('=') else {
+                return Err(ConfigErr::Read {
+                    source_name: format!("flag --set {}", assignment),
+                    message: "expected key=value".to_string(),
+                });
+            };
+            overrides.push((key.trim().to_string(), value.to_string()));
+        }
+        Ok(overrides)
+    }
+}
+
+/// `config/notification.yml` becomes `config/notification.staging.yml`
+fn environment_file(base: &Path, environment: &str) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match base.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, environment, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, environment),
+    };
+    base.with_file_name(file_name)
+}
+
+fn read_file(path: &Path) -> Result<Value, ConfigErr> {
+    let source_name = path.display().to_string();
+    let content = std::fs::read_to_string(path).map_err(|e| ConfigErr::Read {
+        source_name: source_name.clone(),
+        message: e.to_string(),
+    })?;
+    parse(&source_name, &content)
+}
+
+fn parse(source_name: &str, yaml: &str) -> Result<Value, ConfigErr> {
+    serde_yaml::from_str(yaml).map_err(|e| ConfigErr::Read {
+        source_name: source_name.to_string(),
+        message: e.to_string(),
+    })
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+impl Layers {
+    fn merge(&mut self, layer: Value, source: &str) {
+        let mut value = std::mem::take(&mut self.value);
+        self.merge_into(&mut value, layer, "", source);
+        self.value = value;
+    }
+
+    fn merge_into(&mut self, base: &mut Value, layer: Value, path: &str, source: &str) {
+        match (base, layer) {
+            (Value::Mapping(base), Value::Mapping(layer)) => {
+                for (key, value) in layer {
+                    let path = join(path, &key_name(&key));
+                    match base.get_mut(&key) {
+                        Some(existing) => self.merge_into(existing, value, &path, source),
+                        None => {
+                            self.record(&path, &value, source);
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (base, layer) => {
+                self.record(path, &layer, source);
+                *base = layer;
+            }
+        }
+    }
+
+    /// remember the source of `value` and of every key below it
+    fn record(&mut self, path: &str, value: &Value, source: &str) {
+        self.sources.insert(path.to_string(), source.to_string());
+        if let Value::Mapping(mapping) = value {
+            for (key, value) in mapping {
+                self.record(&join(path, &key_name(key)), value, source);
+            }
+        }
+    }
+
+    /// set the dotted `key`, creating the mappings on the way
+    fn set(&mut self, key: &str, value: Value, source: &str) {
+        let mut layer = value;
+        for part in key.rsplit('.') {
+            let mut mapping = Mapping::new();
+            mapping.insert(Value::String(part.to_string()), layer);
+            layer = Value::Mapping(mapping);
+        }
+        self.merge(layer, source);
+    }
+
+    /// replace every `{file: path}` mapping by the content of the file
+    fn read_secrets(&mut self) -> Result<(), ConfigErr> {
+        let mut value = std::mem::take(&mut self.value);
+        let result = self.read_secrets_in(&mut value, "");
+        self.value = value;
+        result
+    }
+
+    fn read_secrets_in(&mut self, value: &mut Value, path: &str) -> Result<(), ConfigErr> {
+        let Value::Mapping(mapping) = value else {
+            return Ok(());
+        };
+        if mapping.len() == 1 && mapping.contains_key(FILE_KEY) {
+            let file_key = join(path, FILE_KEY);
+            let source_name = self.source(&file_key);
+            let Some(Value::String(file)) = mapping.get(FILE_KEY) else {
+                return Err(ConfigErr::Invalid {
+                    key: file_key,
+                    source_name,
+                    message: "expected the path of a file".to_string(),
+                });
+            };
+            let file = self.resolve(file, &source_name);
+            let secret = std::fs::read_to_string(&file).map_err(|e| ConfigErr::Invalid {
+                key: file_key.clone(),
+                source_name,
+                message: format!("{}: {}", file.display(), e),
+            })?;
+            self.sources
+                .insert(path.to_string(), format!("file {}", file.display()));
+            *value = Value::String(secret.trim_end_matches(['\r', '\n']).to_string());
+            return Ok(());
+        }
+        for (key, value) in mapping.iter_mut() {
+            let path = join(path, &key_name(key));
+            self.read_secrets_in(value, &path)?;
+        }
+        Ok(())
+    }
+
+    /// `file` relative to the directory of the config file it is set in, if it is set in one
+    fn resolve(&self, file: &str, source_name: &str) -> PathBuf {
+        let path = Path::new(file);
+        let config = self
+            .files
+            .iter()
+            .find(|config| config.display().to_string() == source_name);
+        match (
+            path.is_relative(),
+            config.and_then(|config| config.parent()),
+        ) {
+            (true, Some(dir)) => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// the files of the `{file: path}` mappings in `value` at `path`
+    fn secret_files(&self, value: &Value, path: &str, files: &mut Vec<PathBuf>) {
+        let Value::Mapping(mapping) = value else {
+            return;
+        };
+        match mapping.get(FILE_KEY) {
+            Some(Value::String(file)) if mapping.len() == 1 => {
+                let source_name = self.source(&join(path, FILE_KEY));
+                files.push(self.resolve(file, &source_name));
+            }
+            _ => {
+                for (key, value) in mapping {
+                    self.secret_files(value, &join(path, &key_name(key)), files);
+                }
+            }
+        }
+    }
+
+    /// the source of `key`, or of the closest parent set by a layer
+    fn source(&self, key: &str) -> String {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.sources.get(key) {
+                return source.clone();
+            }
+            match key.rsplit_once('.') {
+                Some((parent, _)) => key = parent,
+                None => return "no source".to_string(),
+            }
+        }
+    }
+
+    fn deserialize<T>(mut self) -> Result<T, ConfigErr>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = std::mem::take(&mut self.value);
+        serde_path_to_error::deserialize(value).map_err(|e| {
+            let path = e.path().to_string();
+            let path = if path == "." { String::new() } else { path };
+            let message = e.inner().to_string();
+            // a missing key has no source, name the key instead of its parent
+            let missing = message
+                .strip_prefix("missing field `")
+                .and_then(|rest| rest.split('`').next());
+            let (key, source_name) = match missing {
+                Some(field) => (join(&path, field), "no source".to_string()),
+                None => (path.clone(), self.source(&path)),
+            };
+            ConfigErr::Invalid {
+                key,
+                source_name,
+                message,
             }
-            Err(_) => continue,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    struct AppConfig {
+        db: Db,
+        grpc: Grpc,
+    }
+
+    impl Reload for AppConfig {
+        fn validate(&self) -> Result<(), String> {
+            if self.db.password.is_empty() {
+                return Err("db.password is empty".to_string());
+            }
+            Ok(())
+        }
+
+        fn keep_fixed(&mut self, current: &Self) -> Vec<&'static str> {
+            let mut changed = vec![];
+            keep_fixed(
+                "grpc.port",
+                &mut self.grpc.port,
+                &current.grpc.port,
+                &mut changed,
+            );
+            changed
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    struct Db {
+        host: String,
+        port: u16,
+        password: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    struct Grpc {
+        port: u16,
+    }
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("camp-config-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn loader(dir: &Path) -> ConfigLoader {
+        ConfigLoader::new("test").paths([dir.join("missing.yml"), dir.join("test.yml")])
+    }
+
+    #[test]
+    fn test_layers() {
+        let dir = dir("layers");
+        std::fs::write(dir.join("test.yml"), "db:\n  host: base\n  port: 5432\n").unwrap();
+        std::fs::write(dir.join("test.staging.yml"), "db:\n  host: staging\n").unwrap();
+        std::fs::write(dir.join("password"), "secret\n").unwrap();
+        let config: AppConfig = loader(&dir)
+            .defaults("grpc:\n  port: 1\ndb:\n  password: default\n")
+            .environment(Some("staging"))
+            .vars([
+                ("CAMP_TEST__GRPC__PORT".to_string(), "2".to_string()),
+                (
+                    "CAMP_TEST__DB__PASSWORD".to_string(),
+                    format!("{{file: {}}}", dir.join("password").display()),
+                ),
+                ("OTHER__DB__HOST".to_string(), "other".to_string()),
+            ])
+            .args([
+                "--nocapture".to_string(),
+                "--set".to_string(),
+                "grpc.port=3".to_string(),
+            ])
+            .load()
+            .unwrap();
+        assert_eq!(
+            config,
+            AppConfig {
+                db: Db {
+                    host: "staging".to_string(),
+                    port: 5432,
+                    password: "secret".to_string(),
+                },
+                grpc: Grpc { port: 3 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors_name_key_and_source() {
+        let dir = dir("errors");
+        let base = dir.join("test.yml");
+        std::fs::write(&base, "db:\n  host: base\n  port: 5432\n  password: pw\n").unwrap();
+        let err = loader(&dir).load::<AppConfig>().unwrap_err();
+        assert!(
+            matches!(&err, ConfigErr::Invalid { key, .. } if key == "grpc"),
+            "{}",
+            err
+        );
+
+        let err = loader(&dir)
+            .vars([("CAMP_TEST__DB__PORT".to_string(), "high".to_string())])
+            .defaults("grpc:\n  port: 1\n")
+            .load::<AppConfig>()
+            .unwrap_err();
+        let ConfigErr::Invalid {
+            key, source_name, ..
+        } = err
+        else {
+            panic!("{}", err);
+        };
+        assert_eq!(key, "db.port");
+        assert_eq!(source_name, "environment variable CAMP_TEST__DB__PORT");
+
+        let err = loader(&dir)
+            .defaults("grpc:\n  port: 1\n")
+            .args(["--set=db.password.file=/no/such/file".to_string()])
+            .load::<AppConfig>()
+            .unwrap_err();
+        assert!(
+            matches!(&err, ConfigErr::Invalid { key, source_name, .. }
+                if key == "db.password.file" && source_name == "flag --set db.password.file"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_only_file_mappings_are_read() {
+        #[derive(Debug, Deserialize)]
+        struct Tls {
+            cert_file: String,
+            key: String,
         }
+        let dir = dir("files");
+        std::fs::write(dir.join("key.pem"), "pem\n").unwrap();
+        let yaml = format!(
+            "cert_file: /etc/tls/cert.pem\nkey: {{file: {}}}\n",
+            dir.join("key.pem").display()
+        );
+        std::fs::write(dir.join("test.yml"), yaml).unwrap();
+        let tls: Tls = loader(&dir).load().unwrap();
+        assert_eq!(tls.cert_file, "/etc/tls/cert.pem");
+        assert_eq!(tls.key, "pem");
+    }
+
+    #[test]
+    fn test_relative_secret_files_are_next_to_the_config() {
+        #[derive(Debug, Deserialize)]
+        struct Auth {
+            key: String,
+        }
+        let dir = dir("relative");
+        std::fs::create_dir_all(dir.join("secrets")).unwrap();
+        std::fs::write(dir.join("secrets").join("key"), "secret\n").unwrap();
+        std::fs::write(dir.join("test.yml"), "key: {file: secrets/key}\n").unwrap();
+        let loader = loader(&dir);
+        let auth: Auth = loader.load().unwrap();
+        assert_eq!(auth.key, "secret");
+        // it is watched where it is read
+        assert!(loader.files().contains(&dir.join("secrets").join("key")));
+    }
+
+    #[test]
+    fn test_reload_keeps_fixed_settings() {
+        let dir = dir("reload");
+        let base = dir.join("test.yml");
+        let yaml = |host: &str, password: &str, port: u16| {
+            format!(
+                "db:\n  host: {}\n  port: 5432\n  password: '{}'\ngrpc:\n  port: {}\n",
+                host, password, port
+            )
+        };
+        std::fs::write(&base, yaml("old", "pw", 1)).unwrap();
+        let current: AppConfig = loader(&dir).load().unwrap();
+
+        std::fs::write(&base, yaml("new", "pw", 2)).unwrap();
+        let config = loader(&dir).reload(&current).unwrap();
+        assert_eq!(config.db.host, "new");
+        assert_eq!(config.grpc.port, 1);
+
+        std::fs::write(&base, yaml("new", "", 1)).unwrap();
+        assert!(loader(&dir).reload(&current).is_none());
+        std::fs::write(&base, "db: [").unwrap();
+        assert!(loader(&dir).reload(&current).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_publishes_changes() {
+        let dir = dir("watch");
+        let base = dir.join("test.yml");
+        let yaml = "db:\n  host: old\n  port: 5432\n  password: pw\ngrpc:\n  port: 1\n";
+        std::fs::write(&base, yaml).unwrap();
+        let loader = loader(&dir).interval(Duration::from_millis(10));
+        let initial: AppConfig = loader.load().unwrap();
+        let mut config = loader.watch(initial);
+        let db = section(&config, |config: &AppConfig| config.db.clone());
+
+        std::fs::write(&base, yaml.replace("old", "newer")).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), config.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.borrow().db.host, "newer");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(db.borrow().host, "newer");
+
+        // a secret read from a file is watched too
+        let password = dir.join("password");
+        std::fs::write(&password, "first\n").unwrap();
+        let yaml = yaml.replace("pw", &format!("{{file: {}}}", password.display()));
+        std::fs::write(&base, yaml).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), config.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.borrow().db.password, "first");
+        std::fs::write(&password, "rotated\n").unwrap();
+        tokio::time::timeout(Duration::from_secs(5), config.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.borrow_and_update().db.password, "rotated");
     }
-    let path_string = format!("{:?} not exists", paths);
-    info!("Config not exists: {}", path_string);
-    Err(ConfigErr::NotExists(path_string))
 }
@@ -35,6 +35,15 @@ impl Dummy<UniqueEmail> for String {
     }
 }
 
+/// mobile numbers in E.164 format, unique enough for tests
+pub struct UniquePhone;
+impl Dummy<UniquePhone> for String {
+    fn dummy_with_rng<R: Rng + ?Sized>(_config: &UniquePhone, rng: &mut R) -> Self {
+        let prefix = ["138", "139", "150", "186"][rng.gen_range(0..4)];
+        format!("+86{}{:08}", prefix, rng.gen_range(0..100_000_000))
+    }
+}
+
 pub struct IntList(pub i32, pub i32, pub i32);
 
 impl Dummy<IntList> for Vec<i32> {
@@ -0,0 +1,283 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::{net::lookup_host, sync::mpsc::Sender, time::sleep};
+use tonic::{
+    transport::{Channel, Endpoint},
+    Code, Request,
+};
+use tonic_health::pb::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+use tower::discover::Change;
+use tracing::{info, warn};
+
+use crate::{
+    client::ClientConfig,
+    tls::{endpoint, ClientTlsConfig, TlsErr},
+};
+
+/// The endpoints of a service: a url, a list of urls, or a url whose host is resolved
+/// periodically, e.g. `dns: http://notification:50053`.
+///
+/// The calls are balanced across the endpoints of a list or a name, only the ones reported
+/// serving by their health service are called. While none is, a call waits for one and fails
+/// with deadline exceeded once its deadline expires, see [`crate::client::Circuit::call`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Endpoints {
+    Url(String),
+    Urls(Vec<String>),
+    Dns {
+        dns: String,
+        /// the host is resolved again this often, the addresses gone are no longer called
+        #[serde(default = "default_refresh_ms")]
+        refresh_ms: u64,
+    },
+}
+
+fn default_refresh_ms() -> u64 {
+    30_000
+}
+
+impl From<&str> for Endpoints {
+    fn from(url: &str) -> Self {
+        Endpoints::Url(url.to_string())
+    }
+}
+
+impl Endpoints {
+    /// the url if there is nothing to balance
+    pub(crate) fn single(&self) -> Option<&str> {
+        match self {
+            Endpoints::Url(url) => Some(url),
+            Endpoints::Urls(urls) if urls.len() == 1 => Some(&urls[0]),
+            _ => None,
+        }
+    }
+}
+
+/// where the urls of the endpoints come from
+enum Source {
+    Urls(Vec<String>),
+    Dns {
+        scheme: String,
+        host: String,
+        port: u16,
+        refresh: Duration,
+    },
+}
+
+impl Source {
+    async fn resolve(&self) -> std::io::Result<HashSet<String>> {
+        match self {
+            Source::Urls(urls) => Ok(urls.iter().cloned().collect()),
+            Source::Dns {
+                scheme, host, port, ..
+            } => Ok(lookup_host((host.as_str(), *port))
+                .await?
+                .map(|addr| format!("{}://{}", scheme, addr))
+                .collect()),
+        }
+    }
+}
+
+/// a channel balancing the calls across `endpoints`, kept up to date by a task running as long
+/// as the channel is used
+pub(crate) fn balance(
+    endpoints: &Endpoints,
+    tls: Option<&ClientTlsConfig>,
+    config: &ClientConfig,
+) -> Result<Channel, TlsErr> {
+    let mut tls = tls.cloned();
+    let source = match endpoints {
+        Endpoints::Url(url) => {
+            endpoint(url, tls.as_ref())?;
+            Source::Urls(vec![url.clone()])
+        }
+        Endpoints::Urls(urls) => {
+            for url in urls {
+                endpoint(url, tls.as_ref())?;
+            }
+            Source::Urls(urls.clone())
+        }
+        Endpoints::Dns { dns, refresh_ms } => {
+            let uri = endpoint(dns, tls.as_ref())?.uri().clone();
+            let scheme = uri.scheme_str().unwrap_or("http").to_string();
+            let host = uri.host().unwrap_or_default();
+            let host = host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string();
+            let port = uri
+                .port_u16()
+                .unwrap_or(if scheme == "https" { 443 } else { 80 });
+            // the addresses are called, the certificates are still issued for the name
+            if let Some(tls) = &mut tls {
+                tls.domain.get_or_insert_with(|| host.clone());
+            }
+            Source::Dns {
+                scheme,
+                host,
+                port,
+                refresh: Duration::from_millis(*refresh_ms),
+            }
+        }
+    };
+    let (channel, changes) = Channel::balance_channel(64);
+    let discovery = Discovery {
+        source,
+        tls,
+        config: config.clone(),
+        changes,
+        members: HashMap::new(),
+    };
+    tokio::spawn(discovery.run());
+    Ok(channel)
+}
+
+struct Member {
+    endpoint: Endpoint,
+    health: HealthClient<Channel>,
+    /// calls are sent to it
+    balanced: bool,
+}
+
+struct Discovery {
+    source: Source,
+    tls: Option<ClientTlsConfig>,
+    config: ClientConfig,
+    changes: Sender<Change<String, Endpoint>>,
+    members: HashMap<String, Member>,
+}
+
+impl Discovery {
+    async fn run(mut self) {
+        let mut resolved: Option<Instant> = None;
+        while !self.changes.is_closed() {
+            let due = match (&self.source, resolved) {
+                (_, None) => true,
+                (Source::Dns { refresh, .. }, Some(at)) => at.elapsed() >= *refresh,
+                (Source::Urls(_), Some(_)) => false,
+            };
+            if due {
+                self.resolve().await;
+                resolved = Some(Instant::now());
+            }
+            self.check().await;
+            sleep(Duration::from_millis(self.config.health_check_ms)).await;
+        }
+    }
+
+    async fn resolve(&mut self) {
+        let urls = match self.source.resolve().await {
+            Ok(urls) => urls,
+            Err(e) => {
+                // the endpoints resolved before are kept
+                warn!("failed to resolve the endpoints: {}", e);
+                return;
+            }
+        };
+        for url in &urls {
+            if self.members.contains_key(url) {
+                continue;
+            }
+            // every url was checked when the channel was made
+            let Ok(endpoint) = endpoint(url, self.tls.as_ref()) else {
+                continue;
+            };
+            let endpoint =
+                endpoint.connect_timeout(Duration::from_millis(self.config.connect_timeout_ms));
+            let health = HealthClient::new(endpoint.connect_lazy());
+            let member = Member {
+                endpoint,
+                health,
+                balanced: false,
+            };
+            self.members.insert(url.clone(), member);
+        }
+        let gone: Vec<String> = self
+            .members
+            .keys()
+            .filter(|url| !urls.contains(*url))
+            .cloned()
+            .collect();
+        for url in gone {
+            if self
+                .members
+                .remove(&url)
+                .is_some_and(|member| member.balanced)
+            {
+                info!("{} is not resolved anymore, no calls are sent to it", url);
+                let _ = self.changes.send(Change::Remove(url)).await;
+            }
+        }
+    }
+
+    /// balance the calls across the members reported serving
+    async fn check(&mut self) {
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        let checks = self.members.iter().map(|(url, member)| {
+            let mut health = member.health.clone();
+            let url = url.clone();
+            async move {
+                let mut request = Request::new(HealthCheckRequest {
+                    service: String::new(),
+                });
+                request.set_timeout(timeout);
+                let serving = match health.check(request).await {
+                    Ok(response) => response.into_inner().status() == ServingStatus::Serving,
+                    // a server without the health service is called anyway
+                    Err(status) => status.code() == Code::Unimplemented,
+                };
+                (url, serving)
+            }
+        });
+        for (url, serving) in join_all(checks).await {
+            let Some(member) = self.members.get_mut(&url) else {
+                continue;
+            };
+            match (member.balanced, serving) {
+                (false, true) => {
+                    info!("{} is serving, calls are sent to it", url);
+                    let change = Change::Insert(url, member.endpoint.clone());
+                    let _ = self.changes.send(change).await;
+                }
+                (true, false) => {
+                    warn!("{} is not serving, no calls are sent to it", url);
+                    let _ = self.changes.send(Change::Remove(url)).await;
+                }
+                _ => continue,
+            }
+            member.balanced = serving;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_config() {
+        let endpoints: Endpoints = serde_yaml::from_str(r#""http://localhost:50053""#).unwrap();
+        assert_eq!(endpoints.single(), Some("http://localhost:50053"));
+
+        let endpoints: Endpoints =
+            serde_yaml::from_str(r#"["http://a:50053", "http://b:50053"]"#).unwrap();
+        assert_eq!(endpoints.single(), None);
+
+        let endpoints: Endpoints = serde_yaml::from_str("dns: http://notification:50053").unwrap();
+        assert_eq!(
+            endpoints,
+            Endpoints::Dns {
+                dns: "http://notification:50053".to_string(),
+                refresh_ms: 30_000,
+            }
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use prost::Message;
+use prost_types::Any;
+use tonic::{Code, Status};
+
+const ERROR_INFO_TYPE: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const BAD_REQUEST_TYPE: &str = "type.googleapis.com/google.rpc.BadRequest";
+
+/// the `ErrorInfo` metadata telling the caller whether the request may be sent again
+pub const RETRYABLE_KEY: &str = "retryable";
+
+/// `google.rpc.Status`, the details of a [`Status`]
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// `google.rpc.ErrorInfo`, why a request failed
+#[derive(Clone, PartialEq, Message)]
+pub struct ErrorInfo {
+    /// UPPER_SNAKE_CASE, unique within the domain
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    /// the service the reason belongs to
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// `google.rpc.BadRequest`, the invalid fields of a request
+#[derive(Clone, PartialEq, Message)]
+pub struct BadRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub field_violations: Vec<FieldViolation>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FieldViolation {
+    #[prost(string, tag = "1")]
+    pub field: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+}
+
+impl FieldViolation {
+    pub fn new(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// How a domain error is answered to grpc callers.
+///
+/// The status carries an `ErrorInfo` with the reason and whether the request is retryable, and a
+/// `BadRequest` if there are field violations.
+pub trait ClassifyError: std::error::Error {
+    /// the service the error comes from, e.g. `user_stat`
+    fn domain(&self) -> &'static str;
+
+    /// UPPER_SNAKE_CASE reason of the error, e.g. `FIELD_NOT_FOUND`
+    fn reason(&self) -> &'static str;
+
+    fn code(&self) -> Code {
+        Code::Internal
+    }
+
+    /// the fields of the request making it invalid
+    fn field_violations(&self) -> Vec<FieldViolation> {
+        vec![]
+    }
+
+    /// the same request may succeed later, by default if the service was unavailable or busy
+    fn retryable(&self) -> bool {
+        matches!(self.code(), Code::Unavailable | Code::ResourceExhausted)
+    }
+
+    fn to_status(&self) -> Status {
+        let code = self.code();
+        let message = self.to_string();
+        let info = ErrorInfo {
+            reason: self.reason().to_string(),
+            domain: self.domain().to_string(),
+            metadata: [(RETRYABLE_KEY.to_string(), self.retryable().to_string())].into(),
+        };
+        let mut details = vec![Any {
+            type_url: ERROR_INFO_TYPE.to_string(),
+            value: info.encode_to_vec(),
+        }];
+        let field_violations = self.field_violations();
+        if !field_violations.is_empty() {
+            details.push(Any {
+                type_url: BAD_REQUEST_TYPE.to_string(),
+                value: BadRequest { field_violations }.encode_to_vec(),
+            });
+        }
+        let status = RpcStatus {
+            code: code as i32,
+            message: message.clone(),
+            details,
+        };
+        Status::with_details(code, message, Bytes::from(status.encode_to_vec()))
+    }
+}
+
+fn details(status: &Status) -> Vec<Any> {
+    RpcStatus::decode(status.details())
+        .map(|status| status.details)
+        .unwrap_or_default()
+}
+
+/// the `ErrorInfo` of a status built by [`ClassifyError::to_status`]
+pub fn error_info(status: &Status) -> Option<ErrorInfo> {
+    details(status)
+        .into_iter()
+        .find(|any| any.type_url == ERROR_INFO_TYPE)
+        .and_then(|any| ErrorInfo::decode(any.value.as_slice()).ok())
+}
+
+/// the `BadRequest` of a status built by [`ClassifyError::to_status`]
+pub fn bad_request(status: &Status) -> Option<BadRequest> {
+    details(status)
+        .into_iter()
+        .find(|any| any.type_url == BAD_REQUEST_TYPE)
+        .and_then(|any| BadRequest::decode(any.value.as_slice()).ok())
+}
+
+/// Whether a request answered with `status` may be sent again.
+///
+/// The `retryable` flag of its `ErrorInfo` is used if there is one, otherwise only the transient
+/// codes are retryable.
+pub fn is_retryable(status: &Status) -> bool {
+    match error_info(status).and_then(|info| info.metadata.get(RETRYABLE_KEY).cloned()) {
+        Some(retryable) => retryable == "true",
+        None => matches!(
+            status.code(),
+            Code::Unavailable | Code::ResourceExhausted | Code::DeadlineExceeded
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    enum TestErr {
+        #[error("name is empty")]
+        EmptyName,
+        #[error("database is down")]
+        Down,
+    }
+
+    impl ClassifyError for TestErr {
+        fn domain(&self) -> &'static str {
+            "test"
+        }
+
+        fn reason(&self) -> &'static str {
+            match self {
+                TestErr::EmptyName => "EMPTY_NAME",
+                TestErr::Down => "DATABASE_DOWN",
+            }
+        }
+
+        fn code(&self) -> Code {
+            match self {
+                TestErr::EmptyName => Code::InvalidArgument,
+                TestErr::Down => Code::Unavailable,
+            }
+        }
+
+        fn field_violations(&self) -> Vec<FieldViolation> {
+            match self {
+                TestErr::EmptyName => vec![FieldViolation::new("name", "must not be empty")],
+                TestErr::Down => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_has_details() {
+        let status = TestErr::EmptyName.to_status();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "name is empty");
+        let info = error_info(&status).unwrap();
+        assert_eq!(info.reason, "EMPTY_NAME");
+        assert_eq!(info.domain, "test");
+        assert!(!is_retryable(&status));
+        assert_eq!(
+            bad_request(&status).unwrap().field_violations,
+            vec![FieldViolation::new("name", "must not be empty")]
+        );
+
+        let status = TestErr::Down.to_status();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(is_retryable(&status));
+        assert!(bad_request(&status).is_none());
+    }
+
+    #[test]
+    fn test_plain_status_is_retryable_by_code() {
+        assert!(is_retryable(&Status::unavailable("connection refused")));
+        assert!(is_retryable(&Status::deadline_exceeded("too slow")));
+        assert!(!is_retryable(&Status::internal("bug")));
+    }
+}
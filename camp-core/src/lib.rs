@@ -1,4 +1,12 @@
+pub mod auth;
+pub mod client;
 pub mod config;
 pub mod core_fake;
 pub mod core_types;
+pub mod discovery;
+pub mod error;
+pub mod metrics;
 pub mod proto;
+pub mod server;
+pub mod telemetry;
+pub mod tls;
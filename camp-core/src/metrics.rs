@@ -0,0 +1,235 @@
+use std::{
+    convert::Infallible,
+    fmt::Debug,
+    future::Future,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::LazyLock,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use axum::{routing::get, Router};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, Opts, TextEncoder};
+pub use prometheus::{IntCounter, IntCounterVec, IntGaugeVec};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tonic::{body::BoxBody, server::NamedService, transport::Body, Code};
+use tower::Service;
+use tracing::info;
+
+#[derive(Debug, Error)]
+pub enum MetricsErr {
+    #[error("Http error: {0}")]
+    Http(#[from] hyper::Error),
+}
+
+/// The http endpoint serving the metrics of a service at `/metrics`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsConfig {
+    #[serde(default = "default_host")]
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+fn default_host() -> IpAddr {
+    IpAddr::V6(Ipv6Addr::LOCALHOST)
+}
+
+static RPC_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "grpc_server_requests_total",
+        "grpc requests answered, by method and status code",
+        &["service", "method", "code"],
+    )
+});
+
+static RPC_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    let opts = HistogramOpts::new(
+        "grpc_server_request_duration_seconds",
+        "time until a grpc request is answered, the first message of a stream",
+    );
+    let histogram = HistogramVec::new(opts, &["service", "method"]).expect("valid histogram");
+    prometheus::register(Box::new(histogram.clone())).expect("histogram registered once");
+    histogram
+});
+
+/// a counter registered with the metrics served at `/metrics`, e.g. in a `LazyLock` static
+pub fn counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter");
+    prometheus::register(Box::new(counter.clone())).expect("counter registered once");
+    counter
+}
+
+/// a counter without labels, see [`counter_vec`]
+pub fn counter(name: &str, help: &str) -> IntCounter {
+    let counter = IntCounter::new(name, help).expect("valid counter");
+    prometheus::register(Box::new(counter.clone())).expect("counter registered once");
+    counter
+}
+
+/// a gauge registered like [`counter_vec`]
+pub fn gauge_vec(name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels).expect("valid gauge");
+    prometheus::register(Box::new(gauge.clone())).expect("gauge registered once");
+    gauge
+}
+
+/// count a message sent by `label` and the status it was answered with, e.g. `success`, or
+/// `error` if sending it failed
+pub fn count_sent<S: Debug, E>(counter: &IntCounterVec, label: &str, status: Result<S, E>) {
+    let status = match status {
+        Ok(status) => format!("{:?}", status).to_lowercase(),
+        Err(_) => "error".to_string(),
+    };
+    counter.with_label_values(&[label, &status]).inc();
+}
+
+/// the metrics in the prometheus text format
+pub fn gather() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("metrics encoded");
+    String::from_utf8(buffer).expect("metrics are utf-8")
+}
+
+/// serve the metrics until the task is dropped
+pub async fn serve(config: &MetricsConfig) -> Result<(), MetricsErr> {
+    let addr = SocketAddr::new(config.host, config.port);
+    let router = Router::new().route("/metrics", get(|| async { gather() }));
+    info!("metrics are served on http://{}/metrics", addr);
+    axum::Server::try_bind(&addr)?
+        .serve(router.into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// A grpc service counting its requests and timing them, see [`gather`].
+///
+/// The code is the one of the response headers, an error of a stream after its first message is
+/// not counted.
+#[derive(Clone)]
+pub struct Metered<S> {
+    inner: S,
+}
+
+impl<S> Metered<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: NamedService> NamedService for Metered<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for Metered<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let path = request.uri().path().trim_start_matches('/');
+        let (service, method) = path.split_once('/').unwrap_or((path, ""));
+        let (service, method) = (service.to_string(), method.to_string());
+        let start = Instant::now();
+        // the clone may not be ready, call the one which is
+        let clone = self.inner.clone();
+        let response = std::mem::replace(&mut self.inner, clone).call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            let code = response
+                .headers()
+                .get("grpc-status")
+                .map_or(Code::Ok, |code| Code::from_bytes(code.as_bytes()));
+            RPC_SECONDS
+                .with_label_values(&[&service, &method])
+                .observe(start.elapsed().as_secs_f64());
+            RPC_REQUESTS
+                .with_label_values(&[&service, &method, &format!("{:?}", code)])
+                .inc();
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// answers every request with the status in its `x-status` header
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<http::Request<Body>> for Echo {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            let mut response = http::Response::new(tonic::body::empty_body());
+            if let Some(code) = request.headers().get("x-status") {
+                response.headers_mut().insert("grpc-status", code.clone());
+            }
+            std::future::ready(Ok(response))
+        }
+    }
+
+    fn request(code: Option<&'static str>) -> http::Request<Body> {
+        let mut request = http::Request::builder().uri("/test.Metered/Ping");
+        if let Some(code) = code {
+            request = request.header("x-status", code);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_counted_by_code() {
+        let mut service = Metered::new(Echo);
+        service.call(request(None)).await.unwrap();
+        service.call(request(None)).await.unwrap();
+        service.call(request(Some("16"))).await.unwrap();
+
+        let metrics = gather();
+        assert!(metrics.contains(
+            r#"grpc_server_requests_total{code="Ok",method="Ping",service="test.Metered"} 2"#
+        ));
+        assert!(metrics.contains(
+            r#"grpc_server_requests_total{code="Unauthenticated",method="Ping",service="test.Metered"} 1"#
+        ));
+        assert!(metrics.contains(
+            r#"grpc_server_request_duration_seconds_count{method="Ping",service="test.Metered"} 3"#
+        ));
+    }
+
+    #[derive(Debug)]
+    enum Sent {
+        Success,
+    }
+
+    #[test]
+    fn test_sent_messages_are_counted_by_status() {
+        let counter = counter_vec("test_sent_total", "test", &["channel", "status"]);
+        count_sent(&counter, "sms", Ok::<_, ()>(Sent::Success));
+        count_sent(&counter, "sms", Err::<Sent, _>(()));
+        count_sent(&counter, "sms", Err::<Sent, _>(()));
+        assert_eq!(counter.with_label_values(&["sms", "success"]).get(), 1);
+        assert_eq!(counter.with_label_values(&["sms", "error"]).get(), 2);
+    }
+}
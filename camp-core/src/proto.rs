@@ -7,3 +7,7 @@ pub fn utc_to_ts(utc: DateTime<Utc>) -> Timestamp {
         nanos: utc.timestamp_subsec_nanos() as i32,
     }
 }
+
+pub fn ts_to_utc(ts: &Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(ts.seconds, ts.nanos as u32)
+}
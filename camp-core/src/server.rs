@@ -0,0 +1,505 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{net::TcpListener, sync::Notify};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    body::BoxBody,
+    server::NamedService,
+    service::interceptor::InterceptedService,
+    transport::{server::Router, Body, Server},
+};
+use tonic_health::{server::HealthReporter, ServingStatus};
+use tower::Service;
+use tracing::{info, warn};
+
+use crate::{
+    auth::{AuthConfig, AuthErr, Authenticated, Verifier},
+    client::Circuit,
+    metrics::{self, Metered, MetricsConfig},
+    telemetry::Traced,
+    tls::{PeerIdentity, ServerTlsConfig},
+};
+
+#[derive(Debug, Error)]
+pub enum ServerErr {
+    #[error("Auth error: {0}")]
+    Auth(#[from] AuthErr),
+
+    #[error("Reflection error: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// Settings of a grpc server, flattened into the grpc section of a service config next to its
+/// port. Every setting has a default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// address to listen on, `::` or `0.0.0.0` for every interface
+    pub host: IpAddr,
+    /// a request not answered in time is cancelled, streams are not limited once answered
+    pub timeout_ms: Option<u64>,
+    /// requests of one connection being served at the same time
+    pub concurrency_limit: Option<usize>,
+    /// max size of a message received or sent
+    pub max_message_bytes: usize,
+    /// on shutdown, the server reports not serving and keeps serving this long, so that the
+    /// clients balancing across servers stop calling it before it stops accepting requests
+    pub shutdown_delay_ms: u64,
+    /// requests in flight are given this long to finish on shutdown, after the delay
+    pub shutdown_grace_ms: u64,
+    /// plaintext if unset
+    pub tls: Option<ServerTlsConfig>,
+    /// every request is answered if unset
+    pub auth: Option<AuthConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V6(Ipv6Addr::LOCALHOST),
+            timeout_ms: None,
+            concurrency_limit: None,
+            max_message_bytes: 4 * 1024 * 1024,
+            shutdown_delay_ms: 2_000,
+            shutdown_grace_ms: 10_000,
+            tls: None,
+            auth: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn addr(&self, port: u16) -> SocketAddr {
+        SocketAddr::new(self.host, port)
+    }
+}
+
+/// `server`, a generated grpc server, with the message size limits of `config` and zstd
+/// compression, e.g. `configure_service!(UserStatServer::new(grpc), &config.grpc.server)`
+#[macro_export]
+macro_rules! configure_service {
+    ($server:expr, $config:expr) => {{
+        let config: &$crate::server::ServerConfig = $config;
+        $server
+            .max_decoding_message_size(config.max_message_bytes)
+            .max_encoding_message_size(config.max_message_bytes)
+            .accept_compressed(tonic::codec::CompressionEncoding::Zstd)
+            .send_compressed(tonic::codec::CompressionEncoding::Zstd)
+    }};
+}
+
+/// A grpc server with the health service and server reflection, shutting down gracefully on
+/// SIGTERM or ctrl-c.
+///
+/// Every service added is reported serving until shutdown starts, then not serving for
+/// `shutdown_delay_ms` before the server stops accepting requests and drains the ones in
+/// flight. The circuits of the services called are reported as `downstream.<name>`, serving
+/// while closed.
+pub struct GrpcServer {
+    name: String,
+    addr: SocketAddr,
+    listener: Option<TcpListener>,
+    config: ServerConfig,
+    router: Router,
+    reporter: HealthReporter,
+    identity: PeerIdentity,
+    verifier: Option<Arc<Verifier>>,
+    metrics: Option<MetricsConfig>,
+    services: Vec<&'static str>,
+    circuits: Vec<Circuit>,
+    descriptors: Vec<&'static [u8]>,
+}
+
+impl GrpcServer {
+    /// the server `name`, as it is logged, listening on `port`
+    pub fn new(name: &str, port: u16, config: &ServerConfig) -> Result<Self, ServerErr> {
+        let (reporter, health) = tonic_health::server::health_reporter();
+        let mut builder = Server::builder();
+        if let Some(timeout_ms) = config.timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout_ms));
+        }
+        if let Some(limit) = config.concurrency_limit {
+            builder = builder.concurrency_limit_per_connection(limit);
+        }
+        let mut identity = PeerIdentity::default();
+        if let Some(tls) = &config.tls {
+            builder = builder.tls_config(tls.to_tonic())?;
+            identity = tls.interceptor();
+        }
+        let verifier = match &config.auth {
+            Some(auth) => Some(Arc::new(Verifier::try_new(auth)?)),
+            None => None,
+        };
+        let router = builder.add_service(health);
+        Ok(Self {
+            name: name.to_string(),
+            addr: config.addr(port),
+            listener: None,
+            config: config.clone(),
+            router,
+            reporter,
+            identity,
+            verifier,
+            metrics: None,
+            services: vec![],
+            circuits: vec![],
+            descriptors: vec![tonic_health::pb::FILE_DESCRIPTOR_SET],
+        })
+    }
+
+    /// tokens calling `method` of the services added afterwards must have `scope`, if the server
+    /// requires tokens, see [`AuthConfig::scopes`]
+    pub fn require_scope(mut self, method: &str, scope: &str) -> Self {
+        if let Some(verifier) = &mut self.verifier {
+            Arc::make_mut(verifier).require_scope(method, scope);
+        }
+        self
+    }
+
+    /// a service only clients with an allowed SPIFFE id and a valid token can call, see
+    /// [`ServerTlsConfig`] and [`AuthConfig`]
+    pub fn add_service<S>(mut self, service: S) -> Self
+    where
+        S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.services.push(S::NAME);
+        let service = Authenticated::new(service, self.verifier.clone());
+        let service = InterceptedService::new(service, self.identity.clone());
+        let service = Traced::new(Metered::new(service));
+        self.router = self.router.add_service(service);
+        self
+    }
+
+    /// serve the metrics of the process on their own http port while the grpc server runs
+    pub fn metrics(mut self, config: Option<&MetricsConfig>) -> Self {
+        self.metrics = config.cloned();
+        self
+    }
+
+    /// report the circuit of a service called in health, see [`GrpcServer`]
+    pub fn circuit(mut self, circuit: &Circuit) -> Self {
+        self.circuits.push(circuit.clone());
+        self
+    }
+
+    /// serve on `listener` instead of the port, one bound to port 0 by a test for instance
+    pub fn listener(mut self, listener: TcpListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// encoded file descriptor set of the services, for server reflection
+    pub fn file_descriptor_set(mut self, descriptors: &'static [u8]) -> Self {
+        self.descriptors.push(descriptors);
+        self
+    }
+
+    /// serve until SIGTERM or ctrl-c
+    pub async fn serve(self) -> Result<(), ServerErr> {
+        self.serve_with_shutdown(shutdown_signal()).await
+    }
+
+    /// serve until `signal` completes
+    pub async fn serve_with_shutdown(
+        self,
+        signal: impl Future<Output = ()> + Send,
+    ) -> Result<(), ServerErr> {
+        let mut reflection = tonic_reflection::server::Builder::configure();
+        for descriptors in self.descriptors {
+            reflection = reflection.register_encoded_file_descriptor_set(descriptors);
+        }
+        let router = self.router.add_service(reflection.build()?);
+        let metrics = self.metrics.map(|config| {
+            let name = self.name.clone();
+            tokio::spawn(async move {
+                // the grpc server keeps running without its metrics
+                if let Err(e) = metrics::serve(&config).await {
+                    warn!("{} metrics are not served: {}", name, e);
+                }
+            })
+        });
+
+        let mut reporter = self.reporter;
+        for service in &self.services {
+            reporter
+                .set_service_status(service, ServingStatus::Serving)
+                .await;
+        }
+        let circuits: Vec<_> = self
+            .circuits
+            .into_iter()
+            .map(|circuit| {
+                let mut reporter = reporter.clone();
+                tokio::spawn(async move {
+                    let service = format!("downstream.{}", circuit.name());
+                    let mut closed = circuit.watch();
+                    loop {
+                        let status = if *closed.borrow_and_update() {
+                            ServingStatus::Serving
+                        } else {
+                            ServingStatus::NotServing
+                        };
+                        reporter.set_service_status(&service, status).await;
+                        if closed.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        let draining = Arc::new(Notify::new());
+        let shutdown = {
+            let draining = draining.clone();
+            let services = self.services.clone();
+            let name = self.name.clone();
+            let delay = Duration::from_millis(self.config.shutdown_delay_ms);
+            async move {
+                signal.await;
+                info!("{} grpc server shutting down in {:?}", name, delay);
+                // the clients balancing across servers stop calling this one
+                reporter
+                    .set_service_status("", ServingStatus::NotServing)
+                    .await;
+                for service in services {
+                    reporter
+                        .set_service_status(service, ServingStatus::NotServing)
+                        .await;
+                }
+                tokio::time::sleep(delay).await;
+                info!("{} grpc server draining requests", name);
+                draining.notify_one();
+            }
+        };
+
+        let addr = match &self.listener {
+            Some(listener) => listener.local_addr().unwrap_or(self.addr),
+            None => self.addr,
+        };
+        info!("{} grpc server is listening on {}", self.name, addr);
+        let server = async move {
+            match self.listener {
+                Some(listener) => {
+                    let incoming = TcpListenerStream::new(listener);
+                    router
+                        .serve_with_incoming_shutdown(incoming, shutdown)
+                        .await
+                }
+                None => router.serve_with_shutdown(self.addr, shutdown).await,
+            }
+        };
+        tokio::pin!(server);
+        let res = tokio::select! {
+            res = &mut server => res,
+            _ = draining.notified() => {
+                let grace = Duration::from_millis(self.config.shutdown_grace_ms);
+                match tokio::time::timeout(grace, &mut server).await {
+                    Ok(res) => res,
+                    Err(_) => {
+                        warn!(
+                            "{} grpc server did not drain its requests in {:?}",
+                            self.name, grace
+                        );
+                        Ok(())
+                    }
+                }
+            }
+        };
+        if let Some(metrics) = metrics {
+            metrics.abort();
+        }
+        for circuit in circuits {
+            circuit.abort();
+        }
+        res?;
+        info!("{} grpc server stopped", self.name);
+        Ok(())
+    }
+}
+
+/// completes on SIGTERM or ctrl-c
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for ctrl-c: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::task::{Context, Poll};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::oneshot,
+    };
+    use tonic_health::pb::{
+        health_check_response::ServingStatus as Status, health_client::HealthClient,
+        HealthCheckRequest,
+    };
+    use tonic_reflection::pb::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest,
+    };
+
+    /// a service answering every request with an empty response
+    #[derive(Clone)]
+    struct Echo;
+
+    impl NamedService for Echo {
+        const NAME: &'static str = "test.Echo";
+    }
+
+    impl Service<http::Request<Body>> for Echo {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: http::Request<Body>) -> Self::Future {
+            std::future::ready(Ok(http::Response::new(tonic::body::empty_body())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_reflection_and_shutdown() {
+        let config = ServerConfig {
+            timeout_ms: Some(1000),
+            concurrency_limit: Some(16),
+            shutdown_delay_ms: 300,
+            ..Default::default()
+        };
+        // the metrics are served on a port found free
+        let free = std::net::TcpListener::bind("[::1]:0").unwrap();
+        let metrics = MetricsConfig {
+            host: config.host,
+            port: free.local_addr().unwrap().port(),
+        };
+        drop(free);
+        let listener = TcpListener::bind("[::1]:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let circuit = Circuit::new("test", &Default::default());
+        let server = GrpcServer::new("test", 0, &config)
+            .unwrap()
+            .listener(listener)
+            .add_service(Echo)
+            .circuit(&circuit)
+            .metrics(Some(&metrics));
+        let (stop, stopped) = oneshot::channel::<()>();
+        let running = tokio::spawn(server.serve_with_shutdown(async {
+            let _ = stopped.await;
+        }));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let channel = tonic::transport::Channel::from_shared(url)
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut health = HealthClient::new(channel.clone());
+        let status = health
+            .check(HealthCheckRequest {
+                service: "test.Echo".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.status(), Status::Serving);
+        let status = health
+            .check(HealthCheckRequest {
+                service: "downstream.test".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.status(), Status::Serving);
+
+        let mut reflection = ServerReflectionClient::new(channel.clone());
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut responses = reflection
+            .server_reflection_info(tokio_stream::once(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let response = responses.message().await.unwrap().unwrap();
+        let Some(MessageResponse::ListServicesResponse(list)) = response.message_response else {
+            panic!("unexpected response {:?}", response);
+        };
+        assert!(list
+            .service
+            .iter()
+            .any(|service| service.name == "grpc.health.v1.Health"));
+
+        let mut http = tokio::net::TcpStream::connect(("::1", metrics.port))
+            .await
+            .unwrap();
+        http.write_all(b"GET /metrics HTTP/1.0\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        http.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"), "{}", response);
+
+        // not serving but still answering until the delay is over
+        stop.send(()).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = health
+            .check(HealthCheckRequest {
+                service: "test.Echo".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.status(), Status::NotServing);
+        assert!(!running.is_finished());
+
+        drop((channel, health, responses, reflection));
+        tokio::time::timeout(Duration::from_secs(5), running)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+}
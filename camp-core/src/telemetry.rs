@@ -0,0 +1,230 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context as TaskContext, Poll},
+};
+
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::{TraceError, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+use thiserror::Error;
+use tonic::{
+    body::BoxBody,
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
+    server::NamedService,
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::{Body, Channel},
+    Request, Status,
+};
+use tower::Service;
+use tracing::{info, info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    fmt::{self, format::FmtSpan},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    util::TryInitError,
+    EnvFilter,
+};
+
+/// the spans are exported to this OTLP grpc endpoint if set, e.g. `http://localhost:4317`
+pub const OTLP_ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+#[derive(Debug, Error)]
+pub enum TelemetryErr {
+    #[error("Trace error: {0}")]
+    Trace(#[from] TraceError),
+
+    #[error("Init error: {0}")]
+    Init(#[from] TryInitError),
+}
+
+/// Flushes the spans not exported yet when dropped, keep it until the service stops.
+#[must_use]
+pub struct Telemetry;
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        global::shutdown_tracer_provider();
+    }
+}
+
+/// Log to stdout, filtered by `RUST_LOG` or at info, and trace the requests of `service`.
+///
+/// The spans are exported over OTLP to [`OTLP_ENDPOINT_VAR`], if it is unset every span is logged
+/// to stdout when it closes, with its fields and how long it took. Must be called within a tokio
+/// runtime.
+pub fn init(service: &str) -> Result<Telemetry, TelemetryErr> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let config = trace::config().with_resource(Resource::new([KeyValue::new(
+        "service.name",
+        service.to_string(),
+    )]));
+    let endpoint = std::env::var(OTLP_ENDPOINT_VAR).ok();
+    let tracer = match &endpoint {
+        Some(endpoint) => opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(config)
+            .install_batch(runtime::Tokio)?,
+        None => {
+            // trace ids are still created and passed on to the other services
+            let provider = trace::TracerProvider::builder().with_config(config).build();
+            let tracer = provider.tracer(service.to_string());
+            global::set_tracer_provider(provider);
+            tracer
+        }
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_span_events(match endpoint {
+            Some(_) => FmtSpan::NONE,
+            None => FmtSpan::CLOSE,
+        }))
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+    match endpoint {
+        Some(endpoint) => info!("{} spans are exported to {}", service, endpoint),
+        None => info!(
+            "{} spans are logged when closed, set {} to export them",
+            service, OTLP_ENDPOINT_VAR
+        ),
+    }
+    Ok(Telemetry)
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+/// the trace context the caller sent in the `traceparent` header
+pub fn remote_context(headers: &http::HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// A grpc service answering every request in a span continuing the trace of the caller.
+#[derive(Clone)]
+pub struct Traced<S> {
+    inner: S,
+}
+
+impl<S> Traced<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: NamedService> NamedService for Traced<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for Traced<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Infallible>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let path = request.uri().path();
+        let span = info_span!("grpc", otel.name = path, rpc.method = path);
+        span.set_parent(remote_context(request.headers()));
+        // the clone may not be ready, call the one which is
+        let clone = self.inner.clone();
+        let response = span.in_scope(|| std::mem::replace(&mut self.inner, clone).call(request));
+        Box::pin(response.instrument(span))
+    }
+}
+
+/// Sends the trace context of the current span along with every request of a client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceContext;
+
+impl Interceptor for TraceContext {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let context = Span::current().context();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut MetadataInjector(request.metadata_mut()))
+        });
+        Ok(request)
+    }
+}
+
+/// a channel whose requests carry the trace context, see [`TraceContext`]
+pub type TracedChannel = InterceptedService<Channel, TraceContext>;
+
+pub fn traced(channel: Channel) -> TracedChannel {
+    InterceptedService::new(channel, TraceContext)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_subscriber::Registry;
+
+    #[test]
+    fn test_trace_context_is_propagated() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        // the tracer only records spans while its provider is alive
+        let provider = trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("campaign");
+            let _entered = span.enter();
+            let trace_id = span.context().span().span_context().trace_id();
+
+            let request = TraceContext.call(Request::new(())).unwrap();
+            let headers = request.metadata().clone().into_headers();
+            let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+            assert!(traceparent.contains(&trace_id.to_string()));
+
+            let remote = remote_context(&headers);
+            assert_eq!(remote.span().span_context().trace_id(), trace_id);
+            assert!(remote.span().span_context().is_remote());
+        });
+    }
+}
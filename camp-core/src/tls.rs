@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tonic::{
+    service::Interceptor,
+    transport::{Certificate, Channel, Endpoint, Identity},
+    Request, Status,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// scheme of the SPIFFE ids in the URI SANs of the certificates, e.g. `spiffe://camp.local/crm`
+pub const SPIFFE_SCHEME: &str = "spiffe://";
+
+#[derive(Debug, Error)]
+pub enum TlsErr {
+    #[error("TLS is configured for {0}, the url must be https")]
+    Insecure(String),
+
+    #[error("Invalid TLS config: {0}")]
+    Invalid(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// TLS of a grpc server. The certificates and the key are PEM, set them to `{file: path}` to
+/// read them from files.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerTlsConfig {
+    pub cert: String,
+    pub key: String,
+    /// CA bundle the certificates of the clients are verified against, clients are not asked
+    /// for a certificate if unset
+    pub ca: Option<String>,
+    /// accept clients without a certificate even though `ca` is set
+    #[serde(default)]
+    pub client_auth_optional: bool,
+    /// SPIFFE ids of the clients allowed to call, e.g. `spiffe://camp.local/crm`, a trailing `*`
+    /// matches any path. Every client with a valid certificate is allowed if empty
+    #[serde(default)]
+    pub allowed_ids: Vec<String>,
+}
+
+/// TLS of a grpc client, PEM like [`ServerTlsConfig`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClientTlsConfig {
+    /// CA bundle the certificates of the servers are verified against
+    pub ca: String,
+    /// certificate presented to servers asking for one, with `key`
+    pub cert: Option<String>,
+    pub key: Option<String>,
+    /// name the certificates of the servers are issued for, the host of the url if unset
+    pub domain: Option<String>,
+}
+
+impl ServerTlsConfig {
+    pub fn to_tonic(&self) -> tonic::transport::ServerTlsConfig {
+        let mut tls = tonic::transport::ServerTlsConfig::new()
+            .identity(Identity::from_pem(&self.cert, &self.key));
+        if let Some(ca) = &self.ca {
+            tls = tls
+                .client_ca_root(Certificate::from_pem(ca))
+                .client_auth_optional(self.client_auth_optional);
+        }
+        tls
+    }
+
+    /// checks the SPIFFE id of the client of every request
+    pub fn interceptor(&self) -> PeerIdentity {
+        PeerIdentity {
+            allowed_ids: Arc::new(self.allowed_ids.clone()),
+        }
+    }
+}
+
+impl ClientTlsConfig {
+    pub fn to_tonic(&self) -> Result<tonic::transport::ClientTlsConfig, TlsErr> {
+        let mut tls = tonic::transport::ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(&self.ca));
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => tls = tls.identity(Identity::from_pem(cert, key)),
+            (None, None) => {}
+            _ => {
+                return Err(TlsErr::Invalid(
+                    "cert and key must be set together".to_string(),
+                ))
+            }
+        }
+        if let Some(domain) = &self.domain {
+            tls = tls.domain_name(domain);
+        }
+        Ok(tls)
+    }
+}
+
+/// the endpoint `url`, over TLS if `tls` is given
+pub fn endpoint(url: &str, tls: Option<&ClientTlsConfig>) -> Result<Endpoint, TlsErr> {
+    let mut endpoint = Endpoint::from_shared(url.to_string())?;
+    if let Some(tls) = tls {
+        // tonic silently falls back to plaintext for http urls
+        if endpoint.uri().scheme_str() != Some("https") {
+            return Err(TlsErr::Insecure(url.to_string()));
+        }
+        endpoint = endpoint.tls_config(tls.to_tonic()?)?;
+    }
+    Ok(endpoint)
+}
+
+/// a channel to `url`, over TLS if `tls` is given
+pub async fn connect(url: &str, tls: Option<&ClientTlsConfig>) -> Result<Channel, TlsErr> {
+    Ok(endpoint(url, tls)?.connect().await?)
+}
+
+/// Rejects requests of clients whose certificate carries none of the allowed SPIFFE ids.
+#[derive(Debug, Clone, Default)]
+pub struct PeerIdentity {
+    allowed_ids: Arc<Vec<String>>,
+}
+
+impl PeerIdentity {
+    fn allows(&self, id: &str) -> bool {
+        self.allowed_ids
+            .iter()
+            .any(|allowed| match allowed.strip_suffix('*') {
+                Some(prefix) => id.starts_with(prefix),
+                None => id == allowed,
+            })
+    }
+}
+
+impl Interceptor for PeerIdentity {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.allowed_ids.is_empty() {
+            return Ok(request);
+        }
+        let certs = request
+            .peer_certs()
+            .ok_or_else(|| Status::unauthenticated("a client certificate is required"))?;
+        let ids = certs
+            .first()
+            .map(|cert| spiffe_ids(cert.get_ref()))
+            .unwrap_or_default();
+        if ids.iter().any(|id| self.allows(id)) {
+            Ok(request)
+        } else {
+            Err(Status::permission_denied(format!(
+                "client {:?} is not allowed",
+                ids
+            )))
+        }
+    }
+}
+
+/// the SPIFFE ids in the URI SANs of a DER certificate
+pub fn spiffe_ids(der: &[u8]) -> Vec<String> {
+    let Ok((_, cert)) = X509Certificate::from_der(der) else {
+        return vec![];
+    };
+    let Ok(Some(san)) = cert.subject_alternative_name() else {
+        return vec![];
+    };
+    san.value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::URI(uri) if uri.starts_with(SPIFFE_SCHEME) => Some(uri.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rcgen::{Certificate as Cert, CertificateParams, SanType};
+
+    #[test]
+    fn test_spiffe_ids_and_matching() {
+        let mut params = CertificateParams::new(vec!["crm".to_string()]);
+        params
+            .subject_alt_names
+            .push(SanType::URI("spiffe://camp.local/crm".to_string()));
+        let der = Cert::from_params(params).unwrap().serialize_der().unwrap();
+        assert_eq!(spiffe_ids(&der), vec!["spiffe://camp.local/crm"]);
+
+        let identity = PeerIdentity {
+            allowed_ids: Arc::new(vec![
+                "spiffe://camp.local/crm".to_string(),
+                "spiffe://partner.local/*".to_string(),
+            ]),
+        };
+        assert!(identity.allows("spiffe://camp.local/crm"));
+        assert!(identity.allows("spiffe://partner.local/jobs/report"));
+        assert!(!identity.allows("spiffe://camp.local/crm-admin"));
+        assert!(!identity.allows("spiffe://camp.local/notification"));
+    }
+}
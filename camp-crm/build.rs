@@ -1,5 +1,8 @@
 use proto_builder_trait::tonic::BuilderAttributes;
-use std::env::current_dir;
+use std::{
+    env::{self, current_dir},
+    path::PathBuf,
+};
 
 use anyhow::Result;
 
@@ -7,9 +10,11 @@ fn main() -> Result<()> {
     let dir = current_dir()?;
     println!("Current directory: {:?}", dir);
     std::fs::create_dir_all("src/pb")?;
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     let builder = tonic_build::configure();
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(out_dir.join("crm_descriptor.bin"))
         .with_derive_builder(&["WelcomeRequest", "RecallRequest", "RemindRequest"], None)
         .compile(
             &["../protos/crm/messages.proto", "../protos/crm/rpc.proto"],
@@ -1,13 +1,12 @@
 use anyhow::Result;
+use camp_core::telemetry;
 use camp_crm::pb::crm::{crm_client::CrmClient, RecallRequest, RemindRequest, WelcomeRequest};
 use tokio::time::{sleep, Duration};
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let layer = Layer::new().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
+    let _telemetry = telemetry::init("crm")?;
 
     tokio::spawn(async {
         info!("Starting metadata service ...");
@@ -1,12 +1,24 @@
-use camp_core::core_fake::before;
+use camp_core::{
+    core_fake::before,
+    error::ClassifyError,
+    metrics::{count_sent, counter_vec, IntCounterVec},
+};
 use camp_metadata::abi::Tpl;
-use camp_notification::pb::notification::{send_request, EmailMessage, InAppMessage, SendRequest};
+use camp_notification::pb::notification::{
+    send_request, EmailMessage, InAppMessage, SendRequest, SendResponse,
+};
 use derive_builder::Builder;
 use futures::StreamExt as _;
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tokio::sync::{
+    mpsc::{self, error::SendError},
+    watch,
+};
 use tonic::{async_trait, Status};
-use tracing::{info, warn};
+use tracing::{field, info, info_span, warn, Instrument, Span};
 
 use crate::{
     config::WelcomeConfig,
@@ -24,7 +36,8 @@ pub struct CrmGrpc<T: MetaData, D: UserStat, U: Notification> {
     pub metadata_service: Arc<Box<T>>,
     pub user_stat_service: Arc<Box<D>>,
     pub notification_service: Arc<Box<U>>,
-    pub welcome_config: WelcomeConfig,
+    /// reloaded while running, see [`camp_core::config::ConfigLoader::watch`]
+    pub welcome_config: watch::Receiver<WelcomeConfig>,
 }
 
 impl<T: MetaData, D: UserStat, U: Notification> Clone for CrmGrpc<T, D, U> {
@@ -38,9 +51,58 @@ impl<T: MetaData, D: UserStat, U: Notification> Clone for CrmGrpc<T, D, U> {
     }
 }
 
+static CAMPAIGN_MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "crm_campaign_messages_total",
+        "messages of the campaigns answered by notification, by campaign and send status",
+        &["campaign", "status"],
+    )
+});
+
+/// a span per message of a campaign, from when it is sent until notification answers it
+#[derive(Clone, Default)]
+struct MessageSpans(Arc<Mutex<HashMap<String, Span>>>);
+
+impl MessageSpans {
+    /// send `req` to notification and open the span of its message
+    async fn send(
+        &self,
+        tx: &mpsc::Sender<SendRequest>,
+        campaign: &str,
+        req: SendRequest,
+    ) -> Result<(), SendError<SendRequest>> {
+        let id = req
+            .msg
+            .as_ref()
+            .map(|msg| msg.message_id().to_string())
+            .unwrap_or_default();
+        let span = info_span!("message", campaign, message.id = id, status = field::Empty);
+        self.0
+            .lock()
+            .expect("message spans poisoned")
+            .insert(id, span.clone());
+        tx.send(req).instrument(span).await
+    }
+
+    /// close the span of the message `resp` answers
+    fn answered(&self, resp: &Result<SendResponse, Status>) {
+        let Ok(resp) = resp else {
+            return;
+        };
+        let span = self
+            .0
+            .lock()
+            .expect("message spans poisoned")
+            .remove(&resp.message_id);
+        if let Some(span) = span {
+            span.record("status", format!("{:?}", resp.status()).to_lowercase());
+        }
+    }
+}
+
 impl From<ServiceError> for Status {
     fn from(value: ServiceError) -> Self {
-        Status::internal(value.to_string())
+        value.to_status()
     }
 }
 
@@ -56,42 +118,55 @@ impl<T: MetaData, D: UserStat, U: Notification> Crm for CrmGrpc<T, D, U> {
                 .get_content(&welcome.content_ids)
                 .await?,
         );
+        let welcome_config = self.welcome_config.borrow().clone();
         let mut user_stat_stream = self
             .user_stat_service
             .get_new_user_stream(
-                before(self.welcome_config.created_before_lower),
-                before(self.welcome_config.created_before_upper),
+                before(welcome_config.created_before_lower),
+                before(welcome_config.created_before_upper),
             )
             .await?;
         let (tx, rx) = mpsc::channel(1024);
         let mut notification_resp_stream = self.notification_service.notification(rx).await?;
         info!("welcome {:?}", contents);
-        tokio::spawn(async move {
-            while let Some(user_resp) = user_stat_stream.next().await {
-                let contents_clone = contents.clone();
-                let user = match user_resp {
-                    Ok(user) => user,
-                    Err(e) => {
-                        info!("Error while fetching user: {:?}", e);
-                        continue;
-                    }
-                };
-                // TODO use Faker
-                let email_msg = send_request::Msg::Email(EmailMessage {
-                    message_id: uuid::Uuid::new_v4().to_string(),
-                    subject: "welcome".to_string(),
-                    sender: "welcome".to_string(),
-                    recipients: vec![user.email],
-                    body: Tpl(contents_clone.as_ref()).to_body(),
-                });
-                tx.send(SendRequest {
-                    msg: Some(email_msg),
-                })
-                .await
-                .unwrap();
+        let spans = MessageSpans::default();
+        let sends = spans.clone();
+        tokio::spawn(
+            async move {
+                while let Some(user_resp) = user_stat_stream.next().await {
+                    let contents_clone = contents.clone();
+                    let user = match user_resp {
+                        Ok(user) => user,
+                        Err(e) => {
+                            info!("Error while fetching user: {:?}", e);
+                            continue;
+                        }
+                    };
+                    // TODO use Faker
+                    let email_msg = send_request::Msg::Email(EmailMessage {
+                        message_id: uuid::Uuid::new_v4().to_string(),
+                        subject: "welcome".to_string(),
+                        sender: "welcome".to_string(),
+                        recipients: vec![user.email],
+                        body: Tpl(contents_clone.as_ref()).to_body(),
+                        ..Default::default()
+                    });
+                    let req = SendRequest {
+                        msg: Some(email_msg),
+                        ..Default::default()
+                    };
+                    sends.send(&tx, "welcome", req).await.unwrap();
+                }
             }
-        });
+            .instrument(Span::current()),
+        );
         while let Some(notification_resp) = notification_resp_stream.next().await {
+            count_sent(
+                &CAMPAIGN_MESSAGES,
+                "welcome",
+                notification_resp.as_ref().map(|resp| resp.status()),
+            );
+            spans.answered(&notification_resp);
             match notification_resp {
                 Ok(_) => {}
                 Err(e) => {
@@ -119,33 +194,44 @@ impl<T: MetaData, D: UserStat, U: Notification> Crm for CrmGrpc<T, D, U> {
         let contents = Arc::new(self.metadata_service.get_content(&req.content_ids).await?);
         let (tx, rx) = mpsc::channel(1024);
         let mut notification_resp_stream = self.notification_service.notification(rx).await?;
-
-        tokio::spawn(async move {
-            while let Some(user) = user_stream.next().await {
-                match user {
-                    Ok(user) => {
-                        let contents_clone = contents.clone();
-                        let email_msg = send_request::Msg::Email(EmailMessage {
-                            message_id: uuid::Uuid::new_v4().to_string(),
-                            subject: "recall".to_string(),
-                            sender: "recall".to_string(),
-                            recipients: vec![user.email],
-                            body: Tpl(contents_clone.as_ref()).to_body(),
-                        });
-                        tx.send(SendRequest {
-                            msg: Some(email_msg),
-                        })
-                        .await
-                        .unwrap();
-                    }
-                    Err(e) => {
-                        warn!("Failed to get user: {}", e);
+        let spans = MessageSpans::default();
+        let sends = spans.clone();
+        tokio::spawn(
+            async move {
+                while let Some(user) = user_stream.next().await {
+                    match user {
+                        Ok(user) => {
+                            let contents_clone = contents.clone();
+                            let email_msg = send_request::Msg::Email(EmailMessage {
+                                message_id: uuid::Uuid::new_v4().to_string(),
+                                subject: "recall".to_string(),
+                                sender: "recall".to_string(),
+                                recipients: vec![user.email],
+                                body: Tpl(contents_clone.as_ref()).to_body(),
+                                ..Default::default()
+                            });
+                            let req = SendRequest {
+                                msg: Some(email_msg),
+                                ..Default::default()
+                            };
+                            sends.send(&tx, "recall", req).await.unwrap();
+                        }
+                        Err(e) => {
+                            warn!("Failed to get user: {}", e);
+                        }
                     }
                 }
             }
-        });
+            .instrument(Span::current()),
+        );
 
         while let Some(notification_resp) = notification_resp_stream.next().await {
+            count_sent(
+                &CAMPAIGN_MESSAGES,
+                "recall",
+                notification_resp.as_ref().map(|resp| resp.status()),
+            );
+            spans.answered(&notification_resp);
             match notification_resp {
                 Ok(_) => {}
                 Err(e) => {
@@ -173,39 +259,52 @@ impl<T: MetaData, D: UserStat, U: Notification> Crm for CrmGrpc<T, D, U> {
 
         let (tx, rx) = mpsc::channel(1024);
         let mut notification_resp_stream = self.notification_service.notification(rx).await?;
-        tokio::spawn(async move {
-            while let Some(user) = user_stream.next().await {
-                match user {
-                    Ok(user) => match self_clone
-                        .metadata_service
-                        .get_content(&user.started_but_not_finished)
-                        .await
-                    {
-                        Ok(contents) => {
-                            let send_resp = SendRequest {
-                                msg: Some(send_request::Msg::InApp(InAppMessage {
-                                    message_id: uuid::Uuid::new_v4().to_string(),
-                                    sender: "remind".to_string(),
-                                    body: Tpl(&contents).to_body(),
-                                    device_id: "MacBook SN ABCDEF".to_string(),
-                                    title: "inapp".to_string(),
-                                })),
-                            };
-                            tx.send(send_resp).await.unwrap();
-                        }
+        let spans = MessageSpans::default();
+        let sends = spans.clone();
+        tokio::spawn(
+            async move {
+                while let Some(user) = user_stream.next().await {
+                    match user {
+                        Ok(user) => match self_clone
+                            .metadata_service
+                            .get_content(&user.started_but_not_finished)
+                            .instrument(info_span!("unfinished_contents"))
+                            .await
+                        {
+                            Ok(contents) => {
+                                let send_resp = SendRequest {
+                                    msg: Some(send_request::Msg::InApp(InAppMessage {
+                                        message_id: uuid::Uuid::new_v4().to_string(),
+                                        sender: "remind".to_string(),
+                                        body: Tpl(&contents).to_body(),
+                                        device_id: "MacBook SN ABCDEF".to_string(),
+                                        title: "inapp".to_string(),
+                                    })),
+                                    ..Default::default()
+                                };
+                                sends.send(&tx, "remind", send_resp).await.unwrap();
+                            }
+                            Err(e) => {
+                                warn!("get content error {:?}", e)
+                            }
+                        },
                         Err(e) => {
-                            warn!("get content error {:?}", e)
+                            warn!("user stream end with status {:?}", e);
+                            break;
                         }
-                    },
-                    Err(e) => {
-                        warn!("user stream end with status {:?}", e);
-                        break;
                     }
                 }
             }
-        });
+            .instrument(Span::current()),
+        );
 
         while let Some(notification_resp) = notification_resp_stream.next().await {
+            count_sent(
+                &CAMPAIGN_MESSAGES,
+                "remind",
+                notification_resp.as_ref().map(|resp| resp.status()),
+            );
+            spans.answered(&notification_resp);
             match notification_resp {
                 Ok(_) => {}
                 Err(e) => {
@@ -221,6 +320,7 @@ impl<T: MetaData, D: UserStat, U: Notification> Crm for CrmGrpc<T, D, U> {
 }
 
 #[cfg(test)]
+#[allow(clippy::result_large_err)]
 mod test {
     use crate::services::{
         metadata::MockMetaData, notification::MockNotification, user_stat::MockUserStat,
@@ -284,11 +384,13 @@ mod test {
                             message_id: "1".to_string(),
                             timestamp: Some(utc_to_ts(Utc::now())),
                             status: SendResponseType::Success as i32,
+                            ..Default::default()
                         }),
                         Ok(SendResponse {
                             message_id: "2".to_string(),
                             timestamp: Some(utc_to_ts(Utc::now())),
                             status: SendResponseType::Success as i32,
+                            ..Default::default()
                         }),
                     ];
                     Ok(Box::pin(tokio_stream::iter(send_responses)))
@@ -298,17 +400,70 @@ mod test {
             .metadata_service(email)
             .user_stat_service(user_stat)
             .notification_service(notification)
-            .welcome_config(WelcomeConfig {
-                created_before_lower: 1,
-                created_before_upper: 0,
-            })
+            .welcome_config(
+                watch::channel(WelcomeConfig {
+                    created_before_lower: 1,
+                    created_before_upper: 0,
+                })
+                .1,
+            )
             .build()
             .unwrap();
+        // the counter is shared with the other tests of this process
+        let sent = CAMPAIGN_MESSAGES.with_label_values(&["welcome", "success"]);
+        let before = sent.get();
         crm.welcome(Request::new(WelcomeRequest {
             content_ids: vec![1, 2, 3],
             interval: 1,
         }))
         .await?;
+        assert_eq!(sent.get() - before, 2);
         Ok(())
     }
+
+    #[test]
+    fn test_service_error_status() {
+        use crate::services::{metadata::MetaDataError, user_stat::UserError};
+        use camp_core::error::{error_info, is_retryable};
+        use tonic::Code;
+
+        let status: Status =
+            ServiceError::from(MetaDataError::from(Status::unavailable("refused"))).into();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(error_info(&status).unwrap().reason, "METADATA_FAILED");
+        assert!(is_retryable(&status));
+
+        let status: Status =
+            ServiceError::from(UserError::from(Status::invalid_argument("bad query"))).into();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(!is_retryable(&status));
+
+        let status: Status = ServiceError::from(UserError::from(Status::unknown("bug"))).into();
+        assert_eq!(status.code(), Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_message_span_is_closed_once_answered() {
+        let spans = MessageSpans::default();
+        let (tx, mut rx) = mpsc::channel(2);
+        let inapp = |id: &str| SendRequest {
+            msg: Some(send_request::Msg::InApp(InAppMessage {
+                message_id: id.to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        spans.send(&tx, "remind", inapp("m-1")).await.unwrap();
+        spans.send(&tx, "remind", inapp("m-2")).await.unwrap();
+        assert!(rx.recv().await.is_some());
+
+        spans.answered(&Ok(SendResponse {
+            message_id: "m-1".to_string(),
+            status: SendResponseType::Success as i32,
+            ..Default::default()
+        }));
+        spans.answered(&Err(Status::unavailable("gone")));
+        let open = spans.0.lock().unwrap();
+        assert_eq!(open.keys().collect::<Vec<_>>(), vec!["m-2"]);
+    }
 }
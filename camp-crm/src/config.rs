@@ -1,21 +1,42 @@
 use std::{env, path::Path};
 
 use anyhow::Result;
-use camp_core::config::config_load;
+use camp_core::{
+    auth::ClientAuthConfig,
+    client::ClientConfig,
+    config::{keep_fixed, ConfigLoader, Reload},
+    discovery::Endpoints,
+    metrics::MetricsConfig,
+    server::ServerConfig,
+    tls::ClientTlsConfig,
+};
 use serde::Deserialize;
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct AppConfig {
     pub grpc: GrpcConfig,
     pub welcome: WelcomeConfig,
+    /// the `/metrics` http endpoint, not served if unset
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct GrpcConfig {
     pub port: u16,
-    pub metadata: String,
-    pub user_stat: String,
-    pub notification: String,
+    #[serde(flatten)]
+    pub server: ServerConfig,
+    /// a url, a list of urls or a name resolved periodically, see [`Endpoints`]
+    pub metadata: Endpoints,
+    pub user_stat: Endpoints,
+    pub notification: Endpoints,
+    /// TLS of the calls to the services above, their urls must be https if set
+    pub client_tls: Option<ClientTlsConfig>,
+    /// bearer tokens of the calls to the services above
+    pub client_auth: Option<ClientAuthConfig>,
+    /// deadlines, retries and circuit breaking of the calls to the services above
+    #[serde(default)]
+    pub client: ClientConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -25,14 +46,38 @@ pub struct WelcomeConfig {
 }
 
 impl AppConfig {
-    pub fn load() -> Result<Self> {
+    pub fn loader() -> Result<ConfigLoader> {
         let p = Path::new(&env::var("CARGO_MANIFEST_DIR")?).join("crm.yml");
-        Ok(config_load(vec![
-            "./camp-crm/crm.yml".to_string(),
-            "../camp-crm/crm.yml".to_string(),
-            "./crm.yml".to_string(),
-            "/etc/config/crm.yml".to_string(),
-            p.to_string_lossy().to_string(),
-        ])?)
+        Ok(ConfigLoader::new("crm").from_process().paths([
+            "./camp-crm/crm.yml".into(),
+            "../camp-crm/crm.yml".into(),
+            "./crm.yml".into(),
+            "/etc/config/crm.yml".into(),
+            p,
+        ]))
+    }
+
+    pub fn load() -> Result<Self> {
+        Ok(Self::loader()?.load()?)
+    }
+}
+
+/// the welcome window changes live, the port and the services called only on restart
+impl Reload for AppConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.welcome.created_before_lower < self.welcome.created_before_upper {
+            return Err(format!(
+                "welcome.created_before_lower {} is less than welcome.created_before_upper {}",
+                self.welcome.created_before_lower, self.welcome.created_before_upper
+            ));
+        }
+        Ok(())
+    }
+
+    fn keep_fixed(&mut self, current: &Self) -> Vec<&'static str> {
+        let mut changed = vec![];
+        keep_fixed("grpc", &mut self.grpc, &current.grpc, &mut changed);
+        keep_fixed("metrics", &mut self.metrics, &current.metrics, &mut changed);
+        changed
     }
 }
@@ -7,23 +7,36 @@ use crate::{
     AppState, AppStateBuilder,
 };
 use anyhow::Result;
+use camp_core::{auth::ClientAuth, config::section};
 pub type CrmGrpcV1 = CrmGrpc<MetaDataV1, UserStatV1, NotificationV1>;
 
 impl AppState<CrmGrpcV1> {
     pub async fn try_new() -> Result<Self> {
-        let app_config = AppConfig::load()?;
-        let metadata_service = MetaDataV1::try_new(&app_config.grpc.metadata).await?;
-        let user_stat_service = UserStatV1::try_new(&app_config.grpc.user_stat).await?;
-        let notification_service = NotificationV1::try_new(&app_config.grpc.notification).await?;
+        let loader = AppConfig::loader()?;
+        let app_config: AppConfig = loader.load()?;
+        let config = loader.watch(app_config.clone());
+        let grpc = &app_config.grpc;
+        let tls = grpc.client_tls.as_ref();
+        let auth = ClientAuth::from_config(grpc.client_auth.as_ref())?;
+        let client = &grpc.client;
+        let metadata_service = MetaDataV1::try_new(&grpc.metadata, tls, auth.clone(), client)?;
+        let user_stat_service = UserStatV1::try_new(&grpc.user_stat, tls, auth.clone(), client)?;
+        let notification_service = NotificationV1::try_new(&grpc.notification, tls, auth, client)?;
+        let circuits = vec![
+            metadata_service.circuit.clone(),
+            user_stat_service.circuit.clone(),
+            notification_service.circuit.clone(),
+        ];
         let crm_grpc: CrmGrpcV1 = CrmGrpcBuilder::default()
             .metadata_service(Arc::new(Box::new(metadata_service)))
             .user_stat_service(Arc::new(Box::new(user_stat_service)))
             .notification_service(Arc::new(Box::new(notification_service)))
-            .welcome_config(app_config.welcome.clone())
+            .welcome_config(section(&config, |config| config.welcome.clone()))
             .build()?;
         Ok(AppStateBuilder::default()
             .crm_grpc(crm_grpc)
             .app_config(app_config)
+            .circuits(circuits)
             .build()?)
     }
 }
@@ -1,6 +1,5 @@
-use std::net::ToSocketAddrs as _;
-
 use anyhow::Result;
+use camp_core::{client::Circuit, configure_service, server::GrpcServer};
 use config::AppConfig;
 use derive_builder::Builder;
 use pb::crm::crm_server::Crm;
@@ -17,6 +16,9 @@ pub mod services;
 pub struct AppState<T: Crm> {
     pub crm_grpc: T,
     pub app_config: AppConfig,
+    /// of the services called, reported in health
+    #[builder(default)]
+    pub circuits: Vec<Circuit>,
 }
 
 impl<T> AppState<T>
@@ -24,13 +26,19 @@ where
     T: Crm,
 {
     pub async fn grpc_run(self) -> Result<()> {
-        let addr = format!("[::1]:{:?}", self.app_config.grpc.port);
-        println!("grpc server running on {:?}", addr);
-        tonic::transport::Server::builder()
-            .add_service(CrmServer::new(self.crm_grpc))
-            .serve(addr.to_socket_addrs()?.next().unwrap())
+        let config = &self.app_config.grpc;
+        let server = GrpcServer::new("crm", config.port, &config.server)?
+            .add_service(configure_service!(
+                CrmServer::new(self.crm_grpc),
+                &config.server
+            ))
+            .file_descriptor_set(pb::FILE_DESCRIPTOR_SET)
+            .metrics(self.app_config.metrics.as_ref());
+        self.circuits
+            .iter()
+            .fold(server, |server, circuit| server.circuit(circuit))
+            .serve()
             .await?;
-        println!("grpc quit");
         Ok(())
     }
 }
@@ -1,17 +1,13 @@
 use anyhow::Result;
+use camp_core::telemetry;
 use camp_crm::{ioc::CrmGrpcV1, AppState};
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let layer = Layer::new().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
+    let _telemetry = telemetry::init("crm")?;
     let app_state = AppState::<CrmGrpcV1>::try_new().await?;
-    info!(
-        "grpc server running on [::1]:{:?}",
-        &app_state.app_config.grpc.port
-    );
+    info!("Starting crm service ...");
     app_state.grpc_run().await?;
     Ok(())
 }
@@ -8,7 +8,7 @@ pub struct WelcomeRequest {
     /// interval for registered time
     #[prost(uint32, tag = "1")]
     pub interval: u32,
-    ///
+    /// contents to put in the welcome message
     #[prost(uint32, repeated, tag = "2")]
     pub content_ids: ::prost::alloc::vec::Vec<u32>,
 }
@@ -1 +1,5 @@
 pub mod crm;
+
+/// encoded file descriptors of the services, for server reflection
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/crm_descriptor.bin"));
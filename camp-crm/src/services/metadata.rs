@@ -1,10 +1,17 @@
 use anyhow::Result;
+use camp_core::{
+    auth::{AuthChannel, ClientAuth},
+    client::{channel, Circuit, ClientConfig},
+    discovery::Endpoints,
+    telemetry::traced,
+    tls::ClientTlsConfig,
+};
 use camp_metadata::pb::metadata::metadata_client::MetadataClient;
 use camp_metadata::pb::metadata::{Content, MaterializeRequest};
 use futures::{stream, Stream, StreamExt};
 use std::collections::HashSet;
 use thiserror::Error;
-use tonic::{async_trait, transport::Channel};
+use tonic::async_trait;
 use tracing::info;
 
 use super::ServiceError;
@@ -23,25 +30,39 @@ pub trait MetaData: Send + Sync + 'static {
 
 #[derive(Clone)]
 pub struct MetaDataImpl {
-    pub client: MetadataClient<Channel>,
+    pub client: MetadataClient<AuthChannel>,
+    pub circuit: Circuit,
 }
 
 impl MetaDataImpl {
-    pub async fn try_new(url: &str) -> Result<Self> {
-        let client = MetadataClient::connect(url.to_string()).await?;
-        Ok(Self { client })
+    /// the client of `endpoints`, over TLS if `tls` is given, the requests carry the token of
+    /// `auth`. It connects on the first call
+    pub fn try_new(
+        endpoints: &Endpoints,
+        tls: Option<&ClientTlsConfig>,
+        auth: ClientAuth,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let client =
+            MetadataClient::with_interceptor(traced(channel(endpoints, tls, config)?), auth);
+        let circuit = Circuit::new("metadata", config);
+        Ok(Self { client, circuit })
     }
 }
 
 #[async_trait]
 impl MetaData for MetaDataImpl {
     async fn get_content(&self, ids: &[u32]) -> Result<Vec<Content>, ServiceError> {
-        let mut response = match self
-            .client
-            .clone()
-            .materialize(new_content_req_with_ids(ids))
-            .await
-        {
+        // materializing has no side effect, it is retried
+        let response = self
+            .circuit
+            .call(true, || {
+                let mut client = self.client.clone();
+                let request = self.circuit.request(new_content_req_with_ids(ids));
+                async move { client.materialize(request).await }
+            })
+            .await;
+        let mut response = match response {
             Ok(response) => response.into_inner(),
             Err(status) => return Err(MetaDataError::GrpcStatus(status).into()),
         };
@@ -4,9 +4,11 @@ pub mod user_stat;
 
 use std::fmt::{self, Display, Formatter};
 
+use camp_core::error::{is_retryable, ClassifyError};
 pub use metadata::{MetaData, MetaDataImpl as MetaDataV1};
 pub use notification::{Notification, NotificationImpl as NotificationV1};
 use thiserror::Error;
+use tonic::{Code, Status};
 pub use user_stat::{UserStat, UserStatImpl as UserStatV1};
 
 #[derive(Debug, Error)]
@@ -25,3 +27,44 @@ impl Display for ServiceError {
         }
     }
 }
+
+impl ServiceError {
+    /// the status answered by the service crm called
+    fn downstream(&self) -> &Status {
+        match self {
+            ServiceError::MeataData(metadata::MetaDataError::GrpcStatus(status))
+            | ServiceError::UserStat(user_stat::UserError::GrpcStatus(status))
+            | ServiceError::Notification(notification::NotificationError::GrpcStatus(status)) => {
+                status
+            }
+        }
+    }
+}
+
+impl ClassifyError for ServiceError {
+    fn domain(&self) -> &'static str {
+        "crm"
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            ServiceError::MeataData(_) => "METADATA_FAILED",
+            ServiceError::UserStat(_) => "USER_STAT_FAILED",
+            ServiceError::Notification(_) => "NOTIFICATION_FAILED",
+        }
+    }
+
+    /// the client errors of a downstream service are passed on, a service being unreachable or
+    /// too slow is reported as unavailable, anything else is a failure of crm
+    fn code(&self) -> Code {
+        match self.downstream().code() {
+            code @ (Code::InvalidArgument | Code::NotFound | Code::ResourceExhausted) => code,
+            Code::Unavailable | Code::DeadlineExceeded => Code::Unavailable,
+            _ => Code::Internal,
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        is_retryable(self.downstream())
+    }
+}
@@ -1,12 +1,19 @@
 use anyhow::Result;
 use camp_core::core_types::PinBoxTonicStream;
+use camp_core::{
+    auth::{AuthChannel, ClientAuth},
+    client::{channel, Circuit, ClientConfig},
+    discovery::Endpoints,
+    telemetry::traced,
+    tls::ClientTlsConfig,
+};
 use camp_notification::pb::notification::{
     notification_client::NotificationClient, SendRequest, SendResponse,
 };
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
-use tonic::{async_trait, transport::Channel};
+use tonic::async_trait;
 
 use super::ServiceError;
 
@@ -27,7 +34,8 @@ pub trait Notification: Clone + Send + Sync + 'static {
 
 #[derive(Clone)]
 pub struct NotificationImpl {
-    pub client: NotificationClient<Channel>,
+    pub client: NotificationClient<AuthChannel>,
+    pub circuit: Circuit,
 }
 
 #[async_trait]
@@ -36,8 +44,17 @@ impl Notification for NotificationImpl {
         &self,
         request: mpsc::Receiver<SendRequest>,
     ) -> Result<PinBoxTonicStream<SendResponse>, ServiceError> {
-        let stream = ReceiverStream::new(request);
-        let response = match self.client.clone().send(stream).await {
+        // the messages are sent only once, a failed call is not retried
+        let mut request = Some(self.circuit.request(ReceiverStream::new(request)));
+        let response = self
+            .circuit
+            .call(false, || {
+                let mut client = self.client.clone();
+                let request = request.take().expect("the call is made once");
+                async move { client.send(request).await }
+            })
+            .await;
+        let response = match response {
             Ok(response) => response.into_inner(),
             Err(status) => return Err(NotificationError::GrpcStatus(status).into()),
         };
@@ -46,8 +63,17 @@ impl Notification for NotificationImpl {
 }
 
 impl NotificationImpl {
-    pub async fn try_new(url: &str) -> Result<Self> {
-        let client = NotificationClient::connect(url.to_string()).await?;
-        Ok(Self { client })
+    /// the client of `endpoints`, over TLS if `tls` is given, the requests carry the token of
+    /// `auth`. It connects on the first call
+    pub fn try_new(
+        endpoints: &Endpoints,
+        tls: Option<&ClientTlsConfig>,
+        auth: ClientAuth,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let client =
+            NotificationClient::with_interceptor(traced(channel(endpoints, tls, config)?), auth);
+        let circuit = Circuit::new("notification", config);
+        Ok(Self { client, circuit })
     }
 }
@@ -1,14 +1,20 @@
 use anyhow::Result;
 use camp_core::proto::utc_to_ts;
+use camp_core::{
+    auth::{AuthChannel, ClientAuth},
+    client::{channel, Circuit, ClientConfig},
+    discovery::Endpoints,
+    telemetry::traced,
+    tls::ClientTlsConfig,
+};
 use camp_user_stat::pb::user_stat::{
-    user_stat_client::UserStatClient, QueryRequest, RawQueryRequest, TimeQuery, User,
+    user_stat_client::UserStatClient, QueryRequest, TimeQuery, User,
 };
 use chrono::{DateTime, Utc};
 use futures::Stream;
 use std::{collections::HashMap, pin::Pin};
 use thiserror::Error;
-use tonic::{async_trait, transport::Channel, Request, Status};
-use tracing::info;
+use tonic::{async_trait, Status};
 
 use super::ServiceError;
 
@@ -40,7 +46,8 @@ pub trait UserStat: Clone + Send + Sync + 'static {
 
 #[derive(Clone)]
 pub struct UserStatImpl {
-    pub client: UserStatClient<Channel>,
+    pub client: UserStatClient<AuthChannel>,
+    pub circuit: Circuit,
 }
 
 #[async_trait]
@@ -51,11 +58,7 @@ impl UserStat for UserStatImpl {
         upper: DateTime<Utc>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>, ServiceError> {
         let request = new_user_req_created_between(lower, upper);
-        let response = match self.client.clone().query(request).await {
-            Ok(response) => response.into_inner(),
-            Err(status) => return Err(UserError::GrpcStatus(status).into()),
-        };
-        Ok(Box::pin(response))
+        self.query(request).await
     }
 
     async fn get_lasted_visit_before_stream(
@@ -63,39 +66,57 @@ impl UserStat for UserStatImpl {
         lasted_visited_before: DateTime<Utc>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>, ServiceError> {
         let request = new_user_req_last_visite(lasted_visited_before);
-        let response = match self.client.clone().query(request).await {
-            Ok(response) => response.into_inner(),
-            Err(status) => return Err(UserError::GrpcStatus(status).into()),
-        };
-        Ok(Box::pin(response))
+        self.query(request).await
     }
 
     async fn get_lasted_visit_but_not_finished(
         &self,
         lasted_visited_before: DateTime<Utc>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>, ServiceError> {
-        let raw_query = format!("
-            SELECT name, email, started_but_not_finished from user_stats WHERE last_visited_at <= '{:?}'
-        ", lasted_visited_before);
-        info!("raw_query: {}", raw_query);
-        let req = Request::new(RawQueryRequest { query: raw_query });
-        let response = match self.client.clone().raw_query(req).await {
-            Ok(stream) => stream.into_inner(),
-            Err(status) => return Err(UserError::GrpcStatus(status).into()),
-        };
-        Ok(Box::pin(response))
+        // the users come with the contents they started but did not finish
+        let request = new_user_req_last_visite(lasted_visited_before);
+        self.query(request).await
     }
 }
 
 impl UserStatImpl {
-    pub async fn try_new(url: &str) -> Result<Self> {
-        let client = UserStatClient::connect(url.to_string()).await?;
-        Ok(Self { client })
+    /// the client of `endpoints`, over TLS if `tls` is given, the requests carry the token of
+    /// `auth`. It connects on the first call
+    pub fn try_new(
+        endpoints: &Endpoints,
+        tls: Option<&ClientTlsConfig>,
+        auth: ClientAuth,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let client =
+            UserStatClient::with_interceptor(traced(channel(endpoints, tls, config)?), auth);
+        let circuit = Circuit::new("user_stat", config);
+        Ok(Self { client, circuit })
+    }
+
+    /// queries only read, they are retried
+    async fn query(
+        &self,
+        request: QueryRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>, ServiceError> {
+        let response = self
+            .circuit
+            .call(true, || {
+                let mut client = self.client.clone();
+                let request = self.circuit.request(request.clone());
+                async move { client.query(request).await }
+            })
+            .await;
+        let response = match response {
+            Ok(response) => response.into_inner(),
+            Err(status) => return Err(UserError::GrpcStatus(status).into()),
+        };
+        Ok(Box::pin(response))
     }
 }
 
-fn new_user_req_last_visite(time: DateTime<Utc>) -> Request<QueryRequest> {
-    Request::new(QueryRequest {
+fn new_user_req_last_visite(time: DateTime<Utc>) -> QueryRequest {
+    QueryRequest {
         timestamps: vec![(
             "last_visited_at".to_string(),
             TimeQuery {
@@ -106,14 +127,11 @@ fn new_user_req_last_visite(time: DateTime<Utc>) -> Request<QueryRequest> {
         .into_iter()
         .collect(),
         ids: HashMap::new(),
-    })
+    }
 }
 
-fn new_user_req_created_between(
-    lower: DateTime<Utc>,
-    upper: DateTime<Utc>,
-) -> Request<QueryRequest> {
-    Request::new(QueryRequest {
+fn new_user_req_created_between(lower: DateTime<Utc>, upper: DateTime<Utc>) -> QueryRequest {
+    QueryRequest {
         timestamps: vec![(
             "created_at".to_string(),
             TimeQuery {
@@ -124,5 +142,5 @@ fn new_user_req_created_between(
         .into_iter()
         .collect(),
         ids: HashMap::new(),
-    })
+    }
 }
@@ -0,0 +1,296 @@
+use anyhow::Result;
+use camp_core::{
+    auth::ClientAuth,
+    client::ClientConfig,
+    core_types::PinBoxTonicStream,
+    discovery::Endpoints,
+    server::{GrpcServer, ServerConfig},
+};
+use camp_crm::services::{user_stat::UserError, ServiceError, UserStat, UserStatV1};
+use camp_user_stat::pb::user_stat::{
+    user_stat_server::{UserStat as UserStatGrpc, UserStatServer},
+    QueryRequest, RawQueryRequest, User,
+};
+use chrono::Utc;
+use std::{
+    future::Future,
+    net::Ipv4Addr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, sync::oneshot, time::sleep};
+use tokio_stream::StreamExt;
+use tonic::{async_trait, Code, Request, Response, Status};
+
+/// user-stat failing the queries while `down`, or the first `failures` ones, and answering the
+/// others after `delay`
+#[derive(Clone, Default)]
+struct FlakyUserStat {
+    calls: Arc<AtomicUsize>,
+    failures: usize,
+    down: Arc<AtomicBool>,
+    delay: Duration,
+}
+
+#[async_trait]
+impl UserStatGrpc for FlakyUserStat {
+    type QueryStream = PinBoxTonicStream<User>;
+    type RawQueryStream = PinBoxTonicStream<User>;
+
+    async fn query(&self, _: Request<QueryRequest>) -> Result<Response<Self::QueryStream>, Status> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call < self.failures || self.down.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("flaky"));
+        }
+        sleep(self.delay).await;
+        let user = User {
+            email: "flaky@example.com".to_string(),
+            name: "flaky".to_string(),
+            ..Default::default()
+        };
+        Ok(Response::new(Box::pin(tokio_stream::iter([Ok(user)]))))
+    }
+
+    async fn raw_query(
+        &self,
+        _: Request<RawQueryRequest>,
+    ) -> Result<Response<Self::RawQueryStream>, Status> {
+        Ok(Response::new(Box::pin(tokio_stream::empty())))
+    }
+}
+
+/// serve `user_stat` on a free port, returns its url
+async fn serve(user_stat: FlakyUserStat) -> Result<String> {
+    let listener = TcpListener::bind("[::1]:0").await?;
+    serve_until(
+        user_stat,
+        listener,
+        &ServerConfig::default(),
+        std::future::pending(),
+    )
+    .await
+}
+
+/// serve `user_stat` on `listener` until `signal` completes, returns its url
+async fn serve_until(
+    user_stat: FlakyUserStat,
+    listener: TcpListener,
+    config: &ServerConfig,
+    signal: impl Future<Output = ()> + Send + 'static,
+) -> Result<String> {
+    let url = format!("http://{}", listener.local_addr()?);
+    let server = GrpcServer::new("user_stat", 0, config)?
+        .listener(listener)
+        .add_service(UserStatServer::new(user_stat));
+    tokio::spawn(server.serve_with_shutdown(signal));
+    sleep(Duration::from_millis(100)).await;
+    Ok(url)
+}
+
+/// a port found free, nothing listens on it
+fn unused_port() -> Result<u16> {
+    Ok(std::net::TcpListener::bind("[::1]:0")?.local_addr()?.port())
+}
+
+fn code(result: Result<impl Sized, ServiceError>) -> Code {
+    match result {
+        Err(ServiceError::UserStat(UserError::GrpcStatus(status))) => status.code(),
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(_) => Code::Ok,
+    }
+}
+
+#[tokio::test]
+async fn crm_should_connect_lazily_and_retry_queries() -> Result<()> {
+    let config = ClientConfig {
+        retries: 2,
+        backoff_ms: 10,
+        breaker_failures: 10,
+        ..Default::default()
+    };
+    // user-stat is not started yet
+    let port = unused_port()?;
+    let user_stat = UserStatV1::try_new(
+        &format!("http://[::1]:{}", port).as_str().into(),
+        None,
+        ClientAuth::default(),
+        &config,
+    )?;
+    let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+    assert_eq!(code(result), Code::Unavailable);
+
+    let flaky = FlakyUserStat {
+        failures: 2,
+        ..Default::default()
+    };
+    let listener = TcpListener::bind(("::1", port)).await?;
+    serve_until(
+        flaky.clone(),
+        listener,
+        &ServerConfig::default(),
+        std::future::pending(),
+    )
+    .await?;
+    let users: Vec<User> = user_stat
+        .get_new_user_stream(Utc::now(), Utc::now())
+        .await?
+        .collect::<Result<_, _>>()
+        .await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    assert!(!user_stat.circuit.is_open());
+    Ok(())
+}
+
+#[tokio::test]
+async fn crm_should_open_the_circuit_of_a_failing_service() -> Result<()> {
+    let config = ClientConfig {
+        retries: 0,
+        breaker_failures: 2,
+        breaker_open_ms: 200,
+        ..Default::default()
+    };
+    let flaky = FlakyUserStat::default();
+    flaky.down.store(true, Ordering::SeqCst);
+    let url = serve(flaky.clone()).await?;
+    let user_stat =
+        UserStatV1::try_new(&url.as_str().into(), None, ClientAuth::default(), &config)?;
+    let closed = user_stat.circuit.watch();
+
+    for _ in 0..2 {
+        let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+        assert_eq!(code(result), Code::Unavailable);
+    }
+    assert!(user_stat.circuit.is_open());
+    assert!(!*closed.borrow());
+    // failing at once, user-stat is not called
+    let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+    assert_eq!(code(result), Code::Unavailable);
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 2);
+    assert!(camp_core::metrics::gather()
+        .contains(r#"grpc_client_circuit_rejected_total{service="user_stat"}"#));
+
+    flaky.down.store(false, Ordering::SeqCst);
+    sleep(Duration::from_millis(250)).await;
+    let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+    assert_eq!(code(result), Code::Ok);
+    assert!(!user_stat.circuit.is_open());
+    assert!(*closed.borrow());
+    Ok(())
+}
+
+#[tokio::test]
+async fn crm_should_give_up_calls_past_their_deadline() -> Result<()> {
+    let config = ClientConfig {
+        deadline_ms: 100,
+        retries: 1,
+        ..Default::default()
+    };
+    let flaky = FlakyUserStat {
+        delay: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let url = serve(flaky.clone()).await?;
+    let user_stat =
+        UserStatV1::try_new(&url.as_str().into(), None, ClientAuth::default(), &config)?;
+    let result = user_stat
+        .get_lasted_visit_but_not_finished(Utc::now())
+        .await;
+    assert_eq!(code(result), Code::DeadlineExceeded);
+    // a query past its deadline is sent again
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn crm_should_balance_across_serving_endpoints() -> Result<()> {
+    let config = ClientConfig {
+        backoff_ms: 10,
+        health_check_ms: 50,
+        ..Default::default()
+    };
+    let (first, second) = (FlakyUserStat::default(), FlakyUserStat::default());
+    let (stop, stopped) = oneshot::channel::<()>();
+    let signal = async {
+        let _ = stopped.await;
+    };
+    let listener = TcpListener::bind("[::1]:0").await?;
+    let first_url = serve_until(first.clone(), listener, &ServerConfig::default(), signal).await?;
+    let second_url = serve(second.clone()).await?;
+    // nothing listens on the third one, it is never called
+    let endpoints = Endpoints::Urls(vec![
+        first_url,
+        second_url,
+        format!("http://[::1]:{}", unused_port()?),
+    ]);
+    let user_stat = UserStatV1::try_new(&endpoints, None, ClientAuth::default(), &config)?;
+    // both are called, but for once in 2^99 times
+    for _ in 0..100 {
+        let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+        assert_eq!(code(result), Code::Ok);
+    }
+    let (first_calls, second_calls) = (
+        first.calls.load(Ordering::SeqCst),
+        second.calls.load(Ordering::SeqCst),
+    );
+    assert!(first_calls > 0 && second_calls > 0);
+    assert_eq!(first_calls + second_calls, 100);
+
+    // the first one reports not serving on shutdown, then stops
+    stop.send(()).unwrap();
+    sleep(Duration::from_millis(200)).await;
+    for _ in 0..5 {
+        let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+        assert_eq!(code(result), Code::Ok);
+    }
+    assert_eq!(first.calls.load(Ordering::SeqCst), first_calls);
+    assert_eq!(second.calls.load(Ordering::SeqCst), second_calls + 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn crm_should_give_up_calls_while_no_endpoint_is_healthy() -> Result<()> {
+    let config = ClientConfig {
+        deadline_ms: 100,
+        retries: 1,
+        backoff_ms: 10,
+        health_check_ms: 50,
+        ..Default::default()
+    };
+    // nothing listens on either of them
+    let endpoints = Endpoints::Urls(vec![
+        format!("http://[::1]:{}", unused_port()?),
+        format!("http://[::1]:{}", unused_port()?),
+    ]);
+    let user_stat = UserStatV1::try_new(&endpoints, None, ClientAuth::default(), &config)?;
+    let started = Instant::now();
+    let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+    assert_eq!(code(result), Code::DeadlineExceeded);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn crm_should_call_the_addresses_a_name_resolves_to() -> Result<()> {
+    let flaky = FlakyUserStat::default();
+    let server = ServerConfig {
+        host: Ipv4Addr::LOCALHOST.into(),
+        ..Default::default()
+    };
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let port = listener.local_addr()?.port();
+    serve_until(flaky.clone(), listener, &server, std::future::pending()).await?;
+    let endpoints = Endpoints::Dns {
+        dns: format!("http://localhost:{}", port),
+        refresh_ms: 1000,
+    };
+    let user_stat =
+        UserStatV1::try_new(&endpoints, None, ClientAuth::default(), &Default::default())?;
+    let result = user_stat.get_new_user_stream(Utc::now(), Utc::now()).await;
+    assert_eq!(code(result), Code::Ok);
+    assert_eq!(flaky.calls.load(Ordering::SeqCst), 1);
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use camp_core::{
+    auth::ClientAuth,
+    client::ClientConfig,
+    core_types::PinBoxTonicStream,
+    server::{GrpcServer, ServerConfig},
+    tls::{ClientTlsConfig, ServerTlsConfig},
+};
+use camp_crm::services::{UserStat, UserStatV1};
+use camp_user_stat::pb::user_stat::{
+    user_stat_server::{UserStat as UserStatGrpc, UserStatServer},
+    QueryRequest, RawQueryRequest, User,
+};
+use chrono::Utc;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, SanType};
+use std::time::Duration;
+use tokio::{net::TcpListener, time::sleep};
+use tokio_stream::StreamExt;
+use tonic::{async_trait, Code, Request, Response, Status};
+
+/// user-stat answering every query with the same user, without a database
+struct FakeUserStat;
+
+#[async_trait]
+impl UserStatGrpc for FakeUserStat {
+    type QueryStream = PinBoxTonicStream<User>;
+    type RawQueryStream = PinBoxTonicStream<User>;
+
+    async fn query(&self, _: Request<QueryRequest>) -> Result<Response<Self::QueryStream>, Status> {
+        let user = User {
+            email: "tls@example.com".to_string(),
+            name: "tls".to_string(),
+            ..Default::default()
+        };
+        Ok(Response::new(Box::pin(tokio_stream::iter([Ok(user)]))))
+    }
+
+    async fn raw_query(
+        &self,
+        _: Request<RawQueryRequest>,
+    ) -> Result<Response<Self::RawQueryStream>, Status> {
+        Ok(Response::new(Box::pin(tokio_stream::empty())))
+    }
+}
+
+fn ca() -> Certificate {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "camp test ca");
+    Certificate::from_params(params).unwrap()
+}
+
+/// PEM certificate and key of `localhost` with the SPIFFE id, signed by `ca`
+fn issue(ca: &Certificate, spiffe_id: &str) -> (String, String) {
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]);
+    params
+        .subject_alt_names
+        .push(SanType::URI(spiffe_id.to_string()));
+    let cert = Certificate::from_params(params).unwrap();
+    (
+        cert.serialize_pem_with_signer(ca).unwrap(),
+        cert.serialize_private_key_pem(),
+    )
+}
+
+fn client_tls(ca: &Certificate, spiffe_id: Option<&str>) -> ClientTlsConfig {
+    let (cert, key) = match spiffe_id {
+        Some(spiffe_id) => {
+            let (cert, key) = issue(ca, spiffe_id);
+            (Some(cert), Some(key))
+        }
+        None => (None, None),
+    };
+    ClientTlsConfig {
+        ca: ca.serialize_pem().unwrap(),
+        cert,
+        key,
+        domain: Some("localhost".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn crm_should_call_user_stat_over_mtls() -> Result<()> {
+    let ca = ca();
+    let (cert, key) = issue(&ca, "spiffe://camp.local/user-stat");
+    let config = ServerConfig {
+        tls: Some(ServerTlsConfig {
+            cert,
+            key,
+            ca: Some(ca.serialize_pem()?),
+            client_auth_optional: false,
+            allowed_ids: vec!["spiffe://camp.local/crm".to_string()],
+        }),
+        ..Default::default()
+    };
+    let listener = TcpListener::bind("[::1]:0").await?;
+    let port = listener.local_addr()?.port();
+    let server = GrpcServer::new("user_stat", 0, &config)?
+        .listener(listener)
+        .add_service(UserStatServer::new(FakeUserStat));
+    tokio::spawn(server.serve_with_shutdown(std::future::pending()));
+    sleep(Duration::from_millis(100)).await;
+    let url = format!("https://[::1]:{}", port);
+    let client = ClientConfig::default();
+
+    let tls = client_tls(&ca, Some("spiffe://camp.local/crm"));
+    let user_stat = UserStatV1::try_new(
+        &url.as_str().into(),
+        Some(&tls),
+        ClientAuth::default(),
+        &client,
+    )?;
+    let users: Vec<User> = user_stat
+        .get_new_user_stream(Utc::now(), Utc::now())
+        .await?
+        .collect::<Result<_, _>>()
+        .await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].email, "tls@example.com");
+
+    // a certificate of the CA, but not of an allowed service
+    let tls = client_tls(&ca, Some("spiffe://camp.local/notification"));
+    let user_stat = UserStatV1::try_new(
+        &url.as_str().into(),
+        Some(&tls),
+        ClientAuth::default(),
+        &client,
+    )?;
+    let Err(camp_crm::services::ServiceError::UserStat(e)) =
+        user_stat.get_new_user_stream(Utc::now(), Utc::now()).await
+    else {
+        panic!("a client which is not allowed got an answer");
+    };
+    let camp_crm::services::user_stat::UserError::GrpcStatus(status) = e;
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    // no client certificate, the handshake fails
+    let tls = client_tls(&ca, None);
+    let user_stat = UserStatV1::try_new(
+        &url.as_str().into(),
+        Some(&tls),
+        ClientAuth::default(),
+        &client,
+    )?;
+    assert!(user_stat
+        .get_new_user_stream(Utc::now(), Utc::now())
+        .await
+        .is_err());
+
+    // plaintext is refused before connecting
+    assert!(UserStatV1::try_new(
+        &format!("http://[::1]:{}", port).as_str().into(),
+        Some(&tls),
+        ClientAuth::default(),
+        &client
+    )
+    .is_err());
+    Ok(())
+}
@@ -1,6 +1,9 @@
-use std::pin::Pin;
+use std::{pin::Pin, sync::LazyLock};
 
-use camp_core::core_fake::{before, Int, TimeStampBetween, VecFaker};
+use camp_core::{
+    core_fake::{before, Int, TimeStampBetween, VecFaker},
+    metrics::{counter, IntCounter},
+};
 use fake::{
     faker::{lorem::zh_cn::Sentence, name::zh_cn::Name},
     Dummy, Fake, Faker, Rng,
@@ -9,7 +12,7 @@ use futures::Stream;
 use tokio::sync::mpsc;
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::{Request, Response, Status, Streaming};
-use tracing::info;
+use tracing::{info, info_span, Instrument, Span};
 
 use crate::pb::metadata::{
     metadata_server::Metadata, Content, ContentType, MaterializeRequest, Publisher,
@@ -60,6 +63,13 @@ impl Dummy<Faker> for Content {
 
 pub struct MetadataGRPC;
 
+static MATERIALIZED: LazyLock<IntCounter> = LazyLock::new(|| {
+    counter(
+        "metadata_contents_materialized_total",
+        "contents materialized and sent to the clients",
+    )
+});
+
 type ServiceResult<T> = Result<Response<T>, Status>;
 type ResponseStream = Pin<Box<dyn Stream<Item = Result<Content, Status>> + Send>>;
 
@@ -73,22 +83,29 @@ impl Metadata for MetadataGRPC {
     ) -> ServiceResult<Self::MaterializeStream> {
         let mut stream = request.into_inner();
         let (tx, rx) = mpsc::channel(4);
-        tokio::spawn(async move {
-            let uuid = uuid::Uuid::new_v4();
-            info!("metadata streaming request start {:?}", uuid);
-            let mut cnt = 0;
-            while let Some(req) = stream.next().await {
-                let req = req.unwrap();
-                let mut content: Content = Faker.fake();
-                content.id = req.id;
-                tx.send(Ok(content)).await.unwrap();
-                cnt += 1;
+        tokio::spawn(
+            async move {
+                let uuid = uuid::Uuid::new_v4();
+                info!("metadata streaming request start {:?}", uuid);
+                let mut cnt = 0;
+                while let Some(req) = stream.next().await {
+                    let req = req.unwrap();
+                    let content = info_span!("content", content.id = req.id).in_scope(|| {
+                        let mut content: Content = Faker.fake();
+                        content.id = req.id;
+                        content
+                    });
+                    tx.send(Ok(content)).await.unwrap();
+                    MATERIALIZED.inc();
+                    cnt += 1;
+                }
+                info!(
+                    "metadata streaming request {:?} end, which totally send {:?}",
+                    uuid, cnt
+                );
             }
-            info!(
-                "metadata streaming request {:?} end, which totally send {:?}",
-                uuid, cnt
-            );
-        });
+            .instrument(Span::current()),
+        );
         let output_stream = ReceiverStream::new(rx);
         Ok(Response::new(Box::pin(output_stream)))
     }
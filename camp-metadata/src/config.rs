@@ -1,17 +1,39 @@
+use anyhow::Result;
+use camp_core::{
+    auth::AuthConfig, config::ConfigLoader, metrics::MetricsConfig, server::ServerConfig,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AppConfig {
     pub server: Server,
-    pub auth: Auth,
+    /// tokens required by the server
+    pub auth: AuthConfig,
+    /// the `/metrics` http endpoint, not served if unset
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Server {
     pub port: u16,
+    #[serde(flatten)]
+    pub server: ServerConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Auth {
-    pub pk: String,
+impl AppConfig {
+    /// `auth.pk` can be read from a file with `auth.pk: {file: path}`, or
+    /// `CAMP_METADATA__AUTH__PK__FILE=path`
+    pub fn load() -> Result<Self> {
+        let config = ConfigLoader::new("metadata")
+            .from_process()
+            .paths([
+                "../camp-metadata/metadata.yml",
+                "./camp-metadata/metadata.yml",
+                "./metadata.yml",
+                "/etc/config/metadata.yml",
+            ])
+            .load()?;
+        Ok(config)
+    }
 }
@@ -1,18 +1,18 @@
 use anyhow::Result;
-use camp_core::config::config_load;
-use std::{net::ToSocketAddrs as _, time::Duration};
+use std::time::Duration;
 use tokio::time::sleep;
 use tokio_stream::{Stream, StreamExt};
-use tonic::transport::Server;
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+use tonic::{transport::Channel, Code};
+use tracing::info;
 
+use camp_core::{
+    auth::{ClientAuth, ClientAuthConfig},
+    telemetry,
+};
 use camp_metadata::{
-    abi::MetadataGRPC,
     config::AppConfig,
-    pb::metadata::{
-        metadata_client::MetadataClient, metadata_server::MetadataServer, MaterializeRequest,
-    },
+    metadata_server,
+    pb::metadata::{metadata_client::MetadataClient, MaterializeRequest},
 };
 
 fn no_end_stream() -> impl Stream<Item = MaterializeRequest> {
@@ -25,7 +25,25 @@ fn no_end_stream() -> impl Stream<Item = MaterializeRequest> {
 
 pub async fn echo_metadata(config: &AppConfig) -> Result<()> {
     let grpc_url = format!("http://[::1]:{}", config.server.port);
-    let mut client = MetadataClient::connect(grpc_url).await?;
+    let auth = ClientAuth::try_new(&ClientAuthConfig {
+        algorithm: config.auth.algorithm,
+        sk: Some(config.auth.pk.clone()),
+        token: None,
+        subject: "crm".to_string(),
+        issuer: None,
+        scopes: vec![],
+        ttl_secs: 60,
+    })?;
+    let channel = Channel::from_shared(grpc_url)?.connect().await?;
+
+    let mut anonymous = MetadataClient::new(channel.clone());
+    let status = anonymous
+        .materialize(tonic::Request::new(no_end_stream()))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut client = MetadataClient::with_interceptor(channel, auth);
     let request = tonic::Request::new(no_end_stream());
     let mut stream = client.materialize(request).await?.into_inner();
 
@@ -37,22 +55,10 @@ pub async fn echo_metadata(config: &AppConfig) -> Result<()> {
 
 #[tokio::test]
 async fn main() -> Result<()> {
-    let layer = Layer::new().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
-    let server = MetadataGRPC {};
-    let config: AppConfig = config_load(vec![
-        "./camp-metadata/metadata.yml".to_string(),
-        "./metadata.yml".to_string(),
-        "/etc/config/metadata.yml".to_string(),
-    ])?;
-    let addr = format!("[::1]:{:?}", config.server.port);
+    let _telemetry = telemetry::init("metadata")?;
+    let config = AppConfig::load()?;
     info!("grpc server port: {:?}", config.server.port);
-    tokio::spawn(async move {
-        Server::builder()
-            .add_service(MetadataServer::new(server))
-            .serve(addr.to_socket_addrs().unwrap().next().unwrap())
-            .await
-    });
+    tokio::spawn(metadata_server(&config)?.serve());
     sleep(Duration::from_micros(1)).await;
     echo_metadata(&config).await?;
     Ok(())
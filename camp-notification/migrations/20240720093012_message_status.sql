@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TYPE message_status AS enum ('accepted', 'sending', 'sent', 'stored', 'failed', 'dead_lettered');
+
+-- messages were only saved once sending had failed
+ALTER TABLE messages ADD COLUMN status message_status DEFAULT 'stored' NOT NULL;
+ALTER TABLE messages ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at AT TIME ZONE 'UTC';
+ALTER TABLE messages ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at AT TIME ZONE 'UTC';
+
+CREATE INDEX messages_id_idx ON messages(id);
+CREATE INDEX messages_sender_created_at_idx ON messages(sender, created_at);
+CREATE INDEX messages_recipients_idx ON messages USING GIN(recipients);
+CREATE INDEX messages_device_id_idx ON messages(device_id);
+
+CREATE TABLE message_events (
+  id BIGSERIAL PRIMARY KEY,
+  message_id VARCHAR(64) NOT NULL,
+  status message_status NOT NULL,
+  provider_response TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX message_events_message_id_idx ON message_events(message_id, created_at);
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- keep the latest row of messages saved more than once before ids were unique
+DELETE FROM messages a USING messages b
+WHERE a.id = b.id AND (a.updated_at, a.ctid) < (b.updated_at, b.ctid);
+
+DROP INDEX messages_id_idx;
+ALTER TABLE messages ADD CONSTRAINT messages_pkey PRIMARY KEY (id);
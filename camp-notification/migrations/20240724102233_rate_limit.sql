@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE rate_limit_buckets (
+  key VARCHAR(255) PRIMARY KEY,
+  tokens DOUBLE PRECISION NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TYPE message_status ADD VALUE 'scheduled';
+ALTER TYPE message_status ADD VALUE 'cancelled';
+
+ALTER TABLE messages ADD COLUMN send_at TIMESTAMPTZ;
+
+CREATE INDEX messages_send_at_idx ON messages(send_at);
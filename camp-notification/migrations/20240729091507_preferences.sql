@@ -0,0 +1,27 @@
+-- Add migration script here
+ALTER TYPE message_status ADD VALUE 'suppressed';
+
+CREATE TABLE preferences (
+  user_id VARCHAR(64) PRIMARY KEY,
+  -- quiet hours are disabled if timezone is null
+  timezone VARCHAR(64),
+  quiet_start_hour INT NOT NULL DEFAULT 0,
+  quiet_end_hour INT NOT NULL DEFAULT 0,
+  unsubscribed BOOLEAN NOT NULL DEFAULT FALSE,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE preference_channels (
+  user_id VARCHAR(64) NOT NULL REFERENCES preferences(user_id) ON DELETE CASCADE,
+  channel message_type NOT NULL,
+  enabled BOOLEAN NOT NULL,
+  PRIMARY KEY (user_id, channel)
+);
+
+-- an address belongs to one user only
+CREATE TABLE preference_addresses (
+  address VARCHAR(255) PRIMARY KEY,
+  user_id VARCHAR(64) NOT NULL REFERENCES preferences(user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX preference_addresses_user_id_idx ON preference_addresses(user_id);
@@ -0,0 +1,29 @@
+-- Add migration script here
+CREATE TABLE inbox (
+  message_id VARCHAR(64) PRIMARY KEY,
+  device_id VARCHAR(64) NOT NULL,
+  -- owner of the device by the preferences, if known when the message was delivered
+  user_id VARCHAR(64),
+  sender VARCHAR(64) NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  body TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  read_at TIMESTAMPTZ,
+  opened_at TIMESTAMPTZ,
+  deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX inbox_device_id_idx ON inbox(device_id, created_at) WHERE deleted_at IS NULL;
+CREATE INDEX inbox_user_id_idx ON inbox(user_id, created_at) WHERE deleted_at IS NULL;
+
+CREATE TYPE inbox_event_type AS enum ('delivered', 'read', 'opened', 'deleted');
+
+-- engagement with in-app messages, for measuring campaigns
+CREATE TABLE inbox_events (
+  id BIGSERIAL PRIMARY KEY,
+  message_id VARCHAR(64) NOT NULL,
+  event inbox_event_type NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX inbox_events_message_id_idx ON inbox_events(message_id, created_at);
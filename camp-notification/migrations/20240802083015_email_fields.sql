@@ -0,0 +1,8 @@
+-- alternative body, extra addresses, headers and attachments of emails
+ALTER TABLE messages
+  ADD COLUMN html_body TEXT,
+  ADD COLUMN cc VARCHAR(128)[],
+  ADD COLUMN bcc VARCHAR(128)[],
+  ADD COLUMN reply_to VARCHAR(128),
+  ADD COLUMN headers JSONB,
+  ADD COLUMN attachments JSONB;
@@ -0,0 +1,8 @@
+CREATE TYPE suppression_reason AS enum ('bounced', 'complained', 'manual');
+
+-- addresses no message is delivered to
+CREATE TABLE suppressions (
+  address VARCHAR(128) PRIMARY KEY,
+  reason suppression_reason NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
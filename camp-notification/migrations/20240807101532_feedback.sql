@@ -0,0 +1,18 @@
+ALTER TYPE message_status ADD VALUE 'delivered';
+ALTER TYPE message_status ADD VALUE 'bounced';
+ALTER TYPE message_status ADD VALUE 'complained';
+
+CREATE TYPE feedback_type AS enum ('delivered', 'soft_bounce', 'hard_bounce', 'complaint');
+
+-- bounces, complaints and delivery receipts reported by providers
+CREATE TABLE feedback (
+  id BIGSERIAL PRIMARY KEY,
+  message_id VARCHAR(64) NOT NULL,
+  recipient VARCHAR(128) NOT NULL,
+  type feedback_type NOT NULL,
+  provider VARCHAR(64) NOT NULL,
+  description TEXT NOT NULL DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX feedback_message_id_idx ON feedback(message_id, created_at);
@@ -0,0 +1,10 @@
+-- messages sent to many recipients by SendBatch
+CREATE TABLE templates (
+  name VARCHAR(64) PRIMARY KEY,
+  channel message_type NOT NULL,
+  sender VARCHAR(64) NOT NULL,
+  subject TEXT NOT NULL DEFAULT '',
+  body TEXT NOT NULL,
+  html_body TEXT,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,4 @@
+CREATE TYPE message_priority AS enum ('high', 'normal', 'low');
+
+-- lane a message is dispatched in, kept for scheduled messages
+ALTER TABLE messages ADD COLUMN priority message_priority NOT NULL DEFAULT 'normal';
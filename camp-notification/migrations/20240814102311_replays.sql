@@ -0,0 +1,14 @@
+-- audit of the messages an operator sent again
+CREATE TABLE replays (
+  id BIGSERIAL PRIMARY KEY,
+  message_id VARCHAR(64) NOT NULL,
+  operator VARCHAR(64) NOT NULL,
+  reason TEXT NOT NULL DEFAULT '',
+  -- recipients the message was sent to instead of the original ones
+  recipients VARCHAR(128)[],
+  outcome TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX replays_message_id_idx ON replays(message_id, created_at);
+CREATE INDEX messages_status_updated_at_idx ON messages(status, updated_at);
@@ -0,0 +1,2 @@
+-- json payloads posted to partner endpoints, the endpoint name is kept in recipients
+ALTER TYPE message_type ADD VALUE 'webhook';
@@ -0,0 +1,4 @@
+-- the recipient-local window a scheduled message is sent in, kept to move a retry into it
+ALTER TABLE messages ADD COLUMN send_window JSONB;
+-- when a scheduled message was claimed, a claim older than the lease is taken over
+ALTER TABLE messages ADD COLUMN claimed_at TIMESTAMPTZ;
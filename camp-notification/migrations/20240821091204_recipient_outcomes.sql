@@ -0,0 +1,8 @@
+-- the furthest outcome the providers reported for every recipient of a message
+CREATE TABLE recipient_outcomes (
+  message_id VARCHAR(64) NOT NULL,
+  recipient VARCHAR(128) NOT NULL,
+  status message_status NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (message_id, recipient)
+);
@@ -0,0 +1,123 @@
+use futures::{stream, StreamExt as _};
+use sha2::{Digest as _, Sha256};
+use tonic::Status;
+use tracing::info;
+
+use super::NotificationGrpc;
+use crate::{
+    pb::notification::{
+        BatchRecipient, BatchResult, SendBatchRequest, SendBatchResponse, SendRequest,
+        SendResponseType,
+    },
+    services::template::Template,
+};
+
+/// hex digits of the digest of the address in the id of a message of a batch
+const DIGEST_LEN: usize = 16;
+/// message ids are at most 64 characters
+const MAX_BATCH_ID_LEN: usize = 64 - 1 - DIGEST_LEN;
+
+/// id of the message of a batch to a normalized address
+fn batch_message_id(batch_id: &str, address: &str) -> String {
+    let digest = hex::encode(Sha256::digest(address.as_bytes()));
+    format!("{}-{}", batch_id, &digest[..DIGEST_LEN])
+}
+
+impl NotificationGrpc {
+    /// render the template for every recipient and send the messages, a failed message does
+    /// not fail the batch
+    pub async fn send_batch(&self, mut req: SendBatchRequest) -> Result<SendBatchResponse, Status> {
+        if req.batch_id.is_empty() || req.batch_id.len() > MAX_BATCH_ID_LEN {
+            return Err(Status::invalid_argument(format!(
+                "batch_id is required, at most {} characters",
+                MAX_BATCH_ID_LEN
+            )));
+        }
+        if req.recipients.is_empty() {
+            return Err(Status::invalid_argument(
+                "at least one recipient is required",
+            ));
+        }
+        let batch = self.batch.borrow().clone();
+        if req.recipients.len() > batch.max_recipients {
+            return Err(Status::invalid_argument(format!(
+                "a batch has at most {} recipients",
+                batch.max_recipients
+            )));
+        }
+        let template = self.template.get(&req.template).await?;
+        info!(
+            "sending batch {:?} of template {:?} to {} recipients",
+            req.batch_id,
+            template.name,
+            req.recipients.len()
+        );
+        let recipients = std::mem::take(&mut req.recipients);
+        let mut results: Vec<(usize, BatchResult)> =
+            stream::iter(recipients.into_iter().enumerate())
+                .map(|(i, recipient)| {
+                    let address = self
+                        .recipients
+                        .normalized(template.channel, &recipient.address);
+                    let message_id = batch_message_id(&req.batch_id, &address);
+                    let sent = self.send_rendered(&template, message_id, recipient, &req);
+                    async move { (i, sent.await) }
+                })
+                .buffer_unordered(batch.concurrency.max(1))
+                .collect()
+                .await;
+        results.sort_by_key(|(i, _)| *i);
+
+        let mut resp = SendBatchResponse {
+            batch_id: req.batch_id,
+            ..Default::default()
+        };
+        for (_, result) in results {
+            match result.status() {
+                SendResponseType::Failed => resp.failed += 1,
+                SendResponseType::Suppressed | SendResponseType::Throttled => resp.skipped += 1,
+                _ => resp.accepted += 1,
+            }
+            resp.results.push(result);
+        }
+        Ok(resp)
+    }
+
+    /// render the template for one recipient and send it like a single message
+    async fn send_rendered(
+        &self,
+        template: &Template,
+        message_id: String,
+        recipient: BatchRecipient,
+        batch: &SendBatchRequest,
+    ) -> BatchResult {
+        let mut result = BatchResult {
+            message_id: message_id.clone(),
+            address: recipient.address.clone(),
+            ..Default::default()
+        };
+        let sent = match template.render(&recipient.variables) {
+            Ok(rendered) => {
+                let req = SendRequest {
+                    msg: Some(rendered.to_msg(message_id, recipient.address)),
+                    send_at: batch.send_at.clone(),
+                    priority: batch.priority,
+                    ..Default::default()
+                };
+                self.notification(req).await
+            }
+            Err(name) => Err(Status::invalid_argument(format!(
+                "no value for the placeholder {:?}",
+                name
+            ))),
+        };
+        match sent {
+            Ok(resp) => result.status = resp.status,
+            Err(status) => {
+                result.status = SendResponseType::Failed as i32;
+                result.error = status.message().to_string();
+            }
+        }
+        result
+    }
+}
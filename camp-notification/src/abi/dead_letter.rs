@@ -0,0 +1,343 @@
+use camp_core::proto::{ts_to_utc, utc_to_ts};
+use chrono::Utc;
+use futures::{stream, StreamExt as _};
+use std::sync::Arc;
+use tonic::{async_trait, Request, Response, Status};
+use tracing::{info, warn};
+
+use super::NotificationGrpc;
+use crate::{
+    pb::notification::{
+        self, notification_admin_server::NotificationAdmin, send_request::Msg,
+        GetDeadLetterRequest, ListDeadLettersRequest, ListDeadLettersResponse, ReplayRecord,
+        ReplayRequest, ReplayResponse, ReplayResult, SendRequest, SendResponse, SendResponseType,
+    },
+    services::{
+        dead_letter::{
+            DeadLetter, DeadLetterError, DeadLetterQuery, DeadLetters, MessageStatus, MessageType,
+            Replay,
+        },
+        ServiceError,
+    },
+};
+
+/// dead letters listed at a time when replaying the ones matching a filter
+const MATCHING_PAGE: i64 = 100;
+
+/// outcome of a replay recorded when it starts, until the message is sent
+const REPLAYING: &str = "REPLAYING";
+
+#[derive(Clone)]
+pub struct DeadLetterGrpc(pub Arc<Box<dyn DeadLetters>>);
+
+impl From<notification::MessageStatus> for MessageStatus {
+    fn from(value: notification::MessageStatus) -> Self {
+        use notification::MessageStatus as Pb;
+        match value {
+            Pb::Accepted => Self::Accepted,
+            Pb::Sending => Self::Sending,
+            Pb::Sent => Self::Sent,
+            Pb::Stored => Self::Stored,
+            Pb::Failed => Self::Failed,
+            Pb::DeadLettered => Self::DeadLettered,
+            Pb::Scheduled => Self::Scheduled,
+            Pb::Cancelled => Self::Cancelled,
+            Pb::Suppressed => Self::Suppressed,
+            Pb::Delivered => Self::Delivered,
+            Pb::Bounced => Self::Bounced,
+            Pb::Complained => Self::Complained,
+        }
+    }
+}
+
+impl From<ListDeadLettersRequest> for DeadLetterQuery {
+    fn from(value: ListDeadLettersRequest) -> Self {
+        let r#type = match value.r#type().into() {
+            MessageType::Unknown => None,
+            r#type => Some(r#type),
+        };
+        Self {
+            statuses: value.statuses().map(Into::into).collect(),
+            r#type,
+            start: value.start.as_ref().and_then(ts_to_utc),
+            end: value.end.as_ref().and_then(ts_to_utc),
+            error: (!value.error.is_empty()).then_some(value.error),
+            limit: value.limit as i64,
+            offset: value.offset as i64,
+        }
+    }
+}
+
+impl From<DeadLetter> for notification::DeadLetter {
+    fn from(value: DeadLetter) -> Self {
+        let priority = notification::Priority::from(value.message.priority) as i32;
+        Self {
+            message: Some(value.message.clone().into()),
+            request: Some(SendRequest {
+                msg: Some(value.message.into()),
+                priority,
+                ..Default::default()
+            }),
+            error: value.error.unwrap_or_default(),
+            replays: vec![],
+        }
+    }
+}
+
+impl From<Replay> for ReplayRecord {
+    fn from(value: Replay) -> Self {
+        Self {
+            operator: value.operator,
+            reason: value.reason,
+            recipients: value.recipients.unwrap_or_default(),
+            outcome: value.outcome,
+            created_at: Some(utc_to_ts(value.created_at)),
+        }
+    }
+}
+
+impl Msg {
+    /// the message sent to `recipients` instead, the first one is the device of an in-app message
+    /// or the endpoint of a webhook
+    fn with_recipients(self, mut recipients: Vec<String>) -> Self {
+        match self {
+            // the cc and bcc go with the original recipients
+            Msg::Email(mut msg) => {
+                msg.recipients = recipients;
+                msg.cc.clear();
+                msg.bcc.clear();
+                Msg::Email(msg)
+            }
+            Msg::Sms(mut msg) => {
+                msg.recipients = recipients;
+                Msg::Sms(msg)
+            }
+            Msg::InApp(mut msg) => {
+                msg.device_id = recipients.swap_remove(0);
+                Msg::InApp(msg)
+            }
+            Msg::Webhook(mut msg) => {
+                msg.endpoint = recipients.swap_remove(0);
+                Msg::Webhook(msg)
+            }
+        }
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        match self {
+            Msg::Email(msg) => msg.recipients.clone(),
+            Msg::Sms(msg) => msg.recipients.clone(),
+            Msg::InApp(msg) => vec![msg.device_id.clone()],
+            Msg::Webhook(msg) => vec![msg.endpoint.clone()],
+        }
+    }
+}
+
+fn dead_letter_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::DeadLetter(DeadLetterError::NotFound(id)) => {
+            Status::not_found(format!("message {} not found", id))
+        }
+        ServiceError::DeadLetter(e @ DeadLetterError::NotReplayable(..)) => {
+            Status::failed_precondition(e.to_string())
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl DeadLetterGrpc {
+    /// ids of every dead letter matching `filter`, paged through regardless of its limit and
+    /// offset, before any of them is replayed
+    pub async fn matching(&self, filter: ListDeadLettersRequest) -> Result<Vec<String>, Status> {
+        let mut query = DeadLetterQuery {
+            limit: MATCHING_PAGE,
+            offset: 0,
+            ..filter.into()
+        };
+        let mut ids = vec![];
+        loop {
+            let page = self
+                .0
+                .list(query.clone())
+                .await
+                .map_err(dead_letter_status)?;
+            let last = (page.len() as i64) < query.limit;
+            ids.extend(page.into_iter().map(|m| m.message.id));
+            if last {
+                return Ok(ids);
+            }
+            query.offset += query.limit;
+        }
+    }
+
+    pub async fn list_dead_letters(
+        &self,
+        req: ListDeadLettersRequest,
+    ) -> Result<ListDeadLettersResponse, Status> {
+        let messages = self.0.list(req.into()).await.map_err(dead_letter_status)?;
+        Ok(ListDeadLettersResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub async fn get_dead_letter(
+        &self,
+        req: GetDeadLetterRequest,
+    ) -> Result<notification::DeadLetter, Status> {
+        let (message, events, replays) = self
+            .0
+            .get(&req.message_id)
+            .await
+            .map_err(dead_letter_status)?;
+        let mut resp = notification::DeadLetter::from(message);
+        if let Some(message) = resp.message.as_mut() {
+            message.events = events.into_iter().map(Into::into).collect();
+        }
+        resp.replays = replays.into_iter().map(Into::into).collect();
+        Ok(resp)
+    }
+}
+
+impl NotificationGrpc {
+    /// send the messages again without throttling them, each replay is recorded in the audit
+    pub async fn replay_dead_letters(&self, req: ReplayRequest) -> Result<ReplayResponse, Status> {
+        if req.operator.is_empty() {
+            return Err(Status::invalid_argument("operator is required"));
+        }
+        if !req.recipients.is_empty() && req.message_ids.len() != 1 {
+            return Err(Status::invalid_argument(
+                "recipients can only be edited when replaying a single message",
+            ));
+        }
+        let ids = match (req.message_ids.is_empty(), &req.filter) {
+            (false, _) => req.message_ids.clone(),
+            (true, Some(filter)) => self.dead_letter.matching(filter.clone()).await?,
+            (true, None) => {
+                return Err(Status::invalid_argument(
+                    "either message_ids or filter is required",
+                ))
+            }
+        };
+        info!(
+            "{} replays {} messages: {:?}",
+            req.operator,
+            ids.len(),
+            req.reason
+        );
+        let concurrency = self.batch.borrow().concurrency.max(1);
+        let results = stream::iter(ids)
+            .map(|id| self.replay(id, &req))
+            .buffered(concurrency)
+            .collect()
+            .await;
+        Ok(ReplayResponse { results })
+    }
+
+    async fn replay(&self, id: String, req: &ReplayRequest) -> ReplayResult {
+        let mut replay = Replay {
+            message_id: id.clone(),
+            operator: req.operator.clone(),
+            reason: req.reason.clone(),
+            recipients: None,
+            outcome: REPLAYING.to_string(),
+            created_at: Utc::now(),
+        };
+        let mut started = None;
+        let sent = self.replay_one(&mut replay, req, &mut started).await;
+        let mut result = ReplayResult {
+            message_id: id,
+            ..Default::default()
+        };
+        let outcome = match sent {
+            Ok(resp) => {
+                result.status = resp.status;
+                resp.status().as_str_name().to_string()
+            }
+            Err(status) => {
+                result.status = SendResponseType::Failed as i32;
+                result.error = status.message().to_string();
+                status.message().to_string()
+            }
+        };
+        let recorded = match started {
+            Some(replay_id) => self.dead_letter.0.finish_replay(replay_id, &outcome).await,
+            None => {
+                replay.outcome = outcome;
+                self.dead_letter.0.record_replay(replay).await
+            }
+        };
+        if let Err(e) = recorded {
+            warn!("failed to record replay of {}: {}", result.message_id, e);
+        }
+        result
+    }
+
+    /// the recipients of `replay` are set to the normalized ones the message is sent to if
+    /// edited, `started` to the id of the replay once the message is claimed
+    async fn replay_one(
+        &self,
+        replay: &mut Replay,
+        req: &ReplayRequest,
+        started: &mut Option<i64>,
+    ) -> Result<SendResponse, Status> {
+        let (message, _, _) = self
+            .dead_letter
+            .0
+            .get(&replay.message_id)
+            .await
+            .map_err(dead_letter_status)?;
+        let mut msg = Msg::from(message.message);
+        if !req.recipients.is_empty() {
+            msg = msg.with_recipients(req.recipients.clone());
+            self.recipients.normalize(&mut msg).await?;
+            replay.recipients = Some(msg.recipients());
+        }
+        match &msg {
+            Msg::Email(email) => self.email.check(email).await?,
+            Msg::Webhook(webhook) => self.webhook.check(webhook).await?,
+            Msg::Sms(_) | Msg::InApp(_) => {}
+        }
+        let (message, replay_id) = self
+            .dead_letter
+            .0
+            .start_replay(replay)
+            .await
+            .map_err(dead_letter_status)?;
+        *started = Some(replay_id);
+        let priority = message.priority;
+        self.dispatch(Msg::from(message), priority).await
+    }
+}
+
+#[async_trait]
+impl NotificationAdmin for NotificationGrpc {
+    async fn list_dead_letters(
+        &self,
+        request: Request<ListDeadLettersRequest>,
+    ) -> Result<Response<ListDeadLettersResponse>, Status> {
+        let resp = self
+            .dead_letter
+            .list_dead_letters(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn get_dead_letter(
+        &self,
+        request: Request<GetDeadLetterRequest>,
+    ) -> Result<Response<notification::DeadLetter>, Status> {
+        let resp = self
+            .dead_letter
+            .get_dead_letter(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn replay_dead_letters(
+        &self,
+        request: Request<ReplayRequest>,
+    ) -> Result<Response<ReplayResponse>, Status> {
+        let resp = self.replay_dead_letters(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+}
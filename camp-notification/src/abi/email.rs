@@ -5,9 +5,12 @@ use tonic::Status;
 use tracing::info;
 
 use crate::{
-    pb::notification::{EmailMessage, SendResponse, SendResponseType},
+    pb::notification::{
+        email_attachment::Source, BlobReference, EmailAttachment, EmailMessage, SendResponse,
+        SendResponseType,
+    },
     services::{
-        email::{self, Email, EmailError},
+        email::{self, Attachment, AttachmentSource, Email, EmailError},
         ServiceError,
     },
 };
@@ -15,6 +18,10 @@ use crate::{
 #[derive(Clone)]
 pub struct EmailGrpc(pub Arc<Box<dyn Email>>);
 
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
 impl From<EmailMessage> for email::EmailMessage {
     fn from(value: EmailMessage) -> Self {
         Self {
@@ -23,11 +30,62 @@ impl From<EmailMessage> for email::EmailMessage {
             id: value.message_id,
             sender: value.sender,
             recipients: value.recipients,
+            html_body: non_empty(value.html_body),
+            cc: value.cc,
+            bcc: value.bcc,
+            reply_to: non_empty(value.reply_to),
+            headers: value.headers,
+            attachments: value.attachments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<EmailAttachment> for Attachment {
+    fn from(value: EmailAttachment) -> Self {
+        // an attachment without a source has no content and is rejected by the check
+        let source = match value.source {
+            Some(Source::Content(content)) => AttachmentSource::Content(content),
+            Some(Source::Blob(blob)) => AttachmentSource::Blob {
+                url: blob.url,
+                size: blob.size,
+            },
+            None => AttachmentSource::Content(Vec::new()),
+        };
+        Self {
+            filename: value.filename,
+            content_type: value.content_type,
+            source,
+            content_id: non_empty(value.content_id),
+        }
+    }
+}
+
+impl From<Attachment> for EmailAttachment {
+    fn from(value: Attachment) -> Self {
+        let source = match value.source {
+            AttachmentSource::Content(content) => Source::Content(content),
+            AttachmentSource::Blob { url, size } => Source::Blob(BlobReference { url, size }),
+        };
+        Self {
+            filename: value.filename,
+            content_type: value.content_type,
+            source: Some(source),
+            content_id: value.content_id.unwrap_or_default(),
         }
     }
 }
 
 impl EmailGrpc {
+    pub async fn check(&self, req: &EmailMessage) -> Result<(), Status> {
+        match self.0.check(&req.clone().into()).await {
+            Ok(()) => Ok(()),
+            Err(ServiceError::Email(e @ EmailError::Invalid(_))) => {
+                Err(Status::invalid_argument(e.to_string()))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
     pub async fn send_email(&self, req: EmailMessage) -> Result<SendResponse, Status> {
         info!("sending email {:?}", req.message_id);
         match self.0.send_email(req.into()).await {
@@ -35,6 +93,7 @@ impl EmailGrpc {
                 message_id: msg.id,
                 timestamp: Some(utc_to_ts(msg.timestamp)),
                 status: SendResponseType::Success as i32,
+                ..Default::default()
             }),
             Err(e) => match e {
                 ServiceError::Email(e) => match e {
@@ -42,6 +101,7 @@ impl EmailGrpc {
                         message_id: msg.id,
                         timestamp: Some(utc_to_ts(Utc::now())),
                         status: SendResponseType::Stored as i32,
+                        ..Default::default()
                     }),
                     _ => Err(Status::internal(e.to_string())),
                 },
@@ -0,0 +1,92 @@
+use chrono::Utc;
+use std::sync::Arc;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    pb::notification::{self, FeedbackResponse},
+    services::{
+        feedback::{Feedback, FeedbackError, FeedbackType, ProviderFeedback},
+        ServiceError,
+    },
+};
+
+#[derive(Clone)]
+pub struct FeedbackGrpc(pub Arc<Box<dyn Feedback>>);
+
+impl From<FeedbackType> for notification::FeedbackType {
+    fn from(value: FeedbackType) -> Self {
+        match value {
+            FeedbackType::Delivered => Self::Delivered,
+            FeedbackType::SoftBounce => Self::SoftBounce,
+            FeedbackType::HardBounce => Self::HardBounce,
+            FeedbackType::Complaint => Self::Complaint,
+        }
+    }
+}
+
+impl TryFrom<notification::ProviderFeedback> for ProviderFeedback {
+    type Error = Status;
+
+    fn try_from(value: notification::ProviderFeedback) -> Result<Self, Self::Error> {
+        let r#type = match value.r#type() {
+            notification::FeedbackType::Delivered => FeedbackType::Delivered,
+            notification::FeedbackType::SoftBounce => FeedbackType::SoftBounce,
+            notification::FeedbackType::HardBounce => FeedbackType::HardBounce,
+            notification::FeedbackType::Complaint => FeedbackType::Complaint,
+            notification::FeedbackType::Unknown => {
+                return Err(Status::invalid_argument("feedback type is required"))
+            }
+        };
+        if value.message_id.is_empty() || value.recipient.is_empty() || value.provider.is_empty() {
+            return Err(Status::invalid_argument(
+                "message_id, recipient and provider are required",
+            ));
+        }
+        Ok(Self {
+            message_id: value.message_id,
+            recipient: value.recipient,
+            r#type,
+            provider: value.provider,
+            description: value.description,
+            created_at: Utc::now(),
+        })
+    }
+}
+
+fn feedback_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Feedback(FeedbackError::NotFound(id)) => {
+            Status::not_found(format!("message {} not found", id))
+        }
+        ServiceError::Feedback(e @ FeedbackError::InvalidRecipient(..)) => {
+            Status::invalid_argument(e.to_string())
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl FeedbackGrpc {
+    pub async fn report_feedback(
+        &self,
+        req: notification::ProviderFeedback,
+    ) -> Result<FeedbackResponse, Status> {
+        self.record(req.try_into()?).await
+    }
+
+    /// record the feedback of a provider, whether it came in through grpc or a webhook
+    pub async fn record(&self, feedback: ProviderFeedback) -> Result<FeedbackResponse, Status> {
+        info!(
+            "{} reported {:?} of message {:?} for {:?}",
+            feedback.provider, feedback.r#type, feedback.message_id, feedback.recipient
+        );
+        let message_id = feedback.message_id.clone();
+        let suppressed = feedback.r#type.suppression().is_some();
+        let status = self.0.record(feedback).await.map_err(feedback_status)?;
+        Ok(FeedbackResponse {
+            message_id,
+            status: notification::MessageStatus::from(status) as i32,
+            suppressed,
+        })
+    }
+}
@@ -27,6 +27,18 @@ impl From<InAppMessage> for inapp::InAppMessage {
     }
 }
 
+impl From<inapp::InAppMessage> for InAppMessage {
+    fn from(value: inapp::InAppMessage) -> Self {
+        Self {
+            message_id: value.id,
+            device_id: value.device_id,
+            title: value.title,
+            body: value.body,
+            sender: value.sender,
+        }
+    }
+}
+
 impl InAppGrpc {
     pub async fn send_inapp(&self, req: InAppMessage) -> Result<SendResponse, Status> {
         info!("sending inapp {:?}", req.message_id);
@@ -37,6 +49,7 @@ impl InAppGrpc {
                     message_id: msg.id,
                     timestamp: Some(utc_to_ts(msg.timestamp)),
                     status: SendResponseType::Success as i32,
+                    ..Default::default()
                 })
             }
             Err(e) => match e {
@@ -46,6 +59,7 @@ impl InAppGrpc {
                         message_id: e.id,
                         timestamp: Some(utc_to_ts(Utc::now())),
                         status: SendResponseType::Failed as i32,
+                        ..Default::default()
                     })
                 }
                 _ => Err(Status::internal("Internal error")),
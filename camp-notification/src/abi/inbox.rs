@@ -0,0 +1,116 @@
+use camp_core::proto::utc_to_ts;
+use std::sync::Arc;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    pb::notification::{
+        self, DeleteMessageRequest, InboxUpdateResponse, ListInboxRequest, ListInboxResponse,
+        MarkAllReadRequest, MarkReadRequest,
+    },
+    services::{
+        inbox::{Inbox, InboxError, InboxMessage, InboxOwner},
+        ServiceError,
+    },
+};
+
+#[derive(Clone)]
+pub struct InboxGrpc(pub Arc<Box<dyn Inbox>>);
+
+impl From<InboxMessage> for notification::InboxMessage {
+    fn from(value: InboxMessage) -> Self {
+        Self {
+            message_id: value.message_id,
+            device_id: value.device_id,
+            user_id: value.user_id.unwrap_or_default(),
+            sender: value.sender,
+            title: value.title,
+            body: value.body,
+            created_at: Some(utc_to_ts(value.created_at)),
+            read_at: value.read_at.map(utc_to_ts),
+            opened_at: value.opened_at.map(utc_to_ts),
+        }
+    }
+}
+
+/// the inbox of the device, or of the user if no device is given
+fn owner(device_id: String, user_id: String) -> Option<InboxOwner> {
+    match (device_id.is_empty(), user_id.is_empty()) {
+        (false, _) => Some(InboxOwner::Device(device_id)),
+        (true, false) => Some(InboxOwner::User(user_id)),
+        (true, true) => None,
+    }
+}
+
+fn missing_owner() -> Status {
+    Status::invalid_argument("either device_id or user_id is required")
+}
+
+fn inbox_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Inbox(InboxError::NotFound(id)) => {
+            Status::not_found(format!("message {} not found in the inbox", id))
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl InboxGrpc {
+    async fn updated(
+        &self,
+        owner: &InboxOwner,
+        updated: u64,
+    ) -> Result<InboxUpdateResponse, Status> {
+        let unread_count = self.0.unread_count(owner).await.map_err(inbox_status)?;
+        Ok(InboxUpdateResponse {
+            updated: updated as u32,
+            unread_count: unread_count as u32,
+        })
+    }
+
+    pub async fn list_inbox(&self, req: ListInboxRequest) -> Result<ListInboxResponse, Status> {
+        let owner = owner(req.device_id, req.user_id).ok_or_else(missing_owner)?;
+        let messages = self
+            .0
+            .list(&owner, req.unread_only, req.limit as i64, req.offset as i64)
+            .await
+            .map_err(inbox_status)?;
+        let unread_count = self.0.unread_count(&owner).await.map_err(inbox_status)?;
+        Ok(ListInboxResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+            unread_count: unread_count as u32,
+        })
+    }
+
+    pub async fn mark_read(&self, req: MarkReadRequest) -> Result<InboxUpdateResponse, Status> {
+        let owner = owner(req.device_id, req.user_id).ok_or_else(missing_owner)?;
+        let updated = self
+            .0
+            .mark_read(&owner, &req.message_ids, req.opened)
+            .await
+            .map_err(inbox_status)?;
+        self.updated(&owner, updated).await
+    }
+
+    pub async fn mark_all_read(
+        &self,
+        req: MarkAllReadRequest,
+    ) -> Result<InboxUpdateResponse, Status> {
+        let owner = owner(req.device_id, req.user_id).ok_or_else(missing_owner)?;
+        let updated = self.0.mark_all_read(&owner).await.map_err(inbox_status)?;
+        self.updated(&owner, updated).await
+    }
+
+    pub async fn delete_message(
+        &self,
+        req: DeleteMessageRequest,
+    ) -> Result<InboxUpdateResponse, Status> {
+        info!("deleting message {:?} from the inbox", req.message_id);
+        let owner = owner(req.device_id, req.user_id).ok_or_else(missing_owner)?;
+        self.0
+            .delete(&owner, &req.message_id)
+            .await
+            .map_err(inbox_status)?;
+        self.updated(&owner, 1).await
+    }
+}
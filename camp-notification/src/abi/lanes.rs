@@ -0,0 +1,344 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::sync::{oneshot, watch};
+
+use crate::{
+    config::LanesConfig,
+    model::message::Priority,
+    pb::notification::{self, LaneStatsResponse},
+};
+
+/// the lanes in the order a free slot is offered to them
+const PRIORITIES: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+fn lane(priority: Priority) -> usize {
+    match priority {
+        Priority::High => 0,
+        Priority::Normal => 1,
+        Priority::Low => 2,
+    }
+}
+
+impl From<notification::Priority> for Priority {
+    fn from(value: notification::Priority) -> Self {
+        match value {
+            notification::Priority::High => Self::High,
+            notification::Priority::Normal => Self::Normal,
+            notification::Priority::Low => Self::Low,
+        }
+    }
+}
+
+impl From<Priority> for notification::Priority {
+    fn from(value: Priority) -> Self {
+        match value {
+            Priority::High => Self::High,
+            Priority::Normal => Self::Normal,
+            Priority::Low => Self::Low,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaneStats {
+    pub queued: u64,
+    pub in_flight: u64,
+    pub dispatched: u64,
+    pub throttled: u64,
+    pub wait_ms: u64,
+}
+
+#[derive(Default)]
+struct State {
+    /// the slots of the config last applied
+    slots: usize,
+    weights: [u32; 3],
+    /// negative while more messages are dispatched than the slots since lowered
+    free: isize,
+    waiting: [VecDeque<(Instant, oneshot::Sender<LanePermit>)>; 3],
+    /// slots a lane may still take before the credits of every lane are refilled
+    credits: [u32; 3],
+    stats: [LaneStats; 3],
+}
+
+fn weights(config: &LanesConfig) -> [u32; 3] {
+    PRIORITIES.map(|p| {
+        config
+            .weights
+            .get(&p.to_string())
+            .copied()
+            .unwrap_or(1)
+            .max(1)
+    })
+}
+
+impl State {
+    /// apply the slots and weights of `config`
+    fn update(&mut self, config: &LanesConfig) {
+        self.weights = weights(config);
+        let slots = config.slots.max(1);
+        self.free += slots as isize - self.slots as isize;
+        self.slots = slots;
+    }
+
+    /// the lane the next free slot goes to, by weighted round robin over the waiting lanes
+    fn next_lane(&mut self) -> Option<usize> {
+        let waiting: Vec<usize> = (0..3).filter(|&i| !self.waiting[i].is_empty()).collect();
+        if waiting.is_empty() {
+            return None;
+        }
+        if waiting.iter().all(|&i| self.credits[i] == 0) {
+            self.credits = self.weights;
+        }
+        let next = *waiting.iter().find(|&&i| self.credits[i] > 0)?;
+        self.credits[next] -= 1;
+        Some(next)
+    }
+}
+
+/// Dispatch slots shared by the priority lanes.
+///
+/// A free slot is taken right away while no message waits, otherwise the messages queue up in
+/// their lane and the lanes get the freed slots in proportion to their weights.
+/// The slots and weights follow the config, fewer slots apply once enough messages are done.
+#[derive(Clone)]
+pub struct Lanes {
+    config: watch::Receiver<LanesConfig>,
+    state: Arc<Mutex<State>>,
+}
+
+/// a dispatch slot, handed to the next waiting message when dropped
+pub struct LanePermit {
+    lanes: Lanes,
+    lane: usize,
+    /// false for a permit which never reached its waiter, the slot is still with the lanes
+    held: bool,
+}
+
+impl Drop for LanePermit {
+    fn drop(&mut self) {
+        if self.held {
+            self.lanes.release(self.lane);
+        }
+    }
+}
+
+impl Lanes {
+    /// slots added by the config go to the messages waiting right away
+    pub fn new(config: watch::Receiver<LanesConfig>) -> Self {
+        let mut state = State::default();
+        state.update(&config.borrow());
+        state.credits = state.weights;
+        let lanes = Self {
+            config: config.clone(),
+            state: Arc::new(Mutex::new(state)),
+        };
+        let updated = lanes.clone();
+        let mut config = config;
+        tokio::spawn(async move {
+            while config.changed().await.is_ok() {
+                let mut state = updated.lock_updated();
+                updated.hand_over(&mut state);
+            }
+        });
+        lanes
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("lanes poisoned")
+    }
+
+    /// the state with the latest config applied
+    fn lock_updated(&self) -> std::sync::MutexGuard<'_, State> {
+        let mut state = self.lock();
+        state.update(&self.config.borrow());
+        state
+    }
+
+    /// wait for a dispatch slot in the lane of `priority`
+    pub async fn acquire(&self, priority: Priority) -> LanePermit {
+        let lane = lane(priority);
+        let rx = {
+            let mut state = self.lock_updated();
+            if state.free > 0 && state.waiting.iter().all(VecDeque::is_empty) {
+                state.free -= 1;
+                state.stats[lane].in_flight += 1;
+                return self.permit(lane);
+            }
+            let (tx, rx) = oneshot::channel();
+            state.waiting[lane].push_back((Instant::now(), tx));
+            state.stats[lane].queued += 1;
+            rx
+        };
+        // The slot comes as a permit, so that it is released again if this future is dropped
+        // once the slot is handed over. The sender is only dropped after sending.
+        rx.await.expect("lanes dropped a waiter")
+    }
+
+    fn permit(&self, lane: usize) -> LanePermit {
+        LanePermit {
+            lanes: self.clone(),
+            lane,
+            held: true,
+        }
+    }
+
+    fn release(&self, lane: usize) {
+        let mut state = self.lock_updated();
+        state.stats[lane].in_flight -= 1;
+        state.stats[lane].dispatched += 1;
+        state.free += 1;
+        self.hand_over(&mut state);
+    }
+
+    /// give the free slots to the messages waiting
+    fn hand_over(&self, state: &mut State) {
+        while state.free > 0 {
+            let Some(next) = state.next_lane() else {
+                return;
+            };
+            let (queued_at, tx) = state.waiting[next]
+                .pop_front()
+                .expect("next lane is waiting");
+            state.stats[next].queued -= 1;
+            // the waiter may have given up, the slot goes to the next one then
+            match tx.send(self.permit(next)) {
+                Ok(()) => {
+                    state.free -= 1;
+                    state.stats[next].in_flight += 1;
+                    state.stats[next].wait_ms += queued_at.elapsed().as_millis() as u64;
+                }
+                Err(mut permit) => permit.held = false,
+            }
+        }
+    }
+
+    /// count a message of the lane rejected by a rate limit
+    pub fn throttled(&self, priority: Priority) {
+        self.lock().stats[lane(priority)].throttled += 1;
+    }
+
+    pub fn stats(&self) -> Vec<(Priority, LaneStats)> {
+        let state = self.lock();
+        PRIORITIES
+            .iter()
+            .map(|&p| (p, state.stats[lane(p)]))
+            .collect()
+    }
+
+    pub fn lane_stats(&self) -> LaneStatsResponse {
+        let lanes = self
+            .stats()
+            .into_iter()
+            .map(|(priority, stats)| notification::LaneStats {
+                priority: notification::Priority::from(priority) as i32,
+                queued: stats.queued,
+                in_flight: stats.in_flight,
+                dispatched: stats.dispatched,
+                throttled: stats.throttled,
+                wait_ms: stats.wait_ms,
+            })
+            .collect();
+        LaneStatsResponse { lanes }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::FutureExt as _;
+    use std::{collections::HashMap, time::Duration};
+
+    #[tokio::test]
+    async fn test_weighted_lanes() {
+        let lanes = Lanes::new(
+            watch::channel(LanesConfig {
+                slots: 1,
+                weights: HashMap::from([("high".to_string(), 2), ("normal".to_string(), 1)]),
+            })
+            .1,
+        );
+        let held = lanes.acquire(Priority::Normal).await;
+        let order = Arc::new(Mutex::new(vec![]));
+        let mut tasks = vec![];
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            for _ in 0..2 {
+                let lanes = lanes.clone();
+                let order = order.clone();
+                tasks.push(tokio::spawn(async move {
+                    let _permit = lanes.acquire(priority).await;
+                    order.lock().unwrap().push(priority);
+                }));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+        let stats = lanes.stats();
+        assert_eq!(stats[0].1.queued, 2);
+        assert_eq!(stats[1].1.in_flight, 1);
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        use Priority::*;
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec![High, High, Normal, Low, Normal, Low]
+        );
+        let stats = lanes.stats();
+        assert!(stats.iter().all(|(_, s)| s.queued == 0 && s.in_flight == 0));
+        assert_eq!(stats[1].1.dispatched, 3);
+    }
+
+    #[tokio::test]
+    async fn test_slot_of_dropped_waiter_is_released() {
+        let lanes = Lanes::new(
+            watch::channel(LanesConfig {
+                slots: 1,
+                weights: HashMap::new(),
+            })
+            .1,
+        );
+        let held = lanes.acquire(Priority::Normal).await;
+        let mut waiting = Box::pin(lanes.acquire(Priority::High));
+        assert!((&mut waiting).now_or_never().is_none());
+        // the slot is handed over, the waiter gives up before it is woken
+        drop(held);
+        drop(waiting);
+        let permit = tokio::time::timeout(Duration::from_millis(100), lanes.acquire(Priority::Low))
+            .await
+            .expect("slot leaked");
+        drop(permit);
+        let stats = lanes.stats();
+        assert!(stats.iter().all(|(_, s)| s.queued == 0 && s.in_flight == 0));
+    }
+
+    #[tokio::test]
+    async fn test_slots_follow_the_config() {
+        let (tx, rx) = watch::channel(LanesConfig {
+            slots: 2,
+            weights: HashMap::new(),
+        });
+        let lanes = Lanes::new(rx);
+        let first = lanes.acquire(Priority::Normal).await;
+        let second = lanes.acquire(Priority::Normal).await;
+        let mut waiting = Box::pin(lanes.acquire(Priority::Normal));
+        assert!((&mut waiting).now_or_never().is_none());
+
+        // a slot added is given to the message waiting
+        tx.send_modify(|config| config.slots = 3);
+        let third = tokio::time::timeout(Duration::from_millis(100), waiting)
+            .await
+            .expect("slot not added");
+
+        // with a single slot, two have to be done before the next message goes
+        tx.send_modify(|config| config.slots = 1);
+        drop(first);
+        drop(second);
+        assert!(lanes.acquire(Priority::Low).now_or_never().is_none());
+        drop(third);
+        assert!(lanes.acquire(Priority::Low).now_or_never().is_some());
+    }
+}
@@ -0,0 +1,259 @@
+use camp_core::proto::{ts_to_utc, utc_to_ts};
+use std::sync::Arc;
+use tonic::Status;
+use tracing::{info, warn};
+
+use crate::{
+    model::message::{MessageType, Priority},
+    pb::notification::{
+        self, send_request::Msg, GetMessageStatusRequest, ListMessagesRequest,
+        ListMessagesResponse, MessageStatusResponse, SendResponse, SendResponseType,
+    },
+    services::{
+        email, inapp,
+        lifecycle::{
+            Accepted, Lifecycle, LifecycleError, Message, MessageEvent, MessageQuery,
+            MessageStatus, RecipientOutcome,
+        },
+        sms, webhook, ServiceError,
+    },
+};
+
+#[derive(Clone)]
+pub struct LifecycleGrpc(pub Arc<Box<dyn Lifecycle>>);
+
+impl From<&Msg> for Message {
+    fn from(value: &Msg) -> Self {
+        match value.clone() {
+            Msg::Email(msg) => email::EmailMessage::from(msg).into(),
+            Msg::Sms(msg) => sms::SmsMessage::from(msg).into(),
+            Msg::InApp(msg) => inapp::InAppMessage::from(msg).into(),
+            Msg::Webhook(msg) => webhook::WebhookMessage::from(msg).into(),
+        }
+    }
+}
+
+impl Message {
+    /// the message of a request, dispatched in the lane of `priority`
+    pub fn from_request(msg: &Msg, priority: Priority) -> Self {
+        Self {
+            priority,
+            ..msg.into()
+        }
+    }
+}
+
+impl From<MessageStatus> for notification::MessageStatus {
+    fn from(value: MessageStatus) -> Self {
+        match value {
+            MessageStatus::Accepted => Self::Accepted,
+            MessageStatus::Sending => Self::Sending,
+            MessageStatus::Sent => Self::Sent,
+            MessageStatus::Stored => Self::Stored,
+            MessageStatus::Failed => Self::Failed,
+            MessageStatus::DeadLettered => Self::DeadLettered,
+            MessageStatus::Scheduled => Self::Scheduled,
+            MessageStatus::Cancelled => Self::Cancelled,
+            MessageStatus::Suppressed => Self::Suppressed,
+            MessageStatus::Delivered => Self::Delivered,
+            MessageStatus::Bounced => Self::Bounced,
+            MessageStatus::Complained => Self::Complained,
+        }
+    }
+}
+
+impl From<MessageType> for notification::MessageType {
+    fn from(value: MessageType) -> Self {
+        match value {
+            MessageType::Email => Self::Email,
+            MessageType::Sms => Self::Sms,
+            MessageType::Inapp => Self::InApp,
+            MessageType::Webhook => Self::Webhook,
+            MessageType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl From<MessageEvent> for notification::MessageEvent {
+    fn from(value: MessageEvent) -> Self {
+        Self {
+            status: notification::MessageStatus::from(value.status) as i32,
+            timestamp: Some(utc_to_ts(value.created_at)),
+            provider_response: value.provider_response.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<RecipientOutcome> for notification::RecipientOutcome {
+    fn from(value: RecipientOutcome) -> Self {
+        Self {
+            recipient: value.recipient,
+            status: notification::MessageStatus::from(value.status) as i32,
+            updated_at: Some(utc_to_ts(value.updated_at)),
+        }
+    }
+}
+
+impl From<Message> for MessageStatusResponse {
+    fn from(value: Message) -> Self {
+        let recipients = match value.device_id {
+            Some(device_id) if value.r#type == MessageType::Inapp => vec![device_id],
+            _ => value.recipients.unwrap_or_default(),
+        };
+        Self {
+            message_id: value.id,
+            r#type: notification::MessageType::from(value.r#type) as i32,
+            sender: value.sender,
+            recipients,
+            status: notification::MessageStatus::from(value.status) as i32,
+            created_at: Some(utc_to_ts(value.created_at)),
+            updated_at: Some(utc_to_ts(value.updated_at)),
+            events: vec![],
+            send_at: value.send_at.map(utc_to_ts),
+            priority: notification::Priority::from(value.priority) as i32,
+            outcomes: vec![],
+        }
+    }
+}
+
+impl From<ListMessagesRequest> for MessageQuery {
+    fn from(value: ListMessagesRequest) -> Self {
+        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+        Self {
+            recipient: non_empty(value.recipient),
+            sender: non_empty(value.sender),
+            start: value.start.as_ref().and_then(ts_to_utc),
+            end: value.end.as_ref().and_then(ts_to_utc),
+            limit: value.limit as i64,
+            offset: value.offset as i64,
+        }
+    }
+}
+
+impl From<Message> for SendResponse {
+    fn from(value: Message) -> Self {
+        let status = match value.status {
+            MessageStatus::Accepted | MessageStatus::Sending => SendResponseType::Pending,
+            MessageStatus::Sent | MessageStatus::Delivered | MessageStatus::Complained => {
+                SendResponseType::Success
+            }
+            MessageStatus::Stored => SendResponseType::Stored,
+            MessageStatus::Scheduled => SendResponseType::Scheduled,
+            MessageStatus::Suppressed => SendResponseType::Suppressed,
+            MessageStatus::Failed
+            | MessageStatus::DeadLettered
+            | MessageStatus::Cancelled
+            | MessageStatus::Bounced => SendResponseType::Failed,
+        };
+        Self {
+            message_id: value.id,
+            timestamp: Some(utc_to_ts(value.updated_at)),
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+}
+
+fn lifecycle_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Lifecycle(LifecycleError::NotFound(id)) => {
+            Status::not_found(format!("message {} not found", id))
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl LifecycleGrpc {
+    /// persist an incoming message and mark it as being sent.
+    ///
+    /// Returns the original response if the message is a duplicate, which must not be sent again.
+    pub async fn accept(
+        &self,
+        msg: &Msg,
+        priority: Priority,
+    ) -> Result<Option<SendResponse>, Status> {
+        let message = Message::from_request(msg, priority);
+        let message = match self.0.accept(message).await.map_err(lifecycle_status)? {
+            Accepted::New(message) => message,
+            Accepted::Duplicate(message) => {
+                info!("message {:?} is a duplicate, skip sending", message.id);
+                return Ok(Some(message.into()));
+            }
+        };
+        self.0
+            .transition(&message.id, MessageStatus::Sending, None)
+            .await
+            .map_err(lifecycle_status)?;
+        Ok(None)
+    }
+
+    /// forget an accepted message which is not sent now, e.g. throttled, so that the client can
+    /// retry it with the same id
+    pub async fn release(&self, id: &str) {
+        if let Err(e) = self.0.release(id).await {
+            warn!("failed to release message {}: {}", id, e);
+        }
+    }
+
+    /// record the outcome of a send, the response is returned to the client regardless
+    pub async fn record(&self, id: &str, resp: &Result<SendResponse, Status>) {
+        let (status, provider_response) = match resp {
+            Ok(resp) => {
+                let status = match resp.status() {
+                    SendResponseType::Success => MessageStatus::Sent,
+                    SendResponseType::Failed => MessageStatus::Failed,
+                    SendResponseType::Stored => MessageStatus::Stored,
+                    SendResponseType::Pending => MessageStatus::Sending,
+                    SendResponseType::Throttled | SendResponseType::Invalid => {
+                        MessageStatus::Failed
+                    }
+                    SendResponseType::Scheduled => MessageStatus::Scheduled,
+                    SendResponseType::Suppressed => MessageStatus::Suppressed,
+                };
+                (status, resp.status().as_str_name().to_string())
+            }
+            Err(status) => (MessageStatus::DeadLettered, status.message().to_string()),
+        };
+        if let Err(e) = self
+            .0
+            .transition(id, status, Some(&provider_response))
+            .await
+        {
+            warn!(
+                "failed to record status {} of message {}: {}",
+                status, id, e
+            );
+        }
+    }
+
+    pub async fn get_message_status(
+        &self,
+        req: GetMessageStatusRequest,
+    ) -> Result<MessageStatusResponse, Status> {
+        info!("getting status of message {:?}", req.message_id);
+        let (msg, events) = self
+            .0
+            .get(&req.message_id)
+            .await
+            .map_err(lifecycle_status)?;
+        let outcomes = self
+            .0
+            .outcomes(&req.message_id)
+            .await
+            .map_err(lifecycle_status)?;
+        let mut resp: MessageStatusResponse = msg.into();
+        resp.events = events.into_iter().map(Into::into).collect();
+        resp.outcomes = outcomes.into_iter().map(Into::into).collect();
+        Ok(resp)
+    }
+
+    pub async fn list_messages(
+        &self,
+        req: ListMessagesRequest,
+    ) -> Result<ListMessagesResponse, Status> {
+        let messages = self.0.list(req.into()).await.map_err(lifecycle_status)?;
+        Ok(ListMessagesResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+        })
+    }
+}
@@ -0,0 +1,33 @@
+use camp_core::proto::utc_to_ts;
+use chrono::Utc;
+use std::sync::Arc;
+use tonic::Status;
+
+use crate::{
+    model::message::{Message, Priority},
+    pb::notification::{send_request::Msg, SendResponse, SendResponseType},
+    services::limiter::{Admission, Throttle},
+};
+
+#[derive(Clone)]
+pub struct ThrottleGrpc(pub Arc<Box<dyn Throttle>>);
+
+impl ThrottleGrpc {
+    /// returns a THROTTLED response if the message must not be sent now
+    pub async fn admit(
+        &self,
+        msg: &Msg,
+        priority: Priority,
+    ) -> Result<Option<SendResponse>, Status> {
+        match self.0.admit(&Message::from_request(msg, priority)).await {
+            Ok(Admission::Admitted) => Ok(None),
+            Ok(Admission::Throttled(_)) => Ok(Some(SendResponse {
+                message_id: msg.message_id().to_string(),
+                timestamp: Some(utc_to_ts(Utc::now())),
+                status: SendResponseType::Throttled as i32,
+                ..Default::default()
+            })),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+}
@@ -1,18 +1,56 @@
+use camp_core::{
+    metrics::{self, counter_vec, IntCounterVec},
+    proto::utc_to_ts,
+};
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::FuturesUnordered;
 use futures::StreamExt as _;
+use prost::Message as _;
 use prost_types::Timestamp;
-use std::pin::Pin;
-use tokio::sync::mpsc::channel;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tokio::sync::{
+    mpsc::{channel, Sender},
+    watch, OwnedSemaphorePermit, Semaphore,
+};
 use tokio_stream::{wrappers::ReceiverStream, Stream};
-use tonic::{async_trait, Request, Response, Status, Streaming};
-use tracing::info;
+use tonic::{async_trait, Code, Request, Response, Status, Streaming};
+use tracing::{info, info_span, warn, Instrument, Span};
+pub mod batch;
+pub mod dead_letter;
 pub mod email;
+pub mod feedback;
 pub mod inapp;
+pub mod inbox;
+pub mod lanes;
+pub mod lifecycle;
+pub mod limiter;
+pub mod outgoing_webhook;
+pub mod preferences;
+pub mod push;
+pub mod recipients;
+pub mod scheduler;
 pub mod sms;
+pub mod template;
+pub mod webhook;
 
 use crate::{
+    abi::preferences::Filtered,
+    config::{BatchConfig, DispatchConfig, SchedulerConfig},
+    model::message::{MessageType, Priority},
     pb::notification::{
-        notification_server::Notification, send_request::Msg, SendRequest, SendResponse,
-        SendResponseType,
+        notification_server::Notification, send_request::Msg, CancelScheduledRequest,
+        DeleteMessageRequest, FeedbackResponse, GetLaneStatsRequest, GetMessageStatusRequest,
+        GetPreferencesRequest, GetTemplateRequest, InboxUpdateResponse, InvalidRecipients,
+        LaneStatsResponse, ListInboxRequest, ListInboxResponse, ListMessagesRequest,
+        ListMessagesResponse, ListScheduledRequest, MarkAllReadRequest, MarkReadRequest,
+        MessageStatusResponse, Preferences, ProviderFeedback, SendBatchRequest, SendBatchResponse,
+        SendRequest, SendResponse, SendResponseType, SubscribeRequest, Template,
+        UnsubscribeRequest, UnsubscribeResponse,
     },
     services,
 };
@@ -24,17 +62,403 @@ pub struct NotificationGrpc {
     pub email: email::EmailGrpc,
     pub inapp: inapp::InAppGrpc,
     pub sms: sms::SmsGrpc,
+    pub lifecycle: lifecycle::LifecycleGrpc,
+    pub throttle: limiter::ThrottleGrpc,
+    pub scheduler: scheduler::SchedulerGrpc,
+    pub preferences: preferences::PreferencesGrpc,
+    pub inbox: inbox::InboxGrpc,
+    pub push: push::PushGrpc,
+    pub recipients: recipients::RecipientsGrpc,
+    pub feedback: feedback::FeedbackGrpc,
+    pub template: template::TemplateGrpc,
+    pub dead_letter: dead_letter::DeadLetterGrpc,
+    pub webhook: outgoing_webhook::WebhookGrpc,
+    pub batch: watch::Receiver<BatchConfig>,
+    pub concurrency: Concurrency,
+    pub lanes: lanes::Lanes,
+}
+
+/// how many messages are sent at the same time
+#[derive(Clone)]
+pub struct Concurrency {
+    /// messages of one Send stream are read from `stream_concurrency`
+    dispatch: watch::Receiver<DispatchConfig>,
+    /// shared by every stream, channels not listed are not limited
+    channels: Arc<Mutex<ChannelLimits>>,
+}
+
+/// keyed by message type, the semaphore of a channel with the limit it is sized for
+type ChannelLimits = HashMap<String, (Arc<Semaphore>, usize)>;
+
+impl Concurrency {
+    /// the channel limits follow the config, see `semaphore`
+    pub fn new(dispatch: watch::Receiver<DispatchConfig>) -> Self {
+        Self {
+            dispatch,
+            channels: Default::default(),
+        }
+    }
+
+    /// messages of one Send stream
+    pub fn stream(&self) -> usize {
+        self.dispatch.borrow().stream_concurrency.max(1)
+    }
+
+    /// wait for a free slot of the channel, held until the permit is dropped
+    async fn acquire(&self, channel: MessageType) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.semaphore(channel)?;
+        // the semaphores are never closed
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// The semaphore of a limited channel, resized to the latest limits first.
+    ///
+    /// A lower limit applies once enough of the messages being sent are done, a channel no
+    /// longer listed is not limited, even for the messages already waiting.
+    fn semaphore(&self, channel: MessageType) -> Option<Arc<Semaphore>> {
+        let dispatch = self.dispatch.borrow();
+        let mut channels = self.channels.lock().expect("channel limits poisoned");
+        channels.retain(|channel, _| dispatch.channels.contains_key(channel));
+        for (channel, limit) in &dispatch.channels {
+            let limit = (*limit).max(1);
+            match channels.get_mut(channel) {
+                Some((semaphore, current)) if *current < limit => {
+                    semaphore.add_permits(limit - *current);
+                    *current = limit;
+                }
+                Some((semaphore, current)) if *current > limit => {
+                    let shrink = (*current - limit) as u32;
+                    let semaphore = semaphore.clone();
+                    tokio::spawn(async move {
+                        if let Ok(permits) = semaphore.acquire_many_owned(shrink).await {
+                            permits.forget();
+                        }
+                    });
+                    *current = limit;
+                }
+                Some(_) => {}
+                None => {
+                    channels.insert(channel.clone(), (Arc::new(Semaphore::new(limit)), limit));
+                }
+            }
+        }
+        channels
+            .get(&channel.to_string())
+            .map(|(semaphore, _)| semaphore.clone())
+    }
+}
+
+impl Msg {
+    pub fn channel(&self) -> MessageType {
+        match self {
+            Msg::Email(_) => MessageType::Email,
+            Msg::Sms(_) => MessageType::Sms,
+            Msg::InApp(_) => MessageType::Inapp,
+            Msg::Webhook(_) => MessageType::Webhook,
+        }
+    }
+
+    pub fn message_id(&self) -> &str {
+        match self {
+            Msg::Email(msg) => &msg.message_id,
+            Msg::Sms(msg) => &msg.message_id,
+            Msg::InApp(msg) => &msg.message_id,
+            Msg::Webhook(msg) => &msg.message_id,
+        }
+    }
+
+    /// where the message is delivered to, the device id for in-app messages and the endpoint
+    /// name for webhooks
+    pub fn addresses(&self) -> Vec<&str> {
+        match self {
+            Msg::Email(msg) => msg
+                .recipients
+                .iter()
+                .chain(&msg.cc)
+                .chain(&msg.bcc)
+                .map(String::as_str)
+                .collect(),
+            Msg::Sms(msg) => msg.recipients.iter().map(String::as_str).collect(),
+            Msg::InApp(msg) => vec![msg.device_id.as_str()],
+            Msg::Webhook(msg) => vec![msg.endpoint.as_str()],
+        }
+    }
+
+    /// drop the `removed` addresses, `None` if no recipient is left
+    pub fn without(self, removed: &[String]) -> Option<Self> {
+        let keep = |address: &String| !removed.contains(address);
+        match self {
+            Msg::Email(mut msg) => {
+                msg.recipients.retain(keep);
+                msg.cc.retain(keep);
+                msg.bcc.retain(keep);
+                (!msg.recipients.is_empty()).then_some(Msg::Email(msg))
+            }
+            Msg::Sms(mut msg) => {
+                msg.recipients.retain(keep);
+                (!msg.recipients.is_empty()).then_some(Msg::Sms(msg))
+            }
+            Msg::InApp(msg) => keep(&msg.device_id).then_some(Msg::InApp(msg)),
+            Msg::Webhook(msg) => keep(&msg.endpoint).then_some(Msg::Webhook(msg)),
+        }
+    }
+}
+
+static MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "notification_messages_total",
+        "messages dispatched, by channel and send status",
+        &["channel", "status"],
+    )
+});
+
+/// count the outcome of a message sent through `channel`
+fn count_sent(channel: MessageType, resp: &Result<SendResponse, Status>) {
+    let status = resp.as_ref().map(|resp| resp.status());
+    metrics::count_sent(&MESSAGES, &channel.to_string(), status);
+}
+
+/// answer a message rejected before it is accepted, the other messages of the stream go on
+fn rejected(msg: &Msg, e: Status) -> SendResponse {
+    warn!("message {} rejected: {}", msg.message_id(), e.message());
+    let status = match e.code() {
+        Code::InvalidArgument => SendResponseType::Invalid,
+        _ => SendResponseType::Failed,
+    };
+    let resp = SendResponse {
+        message_id: msg.message_id().to_string(),
+        timestamp: Some(utc_to_ts(Utc::now())),
+        status: status as i32,
+        reason: e.message().to_string(),
+        invalid_recipients: InvalidRecipients::decode(e.details())
+            .unwrap_or_default()
+            .recipients,
+    };
+    count_sent(msg.channel(), &Ok(resp.clone()));
+    resp
 }
 
 impl NotificationGrpc {
     async fn notification(&self, req: SendRequest) -> Result<SendResponse, Status> {
-        let Some(msg) = req.msg else {
+        let due = scheduler::SchedulerGrpc::due_at(&req)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let priority = Priority::from(req.priority());
+        let Some(mut msg) = req.msg else {
             return Err(Status::not_found("msg is None"));
         };
-        match msg {
-            Msg::Email(msg) => self.email.send_email(msg).await,
-            Msg::Sms(msg) => self.sms.send_sms(msg).await,
-            Msg::InApp(msg) => self.inapp.send_inapp(msg).await,
+        if let Err(e) = self.recipients.normalize(&mut msg).await {
+            return Ok(rejected(&msg, e));
+        }
+        match &msg {
+            Msg::Email(email) => {
+                if let Err(e) = self.email.check(email).await {
+                    return Ok(rejected(&msg, e));
+                }
+            }
+            Msg::Webhook(webhook) => {
+                if let Err(e) = self.webhook.check(webhook).await {
+                    return Ok(rejected(&msg, e));
+                }
+            }
+            Msg::Sms(_) | Msg::InApp(_) => {}
+        }
+        if let Some((send_at, window)) = due {
+            return self
+                .scheduler
+                .schedule(&msg, priority, send_at, window)
+                .await;
+        }
+        // a duplicate is answered with the original response without taking a token
+        if let Some(resp) = self.lifecycle.accept(&msg, priority).await? {
+            return Ok(resp);
+        }
+        // throttled messages are released so that they can be retried with the same id
+        if let Some(resp) = self.throttle.admit(&msg, priority).await? {
+            self.lifecycle.release(msg.message_id()).await;
+            self.lanes.throttled(priority);
+            count_sent(msg.channel(), &Ok(resp.clone()));
+            return Ok(resp);
+        }
+        self.dispatch(msg, priority).await
+    }
+
+    /// send an accepted message through its channel and record the outcome
+    async fn dispatch(&self, msg: Msg, priority: Priority) -> Result<SendResponse, Status> {
+        let id = msg.message_id().to_string();
+        let channel = msg.channel();
+        let _lane = self.lanes.acquire(priority).await;
+        let _permit = self.concurrency.acquire(msg.channel()).await;
+        let resp = match self.filter(msg).await {
+            Ok(Filtered::Send(Msg::Email(msg))) => self.email.send_email(msg).await,
+            Ok(Filtered::Send(Msg::Sms(msg))) => self.sms.send_sms(msg).await,
+            Ok(Filtered::Send(Msg::InApp(msg))) => self.inapp.send_inapp(msg).await,
+            Ok(Filtered::Send(Msg::Webhook(msg))) => self.webhook.send_webhook(msg).await,
+            Ok(Filtered::Suppressed) => Ok(SendResponse {
+                message_id: id.clone(),
+                timestamp: Some(utc_to_ts(Utc::now())),
+                status: SendResponseType::Suppressed as i32,
+                ..Default::default()
+            }),
+            // put back to the schedule, which records it
+            Ok(Filtered::Quiet(until)) => {
+                match self.scheduler.reschedule(&id, until, "QUIET_HOURS").await {
+                    Ok(()) => {
+                        let resp = Ok(SendResponse {
+                            message_id: id,
+                            timestamp: Some(utc_to_ts(until)),
+                            status: SendResponseType::Scheduled as i32,
+                            ..Default::default()
+                        });
+                        count_sent(channel, &resp);
+                        return resp;
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        self.lifecycle.record(&id, &resp).await;
+        count_sent(channel, &resp);
+        resp
+    }
+
+    /// drop the recipients who are suppressed or opted out, hold the message back during quiet
+    /// hours
+    async fn filter(&self, msg: Msg) -> Result<Filtered, Status> {
+        let Some(msg) = self.recipients.apply(msg).await? else {
+            return Ok(Filtered::Suppressed);
+        };
+        self.preferences.apply(msg).await
+    }
+
+    /// Send the scheduled messages which are due, returns how many were claimed.
+    ///
+    /// A message failing before it is sent is put back to the schedule, the others are still
+    /// sent. One which can not be put back is claimed again once its lease expires.
+    pub async fn dispatch_due(&self, config: &SchedulerConfig) -> Result<usize, Status> {
+        let lease = Duration::seconds(config.lease_secs as i64);
+        let messages = self.scheduler.claim_due(config.batch_size, lease).await?;
+        let claimed = messages.len();
+        for message in messages {
+            let id = message.id.clone();
+            let retry = Duration::seconds(config.throttle_retry_secs as i64);
+            let retry_at = message.retry_at(retry, Utc::now());
+            if let Err(e) = self.dispatch_scheduled(message, retry_at).await {
+                warn!("failed to dispatch scheduled message {}: {}", id, e);
+                if let Err(e) = self.scheduler.reschedule(&id, retry_at, e.message()).await {
+                    warn!("failed to put back scheduled message {}: {}", id, e);
+                }
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// send a claimed message, or put it back to be sent at `retry_at` if it is throttled
+    async fn dispatch_scheduled(
+        &self,
+        message: services::scheduler::Message,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), Status> {
+        let priority = message.priority;
+        let msg = Msg::from(message);
+        if let Some(resp) = self.throttle.admit(&msg, priority).await? {
+            self.lanes.throttled(priority);
+            self.scheduler
+                .reschedule(msg.message_id(), retry_at, resp.status().as_str_name())
+                .await?;
+            return Ok(());
+        }
+        // the outcome is recorded by dispatch
+        let _ = self.dispatch(msg, priority).await;
+        Ok(())
+    }
+
+    /// answer the requests of a Send stream, up to `concurrency.stream()` of them at the same
+    /// time, read when the stream starts.
+    ///
+    /// Responses are sent as soon as they are ready, not in the order of the requests.
+    /// A request repeating the id of one in flight waits for it, so that it is answered with its
+    /// outcome rather than as pending.
+    /// A broken request stream is answered with an error after the messages in flight are sent.
+    async fn serve_stream(
+        self,
+        mut stream: Streaming<SendRequest>,
+        tx: Sender<Result<SendResponse, Status>>,
+    ) {
+        let concurrency = self.concurrency.stream();
+        let mut in_flight = FuturesUnordered::new();
+        // ids in flight, with the requests repeating them
+        let mut held: HashMap<String, VecDeque<SendRequest>> = HashMap::new();
+        let mut reading = true;
+        let mut broken = false;
+        loop {
+            tokio::select! {
+                request = stream.next(), if reading && in_flight.len() < concurrency => {
+                    match request {
+                        Some(Ok(req)) => {
+                            let id = req.msg.as_ref().map_or("", Msg::message_id).to_string();
+                            if id.is_empty() {
+                                in_flight.push(self.clone().answer(id, req));
+                            } else if let Some(repeated) = held.get_mut(&id) {
+                                repeated.push_back(req);
+                            } else {
+                                held.insert(id.clone(), VecDeque::new());
+                                in_flight.push(self.clone().answer(id, req));
+                            }
+                        }
+                        Some(Err(e)) => {
+                            warn!("send stream broken: {}", e);
+                            reading = false;
+                            broken = true;
+                        }
+                        None => reading = false,
+                    }
+                }
+                Some((id, resp)) = in_flight.next() => {
+                    // the client may be gone, the messages in flight are still sent and recorded
+                    let _ = tx.send(resp).await;
+                    match held.get_mut(&id).and_then(VecDeque::pop_front) {
+                        Some(req) => in_flight.push(self.clone().answer(id, req)),
+                        None => {
+                            held.remove(&id);
+                        }
+                    }
+                }
+                else => break,
+            }
+        }
+        if broken {
+            let _ = tx.send(Err(Status::internal("request status error"))).await;
+        }
+    }
+
+    /// answer one request of a Send stream, returned with its message id
+    async fn answer(self, id: String, req: SendRequest) -> (String, Result<SendResponse, Status>) {
+        let span = info_span!("message", message.id = id);
+        let resp = self.notification(req).instrument(span).await;
+        (id, resp)
+    }
+
+    /// poll for due scheduled messages until `shutdown` completes, with the latest config, a
+    /// batch claimed before is still dispatched
+    pub async fn run_scheduler(
+        self,
+        config: watch::Receiver<SchedulerConfig>,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        tokio::pin!(shutdown);
+        loop {
+            let config = config.borrow().clone();
+            let poll = std::time::Duration::from_millis(config.poll_interval_ms);
+            tokio::select! {
+                _ = tokio::time::sleep(poll) => {}
+                _ = &mut shutdown => break,
+            }
+            match self.dispatch_due(&config).await {
+                Ok(0) => {}
+                Ok(n) => info!("dispatched {} scheduled messages", n),
+                Err(e) => warn!("failed to dispatch scheduled messages: {}", e),
+            }
         }
     }
 }
@@ -51,23 +475,161 @@ impl Notification for NotificationGrpc {
     ) -> Result<Response<Self::SendStream>, Status> {
         let (tx, rx) = channel(1024);
         let streamer = ReceiverStream::new(rx);
-        let notification = self.clone();
-        tokio::spawn(async move {
-            let mut stream: Streaming<SendRequest> = request.into_inner();
-            info!("streaming request");
-            while let Some(request) = stream.next().await {
-                let Ok(req) = request else {
-                    tx.send(Err(Status::internal("request status error")))
-                        .await
-                        .unwrap();
-                    break;
-                };
-                tokio::time::sleep(tokio::time::Duration::from_micros(1)).await;
-                tx.send(notification.notification(req).await).await.unwrap();
-            }
-        });
+        info!("streaming request");
+        let stream = self.clone().serve_stream(request.into_inner(), tx);
+        tokio::spawn(stream.instrument(Span::current()));
         Ok(Response::new(Box::pin(streamer)))
     }
+
+    async fn get_message_status(
+        &self,
+        request: Request<GetMessageStatusRequest>,
+    ) -> Result<Response<MessageStatusResponse>, Status> {
+        let resp = self
+            .lifecycle
+            .get_message_status(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn list_messages(
+        &self,
+        request: Request<ListMessagesRequest>,
+    ) -> Result<Response<ListMessagesResponse>, Status> {
+        let resp = self.lifecycle.list_messages(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn list_scheduled(
+        &self,
+        request: Request<ListScheduledRequest>,
+    ) -> Result<Response<ListMessagesResponse>, Status> {
+        let resp = self.scheduler.list_scheduled(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn cancel_scheduled(
+        &self,
+        request: Request<CancelScheduledRequest>,
+    ) -> Result<Response<MessageStatusResponse>, Status> {
+        let resp = self
+            .scheduler
+            .cancel_scheduled(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn get_preferences(
+        &self,
+        request: Request<GetPreferencesRequest>,
+    ) -> Result<Response<Preferences>, Status> {
+        let resp = self
+            .preferences
+            .get_preferences(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn update_preferences(
+        &self,
+        request: Request<Preferences>,
+    ) -> Result<Response<Preferences>, Status> {
+        let resp = self
+            .preferences
+            .update_preferences(request.into_inner())
+            .await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: Request<UnsubscribeRequest>,
+    ) -> Result<Response<UnsubscribeResponse>, Status> {
+        let resp = self.preferences.unsubscribe(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn list_inbox(
+        &self,
+        request: Request<ListInboxRequest>,
+    ) -> Result<Response<ListInboxResponse>, Status> {
+        let resp = self.inbox.list_inbox(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn mark_read(
+        &self,
+        request: Request<MarkReadRequest>,
+    ) -> Result<Response<InboxUpdateResponse>, Status> {
+        let resp = self.inbox.mark_read(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn mark_all_read(
+        &self,
+        request: Request<MarkAllReadRequest>,
+    ) -> Result<Response<InboxUpdateResponse>, Status> {
+        let resp = self.inbox.mark_all_read(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn delete_message(
+        &self,
+        request: Request<DeleteMessageRequest>,
+    ) -> Result<Response<InboxUpdateResponse>, Status> {
+        let resp = self.inbox.delete_message(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    /// Server streaming response type for the Subscribe method.
+    type SubscribeStream = push::InAppStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let stream = self.push.subscribe(request.into_inner()).await?;
+        Ok(Response::new(stream))
+    }
+
+    async fn report_feedback(
+        &self,
+        request: Request<ProviderFeedback>,
+    ) -> Result<Response<FeedbackResponse>, Status> {
+        let resp = self.feedback.report_feedback(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn save_template(
+        &self,
+        request: Request<Template>,
+    ) -> Result<Response<Template>, Status> {
+        let resp = self.template.save_template(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn get_template(
+        &self,
+        request: Request<GetTemplateRequest>,
+    ) -> Result<Response<Template>, Status> {
+        let resp = self.template.get_template(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
+
+    async fn get_lane_stats(
+        &self,
+        _request: Request<GetLaneStatsRequest>,
+    ) -> Result<Response<LaneStatsResponse>, Status> {
+        Ok(Response::new(self.lanes.lane_stats()))
+    }
+
+    async fn send_batch(
+        &self,
+        request: Request<SendBatchRequest>,
+    ) -> Result<Response<SendBatchResponse>, Status> {
+        let resp = self.send_batch(request.into_inner()).await?;
+        Ok(Response::new(resp))
+    }
 }
 
 impl From<services::SendResponse> for SendResponse {
@@ -79,6 +641,7 @@ impl From<services::SendResponse> for SendResponse {
                 nanos: value.timestamp.timestamp_subsec_nanos() as i32,
             }),
             status: SendResponseType::Success as i32,
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use camp_core::proto::utc_to_ts;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    pb::notification::{SendResponse, SendResponseType, WebhookMessage},
+    services::{
+        webhook::{self, Webhook, WebhookError},
+        ServiceError,
+    },
+};
+
+/// webhooks posted to partners, not to be confused with the feedback webhooks of the providers
+#[derive(Clone)]
+pub struct WebhookGrpc(pub Arc<Box<dyn Webhook>>);
+
+impl From<WebhookMessage> for webhook::WebhookMessage {
+    fn from(value: WebhookMessage) -> Self {
+        Self {
+            id: value.message_id,
+            sender: value.sender,
+            endpoint: value.endpoint,
+            event: value.event,
+            payload: value.payload,
+        }
+    }
+}
+
+impl WebhookGrpc {
+    pub async fn check(&self, req: &WebhookMessage) -> Result<(), Status> {
+        match self.0.check(&req.clone().into()).await {
+            Ok(()) => Ok(()),
+            Err(ServiceError::Webhook(e @ WebhookError::Invalid(_))) => {
+                Err(Status::invalid_argument(e.to_string()))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    pub async fn send_webhook(&self, req: WebhookMessage) -> Result<SendResponse, Status> {
+        info!("posting webhook {:?} to {:?}", req.message_id, req.endpoint);
+        match self.0.send_webhook(req.into()).await {
+            Ok(msg) => Ok(SendResponse {
+                message_id: msg.id,
+                timestamp: Some(utc_to_ts(msg.timestamp)),
+                status: SendResponseType::Success as i32,
+                ..Default::default()
+            }),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+}
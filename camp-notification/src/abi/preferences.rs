@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    model::schedule::SendWindow,
+    pb::notification::{
+        self, send_request::Msg, GetPreferencesRequest, LocalWindow, UnsubscribeRequest,
+        UnsubscribeResponse,
+    },
+    services::{
+        preferences::{
+            ChannelPreference, MessageType, Preferences, PreferencesError, UserPreferences,
+        },
+        ServiceError,
+    },
+};
+
+#[derive(Clone)]
+pub struct PreferencesGrpc(pub Arc<Box<dyn Preferences>>);
+
+impl From<notification::MessageType> for MessageType {
+    fn from(value: notification::MessageType) -> Self {
+        match value {
+            notification::MessageType::Email => Self::Email,
+            notification::MessageType::Sms => Self::Sms,
+            notification::MessageType::InApp => Self::Inapp,
+            notification::MessageType::Webhook => Self::Webhook,
+            notification::MessageType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl TryFrom<notification::Preferences> for UserPreferences {
+    type Error = Status;
+
+    fn try_from(value: notification::Preferences) -> Result<Self, Self::Error> {
+        if value.user_id.is_empty() {
+            return Err(Status::invalid_argument("user_id is required"));
+        }
+        let quiet_hours = value
+            .quiet_hours
+            .as_ref()
+            .map(SendWindow::try_from)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(Self {
+            channels: value
+                .channels
+                .iter()
+                .map(|c| ChannelPreference {
+                    channel: c.channel().into(),
+                    enabled: c.enabled,
+                })
+                .collect(),
+            user_id: value.user_id,
+            addresses: value.addresses,
+            quiet_hours,
+            unsubscribed: value.unsubscribed,
+        })
+    }
+}
+
+impl From<UserPreferences> for notification::Preferences {
+    fn from(value: UserPreferences) -> Self {
+        Self {
+            user_id: value.user_id,
+            addresses: value.addresses,
+            channels: value
+                .channels
+                .into_iter()
+                .map(|c| notification::ChannelPreference {
+                    channel: notification::MessageType::from(c.channel) as i32,
+                    enabled: c.enabled,
+                })
+                .collect(),
+            quiet_hours: value.quiet_hours.map(|w| LocalWindow {
+                timezone: w.tz.name().to_string(),
+                start_hour: w.start_hour,
+                end_hour: w.end_hour,
+            }),
+            unsubscribed: value.unsubscribed,
+        }
+    }
+}
+
+fn preferences_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Preferences(PreferencesError::NotFound(user_id)) => {
+            Status::not_found(format!("preferences of user {} not found", user_id))
+        }
+        ServiceError::Preferences(PreferencesError::InvalidToken) => {
+            Status::invalid_argument("invalid unsubscribe token")
+        }
+        ServiceError::Preferences(e @ PreferencesError::AddressTaken(_)) => {
+            Status::already_exists(e.to_string())
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+/// what is done with a message after the preferences of its recipients are applied
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Filtered {
+    Send(Msg),
+    /// nobody is left to send it to
+    Suppressed,
+    /// recipients are in their quiet hours, the message is sent once they are over
+    Quiet(DateTime<Utc>),
+}
+
+impl PreferencesGrpc {
+    /// Drop the recipients who opted out of the message, hold it back while some are in their
+    /// quiet hours.
+    ///
+    /// An email to a single address gets a link to unsubscribe with.
+    pub async fn apply(&self, msg: Msg) -> Result<Filtered, Status> {
+        let suppressed = self
+            .0
+            .suppressed(msg.channel(), &msg.addresses())
+            .await
+            .map_err(preferences_status)?;
+        if !suppressed.addresses.is_empty() {
+            info!(
+                "message {:?} suppressed for {:?}",
+                msg.message_id(),
+                suppressed.addresses
+            );
+        }
+        let Some(msg) = msg.without(&suppressed.addresses) else {
+            return Ok(Filtered::Suppressed);
+        };
+        if let Some(until) = suppressed.quiet_until {
+            info!(
+                "message {:?} held back until the quiet hours end at {}",
+                msg.message_id(),
+                until
+            );
+            return Ok(Filtered::Quiet(until));
+        }
+        let msg = match msg {
+            // copies would carry the link of somebody else
+            Msg::Email(mut m) if m.recipients.len() == 1 && m.cc.is_empty() && m.bcc.is_empty() => {
+                let link = self.0.unsubscribe_link(&m.recipients[0]);
+                m.body = format!("{}\n\nUnsubscribe: {}", m.body, link);
+                if !m.html_body.is_empty() {
+                    m.html_body = format!(
+                        "{}\n<p><a href=\"{}\">Unsubscribe</a></p>",
+                        m.html_body, link
+                    );
+                }
+                Msg::Email(m)
+            }
+            msg => msg,
+        };
+        Ok(Filtered::Send(msg))
+    }
+
+    pub async fn get_preferences(
+        &self,
+        req: GetPreferencesRequest,
+    ) -> Result<notification::Preferences, Status> {
+        let prefs = self.0.get(&req.user_id).await.map_err(preferences_status)?;
+        Ok(prefs.into())
+    }
+
+    pub async fn update_preferences(
+        &self,
+        req: notification::Preferences,
+    ) -> Result<notification::Preferences, Status> {
+        info!("updating preferences of user {:?}", req.user_id);
+        let prefs = self
+            .0
+            .update(req.try_into()?)
+            .await
+            .map_err(preferences_status)?;
+        Ok(prefs.into())
+    }
+
+    pub async fn unsubscribe(
+        &self,
+        req: UnsubscribeRequest,
+    ) -> Result<UnsubscribeResponse, Status> {
+        let address = self
+            .0
+            .unsubscribe(&req.token)
+            .await
+            .map_err(preferences_status)?;
+        info!("{:?} unsubscribed", address);
+        Ok(UnsubscribeResponse { address })
+    }
+}
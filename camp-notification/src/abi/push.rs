@@ -0,0 +1,27 @@
+use std::{pin::Pin, sync::Arc};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt as _};
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    pb::notification::{InAppMessage, SubscribeRequest},
+    services::push::Push,
+};
+
+pub type InAppStream = Pin<Box<dyn Stream<Item = Result<InAppMessage, Status>> + Send>>;
+
+#[derive(Clone)]
+pub struct PushGrpc(pub Arc<Box<dyn Push>>);
+
+impl PushGrpc {
+    /// stream the in-app messages of the device until the client disconnects
+    pub async fn subscribe(&self, req: SubscribeRequest) -> Result<InAppStream, Status> {
+        if req.device_id.is_empty() {
+            return Err(Status::invalid_argument("device_id is required"));
+        }
+        info!("device {:?} subscribed", req.device_id);
+        let rx = self.0.subscribe(&req.device_id);
+        let stream = ReceiverStream::new(rx).map(InAppMessage::from).map(Ok);
+        Ok(Box::pin(stream))
+    }
+}
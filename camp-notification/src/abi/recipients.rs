@@ -0,0 +1,85 @@
+use prost::Message as _;
+use std::sync::Arc;
+use tonic::{Code, Status};
+use tracing::info;
+
+use crate::{
+    pb::notification::{send_request::Msg, InvalidRecipient, InvalidRecipients},
+    services::recipients::{MessageType, Recipients, RecipientsError},
+};
+
+#[derive(Clone)]
+pub struct RecipientsGrpc(pub Arc<Box<dyn Recipients>>);
+
+/// malformed recipients are listed in the details of the status as `InvalidRecipients`
+fn recipients_status(e: RecipientsError) -> Status {
+    let message = e.to_string();
+    match e {
+        RecipientsError::Invalid(invalid) => {
+            let details = InvalidRecipients {
+                recipients: invalid
+                    .into_iter()
+                    .map(|r| InvalidRecipient {
+                        address: r.address,
+                        reason: r.reason,
+                    })
+                    .collect(),
+            };
+            Status::with_details(
+                Code::InvalidArgument,
+                message,
+                details.encode_to_vec().into(),
+            )
+        }
+        RecipientsError::Empty => Status::invalid_argument(message),
+        RecipientsError::Model(_) => Status::internal(message),
+    }
+}
+
+impl RecipientsGrpc {
+    /// normalize the recipients of a message, rejecting it if any of them is malformed
+    pub async fn normalize(&self, msg: &mut Msg) -> Result<(), Status> {
+        let normalized = match msg {
+            Msg::Email(m) => self.0.normalize(
+                MessageType::Email,
+                &mut [&mut m.recipients, &mut m.cc, &mut m.bcc],
+            ),
+            Msg::Sms(m) => self.0.normalize(MessageType::Sms, &mut [&mut m.recipients]),
+            Msg::InApp(_) | Msg::Webhook(_) => Ok(()),
+        };
+        normalized.map_err(recipients_status)
+    }
+
+    /// the address as it is once the recipients of a `channel` message are normalized, as it is
+    /// given if it is malformed
+    pub fn normalized(&self, channel: MessageType, address: &str) -> String {
+        let mut group = vec![address.to_string()];
+        match channel {
+            MessageType::Email | MessageType::Sms => {
+                match self.0.normalize(channel, &mut [&mut group]) {
+                    Ok(()) => group.remove(0),
+                    Err(_) => address.to_string(),
+                }
+            }
+            MessageType::Inapp | MessageType::Webhook | MessageType::Unknown => group.remove(0),
+        }
+    }
+
+    /// drop the recipients on the suppression list, `None` if nobody is left
+    pub async fn apply(&self, msg: Msg) -> Result<Option<Msg>, Status> {
+        let suppressed = self
+            .0
+            .suppressed(&msg.addresses())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if suppressed.is_empty() {
+            return Ok(Some(msg));
+        }
+        info!(
+            "message {:?} not delivered to suppressed {:?}",
+            msg.message_id(),
+            suppressed
+        );
+        Ok(msg.without(&suppressed))
+    }
+}
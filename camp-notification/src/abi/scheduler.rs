@@ -0,0 +1,205 @@
+use camp_core::proto::{ts_to_utc, utc_to_ts};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use sqlx::types::Json;
+use std::sync::Arc;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    model::message::{MessageType, Priority},
+    pb::notification::{
+        send_request::Msg, CancelScheduledRequest, EmailMessage, InAppMessage,
+        ListMessagesResponse, ListScheduledRequest, LocalWindow, MessageStatusResponse,
+        SendRequest, SendResponse, SendResponseType, SmsMessage, WebhookMessage,
+    },
+    services::{
+        lifecycle::Accepted,
+        scheduler::{due_at, Message, MessageQuery, Scheduler, SchedulerError, SendWindow},
+        ServiceError,
+    },
+};
+
+/// the time a message is due and the window it is sent in
+pub type Due = (DateTime<Utc>, Option<SendWindow>);
+
+#[derive(Clone)]
+pub struct SchedulerGrpc(pub Arc<Box<dyn Scheduler>>);
+
+impl TryFrom<&LocalWindow> for SendWindow {
+    type Error = SchedulerError;
+
+    fn try_from(value: &LocalWindow) -> Result<Self, Self::Error> {
+        let tz: Tz = value.timezone.parse().map_err(|_| {
+            SchedulerError::InvalidWindow(format!("unknown timezone {}", value.timezone))
+        })?;
+        if value.start_hour > 23 || value.end_hour > 23 {
+            return Err(SchedulerError::InvalidWindow(
+                "hours must be within 0-23".to_string(),
+            ));
+        }
+        Ok(Self {
+            tz,
+            start_hour: value.start_hour,
+            end_hour: value.end_hour,
+        })
+    }
+}
+
+/// rebuild the request of a stored message so it can be dispatched
+impl From<Message> for Msg {
+    fn from(value: Message) -> Self {
+        match value.r#type {
+            MessageType::Sms => Msg::Sms(SmsMessage {
+                message_id: value.id,
+                sender: value.sender,
+                recipients: value.recipients.unwrap_or_default(),
+                body: value.body,
+            }),
+            MessageType::Inapp => Msg::InApp(InAppMessage {
+                message_id: value.id,
+                device_id: value.device_id.unwrap_or_default(),
+                title: value.title.unwrap_or_default(),
+                body: value.body,
+                sender: value.sender,
+            }),
+            MessageType::Webhook => Msg::Webhook(WebhookMessage {
+                message_id: value.id,
+                endpoint: value
+                    .recipients
+                    .and_then(|r| r.into_iter().next())
+                    .unwrap_or_default(),
+                event: value.subject.unwrap_or_default(),
+                payload: value.body,
+                sender: value.sender,
+            }),
+            MessageType::Email | MessageType::Unknown => Msg::Email(EmailMessage {
+                message_id: value.id,
+                subject: value.subject.unwrap_or_default(),
+                sender: value.sender,
+                recipients: value.recipients.unwrap_or_default(),
+                body: value.body,
+                html_body: value.html_body.unwrap_or_default(),
+                cc: value.cc.unwrap_or_default(),
+                bcc: value.bcc.unwrap_or_default(),
+                reply_to: value.reply_to.unwrap_or_default(),
+                headers: value.headers.map(|h| h.0).unwrap_or_default(),
+                attachments: value
+                    .attachments
+                    .map(|a| a.0.into_iter().map(Into::into).collect())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+impl From<ListScheduledRequest> for MessageQuery {
+    fn from(value: ListScheduledRequest) -> Self {
+        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+        Self {
+            recipient: non_empty(value.recipient),
+            sender: non_empty(value.sender),
+            limit: value.limit as i64,
+            offset: value.offset as i64,
+            ..Default::default()
+        }
+    }
+}
+
+fn scheduler_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Scheduler(SchedulerError::NotFound(id)) => {
+            Status::not_found(format!("message {} not found", id))
+        }
+        ServiceError::Scheduler(e @ SchedulerError::NotCancellable(..)) => {
+            Status::failed_precondition(e.to_string())
+        }
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl SchedulerGrpc {
+    /// the time a request has to wait for and the window it is sent in, `None` if it is sent
+    /// right away
+    pub fn due_at(req: &SendRequest) -> Result<Option<Due>, SchedulerError> {
+        let window = req.window.as_ref().map(SendWindow::try_from).transpose()?;
+        let send_at = req.send_at.as_ref().and_then(ts_to_utc);
+        Ok(due_at(send_at, window, Utc::now()).map(|send_at| (send_at, window)))
+    }
+
+    /// store a message to be sent at `send_at`, retries are moved into `window`
+    pub async fn schedule(
+        &self,
+        msg: &Msg,
+        priority: Priority,
+        send_at: DateTime<Utc>,
+        window: Option<SendWindow>,
+    ) -> Result<SendResponse, Status> {
+        let message = Message {
+            send_window: window.map(Json),
+            ..Message::from_request(msg, priority)
+        };
+        match self
+            .0
+            .schedule(message, send_at)
+            .await
+            .map_err(scheduler_status)?
+        {
+            Accepted::New(message) => {
+                info!("message {:?} scheduled at {}", message.id, send_at);
+                Ok(SendResponse {
+                    message_id: message.id,
+                    timestamp: Some(utc_to_ts(send_at)),
+                    status: SendResponseType::Scheduled as i32,
+                    ..Default::default()
+                })
+            }
+            Accepted::Duplicate(message) => {
+                info!("message {:?} is a duplicate, skip scheduling", message.id);
+                Ok(message.into())
+            }
+        }
+    }
+
+    pub async fn claim_due(&self, limit: i64, lease: Duration) -> Result<Vec<Message>, Status> {
+        self.0
+            .claim_due(limit, lease)
+            .await
+            .map_err(scheduler_status)
+    }
+
+    pub async fn reschedule(
+        &self,
+        id: &str,
+        send_at: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<(), Status> {
+        self.0
+            .reschedule(id, send_at, reason)
+            .await
+            .map_err(scheduler_status)
+    }
+
+    pub async fn list_scheduled(
+        &self,
+        req: ListScheduledRequest,
+    ) -> Result<ListMessagesResponse, Status> {
+        let messages = self.0.list(req.into()).await.map_err(scheduler_status)?;
+        Ok(ListMessagesResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub async fn cancel_scheduled(
+        &self,
+        req: CancelScheduledRequest,
+    ) -> Result<MessageStatusResponse, Status> {
+        info!("cancelling scheduled message {:?}", req.message_id);
+        let message = self
+            .0
+            .cancel(&req.message_id)
+            .await
+            .map_err(scheduler_status)?;
+        Ok(message.into())
+    }
+}
@@ -31,6 +31,7 @@ impl SmsGrpc {
                 message_id: msg.id,
                 timestamp: Some(utc_to_ts(msg.timestamp)),
                 status: SendResponseType::Success as i32,
+                ..Default::default()
             }),
             Err(e) => Err(Status::internal(e.to_string())),
         }
@@ -0,0 +1,115 @@
+use chrono::Utc;
+use std::sync::Arc;
+use tonic::Status;
+use tracing::info;
+
+use crate::{
+    pb::notification::{
+        self, send_request::Msg, EmailMessage, GetTemplateRequest, InAppMessage, SmsMessage,
+        WebhookMessage,
+    },
+    services::{
+        template::{MessageType, Template, TemplateError, Templates},
+        ServiceError,
+    },
+};
+
+#[derive(Clone)]
+pub struct TemplateGrpc(pub Arc<Box<dyn Templates>>);
+
+impl From<notification::Template> for Template {
+    fn from(value: notification::Template) -> Self {
+        Self {
+            channel: value.channel().into(),
+            name: value.name,
+            sender: value.sender,
+            subject: value.subject,
+            body: value.body,
+            html_body: (!value.html_body.is_empty()).then_some(value.html_body),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+impl From<Template> for notification::Template {
+    fn from(value: Template) -> Self {
+        Self {
+            name: value.name,
+            channel: notification::MessageType::from(value.channel) as i32,
+            sender: value.sender,
+            subject: value.subject,
+            body: value.body,
+            html_body: value.html_body.unwrap_or_default(),
+        }
+    }
+}
+
+impl Template {
+    /// the message of a rendered template to `address`
+    pub fn to_msg(self, message_id: String, address: String) -> Msg {
+        match self.channel {
+            MessageType::Email => Msg::Email(EmailMessage {
+                message_id,
+                subject: self.subject,
+                sender: self.sender,
+                recipients: vec![address],
+                body: self.body,
+                html_body: self.html_body.unwrap_or_default(),
+                ..Default::default()
+            }),
+            MessageType::Sms => Msg::Sms(SmsMessage {
+                message_id,
+                sender: self.sender,
+                recipients: vec![address],
+                body: self.body,
+            }),
+            MessageType::Webhook => Msg::Webhook(WebhookMessage {
+                message_id,
+                endpoint: address,
+                event: self.subject,
+                payload: self.body,
+                sender: self.sender,
+            }),
+            // templates are checked for a channel when they are saved
+            MessageType::Inapp | MessageType::Unknown => Msg::InApp(InAppMessage {
+                message_id,
+                device_id: address,
+                title: self.subject,
+                body: self.body,
+                sender: self.sender,
+            }),
+        }
+    }
+}
+
+fn template_status(e: ServiceError) -> Status {
+    match e {
+        ServiceError::Template(TemplateError::NotFound(name)) => {
+            Status::not_found(format!("template {} not found", name))
+        }
+        ServiceError::Template(TemplateError::Invalid(e)) => Status::invalid_argument(e),
+        e => Status::internal(e.to_string()),
+    }
+}
+
+impl TemplateGrpc {
+    pub async fn get(&self, name: &str) -> Result<Template, Status> {
+        self.0.get(name).await.map_err(template_status)
+    }
+
+    pub async fn save_template(
+        &self,
+        req: notification::Template,
+    ) -> Result<notification::Template, Status> {
+        info!("saving template {:?}", req.name);
+        let template = self.0.save(req.into()).await.map_err(template_status)?;
+        Ok(template.into())
+    }
+
+    pub async fn get_template(
+        &self,
+        req: GetTemplateRequest,
+    ) -> Result<notification::Template, Status> {
+        Ok(self.get(&req.name).await?.into())
+    }
+}
@@ -0,0 +1,96 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc};
+use tonic::Code;
+use tracing::warn;
+
+use super::feedback::FeedbackGrpc;
+pub use crate::services::feedback::SIGNATURE_HEADER;
+use crate::{
+    config::FeedbackConfig,
+    services::feedback::{verify_webhook, FeedbackType, ProviderFeedback},
+};
+
+/// body of a feedback webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookFeedback {
+    pub message_id: String,
+    pub recipient: String,
+    pub r#type: FeedbackType,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Clone)]
+struct WebhookState {
+    feedback: FeedbackGrpc,
+    secrets: Arc<HashMap<String, String>>,
+}
+
+type WebhookError = (StatusCode, String);
+
+/// http endpoints the providers post their feedback to
+pub fn router(feedback: FeedbackGrpc, config: &FeedbackConfig) -> Router {
+    Router::new()
+        .route("/feedback/:provider", post(receive_feedback))
+        .with_state(WebhookState {
+            feedback,
+            secrets: Arc::new(config.secrets.clone()),
+        })
+}
+
+async fn receive_feedback(
+    State(state): State<WebhookState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, WebhookError> {
+    let Some(secret) = state.secrets.get(&provider) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("unknown provider {}", provider),
+        ));
+    };
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !verify_webhook(secret, &body, signature) {
+        warn!("rejected webhook of {} with an invalid signature", provider);
+        return Err((StatusCode::UNAUTHORIZED, "invalid signature".to_string()));
+    }
+    let feedback: WebhookFeedback =
+        serde_json::from_slice(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let resp = state
+        .feedback
+        .record(ProviderFeedback {
+            message_id: feedback.message_id,
+            recipient: feedback.recipient,
+            r#type: feedback.r#type,
+            provider,
+            description: feedback.description,
+            created_at: Utc::now(),
+        })
+        .await
+        .map_err(|status| {
+            let code = match status.code() {
+                Code::NotFound => StatusCode::NOT_FOUND,
+                Code::InvalidArgument => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (code, status.message().to_string())
+        })?;
+    Ok(Json(json!({
+        "message_id": resp.message_id,
+        "status": resp.status().as_str_name(),
+        "suppressed": resp.suppressed,
+    })))
+}
@@ -1,13 +1,39 @@
 use anyhow::Result;
+use camp_core::{
+    config::{keep_fixed, ConfigLoader, Reload},
+    metrics::MetricsConfig,
+    server::ServerConfig,
+};
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr},
+};
+
+use crate::model::message::{MessageType, Priority};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub db: DbConfig,
     pub grpc: GrpcConfig,
+    pub idempotency: IdempotencyConfig,
+    pub rate_limit: RateLimitConfig,
+    pub scheduler: SchedulerConfig,
+    pub preferences: PreferencesConfig,
+    pub push: PushConfig,
+    pub email: EmailConfig,
+    pub recipients: RecipientsConfig,
+    pub feedback: FeedbackConfig,
+    pub batch: BatchConfig,
+    pub dispatch: DispatchConfig,
+    pub lanes: LanesConfig,
+    pub webhook: WebhookConfig,
+    /// the `/metrics` http endpoint, not served if unset
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct DbConfig {
     pub host: String,
     pub port: u16,
@@ -16,9 +42,301 @@ pub struct DbConfig {
     pub dbname: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct GrpcConfig {
     pub port: u16,
+    #[serde(flatten)]
+    pub server: ServerConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdempotencyConfig {
+    /// a message id seen within this many seconds is answered with the original response
+    pub window_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackend,
+    pub mode: ThrottleMode,
+    pub max_delay_ms: u64,
+    /// keyed by message type: email, sms, inapp, webhook
+    #[serde(default)]
+    pub channels: HashMap<String, RateLimitRule>,
+    /// keyed by provider name: smtp, sms_gateway, inapp, webhook
+    #[serde(default)]
+    pub providers: HashMap<String, RateLimitRule>,
+    /// keyed by message type, applied to every single recipient
+    #[serde(default)]
+    pub recipients: HashMap<String, RateLimitRule>,
+    /// keyed by priority: high, normal, low
+    #[serde(default)]
+    pub lanes: HashMap<String, RateLimitRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitBackend {
+    Memory,
+    Postgres,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleMode {
+    Delay,
+    Reject,
+}
+
+/// a token bucket holding `capacity` tokens, refilled completely every `per_secs` seconds
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RateLimitRule {
+    pub capacity: u32,
+    pub per_secs: u64,
+}
+
+impl RateLimitRule {
+    pub fn refill_per_sec(&self) -> f64 {
+        self.capacity as f64 / self.per_secs.max(1) as f64
+    }
+}
+
+impl RateLimitConfig {
+    /// a bucket without tokens or refilled in no time never lets a message through, nor does a
+    /// rule keyed by a name which is not a message type or a priority apply
+    pub fn validate(&self) -> Result<(), String> {
+        known_keys(
+            "rate_limit.channels",
+            self.channels.keys(),
+            &message_types(),
+        )?;
+        known_keys(
+            "rate_limit.recipients",
+            self.recipients.keys(),
+            &message_types(),
+        )?;
+        known_keys("rate_limit.lanes", self.lanes.keys(), &priorities())?;
+        let rules = [
+            ("channels", &self.channels),
+            ("providers", &self.providers),
+            ("recipients", &self.recipients),
+            ("lanes", &self.lanes),
+        ];
+        for (section, rules) in rules {
+            for (key, rule) in rules {
+                if rule.capacity == 0 || rule.per_secs == 0 {
+                    return Err(format!(
+                        "rate_limit.{}.{}: capacity and per_secs must be positive",
+                        section, key
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerConfig {
+    /// how often due messages are looked for
+    pub poll_interval_ms: u64,
+    /// max number of due messages dispatched per poll
+    pub batch_size: i64,
+    /// a due message rejected by a rate limit, or failing before it is sent, is tried again
+    /// after this many seconds, within its send window
+    pub throttle_retry_secs: u64,
+    /// a message claimed longer ago and still sending is claimed again, its claimer is taken
+    /// to have died
+    pub lease_secs: u64,
+}
+
+impl SchedulerConfig {
+    /// the scheduler would spin or never dispatch anything
+    pub fn validate(&self) -> Result<(), String> {
+        positive("scheduler.poll_interval_ms", self.poll_interval_ms)?;
+        positive("scheduler.batch_size", self.batch_size.max(0) as u64)?;
+        positive("scheduler.lease_secs", self.lease_secs)
+    }
+}
+
+/// a setting which must not be 0
+fn positive(key: &str, value: u64) -> Result<(), String> {
+    match value {
+        0 => Err(format!("{}: must be positive", key)),
+        _ => Ok(()),
+    }
+}
+
+/// the keys of the map at `section` must be one of `known`
+fn known_keys<'a>(
+    section: &str,
+    mut keys: impl Iterator<Item = &'a String>,
+    known: &[String],
+) -> Result<(), String> {
+    match keys.find(|key| !known.contains(key)) {
+        Some(key) => Err(format!(
+            "{}.{}: not one of {}",
+            section,
+            key,
+            known.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+fn message_types() -> [String; 4] {
+    [
+        MessageType::Email,
+        MessageType::Sms,
+        MessageType::Inapp,
+        MessageType::Webhook,
+    ]
+    .map(|t| t.to_string())
+}
+
+fn priorities() -> [String; 3] {
+    [Priority::High, Priority::Normal, Priority::Low].map(|p| p.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PreferencesConfig {
+    /// key the unsubscribe tokens are signed with
+    pub unsubscribe_secret: String,
+    /// page the unsubscribe token is passed to as the `token` query parameter
+    pub unsubscribe_url: String,
+    /// an unsubscribe link stops working this long after the message was sent
+    pub unsubscribe_ttl_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PushConfig {
+    pub backend: PushBackend,
+    /// postgres channel the in-app messages are published on
+    pub channel: String,
+    /// messages buffered per subscriber, a subscriber falling further behind misses them
+    pub buffer: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PushBackend {
+    /// subscribers only receive messages sent through the same instance
+    Memory,
+    /// messages are published through postgres LISTEN/NOTIFY to every instance
+    Postgres,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EmailConfig {
+    /// max size of an attachment carried in the request
+    pub max_inline_attachment_bytes: u64,
+    /// max size of all attachments of an email, blobs count with their declared size
+    pub max_attachments_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecipientsConfig {
+    /// region of phone numbers given without a country code, e.g. CN
+    pub default_region: Option<phonenumber::country::Id>,
+    /// drop recipients on the suppression list of bounced addresses
+    pub check_suppressions: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FeedbackConfig {
+    /// address the webhook server listens on, `::` or `0.0.0.0` for every interface
+    #[serde(default = "localhost")]
+    pub host: IpAddr,
+    /// port of the http server receiving the webhooks of the providers
+    pub http_port: u16,
+    /// keyed by provider name, the key the provider signs its webhook bodies with
+    #[serde(default)]
+    pub secrets: HashMap<String, String>,
+}
+
+fn localhost() -> IpAddr {
+    IpAddr::V6(Ipv6Addr::LOCALHOST)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchConfig {
+    /// messages of a batch being sent at the same time
+    pub concurrency: usize,
+    /// max number of recipients of a batch
+    pub max_recipients: usize,
+}
+
+impl BatchConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        positive("batch.concurrency", self.concurrency as u64)?;
+        positive("batch.max_recipients", self.max_recipients as u64)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DispatchConfig {
+    /// messages of one Send stream being sent at the same time
+    pub stream_concurrency: usize,
+    /// keyed by message type: email, sms, inapp, webhook, messages being sent at the same time
+    /// by every stream, batch and the scheduler together
+    #[serde(default)]
+    pub channels: HashMap<String, usize>,
+}
+
+impl DispatchConfig {
+    /// a channel limit keyed by a name which is not a message type would never apply
+    pub fn validate(&self) -> Result<(), String> {
+        positive(
+            "dispatch.stream_concurrency",
+            self.stream_concurrency as u64,
+        )?;
+        for (channel, limit) in &self.channels {
+            positive(&format!("dispatch.channels.{}", channel), *limit as u64)?;
+        }
+        known_keys("dispatch.channels", self.channels.keys(), &message_types())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LanesConfig {
+    /// messages being sent at the same time by every lane together
+    pub slots: usize,
+    /// keyed by priority: high, normal, low, share of the freed slots a lane gets while
+    /// messages wait, lanes not listed have a weight of 1
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
+}
+
+impl LanesConfig {
+    /// a weight keyed by a name which is not a priority would never apply
+    pub fn validate(&self) -> Result<(), String> {
+        positive("lanes.slots", self.slots as u64)?;
+        known_keys("lanes.weights", self.weights.keys(), &priorities())?;
+        for (lane, weight) in &self.weights {
+            positive(&format!("lanes.weights.{}", lane), *weight as u64)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookConfig {
+    /// an attempt which takes longer is given up and retried
+    pub timeout_ms: u64,
+    /// attempts after the first one, only timeouts, connection errors, 429 and 5xx are retried
+    pub retries: u32,
+    /// wait before the first retry, doubled for every further one
+    pub backoff_ms: u64,
+    /// keyed by the name the webhook messages address the endpoint with
+    #[serde(default)]
+    pub endpoints: HashMap<String, WebhookEndpoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// key the bodies are signed with, shared with the partner
+    pub secret: String,
 }
 
 impl DbConfig {
@@ -37,14 +355,156 @@ impl DbConfig {
     }
 }
 
+/// values used unless a config file, environment variable or flag sets them
+const DEFAULTS: &str = r#"
+webhook:
+  timeout_ms: 5000
+  retries: 3
+  backoff_ms: 200
+"#;
+
 impl AppConfig {
+    pub fn loader() -> ConfigLoader {
+        ConfigLoader::new("notification")
+            .from_process()
+            .paths([
+                "../camp-notification/notification.yml",
+                "./camp-notification/notification.yml",
+                "./notification.yml",
+                "/etc/config/notification.yml",
+            ])
+            .defaults(DEFAULTS)
+    }
+
     pub fn load() -> Result<Self> {
-        let res = camp_core::config::config_load(vec![
-            "../camp-notification/notification.yml".to_string(),
-            "./camp-notification/notification.yml".to_string(),
-            "./notification.yml".to_string(),
-            "/etc/config/notification.yml".to_string(),
-        ])?;
+        let res: Self = Self::loader().load()?;
+        res.validate().map_err(anyhow::Error::msg)?;
         Ok(res)
     }
 }
+
+/// Rate limits, batches, dispatch limits, lanes, the scheduler and webhooks change live, the
+/// settings the services are built with only on restart.
+impl Reload for AppConfig {
+    fn validate(&self) -> Result<(), String> {
+        self.rate_limit.validate()?;
+        self.scheduler.validate()?;
+        self.batch.validate()?;
+        self.dispatch.validate()?;
+        self.lanes.validate()?;
+        positive("webhook.timeout_ms", self.webhook.timeout_ms)
+    }
+
+    fn keep_fixed(&mut self, current: &Self) -> Vec<&'static str> {
+        let mut changed = vec![];
+        keep_fixed("db", &mut self.db, &current.db, &mut changed);
+        keep_fixed("grpc", &mut self.grpc, &current.grpc, &mut changed);
+        keep_fixed(
+            "idempotency",
+            &mut self.idempotency,
+            &current.idempotency,
+            &mut changed,
+        );
+        keep_fixed(
+            "rate_limit.backend",
+            &mut self.rate_limit.backend,
+            &current.rate_limit.backend,
+            &mut changed,
+        );
+        keep_fixed(
+            "preferences",
+            &mut self.preferences,
+            &current.preferences,
+            &mut changed,
+        );
+        keep_fixed("push", &mut self.push, &current.push, &mut changed);
+        keep_fixed("email", &mut self.email, &current.email, &mut changed);
+        keep_fixed(
+            "recipients",
+            &mut self.recipients,
+            &current.recipients,
+            &mut changed,
+        );
+        keep_fixed(
+            "feedback",
+            &mut self.feedback,
+            &current.feedback,
+            &mut changed,
+        );
+        keep_fixed("metrics", &mut self.metrics, &current.metrics, &mut changed);
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch_channels_must_be_message_types() {
+        let mut dispatch = DispatchConfig {
+            stream_concurrency: 1,
+            channels: HashMap::from([("email".to_string(), 2), ("inapp".to_string(), 2)]),
+        };
+        assert!(dispatch.validate().is_ok());
+        dispatch.channels.insert("emails".to_string(), 2);
+        assert_eq!(
+            dispatch.validate().unwrap_err(),
+            "dispatch.channels.emails: not one of email, sms, inapp, webhook"
+        );
+    }
+
+    #[test]
+    fn test_limits_must_be_positive() {
+        let mut config = AppConfig::load().unwrap();
+        assert!(config.validate().is_ok());
+        config.scheduler.poll_interval_ms = 0;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "scheduler.poll_interval_ms: must be positive"
+        );
+        config.scheduler.poll_interval_ms = 100;
+        config.dispatch.channels.insert("sms".to_string(), 0);
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "dispatch.channels.sms: must be positive"
+        );
+        config.dispatch.channels.insert("sms".to_string(), 1);
+        config.lanes.slots = 0;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "lanes.slots: must be positive"
+        );
+    }
+
+    #[test]
+    fn test_limits_must_be_keyed_by_known_names() {
+        let mut config = AppConfig::load().unwrap();
+        let rule = RateLimitRule {
+            capacity: 1,
+            per_secs: 1,
+        };
+        config
+            .rate_limit
+            .channels
+            .insert("emails".to_string(), rule);
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "rate_limit.channels.emails: not one of email, sms, inapp, webhook"
+        );
+        config.rate_limit.channels.remove("emails");
+        config.rate_limit.lanes.insert("urgent".to_string(), rule);
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "rate_limit.lanes.urgent: not one of high, normal, low"
+        );
+        config.rate_limit.lanes.remove("urgent");
+        config.lanes.weights.insert("hgih".to_string(), 2);
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "lanes.weights.hgih: not one of high, normal, low"
+        );
+        config.lanes.weights.remove("hgih");
+        assert!(config.validate().is_ok());
+    }
+}
@@ -1,5 +1,10 @@
-use crate::model::message::{Message, MessageType};
-use camp_core::core_fake::{before, vec_range_faker, PrefixUUID, UniqueEmail};
+pub mod provider;
+
+use crate::model::{
+    lifecycle::MessageStatus,
+    message::{Message, MessageType, Priority},
+};
+use camp_core::core_fake::{before, vec_range_faker, PrefixUUID, UniqueEmail, UniquePhone};
 use chrono::{DateTime, Utc};
 use fake::{
     faker::{barcode::zh_cn::Isbn10, chrono::zh_cn::DateTimeBetween, name::en::Name},
@@ -26,7 +31,7 @@ pub struct SmsMessageFaker {
     pub id: String,
     #[dummy(faker = "Name()")]
     pub sender: String,
-    #[dummy(faker = "vec_range_faker(1, 10, UniqueEmail)")]
+    #[dummy(faker = "vec_range_faker(1, 10, UniquePhone)")]
     pub recipients: Vec<String>,
     #[dummy(faker = "Isbn10()")]
     pub body: String,
@@ -159,6 +164,16 @@ impl From<MessageFaker> for Message {
             times: value.times,
             device_id: value.device_id,
             title: value.title,
+            status: MessageStatus::Stored,
+            send_at: None,
+            send_window: None,
+            html_body: None,
+            cc: None,
+            bcc: None,
+            reply_to: None,
+            headers: None,
+            attachments: None,
+            priority: Priority::Normal,
             created_at: value.created_at,
             updated_at: value.updated_at,
         }
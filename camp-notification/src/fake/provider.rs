@@ -0,0 +1,48 @@
+use crate::{
+    abi::webhook::{WebhookFeedback, SIGNATURE_HEADER},
+    services::feedback::{sign_webhook, FeedbackType},
+};
+
+/// stands in for an email or sms provider, posting its feedback webhooks like the real one would
+pub struct FakeProvider {
+    pub name: String,
+    pub secret: String,
+    /// base url of the webhook server, e.g. http://[::1]:50054
+    pub url: String,
+    client: reqwest::Client,
+}
+
+impl FakeProvider {
+    pub fn new(name: &str, secret: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            secret: secret.to_string(),
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// post the feedback signed with the secret of the provider
+    pub async fn emit(
+        &self,
+        message_id: &str,
+        recipient: &str,
+        r#type: FeedbackType,
+        description: &str,
+    ) -> reqwest::Result<reqwest::Response> {
+        let feedback = WebhookFeedback {
+            message_id: message_id.to_string(),
+            recipient: recipient.to_string(),
+            r#type,
+            description: description.to_string(),
+        };
+        let body = serde_json::to_vec(&feedback).expect("feedback serializes to json");
+        self.client
+            .post(format!("{}/feedback/{}", self.url, self.name))
+            .header(SIGNATURE_HEADER, sign_webhook(&self.secret, &body))
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+    }
+}
@@ -1,16 +1,31 @@
-use std::net::ToSocketAddrs as _;
-
 use abi::{
-    email::EmailGrpc, inapp::InAppGrpc, sms::SmsGrpc, NotificationGrpc, NotificationGrpcBuilder,
+    dead_letter::DeadLetterGrpc, email::EmailGrpc, feedback::FeedbackGrpc, inapp::InAppGrpc,
+    inbox::InboxGrpc, lanes::Lanes, lifecycle::LifecycleGrpc, limiter::ThrottleGrpc,
+    outgoing_webhook::WebhookGrpc, preferences::PreferencesGrpc, push::PushGrpc,
+    recipients::RecipientsGrpc, scheduler::SchedulerGrpc, sms::SmsGrpc, template::TemplateGrpc,
+    Concurrency, NotificationGrpc, NotificationGrpcBuilder,
 };
 use anyhow::Result;
+use camp_core::{
+    auth::ADMIN_SCOPE,
+    config::section,
+    configure_service,
+    server::{shutdown_signal, GrpcServer},
+};
 use config::AppConfig;
 use derive_builder::Builder;
 use services::{ServicesFactory, ServicesTypes};
 use sqlx::PgPool;
+use std::net::SocketAddr;
+use tokio::{net::TcpListener, sync::watch};
 use tracing::info;
 
-use crate::pb::notification::notification_server::NotificationServer;
+/// scope of `ReportFeedback`, granted to the providers reporting deliveries
+pub const FEEDBACK_SCOPE: &str = "feedback";
+
+use crate::pb::notification::{
+    notification_admin_server::NotificationAdminServer, notification_server::NotificationServer,
+};
 
 pub mod abi;
 pub mod config;
@@ -28,6 +43,7 @@ mod test_utils {
     use super::AppState;
     use sqlx::{Executor, PgPool};
     use sqlx_db_tester::TestPg;
+    use tokio::sync::watch;
 
     use crate::config::AppConfig;
 
@@ -54,8 +70,16 @@ mod test_utils {
 
     impl AppState {
         pub async fn new_for_test() -> Result<(TestPg, Self)> {
-            let (tdb, pool, config) = common_test().await?;
-            Self::new_inner(pool, config)
+            Self::new_for_test_with(|_| {}).await
+        }
+
+        /// the state of a test which needs a config different from notification.yml
+        pub async fn new_for_test_with(
+            configure: impl FnOnce(&mut AppConfig),
+        ) -> Result<(TestPg, Self)> {
+            let (tdb, pool, mut config) = common_test().await?;
+            configure(&mut config);
+            Self::new_inner(pool, watch::channel(config).1)
                 .await
                 .map(|app_state| (tdb, app_state))
         }
@@ -64,25 +88,46 @@ mod test_utils {
 
 #[derive(Clone, Builder)]
 pub struct AppState {
+    /// the config loaded at start, see `config` for the tunables changed since
     pub app_config: AppConfig,
+    /// reloaded while running, see [`camp_core::config::ConfigLoader::watch`]
+    pub config: watch::Receiver<AppConfig>,
     pub pool: PgPool,
     pub notification_grpc: NotificationGrpc,
 }
 
 impl AppState {
-    async fn new_inner(pool: sqlx::PgPool, app_config: AppConfig) -> Result<Self> {
+    async fn new_inner(pool: sqlx::PgPool, config: watch::Receiver<AppConfig>) -> Result<Self> {
+        let app_config = config.borrow().clone();
         let services_factory: Box<dyn ServicesFactory> = Box::new(
-            services::ServicesFactoryImpl::new(ServicesTypes::AllUnimock, pool.clone()),
+            services::ServicesFactoryImpl::new(ServicesTypes::AllUnimock, pool.clone(), &config),
         );
 
         let notification_grpc = NotificationGrpcBuilder::default()
             .email(EmailGrpc(services_factory.email()))
             .inapp(InAppGrpc(services_factory.inapp()))
             .sms(SmsGrpc(services_factory.sms()))
+            .lifecycle(LifecycleGrpc(services_factory.lifecycle()))
+            .throttle(ThrottleGrpc(services_factory.throttle()))
+            .scheduler(SchedulerGrpc(services_factory.scheduler()))
+            .preferences(PreferencesGrpc(services_factory.preferences()))
+            .inbox(InboxGrpc(services_factory.inbox()))
+            .push(PushGrpc(services_factory.push()))
+            .recipients(RecipientsGrpc(services_factory.recipients()))
+            .feedback(FeedbackGrpc(services_factory.feedback()))
+            .template(TemplateGrpc(services_factory.templates()))
+            .dead_letter(DeadLetterGrpc(services_factory.dead_letters()))
+            .webhook(WebhookGrpc(services_factory.webhook()))
+            .batch(section(&config, |config| config.batch.clone()))
+            .concurrency(Concurrency::new(section(&config, |config| {
+                config.dispatch.clone()
+            })))
+            .lanes(Lanes::new(section(&config, |config| config.lanes.clone())))
             .build()?;
 
         Ok(Self {
             app_config,
+            config,
             pool,
             notification_grpc,
         })
@@ -90,15 +135,80 @@ impl AppState {
     pub async fn new() -> Result<Self> {
         let app_config = AppConfig::load()?;
         let pool = PgPool::connect(&app_config.db.to_connect_url()).await?;
-        Self::new_inner(pool, app_config).await
+        Self::new_inner(pool, AppConfig::loader().watch(app_config)).await
     }
 
     pub async fn grpc_run(&self) -> Result<()> {
-        let addr = format!("[::1]:{:?}", self.app_config.grpc.port);
-        info!("notification_grpc server is ready to running on {:?}", addr);
-        tonic::transport::Server::builder()
-            .add_service(NotificationServer::new(self.notification_grpc.clone()))
-            .serve(addr.to_socket_addrs()?.next().unwrap())
+        self.grpc_serve(None).await
+    }
+
+    /// serve grpc on `listener` instead of the configured port
+    pub async fn grpc_run_on(&self, listener: TcpListener) -> Result<()> {
+        self.grpc_serve(Some(listener)).await
+    }
+
+    async fn grpc_serve(&self, listener: Option<TcpListener>) -> Result<()> {
+        let notification = self.notification_grpc.clone();
+        let scheduler_config = section(&self.config, |config| config.scheduler.clone());
+        // the scheduler stops claiming messages once shutting down, while the server drains
+        let (stopping, mut stopped) = watch::channel(false);
+        let scheduler = tokio::spawn(notification.run_scheduler(scheduler_config, async move {
+            let _ = stopped.wait_for(|stopped| *stopped).await;
+        }));
+        let push = self.notification_grpc.push.0.clone();
+        let push_listener = tokio::spawn(async move { push.listen().await });
+        let config = &self.app_config.grpc;
+        let mut server = GrpcServer::new("notification", config.port, &config.server)?
+            .require_scope("notification.NotificationAdmin", ADMIN_SCOPE)
+            .require_scope("/notification.Notification/ReportFeedback", FEEDBACK_SCOPE)
+            .require_scope("/notification.Notification/SaveTemplate", ADMIN_SCOPE)
+            .add_service(configure_service!(
+                NotificationServer::new(self.notification_grpc.clone()),
+                &config.server
+            ))
+            .add_service(configure_service!(
+                NotificationAdminServer::new(self.notification_grpc.clone()),
+                &config.server
+            ))
+            .file_descriptor_set(pb::FILE_DESCRIPTOR_SET)
+            .metrics(self.app_config.metrics.as_ref());
+        if let Some(listener) = listener {
+            server = server.listener(listener);
+        }
+        let served = server
+            .serve_with_shutdown(async move {
+                shutdown_signal().await;
+                let _ = stopping.send(true);
+            })
+            .await;
+        scheduler.abort();
+        // the push listener holds a connection of its own
+        push_listener.abort();
+        served?;
+        Ok(())
+    }
+
+    /// serve the http webhooks of the providers
+    pub async fn webhook_run(&self) -> Result<()> {
+        let config = &self.app_config.feedback;
+        let addr = SocketAddr::new(config.host, config.http_port);
+        self.webhook_run_on(std::net::TcpListener::bind(addr)?)
+            .await
+    }
+
+    /// serve the http webhooks on `listener` instead of the configured port
+    pub async fn webhook_run_on(&self, listener: std::net::TcpListener) -> Result<()> {
+        info!(
+            "feedback webhook server is ready to running on {:?}",
+            listener.local_addr()?
+        );
+        let webhook = abi::webhook::router(
+            self.notification_grpc.feedback.clone(),
+            &self.app_config.feedback,
+        );
+        axum::Server::from_tcp(listener)?
+            .serve(webhook.into_make_service())
+            .with_graceful_shutdown(shutdown_signal())
             .await?;
         Ok(())
     }
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+use super::{
+    lifecycle::{MessageEvent, MessageStatus},
+    message::{Message, MessageError, MessageType},
+};
+
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+/// statuses a message can be replayed from
+pub const REPLAYABLE: [MessageStatus; 3] = [
+    MessageStatus::DeadLettered,
+    MessageStatus::Stored,
+    MessageStatus::Failed,
+];
+
+#[derive(Debug, Default, Clone)]
+pub struct DeadLetterQuery {
+    /// dead-lettered and stored messages if empty
+    pub statuses: Vec<MessageStatus>,
+    pub r#type: Option<MessageType>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// text the last error contains, case insensitive
+    pub error: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// a message which could not be sent together with the response of its last attempt
+#[derive(Debug, Clone, FromRow)]
+pub struct DeadLetter {
+    #[sqlx(flatten)]
+    pub message: Message,
+    pub error: Option<String>,
+}
+
+/// audit record of a message an operator sent again
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct Replay {
+    pub message_id: String,
+    pub operator: String,
+    pub reason: String,
+    /// recipients the message was sent to instead of the original ones
+    pub recipients: Option<Vec<String>>,
+    /// status the replay ended in, or the error it failed with
+    pub outcome: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> DeadLetter {
+    pub async fn list<T>(query: &DeadLetterQuery, executor: T) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let limit = match query.limit {
+            0 => DEFAULT_LIST_LIMIT,
+            limit => limit.min(MAX_LIST_LIMIT),
+        };
+        let statuses = match query.statuses.as_slice() {
+            [] => vec![MessageStatus::DeadLettered, MessageStatus::Stored],
+            statuses => statuses.to_vec(),
+        };
+        let messages = sqlx::query_as(
+            r#"
+            SELECT m.*, e.provider_response AS error FROM messages m
+            LEFT JOIN LATERAL (
+              SELECT provider_response FROM message_events
+              WHERE message_id = m.id ORDER BY created_at DESC, id DESC LIMIT 1
+            ) e ON TRUE
+            WHERE m.status = ANY($1)
+              AND ($2::message_type IS NULL OR m.type = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR m.created_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR m.created_at < $4)
+              AND ($5::TEXT IS NULL OR e.provider_response ILIKE '%' || $5 || '%')
+            ORDER BY m.updated_at DESC, m.id
+            LIMIT $6 OFFSET $7
+            "#,
+        )
+        .bind(statuses)
+        .bind(query.r#type)
+        .bind(query.start)
+        .bind(query.end)
+        .bind(&query.error)
+        .bind(limit)
+        .bind(query.offset)
+        .fetch_all(executor)
+        .await?;
+        Ok(messages)
+    }
+
+    pub async fn find<T>(id: &str, executor: T) -> Result<Option<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let message = sqlx::query_as(
+            r#"
+            SELECT m.*, e.provider_response AS error FROM messages m
+            LEFT JOIN LATERAL (
+              SELECT provider_response FROM message_events
+              WHERE message_id = m.id ORDER BY created_at DESC, id DESC LIMIT 1
+            ) e ON TRUE
+            WHERE m.id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+        Ok(message)
+    }
+
+    /// claim a replayable message, marking it sending, and record `replay` in the audit, with the
+    /// recipients of the replay in place of the original ones if given, the device id of an
+    /// in-app message. The cc and bcc of an email are dropped with its original recipients.
+    ///
+    /// Returns the message and the id of its replay, `None` if the message is not in a
+    /// replayable status, e.g. it is being replayed.
+    pub async fn start_replay(
+        replay: &Replay,
+        pool: &PgPool,
+    ) -> Result<Option<(Message, i64)>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let message: Option<Message> = sqlx::query_as(
+            r#"
+            UPDATE messages SET status = 'sending', times = times + 1, updated_at = NOW(),
+              claimed_at = NOW(),
+              recipients = CASE WHEN $2::VARCHAR[] IS NULL OR type = 'inapp' THEN recipients ELSE $2 END,
+              device_id = CASE WHEN $2::VARCHAR[] IS NOT NULL AND type = 'inapp' THEN $2[1] ELSE device_id END,
+              cc = CASE WHEN $2::VARCHAR[] IS NULL THEN cc END,
+              bcc = CASE WHEN $2::VARCHAR[] IS NULL THEN bcc END
+            WHERE id = $1 AND status = ANY($3)
+            RETURNING *
+            "#,
+        )
+        .bind(&replay.message_id)
+        .bind(&replay.recipients)
+        .bind(REPLAYABLE)
+        .fetch_optional(&mut *ts)
+        .await?;
+        let Some(message) = message else {
+            return Ok(None);
+        };
+        let note = format!("replayed by {}", replay.operator);
+        MessageEvent::insert(&message.id, MessageStatus::Sending, Some(&note), &mut *ts).await?;
+        let replay_id = replay.insert(&mut *ts).await?;
+        ts.commit().await?;
+        Ok(Some((message, replay_id)))
+    }
+}
+
+impl<'a> Replay {
+    /// returns the id of the record
+    pub async fn insert<T>(&self, executor: T) -> Result<i64, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let (id,) = sqlx::query_as(
+            r#"
+            INSERT INTO replays (message_id, operator, reason, recipients, outcome)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(&self.message_id)
+        .bind(&self.operator)
+        .bind(&self.reason)
+        .bind(&self.recipients)
+        .bind(&self.outcome)
+        .fetch_one(executor)
+        .await?;
+        Ok(id)
+    }
+
+    /// set the outcome of a replay recorded when it started
+    pub async fn finish<T>(id: i64, outcome: &str, executor: T) -> Result<(), MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE replays SET outcome = $2 WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(outcome)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_by_message<T>(
+        message_id: &str,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let replays = sqlx::query_as(
+            r#"
+            SELECT message_id, operator, reason, recipients, outcome, created_at FROM replays
+            WHERE message_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(replays)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::message::SmsMessage, test_utils::common_test};
+    use chrono::Duration;
+
+    fn sms(id: &str) -> Message {
+        SmsMessage {
+            id: id.to_string(),
+            sender: "sender".to_string(),
+            body: "body".to_string(),
+            recipients: vec!["+8613800138000".to_string()],
+        }
+        .into()
+    }
+
+    async fn dead_lettered(id: &str, error: &str, pool: &PgPool) -> Message {
+        let message = sms(id);
+        let message = message.accept(Utc::now(), pool).await.unwrap().unwrap();
+        Message::transition(id, MessageStatus::DeadLettered, Some(error), pool)
+            .await
+            .unwrap();
+        message
+    }
+
+    #[tokio::test]
+    async fn test_list_and_replay() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        dead_lettered("dl-1", "gateway timeout", &pool).await;
+        dead_lettered("dl-2", "invalid number", &pool).await;
+
+        let query = DeadLetterQuery {
+            error: Some("TIMEOUT".to_string()),
+            ..Default::default()
+        };
+        let found = DeadLetter::list(&query, &pool).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message.id, "dl-1");
+        assert_eq!(found[0].error.as_deref(), Some("gateway timeout"));
+        let query = DeadLetterQuery {
+            r#type: Some(MessageType::Inapp),
+            ..Default::default()
+        };
+        assert!(DeadLetter::list(&query, &pool).await.unwrap().is_empty());
+
+        let recipients = vec!["+8613900139000".to_string()];
+        let replay = Replay {
+            message_id: "dl-2".to_string(),
+            operator: "ops".to_string(),
+            reason: "number fixed".to_string(),
+            recipients: Some(recipients.clone()),
+            outcome: "REPLAYING".to_string(),
+            created_at: Utc::now(),
+        };
+        let (replayed, replay_id) = DeadLetter::start_replay(&replay, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(replayed.status, MessageStatus::Sending);
+        assert_eq!(replayed.recipients, Some(recipients));
+        assert_eq!(replayed.times, 2);
+        // the replay is recorded with the claim
+        let replays = Replay::list_by_message("dl-2", &pool).await.unwrap();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].outcome, "REPLAYING");
+        // a message being replayed can not be replayed again, nor is it recorded
+        let again = DeadLetter::start_replay(&replay, &pool).await.unwrap();
+        assert!(again.is_none());
+
+        Replay::finish(replay_id, "SEND_RESPONSE_TYPE_SUCCESS", &pool)
+            .await
+            .unwrap();
+        let replays = Replay::list_by_message("dl-2", &pool).await.unwrap();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].reason, "number fixed");
+        assert_eq!(replays[0].outcome, "SEND_RESPONSE_TYPE_SUCCESS");
+    }
+
+    #[tokio::test]
+    async fn test_replay_is_not_claimed_by_the_scheduler() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let mut message = sms("dl-scheduled");
+        message.status = MessageStatus::Scheduled;
+        message.send_at = Some(Utc::now() - Duration::seconds(1));
+        message
+            .accept(Utc::now() - Duration::hours(1), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        let lease = Duration::milliseconds(40);
+        assert_eq!(Message::claim_due(10, lease, &pool).await.unwrap().len(), 1);
+        Message::transition(
+            "dl-scheduled",
+            MessageStatus::DeadLettered,
+            Some("gateway timeout"),
+            &pool,
+        )
+        .await
+        .unwrap();
+        // the claim of the scheduler expires
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+
+        let replay = Replay {
+            message_id: "dl-scheduled".to_string(),
+            operator: "ops".to_string(),
+            reason: "gateway back".to_string(),
+            recipients: None,
+            outcome: "REPLAYING".to_string(),
+            created_at: Utc::now(),
+        };
+        DeadLetter::start_replay(&replay, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(Message::claim_due(10, lease, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
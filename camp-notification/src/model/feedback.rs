@@ -0,0 +1,305 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool, Postgres, Transaction, Type};
+
+use super::{
+    lifecycle::{MessageEvent, MessageStatus},
+    message::MessageError,
+    suppression::{Suppression, SuppressionReason},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "feedback_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackType {
+    Delivered,
+    SoftBounce,
+    HardBounce,
+    Complaint,
+}
+
+impl FeedbackType {
+    /// status the message moves to, a soft bounce leaves it as it is
+    pub fn status(&self) -> Option<MessageStatus> {
+        match self {
+            FeedbackType::Delivered => Some(MessageStatus::Delivered),
+            FeedbackType::SoftBounce => None,
+            FeedbackType::HardBounce => Some(MessageStatus::Bounced),
+            FeedbackType::Complaint => Some(MessageStatus::Complained),
+        }
+    }
+
+    /// why the recipient must not receive further messages, if at all
+    pub fn suppression(&self) -> Option<SuppressionReason> {
+        match self {
+            FeedbackType::HardBounce => Some(SuppressionReason::Bounced),
+            FeedbackType::Complaint => Some(SuppressionReason::Complained),
+            FeedbackType::Delivered | FeedbackType::SoftBounce => None,
+        }
+    }
+}
+
+/// how far feedback took a message or recipient, `None` for the statuses feedback does not move.
+///
+/// The feedback may come in before the outcome of the send is recorded.
+fn progress(status: MessageStatus) -> Option<u8> {
+    match status {
+        MessageStatus::Accepted
+        | MessageStatus::Sending
+        | MessageStatus::Sent
+        | MessageStatus::Stored => Some(0),
+        MessageStatus::Delivered => Some(1),
+        MessageStatus::Bounced => Some(2),
+        MessageStatus::Complained => Some(3),
+        _ => None,
+    }
+}
+
+/// feedback only moves forward, a late delivery receipt does not undo a bounce
+fn moves_forward(current: Option<MessageStatus>, next: MessageStatus) -> bool {
+    match current {
+        None => true,
+        Some(current) => matches!(
+            (progress(current), progress(next)),
+            (Some(current), Some(next)) if current < next
+        ),
+    }
+}
+
+/// the outcome a provider reported for one recipient of a message
+#[derive(Debug, Clone, FromRow)]
+pub struct RecipientOutcome {
+    pub message_id: String,
+    pub recipient: String,
+    pub status: MessageStatus,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'a> RecipientOutcome {
+    pub async fn list_by_message<T>(
+        message_id: &str,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let outcomes = sqlx::query_as(
+            r#"
+            SELECT message_id, recipient, status, updated_at FROM recipient_outcomes
+            WHERE message_id = $1
+            ORDER BY recipient
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(outcomes)
+    }
+}
+
+/// a bounce, complaint or delivery receipt reported by a provider
+#[derive(Debug, Clone, FromRow)]
+pub struct Feedback {
+    pub message_id: String,
+    pub recipient: String,
+    pub r#type: FeedbackType,
+    pub provider: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> Feedback {
+    /// store the feedback, move the recipient and the message on and suppress the recipient if the
+    /// feedback asks for it. The recipient must be normalized like the recipients of the message.
+    ///
+    /// Returns the status of the message afterwards, `None` if the message is unknown.
+    pub async fn record(&self, pool: &PgPool) -> Result<Option<MessageStatus>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let current: Option<(MessageStatus,)> =
+            sqlx::query_as("SELECT status FROM messages WHERE id = $1 FOR UPDATE")
+                .bind(&self.message_id)
+                .fetch_optional(&mut *ts)
+                .await?;
+        let Some((current,)) = current else {
+            return Ok(None);
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO feedback (message_id, recipient, type, provider, description, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&self.message_id)
+        .bind(&self.recipient)
+        .bind(self.r#type)
+        .bind(&self.provider)
+        .bind(&self.description)
+        .bind(self.created_at)
+        .execute(&mut *ts)
+        .await?;
+        let status = match self.r#type.status() {
+            Some(next) => self.advance(current, next, &mut ts).await?,
+            None => current,
+        };
+        if let Some(reason) = self.r#type.suppression() {
+            Suppression::insert(&self.recipient, reason, &mut *ts).await?;
+        }
+        ts.commit().await?;
+        Ok(Some(status))
+    }
+
+    /// move the recipient and the message to `next` if it is further, returns the message status
+    async fn advance(
+        &self,
+        current: MessageStatus,
+        next: MessageStatus,
+        ts: &mut Transaction<'_, Postgres>,
+    ) -> Result<MessageStatus, MessageError> {
+        // the message is locked, so are the outcomes of its recipients
+        let outcome: Option<(MessageStatus,)> = sqlx::query_as(
+            "SELECT status FROM recipient_outcomes WHERE message_id = $1 AND recipient = $2",
+        )
+        .bind(&self.message_id)
+        .bind(&self.recipient)
+        .fetch_optional(&mut **ts)
+        .await?;
+        if moves_forward(outcome.map(|(status,)| status), next) {
+            sqlx::query(
+                r#"
+                INSERT INTO recipient_outcomes (message_id, recipient, status) VALUES ($1, $2, $3)
+                ON CONFLICT (message_id, recipient) DO UPDATE SET
+                  status = EXCLUDED.status, updated_at = NOW()
+                "#,
+            )
+            .bind(&self.message_id)
+            .bind(&self.recipient)
+            .bind(next)
+            .execute(&mut **ts)
+            .await?;
+        }
+        if !moves_forward(Some(current), next) {
+            return Ok(current);
+        }
+        sqlx::query("UPDATE messages SET status = $2, updated_at = NOW() WHERE id = $1")
+            .bind(&self.message_id)
+            .bind(next)
+            .execute(&mut **ts)
+            .await?;
+        let response = format!("{} {}: {}", self.provider, self.recipient, self.description);
+        MessageEvent::insert(&self.message_id, next, Some(&response), &mut **ts).await?;
+        Ok(next)
+    }
+
+    /// feedback on a message, oldest first
+    pub async fn list_by_message<T>(
+        message_id: &str,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let feedback = sqlx::query_as(
+            r#"
+            SELECT message_id, recipient, type, provider, description, created_at FROM feedback
+            WHERE message_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(feedback)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::message::{EmailMessage, Message},
+        test_utils::common_test,
+    };
+
+    fn feedback(recipient: &str, r#type: FeedbackType) -> Feedback {
+        Feedback {
+            message_id: "feedback_id".to_string(),
+            recipient: recipient.to_string(),
+            r#type,
+            provider: "smtp".to_string(),
+            description: "550 mailbox unavailable".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_feedback() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        assert_eq!(
+            feedback("a@test.com", FeedbackType::Delivered)
+                .record(&pool)
+                .await
+                .unwrap(),
+            None
+        );
+        Message::insert_email(
+            EmailMessage {
+                id: "feedback_id".to_string(),
+                recipients: vec!["a@test.com".to_string(), "b@test.com".to_string()],
+                ..Default::default()
+            },
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let status = feedback("a@test.com", FeedbackType::Delivered)
+            .record(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, Some(MessageStatus::Delivered));
+        let status = feedback("b@test.com", FeedbackType::SoftBounce)
+            .record(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, Some(MessageStatus::Delivered));
+        let status = feedback("b@test.com", FeedbackType::HardBounce)
+            .record(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, Some(MessageStatus::Bounced));
+        // neither the message nor the recipient move back
+        let status = feedback("b@test.com", FeedbackType::Delivered)
+            .record(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, Some(MessageStatus::Bounced));
+        let outcomes = RecipientOutcome::list_by_message("feedback_id", &pool)
+            .await
+            .unwrap();
+        let outcomes: Vec<_> = outcomes
+            .iter()
+            .map(|o| (o.recipient.as_str(), o.status))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("a@test.com", MessageStatus::Delivered),
+                ("b@test.com", MessageStatus::Bounced)
+            ]
+        );
+
+        let suppressed = Suppression::find(&["a@test.com", "b@test.com"], &pool)
+            .await
+            .unwrap();
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(suppressed[0].address, "b@test.com");
+        let recorded = Feedback::list_by_message("feedback_id", &pool)
+            .await
+            .unwrap();
+        assert_eq!(recorded.len(), 4);
+        let events = MessageEvent::list_by_message("feedback_id", &pool)
+            .await
+            .unwrap();
+        assert_eq!(events.last().unwrap().status, MessageStatus::Bounced);
+    }
+}
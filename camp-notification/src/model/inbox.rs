@@ -0,0 +1,342 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool, Postgres, Transaction, Type};
+
+use super::message::{InAppMessage, MessageError};
+
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct InboxMessage {
+    pub message_id: String,
+    pub device_id: String,
+    pub user_id: Option<String>,
+    pub sender: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub opened_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "inbox_event_type", rename_all = "snake_case")]
+pub enum InboxEvent {
+    Delivered,
+    Read,
+    Opened,
+    Deleted,
+}
+
+/// whose inbox to look at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InboxOwner {
+    Device(String),
+    /// every device of the user
+    User(String),
+}
+
+impl InboxOwner {
+    /// (device_id, user_id) to bind, the one not in use is `None` and matches nothing
+    fn binds(&self) -> (Option<&str>, Option<&str>) {
+        match self {
+            InboxOwner::Device(device_id) => (Some(device_id), None),
+            InboxOwner::User(user_id) => (None, Some(user_id)),
+        }
+    }
+}
+
+async fn insert_events(
+    ids: &[String],
+    event: InboxEvent,
+    ts: &mut Transaction<'_, Postgres>,
+) -> Result<(), MessageError> {
+    sqlx::query(
+        r#"
+        INSERT INTO inbox_events (message_id, event) SELECT UNNEST($1::VARCHAR[]), $2
+        "#,
+    )
+    .bind(ids)
+    .bind(event)
+    .execute(&mut **ts)
+    .await?;
+    Ok(())
+}
+
+impl From<InboxMessage> for InAppMessage {
+    fn from(message: InboxMessage) -> Self {
+        Self {
+            id: message.message_id,
+            sender: message.sender,
+            body: message.body,
+            device_id: message.device_id,
+            title: message.title,
+        }
+    }
+}
+
+impl<'a> InboxMessage {
+    /// store an in-app message in the inbox of its device
+    pub async fn deliver(msg: &InAppMessage, pool: &PgPool) -> Result<Self, MessageError> {
+        let mut ts = pool.begin().await?;
+        let message: Self = sqlx::query_as(
+            r#"
+            INSERT INTO inbox (message_id, device_id, user_id, sender, title, body)
+            VALUES ($1, $2, (SELECT user_id FROM preference_addresses WHERE address = $2), $3, $4, $5)
+            ON CONFLICT (message_id) DO UPDATE SET
+              device_id = EXCLUDED.device_id, user_id = EXCLUDED.user_id, sender = EXCLUDED.sender,
+              title = EXCLUDED.title, body = EXCLUDED.body, created_at = NOW(),
+              read_at = NULL, opened_at = NULL, deleted_at = NULL
+            RETURNING message_id, device_id, user_id, sender, title, body, created_at, read_at, opened_at
+            "#,
+        )
+        .bind(&msg.id)
+        .bind(&msg.device_id)
+        .bind(&msg.sender)
+        .bind(&msg.title)
+        .bind(&msg.body)
+        .fetch_one(&mut *ts)
+        .await?;
+        insert_events(
+            std::slice::from_ref(&message.message_id),
+            InboxEvent::Delivered,
+            &mut ts,
+        )
+        .await?;
+        ts.commit().await?;
+        Ok(message)
+    }
+
+    /// the message if it is still in the inbox
+    pub async fn get<T>(id: &str, executor: T) -> Result<Option<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let message = sqlx::query_as(
+            r#"
+            SELECT message_id, device_id, user_id, sender, title, body, created_at, read_at, opened_at
+            FROM inbox
+            WHERE message_id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+        Ok(message)
+    }
+
+    pub async fn list<T>(
+        owner: &InboxOwner,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let limit = match limit {
+            0 => DEFAULT_LIST_LIMIT,
+            limit => limit.min(MAX_LIST_LIMIT),
+        };
+        let (device_id, user_id) = owner.binds();
+        let messages = sqlx::query_as(
+            r#"
+            SELECT message_id, device_id, user_id, sender, title, body, created_at, read_at, opened_at
+            FROM inbox
+            WHERE (device_id = $1 OR user_id = $2) AND deleted_at IS NULL
+              AND (NOT $3 OR read_at IS NULL)
+            ORDER BY created_at DESC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(executor)
+        .await?;
+        Ok(messages)
+    }
+
+    pub async fn unread_count<T>(owner: &InboxOwner, executor: T) -> Result<i64, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let (device_id, user_id) = owner.binds();
+        let (count,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM inbox
+            WHERE (device_id = $1 OR user_id = $2) AND deleted_at IS NULL AND read_at IS NULL
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .fetch_one(executor)
+        .await?;
+        Ok(count)
+    }
+
+    /// mark messages as read, and as opened if `opened`, returns the number of messages changed
+    pub async fn mark_read(
+        owner: &InboxOwner,
+        ids: &[String],
+        opened: bool,
+        pool: &PgPool,
+    ) -> Result<u64, MessageError> {
+        let (device_id, user_id) = owner.binds();
+        let mut ts = pool.begin().await?;
+        let read: Vec<(String,)> = sqlx::query_as(
+            r#"
+            UPDATE inbox SET read_at = NOW()
+            WHERE (device_id = $1 OR user_id = $2) AND message_id = ANY($3)
+              AND deleted_at IS NULL AND read_at IS NULL
+            RETURNING message_id
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .bind(ids)
+        .fetch_all(&mut *ts)
+        .await?;
+        let read: Vec<String> = read.into_iter().map(|(id,)| id).collect();
+        insert_events(&read, InboxEvent::Read, &mut ts).await?;
+        let mut updated = read.len() as u64;
+        if opened {
+            let opened: Vec<(String,)> = sqlx::query_as(
+                r#"
+                UPDATE inbox SET opened_at = NOW()
+                WHERE (device_id = $1 OR user_id = $2) AND message_id = ANY($3)
+                  AND deleted_at IS NULL AND opened_at IS NULL
+                RETURNING message_id
+                "#,
+            )
+            .bind(device_id)
+            .bind(user_id)
+            .bind(ids)
+            .fetch_all(&mut *ts)
+            .await?;
+            let opened: Vec<String> = opened.into_iter().map(|(id,)| id).collect();
+            insert_events(&opened, InboxEvent::Opened, &mut ts).await?;
+            updated = updated.max(opened.len() as u64);
+        }
+        ts.commit().await?;
+        Ok(updated)
+    }
+
+    pub async fn mark_all_read(owner: &InboxOwner, pool: &PgPool) -> Result<u64, MessageError> {
+        let (device_id, user_id) = owner.binds();
+        let mut ts = pool.begin().await?;
+        let read: Vec<(String,)> = sqlx::query_as(
+            r#"
+            UPDATE inbox SET read_at = NOW()
+            WHERE (device_id = $1 OR user_id = $2) AND deleted_at IS NULL AND read_at IS NULL
+            RETURNING message_id
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .fetch_all(&mut *ts)
+        .await?;
+        let read: Vec<String> = read.into_iter().map(|(id,)| id).collect();
+        insert_events(&read, InboxEvent::Read, &mut ts).await?;
+        ts.commit().await?;
+        Ok(read.len() as u64)
+    }
+
+    /// hide a message from the inbox, its engagement is kept
+    pub async fn delete(owner: &InboxOwner, id: &str, pool: &PgPool) -> Result<u64, MessageError> {
+        let (device_id, user_id) = owner.binds();
+        let mut ts = pool.begin().await?;
+        let deleted: Vec<(String,)> = sqlx::query_as(
+            r#"
+            UPDATE inbox SET deleted_at = NOW()
+            WHERE (device_id = $1 OR user_id = $2) AND message_id = $3 AND deleted_at IS NULL
+            RETURNING message_id
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .bind(id)
+        .fetch_all(&mut *ts)
+        .await?;
+        let deleted: Vec<String> = deleted.into_iter().map(|(id,)| id).collect();
+        insert_events(&deleted, InboxEvent::Deleted, &mut ts).await?;
+        ts.commit().await?;
+        Ok(deleted.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::common_test;
+
+    fn inapp(id: &str, device_id: &str) -> InAppMessage {
+        InAppMessage {
+            id: id.to_string(),
+            sender: "inbox_sender".to_string(),
+            body: "body".to_string(),
+            device_id: device_id.to_string(),
+            title: "title".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inbox() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        for id in ["m1", "m2", "m3"] {
+            InboxMessage::deliver(&inapp(id, "device-1"), &pool)
+                .await
+                .unwrap();
+        }
+        InboxMessage::deliver(&inapp("other", "device-2"), &pool)
+            .await
+            .unwrap();
+        let owner = InboxOwner::Device("device-1".to_string());
+        assert_eq!(InboxMessage::unread_count(&owner, &pool).await.unwrap(), 3);
+
+        let ids = vec!["m1".to_string(), "other".to_string()];
+        let updated = InboxMessage::mark_read(&owner, &ids, true, &pool)
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+        let unread = InboxMessage::list(&owner, true, 0, 0, &pool).await.unwrap();
+        assert_eq!(unread.len(), 2);
+        assert!(unread.iter().all(|m| m.read_at.is_none()));
+
+        assert_eq!(InboxMessage::delete(&owner, "m2", &pool).await.unwrap(), 1);
+        assert_eq!(InboxMessage::delete(&owner, "m2", &pool).await.unwrap(), 0);
+        assert!(InboxMessage::get("m2", &pool).await.unwrap().is_none());
+        let stored = InboxMessage::get("m3", &pool).await.unwrap().unwrap();
+        let stored = InAppMessage::from(stored);
+        assert_eq!(
+            (stored.id.as_str(), stored.device_id.as_str()),
+            ("m3", "device-1")
+        );
+        assert_eq!(InboxMessage::mark_all_read(&owner, &pool).await.unwrap(), 1);
+        assert_eq!(InboxMessage::unread_count(&owner, &pool).await.unwrap(), 0);
+        let all = InboxMessage::list(&owner, false, 0, 0, &pool)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+
+        // the events are read by the campaign reports, not through the service
+        let events: Vec<(InboxEvent,)> = sqlx::query_as(
+            "SELECT event FROM inbox_events WHERE message_id = $1 ORDER BY created_at, id",
+        )
+        .bind("m1")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let events: Vec<_> = events.into_iter().map(|(e,)| e).collect();
+        assert_eq!(
+            events,
+            vec![InboxEvent::Delivered, InboxEvent::Read, InboxEvent::Opened]
+        );
+        let other = InboxOwner::Device("device-2".to_string());
+        assert_eq!(InboxMessage::unread_count(&other, &pool).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    postgres::{PgHasArrayType, PgTypeInfo},
+    FromRow, PgExecutor, PgPool, Type,
+};
+
+use super::message::{Message, MessageError};
+
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "message_status", rename_all = "snake_case")]
+pub enum MessageStatus {
+    Accepted,
+    Sending,
+    Sent,
+    Stored,
+    Failed,
+    DeadLettered,
+    Scheduled,
+    Cancelled,
+    Suppressed,
+    Delivered,
+    Bounced,
+    Complained,
+}
+
+impl PgHasArrayType for MessageStatus {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_message_status")
+    }
+}
+
+impl Display for MessageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageStatus::Accepted => write!(f, "accepted"),
+            MessageStatus::Sending => write!(f, "sending"),
+            MessageStatus::Sent => write!(f, "sent"),
+            MessageStatus::Stored => write!(f, "stored"),
+            MessageStatus::Failed => write!(f, "failed"),
+            MessageStatus::DeadLettered => write!(f, "dead_lettered"),
+            MessageStatus::Scheduled => write!(f, "scheduled"),
+            MessageStatus::Cancelled => write!(f, "cancelled"),
+            MessageStatus::Suppressed => write!(f, "suppressed"),
+            MessageStatus::Delivered => write!(f, "delivered"),
+            MessageStatus::Bounced => write!(f, "bounced"),
+            MessageStatus::Complained => write!(f, "complained"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageEvent {
+    pub message_id: String,
+    pub status: MessageStatus,
+    pub provider_response: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MessageQuery {
+    pub recipient: Option<String>,
+    pub sender: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl<'a> MessageEvent {
+    pub async fn insert<T>(
+        message_id: &str,
+        status: MessageStatus,
+        provider_response: Option<&str>,
+        executor: T,
+    ) -> Result<(), MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO message_events (message_id, status, provider_response)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(message_id)
+        .bind(status)
+        .bind(provider_response)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_by_message<T>(
+        message_id: &str,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let events = sqlx::query_as(
+            r#"
+            SELECT message_id, status, provider_response, created_at FROM message_events
+            WHERE message_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(events)
+    }
+}
+
+impl<'a> Message {
+    /// persist a newly accepted message together with its first event.
+    ///
+    /// Returns `None` if a message with the same id was accepted at or after `window_start`,
+    /// an older message with the same id is replaced.
+    pub async fn accept(
+        self,
+        window_start: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<Option<Self>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let message: Option<Self> = sqlx::query_as(
+            r#"
+            INSERT INTO messages (id, type, sender, body, created_at, updated_at, subject, recipients, device_id, title, times, status, send_at,
+              html_body, cc, bcc, reply_to, headers, attachments, priority, send_window)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 1, $11, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+            ON CONFLICT (id) DO UPDATE SET
+              type = EXCLUDED.type, sender = EXCLUDED.sender, body = EXCLUDED.body,
+              created_at = EXCLUDED.created_at, updated_at = EXCLUDED.updated_at,
+              subject = EXCLUDED.subject, recipients = EXCLUDED.recipients,
+              device_id = EXCLUDED.device_id, title = EXCLUDED.title,
+              times = EXCLUDED.times, status = EXCLUDED.status, send_at = EXCLUDED.send_at,
+              html_body = EXCLUDED.html_body, cc = EXCLUDED.cc, bcc = EXCLUDED.bcc,
+              reply_to = EXCLUDED.reply_to, headers = EXCLUDED.headers,
+              attachments = EXCLUDED.attachments, priority = EXCLUDED.priority,
+              send_window = EXCLUDED.send_window, claimed_at = NULL
+            WHERE messages.created_at < $12
+            RETURNING *
+            "#,
+        )
+        .bind(&self.id)
+        .bind(self.r#type)
+        .bind(&self.sender)
+        .bind(&self.body)
+        .bind(self.created_at)
+        .bind(self.updated_at)
+        .bind(&self.subject)
+        .bind(&self.recipients)
+        .bind(&self.device_id)
+        .bind(&self.title)
+        .bind(self.status)
+        .bind(window_start)
+        .bind(self.send_at)
+        .bind(&self.html_body)
+        .bind(&self.cc)
+        .bind(&self.bcc)
+        .bind(&self.reply_to)
+        .bind(&self.headers)
+        .bind(&self.attachments)
+        .bind(self.priority)
+        .bind(self.send_window)
+        .fetch_optional(&mut *ts)
+        .await?;
+        let Some(message) = message else {
+            return Ok(None);
+        };
+        MessageEvent::insert(&message.id, message.status, None, &mut *ts).await?;
+        ts.commit().await?;
+        Ok(Some(message))
+    }
+
+    /// move a message to `status`, the claim of a scheduler ends once it is no longer sending
+    pub async fn transition(
+        id: &str,
+        status: MessageStatus,
+        provider_response: Option<&str>,
+        pool: &PgPool,
+    ) -> Result<(), MessageError> {
+        let mut ts = pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE messages SET status = $2, updated_at = NOW(),
+              claimed_at = CASE WHEN $2 = 'sending' THEN claimed_at END
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .execute(&mut *ts)
+        .await?;
+        MessageEvent::insert(id, status, provider_response, &mut *ts).await?;
+        ts.commit().await?;
+        Ok(())
+    }
+
+    /// forget a message accepted but not sent, so that it can be sent again with the same id
+    pub async fn release(id: &str, pool: &PgPool) -> Result<(), MessageError> {
+        let mut ts = pool.begin().await?;
+        let released = sqlx::query(
+            r#"
+            DELETE FROM messages WHERE id = $1 AND status IN ('accepted', 'sending')
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *ts)
+        .await?;
+        if released.rows_affected() > 0 {
+            sqlx::query(
+                r#"
+                DELETE FROM message_events WHERE message_id = $1
+                "#,
+            )
+            .bind(id)
+            .execute(&mut *ts)
+            .await?;
+        }
+        ts.commit().await?;
+        Ok(())
+    }
+
+    pub async fn find<T>(id: &str, executor: T) -> Result<Option<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let message = sqlx::query_as(
+            r#"
+            SELECT * FROM messages WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+        Ok(message)
+    }
+
+    pub async fn list<T>(query: &MessageQuery, executor: T) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let limit = match query.limit {
+            0 => DEFAULT_LIST_LIMIT,
+            limit => limit.min(MAX_LIST_LIMIT),
+        };
+        let messages = sqlx::query_as(
+            r#"
+            SELECT * FROM messages
+            WHERE ($1::TEXT IS NULL OR $1 = ANY(recipients) OR $1 = ANY(cc) OR $1 = ANY(bcc)
+              OR device_id = $1)
+              AND ($2::TEXT IS NULL OR sender = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR created_at < $4)
+            ORDER BY created_at DESC
+            LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(&query.recipient)
+        .bind(&query.sender)
+        .bind(query.start)
+        .bind(query.end)
+        .bind(limit)
+        .bind(query.offset)
+        .fetch_all(executor)
+        .await?;
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::message::EmailMessage, test_utils::common_test};
+    use chrono::Duration;
+
+    fn email(id: &str) -> Message {
+        EmailMessage {
+            id: id.to_string(),
+            subject: "test_subject".to_string(),
+            sender: "lifecycle_sender".to_string(),
+            recipients: vec!["lifecycle@test.com".to_string()],
+            body: "test_body".to_string(),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_message_lifecycle() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let window_start = Utc::now() - Duration::hours(1);
+        email("lifecycle_id")
+            .accept(window_start, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        Message::transition("lifecycle_id", MessageStatus::Sending, None, &pool)
+            .await
+            .unwrap();
+        Message::transition("lifecycle_id", MessageStatus::Sent, Some("250 OK"), &pool)
+            .await
+            .unwrap();
+
+        let message = Message::find("lifecycle_id", &pool).await.unwrap().unwrap();
+        assert_eq!(message.status, MessageStatus::Sent);
+        let events = MessageEvent::list_by_message("lifecycle_id", &pool)
+            .await
+            .unwrap();
+        let statuses: Vec<_> = events.iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                MessageStatus::Accepted,
+                MessageStatus::Sending,
+                MessageStatus::Sent
+            ]
+        );
+        assert_eq!(events[2].provider_response.as_deref(), Some("250 OK"));
+
+        let query = MessageQuery {
+            recipient: Some("lifecycle@test.com".to_string()),
+            ..Default::default()
+        };
+        let messages = Message::list(&query, &pool).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        let query = MessageQuery {
+            sender: Some("sender1".to_string()),
+            ..Default::default()
+        };
+        let messages = Message::list(&query, &pool).await.unwrap();
+        assert_eq!(messages[0].id, "2");
+    }
+
+    #[tokio::test]
+    async fn test_list_finds_cc_and_bcc_recipients() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let window_start = Utc::now() - Duration::hours(1);
+        let message: Message = EmailMessage {
+            id: "copied_id".to_string(),
+            subject: "test_subject".to_string(),
+            sender: "lifecycle_sender".to_string(),
+            recipients: vec!["to@test.com".to_string()],
+            cc: vec!["cc@test.com".to_string()],
+            bcc: vec!["bcc@test.com".to_string()],
+            body: "test_body".to_string(),
+            ..Default::default()
+        }
+        .into();
+        message.accept(window_start, &pool).await.unwrap().unwrap();
+
+        for recipient in ["to@test.com", "cc@test.com", "bcc@test.com"] {
+            let query = MessageQuery {
+                recipient: Some(recipient.to_string()),
+                ..Default::default()
+            };
+            let messages = Message::list(&query, &pool).await.unwrap();
+            assert_eq!(messages.len(), 1, "{}", recipient);
+            assert_eq!(messages[0].id, "copied_id");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept_dedupes_within_window() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let window_start = Utc::now() - Duration::hours(1);
+        let accepted = email("dup_id").accept(window_start, &pool).await.unwrap();
+        assert!(accepted.is_some());
+        Message::transition("dup_id", MessageStatus::Sent, None, &pool)
+            .await
+            .unwrap();
+
+        let accepted = email("dup_id").accept(window_start, &pool).await.unwrap();
+        assert!(accepted.is_none());
+        let message = Message::find("dup_id", &pool).await.unwrap().unwrap();
+        assert_eq!(message.status, MessageStatus::Sent);
+
+        // a released message is sent again
+        let accepted = email("released_id")
+            .accept(window_start, &pool)
+            .await
+            .unwrap();
+        assert!(accepted.is_some());
+        Message::release("released_id", &pool).await.unwrap();
+        assert!(Message::find("released_id", &pool).await.unwrap().is_none());
+        let accepted = email("released_id")
+            .accept(window_start, &pool)
+            .await
+            .unwrap();
+        assert!(accepted.is_some());
+        // a sent one is kept
+        Message::release("dup_id", &pool).await.unwrap();
+        assert!(Message::find("dup_id", &pool).await.unwrap().is_some());
+
+        // fixture message "1" was accepted long before the window
+        let accepted = email("1").accept(window_start, &pool).await.unwrap();
+        assert_eq!(accepted.unwrap().status, MessageStatus::Accepted);
+    }
+}
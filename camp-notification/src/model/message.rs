@@ -1,10 +1,12 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgExecutor, Type};
+use sqlx::{types::Json, FromRow, PgExecutor, Type};
 use thiserror::Error;
 
+use super::{lifecycle::MessageStatus, schedule::SendWindow};
+
 #[derive(Debug, Error)]
 pub enum MessageError {
     #[error("sqlx error: {0}")]
@@ -21,13 +23,75 @@ impl Display for EmailMessage {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct EmailMessage {
     pub id: String,
     pub subject: String,
     pub sender: String,
     pub recipients: Vec<String>,
+    /// plain text body
     pub body: String,
+    /// html alternative of the plain text body
+    pub html_body: Option<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub reply_to: Option<String>,
+    pub headers: HashMap<String, String>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl EmailMessage {
+    /// every address the email is delivered to
+    pub fn addresses(&self) -> impl Iterator<Item = &String> {
+        self.recipients.iter().chain(&self.cc).chain(&self.bcc)
+    }
+
+    /// size of the attachments in bytes, blobs count with their declared size
+    pub fn attachments_size(&self) -> u64 {
+        self.attachments.iter().map(Attachment::size).sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub source: AttachmentSource,
+    /// referenced from the html body with cid:, attached as a file if `None`
+    pub content_id: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentSource {
+    /// content carried with the message
+    Content(#[serde(with = "base64_bytes")] Vec<u8>),
+    /// file in blob storage, fetched when the email is sent
+    Blob { url: String, size: u64 },
+}
+
+impl Attachment {
+    pub fn size(&self) -> u64 {
+        match &self.source {
+            AttachmentSource::Content(content) => content.len() as u64,
+            AttachmentSource::Blob { size, .. } => *size,
+        }
+    }
+}
+
+/// stores attachment content as base64 instead of an array of numbers
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(D::Error::custom)
+    }
 }
 
 impl From<EmailMessage> for Message {
@@ -43,13 +107,23 @@ impl From<EmailMessage> for Message {
             times: 1,
             device_id: None,
             title: None,
+            status: MessageStatus::Accepted,
+            send_at: None,
+            send_window: None,
+            html_body: email.html_body,
+            cc: Some(email.cc),
+            bcc: Some(email.bcc),
+            reply_to: email.reply_to,
+            headers: Some(Json(email.headers)),
+            attachments: Some(Json(email.attachments)),
+            priority: Priority::Normal,
             created_at: now,
             updated_at: now,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SmsMessage {
     pub id: String,
     pub sender: String,
@@ -80,13 +154,23 @@ impl From<SmsMessage> for Message {
             times: 1,
             device_id: None,
             title: None,
+            status: MessageStatus::Accepted,
+            send_at: None,
+            send_window: None,
+            html_body: None,
+            cc: None,
+            bcc: None,
+            reply_to: None,
+            headers: None,
+            attachments: None,
+            priority: Priority::Normal,
             created_at: now,
             updated_at: now,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InAppMessage {
     pub id: String,
     pub sender: String,
@@ -118,18 +202,102 @@ impl From<InAppMessage> for Message {
             times: 1,
             device_id: Some(value.device_id),
             title: Some(value.title),
+            status: MessageStatus::Accepted,
+            send_at: None,
+            send_window: None,
+            html_body: None,
+            cc: None,
+            bcc: None,
+            reply_to: None,
+            headers: None,
+            attachments: None,
+            priority: Priority::Normal,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookMessage {
+    pub id: String,
+    pub sender: String,
+    /// name of the registered endpoint the payload is posted to
+    pub endpoint: String,
+    pub event: String,
+    /// json body of the request
+    pub payload: String,
+}
+
+impl Display for WebhookMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "WebhookMessage {{ id: {}, sender: {}, endpoint: {}, event: {}, payload: {} }}",
+            self.id, self.sender, self.endpoint, self.event, self.payload
+        )
+    }
+}
+
+/// the endpoint is stored as the only recipient and the event as the subject
+impl From<WebhookMessage> for Message {
+    fn from(value: WebhookMessage) -> Self {
+        let now = Utc::now();
+        Message {
+            id: value.id,
+            r#type: MessageType::Webhook,
+            sender: value.sender,
+            body: value.payload,
+            subject: Some(value.event),
+            recipients: Some(vec![value.endpoint]),
+            times: 1,
+            device_id: None,
+            title: None,
+            status: MessageStatus::Accepted,
+            send_at: None,
+            send_window: None,
+            html_body: None,
+            cc: None,
+            bcc: None,
+            reply_to: None,
+            headers: None,
+            attachments: None,
+            priority: Priority::Normal,
             created_at: now,
             updated_at: now,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Type)]
+/// lane a message is dispatched in, higher lanes get more of the dispatch slots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "message_priority", rename_all = "snake_case")]
+pub enum Priority {
+    /// transactional messages, e.g. password resets
+    High,
+    #[default]
+    Normal,
+    /// marketing messages
+    Low,
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::High => write!(f, "high"),
+            Priority::Normal => write!(f, "normal"),
+            Priority::Low => write!(f, "low"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
 #[sqlx(type_name = "message_type", rename_all = "snake_case")]
 pub enum MessageType {
     Email,
     Sms,
     Inapp,
+    Webhook,
     Unknown,
 }
 
@@ -139,6 +307,7 @@ impl Display for MessageType {
             MessageType::Email => write!(f, "email"),
             MessageType::Sms => write!(f, "sms"),
             MessageType::Inapp => write!(f, "inapp"),
+            MessageType::Webhook => write!(f, "webhook"),
             MessageType::Unknown => write!(f, "unknown"),
         }
     }
@@ -148,13 +317,13 @@ impl Display for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Message {{ id: {}, type: {}, sender: {}, body: {}, created_at: {}, updated_at: {}, subject: {:?}, recipients: {:?}, device_id: {:?}, title: {:?}, times: {} }}",
-            self.id, self.r#type, self.sender, self.body, self.created_at, self.updated_at, self.subject, self.recipients, self.device_id, self.title, self.times
+            "Message {{ id: {}, type: {}, status: {}, sender: {}, body: {}, created_at: {}, updated_at: {}, subject: {:?}, recipients: {:?}, device_id: {:?}, title: {:?}, times: {} }}",
+            self.id, self.r#type, self.status, self.sender, self.body, self.created_at, self.updated_at, self.subject, self.recipients, self.device_id, self.title, self.times
         )
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Message {
     pub id: String,
     pub r#type: MessageType,
@@ -167,6 +336,28 @@ pub struct Message {
     pub device_id: Option<String>,
     pub title: Option<String>,
     pub times: i32,
+    pub status: MessageStatus,
+    pub send_at: Option<DateTime<Utc>>,
+    /// the recipient-local hours a scheduled message is sent in
+    pub send_window: Option<Json<SendWindow>>,
+    pub html_body: Option<String>,
+    pub cc: Option<Vec<String>>,
+    pub bcc: Option<Vec<String>>,
+    pub reply_to: Option<String>,
+    pub headers: Option<Json<HashMap<String, String>>>,
+    pub attachments: Option<Json<Vec<Attachment>>>,
+    pub priority: Priority,
+}
+
+impl Message {
+    /// where the message is delivered to, the device id for in-app messages
+    pub fn addresses(&self) -> Vec<&str> {
+        match (&self.recipients, &self.device_id) {
+            (Some(recipients), _) => recipients.iter().map(String::as_str).collect(),
+            (None, Some(device_id)) => vec![device_id.as_str()],
+            (None, None) => vec![],
+        }
+    }
 }
 
 impl<'a> Message {
@@ -179,17 +370,41 @@ impl<'a> Message {
         Ok(message)
     }
 
-    async fn pg_insert<T>(&self, executor: T) -> Result<(), MessageError>
+    /// mark an email as stored for a later retry, inserting it if it was never accepted
+    pub async fn store_email<T>(msg: EmailMessage, executor: T) -> Result<Self, MessageError>
+    where
+        T: PgExecutor<'a> + Copy,
+    {
+        let mut message: Self = msg.into();
+        message.status = MessageStatus::Stored;
+        let updated = sqlx::query(
+            r#"
+            UPDATE messages SET status = $2, updated_at = $3 WHERE id = $1
+            "#,
+        )
+        .bind(&message.id)
+        .bind(message.status)
+        .bind(message.updated_at)
+        .execute(executor)
+        .await?;
+        if updated.rows_affected() == 0 {
+            message.pg_insert(executor).await?;
+        }
+        Ok(message)
+    }
+
+    pub(crate) async fn pg_insert<T>(&self, executor: T) -> Result<(), MessageError>
     where
         T: PgExecutor<'a>,
     {
         sqlx::query(
             r#"
-            INSERT INTO messages (id, type, sender, body, created_at, updated_at, subject, recipients, device_id, title, times)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            INSERT INTO messages (id, type, sender, body, created_at, updated_at, subject, recipients, device_id, title, times, status, send_at,
+              html_body, cc, bcc, reply_to, headers, attachments, priority, send_window)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
             "#,
         ).bind(&self.id)
-            .bind(&self.r#type)
+            .bind(self.r#type)
             .bind(&self.sender)
             .bind(&self.body)
             .bind(self.created_at)
@@ -199,6 +414,16 @@ impl<'a> Message {
             .bind(&self.device_id)
             .bind(&self.title)
             .bind(1)
+            .bind(self.status)
+            .bind(self.send_at)
+            .bind(&self.html_body)
+            .bind(&self.cc)
+            .bind(&self.bcc)
+            .bind(&self.reply_to)
+            .bind(&self.headers)
+            .bind(&self.attachments)
+            .bind(self.priority)
+            .bind(self.send_window)
             .execute(executor)
             .await?;
         Ok(())
@@ -220,6 +445,7 @@ mod test {
                 sender: "test_sender".to_string(),
                 recipients: vec!["test_recipient".to_string()],
                 body: "test_body".to_string(),
+                ..Default::default()
             },
             &pool,
         )
@@ -227,4 +453,52 @@ mod test {
         .unwrap();
         assert_eq!(message.id, "test_id")
     }
+
+    #[tokio::test]
+    async fn test_email_fields_round_trip() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let attachments = vec![
+            Attachment {
+                filename: "report.pdf".to_string(),
+                content_type: "application/pdf".to_string(),
+                source: AttachmentSource::Content(vec![0, 159, 146, 150]),
+                content_id: None,
+            },
+            Attachment {
+                filename: "logo.png".to_string(),
+                content_type: "image/png".to_string(),
+                source: AttachmentSource::Blob {
+                    url: "s3://assets/logo.png".to_string(),
+                    size: 2048,
+                },
+                content_id: Some("logo".to_string()),
+            },
+        ];
+        let email = EmailMessage {
+            id: "rich_id".to_string(),
+            subject: "test_subject".to_string(),
+            sender: "test_sender".to_string(),
+            recipients: vec!["to@test.com".to_string()],
+            body: "test_body".to_string(),
+            html_body: Some("<p>test_body</p><img src=\"cid:logo\">".to_string()),
+            cc: vec!["cc@test.com".to_string()],
+            bcc: vec!["bcc@test.com".to_string()],
+            reply_to: Some("reply@test.com".to_string()),
+            headers: [("X-Campaign".to_string(), "welcome".to_string())].into(),
+            attachments: attachments.clone(),
+        };
+        assert_eq!(email.attachments_size(), 2052);
+        assert_eq!(email.addresses().count(), 3);
+        Message::insert_email(email, &pool).await.unwrap();
+
+        let message: Message = sqlx::query_as("SELECT * FROM messages WHERE id = $1")
+            .bind("rich_id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(message.cc, Some(vec!["cc@test.com".to_string()]));
+        assert_eq!(message.reply_to.as_deref(), Some("reply@test.com"));
+        assert_eq!(message.headers.unwrap().0["X-Campaign"], "welcome");
+        assert_eq!(message.attachments.unwrap().0, attachments);
+    }
 }
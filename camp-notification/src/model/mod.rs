@@ -1 +1,10 @@
+pub mod dead_letter;
+pub mod feedback;
+pub mod inbox;
+pub mod lifecycle;
 pub mod message;
+pub mod preference;
+pub mod rate_limit;
+pub mod schedule;
+pub mod suppression;
+pub mod template;
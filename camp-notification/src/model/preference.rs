@@ -0,0 +1,344 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+use super::{
+    message::{MessageError, MessageType},
+    schedule::SendWindow,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRow)]
+pub struct ChannelPreference {
+    pub channel: MessageType,
+    pub enabled: bool,
+}
+
+/// notification preferences of a user
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UserPreferences {
+    pub user_id: String,
+    /// email addresses, phone numbers and device ids of the user
+    pub addresses: Vec<String>,
+    /// channels not listed are enabled
+    pub channels: Vec<ChannelPreference>,
+    pub quiet_hours: Option<SendWindow>,
+    pub unsubscribed: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct PreferencesRow {
+    user_id: String,
+    timezone: Option<String>,
+    quiet_start_hour: i32,
+    quiet_end_hour: i32,
+    unsubscribed: bool,
+}
+
+impl UserPreferences {
+    /// whether the user wants to receive messages of `channel` at all
+    pub fn allows(&self, channel: MessageType) -> bool {
+        if self.unsubscribed {
+            return false;
+        }
+        !self
+            .channels
+            .iter()
+            .any(|c| c.channel == channel && !c.enabled)
+    }
+
+    /// the end of the quiet hours the user is in at `now`, `None` if the user is not in them
+    pub fn quiet_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let quiet = self.quiet_hours.filter(|w| w.covers(now))?;
+        let awake = SendWindow {
+            start_hour: quiet.end_hour,
+            end_hour: quiet.start_hour,
+            ..quiet
+        };
+        Some(awake.next_open(now))
+    }
+}
+
+impl<'a> UserPreferences {
+    pub async fn find<T>(user_id: &str, executor: T) -> Result<Option<Self>, MessageError>
+    where
+        T: PgExecutor<'a> + Copy,
+    {
+        let row: Option<PreferencesRow> = sqlx::query_as(
+            r#"
+            SELECT user_id, timezone, quiet_start_hour, quiet_end_hour, unsubscribed
+            FROM preferences WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let addresses: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT address FROM preference_addresses WHERE user_id = $1 ORDER BY address
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(executor)
+        .await?;
+        let channels = sqlx::query_as(
+            r#"
+            SELECT channel, enabled FROM preference_channels WHERE user_id = $1 ORDER BY channel
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(executor)
+        .await?;
+        // a time zone which can not be parsed disables the quiet hours
+        let quiet_hours = row
+            .timezone
+            .and_then(|tz| tz.parse::<Tz>().ok())
+            .map(|tz| SendWindow {
+                tz,
+                start_hour: row.quiet_start_hour as u32,
+                end_hour: row.quiet_end_hour as u32,
+            });
+        Ok(Some(Self {
+            user_id: row.user_id,
+            addresses: addresses.into_iter().map(|(a,)| a).collect(),
+            channels,
+            quiet_hours,
+            unsubscribed: row.unsubscribed,
+        }))
+    }
+
+    /// the preferences of the users owning `addresses`, keyed by address
+    pub async fn find_by_addresses<T>(
+        addresses: &[&str],
+        executor: T,
+    ) -> Result<Vec<(String, Self)>, MessageError>
+    where
+        T: PgExecutor<'a> + Copy,
+    {
+        let owners: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT address, user_id FROM preference_addresses WHERE address = ANY($1)
+            "#,
+        )
+        .bind(addresses)
+        .fetch_all(executor)
+        .await?;
+        let mut found = Vec::with_capacity(owners.len());
+        for (address, user_id) in owners {
+            if let Some(prefs) = Self::find(&user_id, executor).await? {
+                found.push((address, prefs));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Replace the preferences of the user.
+    ///
+    /// Returns the addresses owned by other users, nothing is saved if there are any.
+    pub async fn save(&self, pool: &PgPool) -> Result<Vec<String>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let (timezone, start, end) = match &self.quiet_hours {
+            Some(w) => (Some(w.tz.name()), w.start_hour as i32, w.end_hour as i32),
+            None => (None, 0, 0),
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO preferences (user_id, timezone, quiet_start_hour, quiet_end_hour, unsubscribed)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE SET
+              timezone = EXCLUDED.timezone, quiet_start_hour = EXCLUDED.quiet_start_hour,
+              quiet_end_hour = EXCLUDED.quiet_end_hour, unsubscribed = EXCLUDED.unsubscribed,
+              updated_at = NOW()
+            "#,
+        )
+        .bind(&self.user_id)
+        .bind(timezone)
+        .bind(start)
+        .bind(end)
+        .bind(self.unsubscribed)
+        .execute(&mut *ts)
+        .await?;
+        sqlx::query("DELETE FROM preference_channels WHERE user_id = $1")
+            .bind(&self.user_id)
+            .execute(&mut *ts)
+            .await?;
+        for channel in &self.channels {
+            sqlx::query(
+                r#"
+                INSERT INTO preference_channels (user_id, channel, enabled) VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, channel) DO UPDATE SET enabled = EXCLUDED.enabled
+                "#,
+            )
+            .bind(&self.user_id)
+            .bind(channel.channel)
+            .bind(channel.enabled)
+            .execute(&mut *ts)
+            .await?;
+        }
+        sqlx::query("DELETE FROM preference_addresses WHERE user_id = $1")
+            .bind(&self.user_id)
+            .execute(&mut *ts)
+            .await?;
+        let saved: Vec<(String,)> = sqlx::query_as(
+            r#"
+            INSERT INTO preference_addresses (address, user_id) SELECT UNNEST($1::VARCHAR[]), $2
+            ON CONFLICT (address) DO NOTHING
+            RETURNING address
+            "#,
+        )
+        .bind(&self.addresses)
+        .bind(&self.user_id)
+        .fetch_all(&mut *ts)
+        .await?;
+        let taken: Vec<String> = self
+            .addresses
+            .iter()
+            .filter(|address| !saved.iter().any(|(saved,)| saved == *address))
+            .cloned()
+            .collect();
+        if !taken.is_empty() {
+            ts.rollback().await?;
+            return Ok(taken);
+        }
+        ts.commit().await?;
+        Ok(taken)
+    }
+
+    /// unsubscribe the owner of `address` from every channel, returns the owner's user id.
+    ///
+    /// An address without an owner gets preferences of its own with the address as user id.
+    pub async fn unsubscribe(address: &str, pool: &PgPool) -> Result<String, MessageError> {
+        let mut ts = pool.begin().await?;
+        let owner: Option<(String,)> =
+            sqlx::query_as("SELECT user_id FROM preference_addresses WHERE address = $1")
+                .bind(address)
+                .fetch_optional(&mut *ts)
+                .await?;
+        let user_id = owner.map(|(u,)| u).unwrap_or_else(|| address.to_string());
+        sqlx::query(
+            r#"
+            INSERT INTO preferences (user_id, unsubscribed) VALUES ($1, TRUE)
+            ON CONFLICT (user_id) DO UPDATE SET unsubscribed = TRUE, updated_at = NOW()
+            "#,
+        )
+        .bind(&user_id)
+        .execute(&mut *ts)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO preference_addresses (address, user_id) VALUES ($1, $2)
+            ON CONFLICT (address) DO NOTHING
+            "#,
+        )
+        .bind(address)
+        .bind(&user_id)
+        .execute(&mut *ts)
+        .await?;
+        ts.commit().await?;
+        Ok(user_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::common_test;
+
+    fn prefs() -> UserPreferences {
+        UserPreferences {
+            user_id: "user-1".to_string(),
+            addresses: vec!["a@test.com".to_string(), "+8613800000000".to_string()],
+            channels: vec![ChannelPreference {
+                channel: MessageType::Sms,
+                enabled: false,
+            }],
+            quiet_hours: Some(SendWindow {
+                tz: chrono_tz::Asia::Shanghai,
+                start_hour: 22,
+                end_hour: 8,
+            }),
+            unsubscribed: false,
+        }
+    }
+
+    #[test]
+    fn test_allows() {
+        let prefs = prefs();
+        assert!(prefs.allows(MessageType::Email));
+        assert!(!prefs.allows(MessageType::Sms));
+        let unsubscribed = UserPreferences {
+            unsubscribed: true,
+            ..prefs
+        };
+        assert!(!unsubscribed.allows(MessageType::Email));
+    }
+
+    #[test]
+    fn test_quiet_until() {
+        let prefs = prefs();
+        // 12:00 in Shanghai
+        let noon: DateTime<Utc> = "2024-07-29T04:00:00Z".parse().unwrap();
+        assert_eq!(prefs.quiet_until(noon), None);
+        // 23:00 in Shanghai waits until 08:00 the next day
+        let night: DateTime<Utc> = "2024-07-29T15:00:00Z".parse().unwrap();
+        let morning: DateTime<Utc> = "2024-07-30T00:00:00Z".parse().unwrap();
+        assert_eq!(prefs.quiet_until(night), Some(morning));
+    }
+
+    #[tokio::test]
+    async fn test_save_and_unsubscribe() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let prefs = prefs();
+        assert!(prefs.save(&pool).await.unwrap().is_empty());
+        let found = UserPreferences::find("user-1", &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.channels, prefs.channels);
+        assert_eq!(found.quiet_hours, prefs.quiet_hours);
+        assert_eq!(found.addresses.len(), 2);
+
+        let found = UserPreferences::find_by_addresses(&["a@test.com", "b@test.com"], &pool)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.user_id, "user-1");
+
+        // the address of user-1 is not taken over by somebody else
+        let other = UserPreferences {
+            user_id: "user-2".to_string(),
+            addresses: vec!["a@test.com".to_string(), "c@test.com".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            other.save(&pool).await.unwrap(),
+            vec!["a@test.com".to_string()]
+        );
+        assert!(UserPreferences::find("user-2", &pool)
+            .await
+            .unwrap()
+            .is_none());
+        let found = UserPreferences::find("user-1", &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.addresses.len(), 2);
+
+        let user_id = UserPreferences::unsubscribe("a@test.com", &pool)
+            .await
+            .unwrap();
+        assert_eq!(user_id, "user-1");
+        let found = UserPreferences::find("user-1", &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(found.unsubscribed);
+
+        let user_id = UserPreferences::unsubscribe("b@test.com", &pool)
+            .await
+            .unwrap();
+        assert_eq!(user_id, "b@test.com");
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgExecutor};
+
+use super::message::MessageError;
+use crate::config::RateLimitRule;
+
+/// state of a token bucket
+#[derive(Debug, Clone, Copy, FromRow)]
+pub struct Bucket {
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    pub fn full(rule: &RateLimitRule, now: DateTime<Utc>) -> Self {
+        Self {
+            tokens: rule.capacity as f64,
+            updated_at: now,
+        }
+    }
+
+    /// take a token, or return how long it takes until one is available
+    pub fn take(&mut self, rule: &RateLimitRule, now: DateTime<Utc>) -> Option<Duration> {
+        let elapsed = (now - self.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * rule.refill_per_sec()).min(rule.capacity as f64);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (1.0 - self.tokens) / rule.refill_per_sec(),
+        ))
+    }
+
+    /// put back a token taken for a message which was not sent after all
+    pub fn refund(&mut self, rule: &RateLimitRule) {
+        self.tokens = (self.tokens + 1.0).min(rule.capacity as f64);
+    }
+}
+
+impl<'a> Bucket {
+    /// take a token from the bucket shared through postgres
+    pub async fn pg_take<T>(
+        key: &str,
+        rule: &RateLimitRule,
+        executor: T,
+    ) -> Result<Option<Duration>, MessageError>
+    where
+        T: PgExecutor<'a> + Copy,
+    {
+        let taken: Option<(f64,)> = sqlx::query_as(
+            r#"
+            INSERT INTO rate_limit_buckets (key, tokens, updated_at) VALUES ($1, $2 - 1, NOW())
+            ON CONFLICT (key) DO UPDATE SET
+              tokens = LEAST($2, rate_limit_buckets.tokens
+                + EXTRACT(EPOCH FROM NOW() - rate_limit_buckets.updated_at) * $3) - 1,
+              updated_at = NOW()
+            WHERE LEAST($2, rate_limit_buckets.tokens
+                + EXTRACT(EPOCH FROM NOW() - rate_limit_buckets.updated_at) * $3) >= 1
+            RETURNING tokens
+            "#,
+        )
+        .bind(key)
+        .bind(rule.capacity as f64)
+        .bind(rule.refill_per_sec())
+        .fetch_optional(executor)
+        .await?;
+        if taken.is_some() {
+            return Ok(None);
+        }
+        let bucket: Option<Self> = sqlx::query_as(
+            r#"
+            SELECT tokens, updated_at FROM rate_limit_buckets WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(executor)
+        .await?;
+        let wait = bucket
+            .and_then(|mut bucket| bucket.take(rule, Utc::now()))
+            .unwrap_or_default();
+        Ok(Some(wait))
+    }
+
+    /// put back a token taken from the bucket shared through postgres
+    pub async fn pg_refund<T>(
+        key: &str,
+        rule: &RateLimitRule,
+        executor: T,
+    ) -> Result<(), MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        sqlx::query(
+            r#"
+            UPDATE rate_limit_buckets SET tokens = LEAST($2, tokens + 1) WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .bind(rule.capacity as f64)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::common_test;
+
+    #[test]
+    fn test_bucket_take() {
+        let rule = RateLimitRule {
+            capacity: 2,
+            per_secs: 2,
+        };
+        let now = Utc::now();
+        let mut bucket = Bucket::full(&rule, now);
+        assert!(bucket.take(&rule, now).is_none());
+        assert!(bucket.take(&rule, now).is_none());
+        assert_eq!(bucket.take(&rule, now), Some(Duration::from_secs(1)));
+        let later = now + chrono::Duration::milliseconds(1500);
+        assert!(bucket.take(&rule, later).is_none());
+        bucket.refund(&rule);
+        bucket.refund(&rule);
+        assert!(bucket.take(&rule, later).is_none());
+        assert!(bucket.take(&rule, later).is_none());
+        assert!(bucket.take(&rule, later).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pg_take() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let rule = RateLimitRule {
+            capacity: 2,
+            per_secs: 3600,
+        };
+        assert!(Bucket::pg_take("test", &rule, &pool)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(Bucket::pg_take("test", &rule, &pool)
+            .await
+            .unwrap()
+            .is_none());
+        let wait = Bucket::pg_take("test", &rule, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(wait > Duration::from_secs(1700));
+        Bucket::pg_refund("test", &rule, &pool).await.unwrap();
+        assert!(Bucket::pg_take("test", &rule, &pool)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(Bucket::pg_take("other", &rule, &pool)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
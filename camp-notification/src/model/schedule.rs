@@ -0,0 +1,388 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+
+use super::{
+    lifecycle::{MessageEvent, MessageQuery, MessageStatus},
+    message::{Message, MessageError},
+};
+
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+/// hours of the day in the recipient's time zone a message may be delivered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendWindow {
+    #[serde(with = "tz_name")]
+    pub tz: Tz,
+    pub start_hour: u32,
+    /// a window ending before it starts spans midnight
+    pub end_hour: u32,
+}
+
+impl SendWindow {
+    fn contains(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+
+    /// whether `at` falls within the window, a window starting and ending at the same hour is empty
+    pub fn covers(&self, at: DateTime<Utc>) -> bool {
+        self.start_hour != self.end_hour && self.contains(at.with_timezone(&self.tz).hour())
+    }
+
+    /// the earliest time at or after `at` which is within the window
+    pub fn next_open(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let local = at.with_timezone(&self.tz);
+        if self.start_hour == self.end_hour || self.contains(local.hour()) {
+            return at;
+        }
+        let mut date = local.date_naive();
+        if local.hour() >= self.start_hour {
+            date = date.succ_opt().unwrap_or(date);
+        }
+        let mut start = date.and_time(NaiveTime::MIN) + Duration::hours(self.start_hour as i64);
+        loop {
+            // the start hour may be skipped by a daylight saving change
+            if let Some(open) = self.tz.from_local_datetime(&start).earliest() {
+                return open.with_timezone(&Utc);
+            }
+            start += Duration::hours(1);
+        }
+    }
+}
+
+/// stores a time zone as its IANA name, e.g. `Asia/Shanghai`
+mod tz_name {
+    use chrono_tz::Tz;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(tz: &Tz, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(tz.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tz, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(D::Error::custom)
+    }
+}
+
+/// the time a message is due, `None` if it is due already
+pub fn due_at(
+    send_at: Option<DateTime<Utc>>,
+    window: Option<SendWindow>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let at = send_at.unwrap_or(now).max(now);
+    let at = match window {
+        Some(window) => window.next_open(at),
+        None => at,
+    };
+    (at > now).then_some(at)
+}
+
+impl Message {
+    /// the time to try a scheduled message again `after` from `now`, within its send window
+    pub fn retry_at(&self, after: Duration, now: DateTime<Utc>) -> DateTime<Utc> {
+        let at = now + after;
+        match &self.send_window {
+            Some(window) => window.next_open(at),
+            None => at,
+        }
+    }
+}
+
+impl<'a> Message {
+    /// Mark the due scheduled messages as sending, each message is claimed by one caller only.
+    ///
+    /// A message claimed longer than `lease` ago and still sending is claimed again, its
+    /// claimer is taken to have died before sending it.
+    pub async fn claim_due(
+        limit: i64,
+        lease: Duration,
+        pool: &PgPool,
+    ) -> Result<Vec<Self>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let messages: Vec<Self> = sqlx::query_as(
+            r#"
+            UPDATE messages SET status = 'sending', claimed_at = NOW(), updated_at = NOW()
+            WHERE id IN (
+              SELECT id FROM messages
+              WHERE (status = 'scheduled' AND send_at <= NOW())
+                OR (status = 'sending' AND claimed_at < NOW() - $2 * INTERVAL '1 millisecond')
+              ORDER BY send_at
+              LIMIT $1
+              FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease.num_milliseconds() as f64)
+        .fetch_all(&mut *ts)
+        .await?;
+        for message in &messages {
+            MessageEvent::insert(&message.id, message.status, None, &mut *ts).await?;
+        }
+        ts.commit().await?;
+        Ok(messages)
+    }
+
+    /// put a message back to the schedule to be sent at `send_at`
+    pub async fn reschedule(
+        id: &str,
+        send_at: DateTime<Utc>,
+        reason: &str,
+        pool: &PgPool,
+    ) -> Result<(), MessageError> {
+        let mut ts = pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE messages SET status = 'scheduled', send_at = $2, claimed_at = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(send_at)
+        .execute(&mut *ts)
+        .await?;
+        MessageEvent::insert(id, MessageStatus::Scheduled, Some(reason), &mut *ts).await?;
+        ts.commit().await?;
+        Ok(())
+    }
+
+    /// cancel a message which is still scheduled, returns `None` if there is no such message
+    pub async fn cancel(id: &str, pool: &PgPool) -> Result<Option<Self>, MessageError> {
+        let mut ts = pool.begin().await?;
+        let message: Option<Self> = sqlx::query_as(
+            r#"
+            UPDATE messages SET status = 'cancelled', updated_at = NOW()
+            WHERE id = $1 AND status = 'scheduled'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *ts)
+        .await?;
+        if message.is_some() {
+            MessageEvent::insert(id, MessageStatus::Cancelled, None, &mut *ts).await?;
+        }
+        ts.commit().await?;
+        Ok(message)
+    }
+
+    pub async fn list_scheduled<T>(
+        query: &MessageQuery,
+        executor: T,
+    ) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let limit = match query.limit {
+            0 => DEFAULT_LIST_LIMIT,
+            limit => limit.min(MAX_LIST_LIMIT),
+        };
+        let messages = sqlx::query_as(
+            r#"
+            SELECT * FROM messages
+            WHERE status = 'scheduled'
+              AND ($1::TEXT IS NULL OR $1 = ANY(recipients) OR device_id = $1)
+              AND ($2::TEXT IS NULL OR sender = $2)
+            ORDER BY send_at
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(&query.recipient)
+        .bind(&query.sender)
+        .bind(limit)
+        .bind(query.offset)
+        .fetch_all(executor)
+        .await?;
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::message::EmailMessage, test_utils::common_test};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn scheduled(id: &str, send_at: DateTime<Utc>) -> Message {
+        let mut message: Message = EmailMessage {
+            id: id.to_string(),
+            subject: "test_subject".to_string(),
+            sender: "schedule_sender".to_string(),
+            recipients: vec!["schedule@test.com".to_string()],
+            body: "test_body".to_string(),
+            ..Default::default()
+        }
+        .into();
+        message.status = MessageStatus::Scheduled;
+        message.send_at = Some(send_at);
+        message
+    }
+
+    #[test]
+    fn test_window_next_open() {
+        let window = SendWindow {
+            tz: chrono_tz::Asia::Shanghai,
+            start_hour: 10,
+            end_hour: 20,
+        };
+        // 08:00 in Shanghai waits until 10:00 the same day
+        let open = window.next_open(utc("2024-07-26T00:00:00Z"));
+        assert_eq!(open, utc("2024-07-26T02:00:00Z"));
+        // 12:00 in Shanghai is within the window
+        let open = window.next_open(utc("2024-07-26T04:00:00Z"));
+        assert_eq!(open, utc("2024-07-26T04:00:00Z"));
+        // 21:00 in Shanghai waits until 10:00 the next day
+        let open = window.next_open(utc("2024-07-26T13:00:00Z"));
+        assert_eq!(open, utc("2024-07-27T02:00:00Z"));
+
+        let overnight = SendWindow {
+            tz: chrono_tz::UTC,
+            start_hour: 22,
+            end_hour: 2,
+        };
+        let open = overnight.next_open(utc("2024-07-26T01:00:00Z"));
+        assert_eq!(open, utc("2024-07-26T01:00:00Z"));
+        let open = overnight.next_open(utc("2024-07-26T03:00:00Z"));
+        assert_eq!(open, utc("2024-07-26T22:00:00Z"));
+    }
+
+    #[test]
+    fn test_due_at() {
+        let now = utc("2024-07-26T04:00:00Z");
+        assert_eq!(due_at(None, None, now), None);
+        assert_eq!(due_at(Some(utc("2024-07-25T00:00:00Z")), None, now), None);
+        let later = utc("2024-07-26T05:00:00Z");
+        assert_eq!(due_at(Some(later), None, now), Some(later));
+        let window = SendWindow {
+            tz: chrono_tz::Asia::Shanghai,
+            start_hour: 8,
+            end_hour: 10,
+        };
+        assert_eq!(
+            due_at(None, Some(window), now),
+            Some(utc("2024-07-27T00:00:00Z"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_and_cancel() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let window_start = Utc::now() - Duration::hours(1);
+        let past = Utc::now() - Duration::seconds(1);
+        let future = Utc::now() + Duration::hours(1);
+        let lease = Duration::minutes(5);
+        for (id, send_at) in [("due", past), ("later", future), ("cancel", future)] {
+            scheduled(id, send_at)
+                .accept(window_start, &pool)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let query = MessageQuery {
+            sender: Some("schedule_sender".to_string()),
+            ..Default::default()
+        };
+        let messages = Message::list_scheduled(&query, &pool).await.unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].id, "due");
+
+        let claimed = Message::claim_due(10, lease, &pool).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, "due");
+        assert_eq!(claimed[0].status, MessageStatus::Sending);
+        assert!(Message::claim_due(10, lease, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let cancelled = Message::cancel("cancel", &pool).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, MessageStatus::Cancelled);
+        assert!(Message::cancel("cancel", &pool).await.unwrap().is_none());
+        assert!(Message::cancel("due", &pool).await.unwrap().is_none());
+
+        Message::reschedule("due", past, "THROTTLED", &pool)
+            .await
+            .unwrap();
+        let messages = Message::list_scheduled(&query, &pool).await.unwrap();
+        let ids: Vec<_> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["due", "later"]);
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_takes_over_expired_claims() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let window_start = Utc::now() - Duration::hours(1);
+        let past = Utc::now() - Duration::seconds(1);
+        scheduled("stuck", past)
+            .accept(window_start, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        let claimed = Message::claim_due(10, Duration::minutes(5), &pool)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        // the claimer died, the message is still sending
+        assert!(Message::claim_due(10, Duration::minutes(5), &pool)
+            .await
+            .unwrap()
+            .is_empty());
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let claimed = Message::claim_due(10, Duration::milliseconds(10), &pool)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, "stuck");
+
+        // a sent message is never claimed again
+        Message::transition("stuck", MessageStatus::Sent, None, &pool)
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(Message::claim_due(10, Duration::milliseconds(10), &pool)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_retry_within_window() {
+        let window = SendWindow {
+            tz: chrono_tz::Asia::Shanghai,
+            start_hour: 10,
+            end_hour: 20,
+        };
+        let mut message = scheduled("retry", utc("2024-07-26T11:30:00Z"));
+        // 19:30 in Shanghai, a retry a minute later is still within the window
+        let now = utc("2024-07-26T11:30:00Z");
+        assert_eq!(
+            message.retry_at(Duration::minutes(1), now),
+            utc("2024-07-26T11:31:00Z")
+        );
+        message.send_window = Some(sqlx::types::Json(window));
+        assert_eq!(
+            message.retry_at(Duration::minutes(1), now),
+            utc("2024-07-26T11:31:00Z")
+        );
+        // one an hour later waits until 10:00 the next day
+        assert_eq!(
+            message.retry_at(Duration::hours(1), now),
+            utc("2024-07-27T02:00:00Z")
+        );
+        let json = serde_json::to_string(&window).unwrap();
+        assert_eq!(serde_json::from_str::<SendWindow>(&json).unwrap(), window);
+    }
+}
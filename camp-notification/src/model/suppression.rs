@@ -0,0 +1,112 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Type};
+
+use super::message::MessageError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "suppression_reason", rename_all = "snake_case")]
+pub enum SuppressionReason {
+    Bounced,
+    Complained,
+    Manual,
+}
+
+impl Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuppressionReason::Bounced => write!(f, "bounced"),
+            SuppressionReason::Complained => write!(f, "complained"),
+            SuppressionReason::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+/// an address no message is delivered to
+#[derive(Debug, Clone, FromRow)]
+pub struct Suppression {
+    pub address: String,
+    pub reason: SuppressionReason,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> Suppression {
+    /// the suppressions of `addresses`
+    pub async fn find<T>(addresses: &[&str], executor: T) -> Result<Vec<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let suppressions = sqlx::query_as(
+            r#"
+            SELECT address, reason, created_at FROM suppressions WHERE address = ANY($1)
+            "#,
+        )
+        .bind(addresses)
+        .fetch_all(executor)
+        .await?;
+        Ok(suppressions)
+    }
+
+    /// suppress an address, the reason of an address suppressed before is replaced
+    pub async fn insert<T>(
+        address: &str,
+        reason: SuppressionReason,
+        executor: T,
+    ) -> Result<(), MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO suppressions (address, reason) VALUES ($1, $2)
+            ON CONFLICT (address) DO UPDATE SET reason = EXCLUDED.reason
+            "#,
+        )
+        .bind(address)
+        .bind(reason)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// lift the suppression of an address, returns whether it was suppressed
+    pub async fn remove<T>(address: &str, executor: T) -> Result<bool, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let removed = sqlx::query("DELETE FROM suppressions WHERE address = $1")
+            .bind(address)
+            .execute(executor)
+            .await?;
+        Ok(removed.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::common_test;
+
+    #[tokio::test]
+    async fn test_suppressions() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        Suppression::insert("a@test.com", SuppressionReason::Manual, &pool)
+            .await
+            .unwrap();
+        Suppression::insert("a@test.com", SuppressionReason::Bounced, &pool)
+            .await
+            .unwrap();
+        let found = Suppression::find(&["a@test.com", "b@test.com"], &pool)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].reason, SuppressionReason::Bounced);
+
+        assert!(Suppression::remove("a@test.com", &pool).await.unwrap());
+        assert!(!Suppression::remove("a@test.com", &pool).await.unwrap());
+        let found = Suppression::find(&["a@test.com"], &pool).await.unwrap();
+        assert!(found.is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgExecutor};
+
+use super::message::{MessageError, MessageType};
+
+/// message sent to many recipients, `{{name}}` placeholders are replaced per recipient
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct Template {
+    pub name: String,
+    pub channel: MessageType,
+    pub sender: String,
+    /// subject of an email or title of an in-app message
+    pub subject: String,
+    pub body: String,
+    pub html_body: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// how the values are escaped for the text they are put in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    None,
+    Html,
+    /// inside a json string, the placeholder is quoted by the template
+    Json,
+}
+
+impl Escape {
+    fn push(self, rendered: &mut String, value: &str) {
+        match self {
+            Escape::None => rendered.push_str(value),
+            Escape::Html => {
+                for c in value.chars() {
+                    match c {
+                        '&' => rendered.push_str("&amp;"),
+                        '<' => rendered.push_str("&lt;"),
+                        '>' => rendered.push_str("&gt;"),
+                        '"' => rendered.push_str("&quot;"),
+                        '\'' => rendered.push_str("&#x27;"),
+                        c => rendered.push(c),
+                    }
+                }
+            }
+            Escape::Json => {
+                let quoted = serde_json::Value::from(value).to_string();
+                rendered.push_str(&quoted[1..quoted.len() - 1]);
+            }
+        }
+    }
+}
+
+/// replace the `{{name}}` placeholders of `text`, a placeholder without a value is returned as error
+pub fn render(
+    text: &str,
+    variables: &HashMap<String, String>,
+    escape: Escape,
+) -> Result<String, String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        let value = variables.get(name).ok_or_else(|| name.to_string())?;
+        rendered.push_str(&rest[..start]);
+        escape.push(&mut rendered, value);
+        rest = &rest[start + 2 + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+impl Template {
+    /// the template with the placeholders replaced by `variables`, escaped for html bodies and
+    /// webhook payloads
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<Self, String> {
+        let body = match self.channel {
+            MessageType::Webhook => Escape::Json,
+            _ => Escape::None,
+        };
+        Ok(Self {
+            subject: render(&self.subject, variables, Escape::None)?,
+            body: render(&self.body, variables, body)?,
+            html_body: self
+                .html_body
+                .as_deref()
+                .map(|html| render(html, variables, Escape::Html))
+                .transpose()?,
+            ..self.clone()
+        })
+    }
+}
+
+impl<'a> Template {
+    pub async fn find<T>(name: &str, executor: T) -> Result<Option<Self>, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let template = sqlx::query_as(
+            r#"
+            SELECT name, channel, sender, subject, body, html_body, updated_at
+            FROM templates WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(executor)
+        .await?;
+        Ok(template)
+    }
+
+    /// create or replace the template of the same name
+    pub async fn save<T>(&self, executor: T) -> Result<Self, MessageError>
+    where
+        T: PgExecutor<'a>,
+    {
+        let template = sqlx::query_as(
+            r#"
+            INSERT INTO templates (name, channel, sender, subject, body, html_body)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO UPDATE SET
+              channel = EXCLUDED.channel, sender = EXCLUDED.sender, subject = EXCLUDED.subject,
+              body = EXCLUDED.body, html_body = EXCLUDED.html_body, updated_at = NOW()
+            RETURNING name, channel, sender, subject, body, html_body, updated_at
+            "#,
+        )
+        .bind(&self.name)
+        .bind(self.channel)
+        .bind(&self.sender)
+        .bind(&self.subject)
+        .bind(&self.body)
+        .bind(&self.html_body)
+        .fetch_one(executor)
+        .await?;
+        Ok(template)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::common_test;
+
+    fn template() -> Template {
+        Template {
+            name: "welcome".to_string(),
+            channel: MessageType::Email,
+            sender: "camp".to_string(),
+            subject: "Welcome {{ name }}".to_string(),
+            body: "Hi {{name}}, your code is {{code}}.".to_string(),
+            html_body: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let variables = HashMap::from([
+            ("name".to_string(), "Alice".to_string()),
+            ("code".to_string(), "{{name}}".to_string()),
+        ]);
+        let rendered = template().render(&variables).unwrap();
+        assert_eq!(rendered.subject, "Welcome Alice");
+        // values are not rendered again
+        assert_eq!(rendered.body, "Hi Alice, your code is {{name}}.");
+        assert_eq!(
+            render("{{ unclosed", &variables, Escape::None).unwrap(),
+            "{{ unclosed"
+        );
+        assert_eq!(
+            render("{{missing}}", &variables, Escape::None),
+            Err("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_escaped() {
+        let variables = HashMap::from([
+            ("name".to_string(), "<b>\"Bob\" & 'Eve'</b>\n".to_string()),
+            ("code".to_string(), "42".to_string()),
+        ]);
+        let html = Template {
+            html_body: Some("<p>Hi {{name}}</p>".to_string()),
+            ..template()
+        }
+        .render(&variables)
+        .unwrap();
+        assert_eq!(
+            html.html_body.unwrap(),
+            "<p>Hi &lt;b&gt;&quot;Bob&quot; &amp; &#x27;Eve&#x27;&lt;/b&gt;\n</p>"
+        );
+        // the plain text body is not escaped
+        assert!(html.body.starts_with("Hi <b>\"Bob\""));
+
+        let webhook = Template {
+            channel: MessageType::Webhook,
+            body: r#"{"name": "{{name}}"}"#.to_string(),
+            ..template()
+        }
+        .render(&variables)
+        .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&webhook.body).unwrap();
+        assert_eq!(payload["name"], variables["name"]);
+    }
+
+    #[tokio::test]
+    async fn test_save_template() {
+        let (_tdb, pool, _) = common_test().await.unwrap();
+        let saved = template().save(&pool).await.unwrap();
+        let updated = Template {
+            html_body: Some("<p>Hi {{name}}</p>".to_string()),
+            ..saved.clone()
+        }
+        .save(&pool)
+        .await
+        .unwrap();
+        assert!(updated.updated_at >= saved.updated_at);
+        let found = Template::find("welcome", &pool).await.unwrap().unwrap();
+        assert_eq!(found, updated);
+        assert!(Template::find("missing", &pool).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod notification;
+
+/// encoded file descriptors of the services, for server reflection
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/notification_descriptor.bin"));
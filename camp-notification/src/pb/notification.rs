@@ -0,0 +1,2871 @@
+// This file is @generated by prost-build.
+/// email message to be sent
+#[derive(derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EmailMessage {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// subject of the email
+    #[prost(string, tag = "2")]
+    pub subject: ::prost::alloc::string::String,
+    /// sender of the email
+    #[prost(string, tag = "3")]
+    pub sender: ::prost::alloc::string::String,
+    /// recipients of the email
+    #[prost(string, repeated, tag = "4")]
+    pub recipients: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// plain text body of the email
+    #[prost(string, tag = "5")]
+    pub body: ::prost::alloc::string::String,
+    /// html alternative of the plain text body
+    #[prost(string, tag = "6")]
+    pub html_body: ::prost::alloc::string::String,
+    /// carbon copy recipients of the email
+    #[prost(string, repeated, tag = "7")]
+    pub cc: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// blind carbon copy recipients of the email
+    #[prost(string, repeated, tag = "8")]
+    pub bcc: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// address replies go to, the sender if empty
+    #[prost(string, tag = "9")]
+    pub reply_to: ::prost::alloc::string::String,
+    /// extra headers of the email, the standard address and subject headers can not be set here
+    #[prost(map = "string, string", tag = "10")]
+    pub headers:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
+    /// files attached to the email
+    #[prost(message, repeated, tag = "11")]
+    pub attachments: ::prost::alloc::vec::Vec<EmailAttachment>,
+}
+/// file attached to an email
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EmailAttachment {
+    /// name of the file
+    #[prost(string, tag = "1")]
+    pub filename: ::prost::alloc::string::String,
+    /// mime type of the file, e.g. application/pdf
+    #[prost(string, tag = "2")]
+    pub content_type: ::prost::alloc::string::String,
+    /// content id to reference the attachment from the html body with cid:, attached as a file if empty
+    #[prost(string, tag = "5")]
+    pub content_id: ::prost::alloc::string::String,
+    /// where the content of the file comes from
+    #[prost(oneof = "email_attachment::Source", tags = "3, 4")]
+    pub source: ::core::option::Option<email_attachment::Source>,
+}
+/// Nested message and enum types in `EmailAttachment`.
+pub mod email_attachment {
+    /// where the content of the file comes from
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Source {
+        /// content of the file carried in the request
+        #[prost(bytes, tag = "3")]
+        Content(::prost::alloc::vec::Vec<u8>),
+        /// file kept in blob storage
+        #[prost(message, tag = "4")]
+        Blob(super::BlobReference),
+    }
+}
+/// file kept in blob storage, fetched when the email is sent
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BlobReference {
+    /// url of the file
+    #[prost(string, tag = "1")]
+    pub url: ::prost::alloc::string::String,
+    /// size of the file in bytes
+    #[prost(uint64, tag = "2")]
+    pub size: u64,
+}
+/// sms message to be sent
+#[derive(derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SmsMessage {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// sender of the sms
+    #[prost(string, tag = "2")]
+    pub sender: ::prost::alloc::string::String,
+    /// recipients of the sms
+    #[prost(string, repeated, tag = "3")]
+    pub recipients: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// body of the sms
+    #[prost(string, tag = "4")]
+    pub body: ::prost::alloc::string::String,
+}
+/// in-app message to be sent
+#[derive(derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InAppMessage {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// device id of the device to send the in-app message
+    #[prost(string, tag = "2")]
+    pub device_id: ::prost::alloc::string::String,
+    /// title of the in-app message
+    #[prost(string, tag = "3")]
+    pub title: ::prost::alloc::string::String,
+    /// body of the in-app message
+    #[prost(string, tag = "4")]
+    pub body: ::prost::alloc::string::String,
+    /// sender of the inapp
+    #[prost(string, tag = "5")]
+    pub sender: ::prost::alloc::string::String,
+}
+/// json payload posted to a registered webhook endpoint of a partner
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WebhookMessage {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// name the endpoint is registered under, its url and signing secret are configured
+    #[prost(string, tag = "2")]
+    pub endpoint: ::prost::alloc::string::String,
+    /// event the payload describes, sent in the X-Webhook-Event header
+    #[prost(string, tag = "3")]
+    pub event: ::prost::alloc::string::String,
+    /// json body of the request
+    #[prost(string, tag = "4")]
+    pub payload: ::prost::alloc::string::String,
+    /// sender of the webhook
+    #[prost(string, tag = "5")]
+    pub sender: ::prost::alloc::string::String,
+}
+/// hours of the day in the recipient's time zone a message may be delivered in
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LocalWindow {
+    /// IANA time zone of the recipient, e.g. Asia/Shanghai
+    #[prost(string, tag = "1")]
+    pub timezone: ::prost::alloc::string::String,
+    /// first hour of the window, 0-23
+    #[prost(uint32, tag = "2")]
+    pub start_hour: u32,
+    /// hour the window ends at, 0-23, a window ending before it starts spans midnight
+    #[prost(uint32, tag = "3")]
+    pub end_hour: u32,
+}
+/// request to send a message
+#[derive(derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendRequest {
+    /// send the message no earlier than this time, right away if not set
+    #[prost(message, optional, tag = "5")]
+    pub send_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// delay the message until the recipient's local time is within the window
+    #[prost(message, optional, tag = "6")]
+    pub window: ::core::option::Option<LocalWindow>,
+    /// lane the message is dispatched in
+    #[prost(enumeration = "Priority", tag = "7")]
+    pub priority: i32,
+    /// one of the message types to send
+    #[prost(oneof = "send_request::Msg", tags = "2, 3, 4, 8")]
+    pub msg: ::core::option::Option<send_request::Msg>,
+}
+/// Nested message and enum types in `SendRequest`.
+pub mod send_request {
+    /// one of the message types to send
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Msg {
+        #[prost(message, tag = "2")]
+        Email(super::EmailMessage),
+        #[prost(message, tag = "3")]
+        Sms(super::SmsMessage),
+        #[prost(message, tag = "4")]
+        InApp(super::InAppMessage),
+        #[prost(message, tag = "8")]
+        Webhook(super::WebhookMessage),
+    }
+}
+/// response to a send request
+#[derive(derive_builder::Builder)]
+#[builder(setter(into, strip_option), default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendResponse {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// timestamp of when the message was sent
+    #[prost(message, optional, tag = "2")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+    /// status of the message
+    #[prost(enumeration = "SendResponseType", tag = "3")]
+    pub status: i32,
+    /// why the message was not accepted, set when it is invalid or failed before it was accepted
+    #[prost(string, tag = "4")]
+    pub reason: ::prost::alloc::string::String,
+    /// malformed recipients of an invalid message
+    #[prost(message, repeated, tag = "5")]
+    pub invalid_recipients: ::prost::alloc::vec::Vec<InvalidRecipient>,
+}
+/// recipient rejected by a send request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvalidRecipient {
+    /// address as given in the request
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// why the address was rejected
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// details of an INVALID_ARGUMENT status returned for malformed recipients
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InvalidRecipients {
+    #[prost(message, repeated, tag = "1")]
+    pub recipients: ::prost::alloc::vec::Vec<InvalidRecipient>,
+}
+/// a state change of a message
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageEvent {
+    /// status the message moved to
+    #[prost(enumeration = "MessageStatus", tag = "1")]
+    pub status: i32,
+    /// timestamp of the state change
+    #[prost(message, optional, tag = "2")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+    /// response of the provider, if any
+    #[prost(string, tag = "3")]
+    pub provider_response: ::prost::alloc::string::String,
+}
+/// furthest outcome the providers reported for one recipient of a message
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecipientOutcome {
+    /// address as the message was sent to
+    #[prost(string, tag = "1")]
+    pub recipient: ::prost::alloc::string::String,
+    /// delivered, bounced or complained
+    #[prost(enumeration = "MessageStatus", tag = "2")]
+    pub status: i32,
+    /// timestamp of the feedback which moved the recipient there
+    #[prost(message, optional, tag = "3")]
+    pub updated_at: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// request to look up a message by its id
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMessageStatusRequest {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+}
+/// a persisted message with its lifecycle
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageStatusResponse {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// type of the message
+    #[prost(enumeration = "MessageType", tag = "2")]
+    pub r#type: i32,
+    /// sender of the message
+    #[prost(string, tag = "3")]
+    pub sender: ::prost::alloc::string::String,
+    /// recipients of the message, the device id for in-app messages
+    #[prost(string, repeated, tag = "4")]
+    pub recipients: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// current status of the message
+    #[prost(enumeration = "MessageStatus", tag = "5")]
+    pub status: i32,
+    /// timestamp of when the message was accepted
+    #[prost(message, optional, tag = "6")]
+    pub created_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// timestamp of the last state change
+    #[prost(message, optional, tag = "7")]
+    pub updated_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// state changes of the message, oldest first
+    #[prost(message, repeated, tag = "8")]
+    pub events: ::prost::alloc::vec::Vec<MessageEvent>,
+    /// time the message is scheduled to be sent at, if it was scheduled
+    #[prost(message, optional, tag = "9")]
+    pub send_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// lane the message is dispatched in
+    #[prost(enumeration = "Priority", tag = "10")]
+    pub priority: i32,
+    /// outcomes reported by the providers, by recipient
+    #[prost(message, repeated, tag = "11")]
+    pub outcomes: ::prost::alloc::vec::Vec<RecipientOutcome>,
+}
+/// request to list messages, empty fields are not filtered on
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMessagesRequest {
+    /// email address, phone number or device id the message was sent to
+    #[prost(string, tag = "1")]
+    pub recipient: ::prost::alloc::string::String,
+    /// sender of the message
+    #[prost(string, tag = "2")]
+    pub sender: ::prost::alloc::string::String,
+    /// messages accepted at or after this time
+    #[prost(message, optional, tag = "3")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    /// messages accepted before this time
+    #[prost(message, optional, tag = "4")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    /// max number of messages to return, defaults to 100
+    #[prost(uint32, tag = "5")]
+    pub limit: u32,
+    /// number of messages to skip
+    #[prost(uint32, tag = "6")]
+    pub offset: u32,
+}
+/// messages matching a list request, newest first
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMessagesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: ::prost::alloc::vec::Vec<MessageStatusResponse>,
+}
+/// request to list pending scheduled messages, empty fields are not filtered on
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListScheduledRequest {
+    /// email address, phone number or device id the message is sent to
+    #[prost(string, tag = "1")]
+    pub recipient: ::prost::alloc::string::String,
+    /// sender of the message
+    #[prost(string, tag = "2")]
+    pub sender: ::prost::alloc::string::String,
+    /// max number of messages to return, defaults to 100
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+    /// number of messages to skip
+    #[prost(uint32, tag = "4")]
+    pub offset: u32,
+}
+/// request to cancel a pending scheduled message
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelScheduledRequest {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+}
+/// whether a user wants to receive messages of a channel
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ChannelPreference {
+    /// channel the preference applies to
+    #[prost(enumeration = "MessageType", tag = "1")]
+    pub channel: i32,
+    /// opted in if true, opted out otherwise
+    #[prost(bool, tag = "2")]
+    pub enabled: bool,
+}
+/// notification preferences of a user
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Preferences {
+    /// unique identifier of the user
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    /// email addresses, phone numbers and device ids of the user
+    #[prost(string, repeated, tag = "2")]
+    pub addresses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// per channel opt-ins and opt-outs, channels not listed are enabled
+    #[prost(message, repeated, tag = "3")]
+    pub channels: ::prost::alloc::vec::Vec<ChannelPreference>,
+    /// hours of the day no message is delivered in, in the user's time zone
+    #[prost(message, optional, tag = "4")]
+    pub quiet_hours: ::core::option::Option<LocalWindow>,
+    /// user unsubscribed from every channel
+    #[prost(bool, tag = "5")]
+    pub unsubscribed: bool,
+}
+/// request to get the preferences of a user
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPreferencesRequest {
+    /// unique identifier of the user
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+}
+/// request to unsubscribe with the token sent in an email
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnsubscribeRequest {
+    /// signed token from the unsubscribe link
+    #[prost(string, tag = "1")]
+    pub token: ::prost::alloc::string::String,
+}
+/// response to an unsubscribe request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnsubscribeResponse {
+    /// address which was unsubscribed
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+}
+/// an in-app message stored in the inbox of a device
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InboxMessage {
+    /// unique identifier of the message
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// device the message was sent to
+    #[prost(string, tag = "2")]
+    pub device_id: ::prost::alloc::string::String,
+    /// owner of the device, if known
+    #[prost(string, tag = "3")]
+    pub user_id: ::prost::alloc::string::String,
+    /// sender of the message
+    #[prost(string, tag = "4")]
+    pub sender: ::prost::alloc::string::String,
+    /// title of the message
+    #[prost(string, tag = "5")]
+    pub title: ::prost::alloc::string::String,
+    /// body of the message
+    #[prost(string, tag = "6")]
+    pub body: ::prost::alloc::string::String,
+    /// timestamp of when the message was delivered to the inbox
+    #[prost(message, optional, tag = "7")]
+    pub created_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// timestamp of when the message was read, unset if unread
+    #[prost(message, optional, tag = "8")]
+    pub read_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// timestamp of when the message was opened, unset if never opened
+    #[prost(message, optional, tag = "9")]
+    pub opened_at: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// request to list an inbox, by device id or by user id
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListInboxRequest {
+    /// device whose inbox to list
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+    /// user whose inboxes on every device to list, used if device_id is empty
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+    /// only list unread messages
+    #[prost(bool, tag = "3")]
+    pub unread_only: bool,
+    /// max number of messages to return, defaults to 100
+    #[prost(uint32, tag = "4")]
+    pub limit: u32,
+    /// number of messages to skip
+    #[prost(uint32, tag = "5")]
+    pub offset: u32,
+}
+/// messages of an inbox, newest first
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListInboxResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: ::prost::alloc::vec::Vec<InboxMessage>,
+    /// number of unread messages in the inbox
+    #[prost(uint32, tag = "2")]
+    pub unread_count: u32,
+}
+/// request to mark messages of an inbox as read
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MarkReadRequest {
+    /// device the messages were sent to
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+    /// user the messages were sent to, used if device_id is empty
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+    /// messages to mark as read
+    #[prost(string, repeated, tag = "3")]
+    pub message_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// the messages were opened rather than only seen
+    #[prost(bool, tag = "4")]
+    pub opened: bool,
+}
+/// request to mark every message of an inbox as read
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MarkAllReadRequest {
+    /// device whose inbox to mark
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+    /// user whose inboxes to mark, used if device_id is empty
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+}
+/// request to delete a message from an inbox
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteMessageRequest {
+    /// device the message was sent to
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+    /// user the message was sent to, used if device_id is empty
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+    /// message to delete
+    #[prost(string, tag = "3")]
+    pub message_id: ::prost::alloc::string::String,
+}
+/// result of changing an inbox
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InboxUpdateResponse {
+    /// number of messages changed
+    #[prost(uint32, tag = "1")]
+    pub updated: u32,
+    /// number of unread messages left in the inbox
+    #[prost(uint32, tag = "2")]
+    pub unread_count: u32,
+}
+/// request to receive the in-app messages of a device as they are sent
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequest {
+    /// device to receive the messages of
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+}
+/// bounce, complaint or delivery receipt of a provider
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProviderFeedback {
+    /// id of the message the feedback is about
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// recipient the feedback is about
+    #[prost(string, tag = "2")]
+    pub recipient: ::prost::alloc::string::String,
+    #[prost(enumeration = "FeedbackType", tag = "3")]
+    pub r#type: i32,
+    /// name of the provider reporting the feedback
+    #[prost(string, tag = "4")]
+    pub provider: ::prost::alloc::string::String,
+    /// explanation of the provider, e.g. the smtp response
+    #[prost(string, tag = "5")]
+    pub description: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FeedbackResponse {
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    /// status of the message after the feedback
+    #[prost(enumeration = "MessageStatus", tag = "2")]
+    pub status: i32,
+    /// whether the recipient was added to the suppression list
+    #[prost(bool, tag = "3")]
+    pub suppressed: bool,
+}
+/// message sent to many recipients, `{{name}}` placeholders are replaced with the variables of a recipient
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Template {
+    /// unique name to reference the template by
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// channel the template is sent through
+    #[prost(enumeration = "MessageType", tag = "2")]
+    pub channel: i32,
+    #[prost(string, tag = "3")]
+    pub sender: ::prost::alloc::string::String,
+    /// subject of an email or title of an in-app message, unused for sms
+    #[prost(string, tag = "4")]
+    pub subject: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub body: ::prost::alloc::string::String,
+    /// html body of an email
+    #[prost(string, tag = "6")]
+    pub html_body: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTemplateRequest {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+}
+/// recipient of a batch
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchRecipient {
+    /// email address, phone number or device id, depending on the channel of the template
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// values of the placeholders of the template
+    #[prost(map = "string, string", tag = "2")]
+    pub variables:
+        ::std::collections::HashMap<::prost::alloc::string::String, ::prost::alloc::string::String>,
+}
+/// request to send a template to many recipients
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendBatchRequest {
+    /// unique identifier of the batch, at most 47 characters. The message of a recipient gets the id
+    /// `{batch_id}-{digest}`, the digest of its normalized address, so that a batch sent again is not
+    /// delivered twice whatever the order of its recipients
+    #[prost(string, tag = "1")]
+    pub batch_id: ::prost::alloc::string::String,
+    /// name of the template to send
+    #[prost(string, tag = "2")]
+    pub template: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub recipients: ::prost::alloc::vec::Vec<BatchRecipient>,
+    /// send the messages no earlier than this time, right away if not set
+    #[prost(message, optional, tag = "4")]
+    pub send_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// lane the messages are dispatched in, campaigns usually go to the low lane
+    #[prost(enumeration = "Priority", tag = "5")]
+    pub priority: i32,
+}
+/// outcome of the message of one recipient of a batch
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchResult {
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+    #[prost(enumeration = "SendResponseType", tag = "3")]
+    pub status: i32,
+    /// why the message was not sent, empty unless the status is FAILED
+    #[prost(string, tag = "4")]
+    pub error: ::prost::alloc::string::String,
+}
+/// outcome of a batch, the results are in the order of the recipients
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendBatchResponse {
+    #[prost(string, tag = "1")]
+    pub batch_id: ::prost::alloc::string::String,
+    /// number of messages sent, stored, scheduled or pending from an earlier attempt
+    #[prost(uint32, tag = "2")]
+    pub accepted: u32,
+    /// number of messages suppressed or throttled
+    #[prost(uint32, tag = "3")]
+    pub skipped: u32,
+    /// number of messages failed
+    #[prost(uint32, tag = "4")]
+    pub failed: u32,
+    #[prost(message, repeated, tag = "5")]
+    pub results: ::prost::alloc::vec::Vec<BatchResult>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLaneStatsRequest {}
+/// counters of a dispatch lane since the service started
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LaneStats {
+    #[prost(enumeration = "Priority", tag = "1")]
+    pub priority: i32,
+    /// messages waiting for a dispatch slot
+    #[prost(uint64, tag = "2")]
+    pub queued: u64,
+    /// messages being sent
+    #[prost(uint64, tag = "3")]
+    pub in_flight: u64,
+    /// messages which were sent, whatever the outcome
+    #[prost(uint64, tag = "4")]
+    pub dispatched: u64,
+    /// messages rejected by a rate limit
+    #[prost(uint64, tag = "5")]
+    pub throttled: u64,
+    /// total time the dispatched messages waited for a slot, in milliseconds
+    #[prost(uint64, tag = "6")]
+    pub wait_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LaneStatsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub lanes: ::prost::alloc::vec::Vec<LaneStats>,
+}
+/// request to list the messages which could not be sent
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeadLettersRequest {
+    /// statuses to list, DEAD_LETTERED and STORED if empty
+    #[prost(enumeration = "MessageStatus", repeated, tag = "1")]
+    pub statuses: ::prost::alloc::vec::Vec<i32>,
+    /// type of the messages, any type if UNKNOWN
+    #[prost(enumeration = "MessageType", tag = "2")]
+    pub r#type: i32,
+    /// messages accepted at or after this time
+    #[prost(message, optional, tag = "3")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    /// messages accepted before this time
+    #[prost(message, optional, tag = "4")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    /// text the last error of the message contains, case insensitive
+    #[prost(string, tag = "5")]
+    pub error: ::prost::alloc::string::String,
+    /// max number of messages to return, defaults to 100
+    #[prost(uint32, tag = "6")]
+    pub limit: u32,
+    /// number of messages to skip
+    #[prost(uint32, tag = "7")]
+    pub offset: u32,
+}
+/// a message which could not be sent
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeadLetter {
+    /// status of the message with its attempt history
+    #[prost(message, optional, tag = "1")]
+    pub message: ::core::option::Option<MessageStatusResponse>,
+    /// the message as it is sent again by a replay
+    #[prost(message, optional, tag = "2")]
+    pub request: ::core::option::Option<SendRequest>,
+    /// response of the last attempt
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+    /// replays of the message, oldest first, only set by GetDeadLetter
+    #[prost(message, repeated, tag = "4")]
+    pub replays: ::prost::alloc::vec::Vec<ReplayRecord>,
+}
+/// messages matching a list request, the most recently updated first
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeadLettersResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: ::prost::alloc::vec::Vec<DeadLetter>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetDeadLetterRequest {
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+}
+/// audit record of a replay
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplayRecord {
+    #[prost(string, tag = "1")]
+    pub operator: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+    /// recipients the message was sent to instead of the original ones, empty if not edited
+    #[prost(string, repeated, tag = "3")]
+    pub recipients: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// status the replay ended in, or the error it failed with
+    #[prost(string, tag = "4")]
+    pub outcome: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "5")]
+    pub created_at: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// request to send dead-lettered, stored or failed messages again
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplayRequest {
+    /// messages to replay, the messages matching `filter` if empty
+    #[prost(string, repeated, tag = "1")]
+    pub message_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "2")]
+    pub filter: ::core::option::Option<ListDeadLettersRequest>,
+    /// new recipients of the message, the device id of an in-app message, only for a single message
+    #[prost(string, repeated, tag = "3")]
+    pub recipients: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// who replays the messages, recorded in the audit
+    #[prost(string, tag = "4")]
+    pub operator: ::prost::alloc::string::String,
+    /// why the messages are replayed, recorded in the audit
+    #[prost(string, tag = "5")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplayResult {
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    #[prost(enumeration = "SendResponseType", tag = "2")]
+    pub status: i32,
+    /// why the message could not be replayed, the status is FAILED then
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplayResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<ReplayResult>,
+}
+/// lane a message is dispatched in, messages of a higher lane get more of the dispatch slots
+/// and have rate limits of their own so that they are not held up by a backlog of another lane
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Priority {
+    Normal = 0,
+    /// transactional messages, e.g. password resets
+    High = 1,
+    /// marketing messages
+    Low = 2,
+}
+impl Priority {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Priority::Normal => "PRIORITY_NORMAL",
+            Priority::High => "PRIORITY_HIGH",
+            Priority::Low => "PRIORITY_LOW",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PRIORITY_NORMAL" => Some(Self::Normal),
+            "PRIORITY_HIGH" => Some(Self::High),
+            "PRIORITY_LOW" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SendResponseType {
+    /// message was successfully sent
+    Success = 0,
+    /// message failed to send
+    Failed = 1,
+    /// message failed to send but was stored for a later retry
+    Stored = 2,
+    /// a message with the same id was accepted earlier and is still being sent
+    Pending = 3,
+    /// message exceeded a rate limit and was not sent, it can be retried later
+    Throttled = 4,
+    /// message was stored and will be sent at its scheduled time
+    Scheduled = 5,
+    /// every recipient opted out of the message by their preferences
+    Suppressed = 6,
+    /// message was rejected before it was accepted, it must be fixed before it is sent again
+    Invalid = 7,
+}
+impl SendResponseType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SendResponseType::Success => "SEND_RESPONSE_TYPE_SUCCESS",
+            SendResponseType::Failed => "SEND_RESPONSE_TYPE_FAILED",
+            SendResponseType::Stored => "SEND_RESPONSE_TYPE_STORED",
+            SendResponseType::Pending => "SEND_RESPONSE_TYPE_PENDING",
+            SendResponseType::Throttled => "SEND_RESPONSE_TYPE_THROTTLED",
+            SendResponseType::Scheduled => "SEND_RESPONSE_TYPE_SCHEDULED",
+            SendResponseType::Suppressed => "SEND_RESPONSE_TYPE_SUPPRESSED",
+            SendResponseType::Invalid => "SEND_RESPONSE_TYPE_INVALID",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SEND_RESPONSE_TYPE_SUCCESS" => Some(Self::Success),
+            "SEND_RESPONSE_TYPE_FAILED" => Some(Self::Failed),
+            "SEND_RESPONSE_TYPE_STORED" => Some(Self::Stored),
+            "SEND_RESPONSE_TYPE_PENDING" => Some(Self::Pending),
+            "SEND_RESPONSE_TYPE_THROTTLED" => Some(Self::Throttled),
+            "SEND_RESPONSE_TYPE_SCHEDULED" => Some(Self::Scheduled),
+            "SEND_RESPONSE_TYPE_SUPPRESSED" => Some(Self::Suppressed),
+            "SEND_RESPONSE_TYPE_INVALID" => Some(Self::Invalid),
+            _ => None,
+        }
+    }
+}
+/// type of a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MessageType {
+    Unknown = 0,
+    Email = 1,
+    Sms = 2,
+    InApp = 3,
+    Webhook = 4,
+}
+impl MessageType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            MessageType::Unknown => "MESSAGE_TYPE_UNKNOWN",
+            MessageType::Email => "MESSAGE_TYPE_EMAIL",
+            MessageType::Sms => "MESSAGE_TYPE_SMS",
+            MessageType::InApp => "MESSAGE_TYPE_IN_APP",
+            MessageType::Webhook => "MESSAGE_TYPE_WEBHOOK",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "MESSAGE_TYPE_UNKNOWN" => Some(Self::Unknown),
+            "MESSAGE_TYPE_EMAIL" => Some(Self::Email),
+            "MESSAGE_TYPE_SMS" => Some(Self::Sms),
+            "MESSAGE_TYPE_IN_APP" => Some(Self::InApp),
+            "MESSAGE_TYPE_WEBHOOK" => Some(Self::Webhook),
+            _ => None,
+        }
+    }
+}
+/// lifecycle status of a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum MessageStatus {
+    /// message was accepted by the service
+    Accepted = 0,
+    /// message is being handed to the provider
+    Sending = 1,
+    /// provider accepted the message
+    Sent = 2,
+    /// message failed to send but was stored for a later retry
+    Stored = 3,
+    /// provider rejected the message
+    Failed = 4,
+    /// message could neither be sent nor stored and was given up on
+    DeadLettered = 5,
+    /// message is waiting for its scheduled time
+    Scheduled = 6,
+    /// scheduled message was cancelled before it was sent
+    Cancelled = 7,
+    /// message was not sent because of the recipients' preferences
+    Suppressed = 8,
+    /// provider reported the message as delivered
+    Delivered = 9,
+    /// provider reported a permanent delivery failure
+    Bounced = 10,
+    /// recipient reported the message as spam
+    Complained = 11,
+}
+impl MessageStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            MessageStatus::Accepted => "MESSAGE_STATUS_ACCEPTED",
+            MessageStatus::Sending => "MESSAGE_STATUS_SENDING",
+            MessageStatus::Sent => "MESSAGE_STATUS_SENT",
+            MessageStatus::Stored => "MESSAGE_STATUS_STORED",
+            MessageStatus::Failed => "MESSAGE_STATUS_FAILED",
+            MessageStatus::DeadLettered => "MESSAGE_STATUS_DEAD_LETTERED",
+            MessageStatus::Scheduled => "MESSAGE_STATUS_SCHEDULED",
+            MessageStatus::Cancelled => "MESSAGE_STATUS_CANCELLED",
+            MessageStatus::Suppressed => "MESSAGE_STATUS_SUPPRESSED",
+            MessageStatus::Delivered => "MESSAGE_STATUS_DELIVERED",
+            MessageStatus::Bounced => "MESSAGE_STATUS_BOUNCED",
+            MessageStatus::Complained => "MESSAGE_STATUS_COMPLAINED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "MESSAGE_STATUS_ACCEPTED" => Some(Self::Accepted),
+            "MESSAGE_STATUS_SENDING" => Some(Self::Sending),
+            "MESSAGE_STATUS_SENT" => Some(Self::Sent),
+            "MESSAGE_STATUS_STORED" => Some(Self::Stored),
+            "MESSAGE_STATUS_FAILED" => Some(Self::Failed),
+            "MESSAGE_STATUS_DEAD_LETTERED" => Some(Self::DeadLettered),
+            "MESSAGE_STATUS_SCHEDULED" => Some(Self::Scheduled),
+            "MESSAGE_STATUS_CANCELLED" => Some(Self::Cancelled),
+            "MESSAGE_STATUS_SUPPRESSED" => Some(Self::Suppressed),
+            "MESSAGE_STATUS_DELIVERED" => Some(Self::Delivered),
+            "MESSAGE_STATUS_BOUNCED" => Some(Self::Bounced),
+            "MESSAGE_STATUS_COMPLAINED" => Some(Self::Complained),
+            _ => None,
+        }
+    }
+}
+/// kind of feedback a provider reports about a sent message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum FeedbackType {
+    Unknown = 0,
+    /// message reached the recipient
+    Delivered = 1,
+    /// temporary delivery failure, the provider keeps trying
+    SoftBounce = 2,
+    /// permanent delivery failure, the recipient is suppressed
+    HardBounce = 3,
+    /// recipient reported the message as spam, the recipient is suppressed
+    Complaint = 4,
+}
+impl FeedbackType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            FeedbackType::Unknown => "FEEDBACK_TYPE_UNKNOWN",
+            FeedbackType::Delivered => "FEEDBACK_TYPE_DELIVERED",
+            FeedbackType::SoftBounce => "FEEDBACK_TYPE_SOFT_BOUNCE",
+            FeedbackType::HardBounce => "FEEDBACK_TYPE_HARD_BOUNCE",
+            FeedbackType::Complaint => "FEEDBACK_TYPE_COMPLAINT",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "FEEDBACK_TYPE_UNKNOWN" => Some(Self::Unknown),
+            "FEEDBACK_TYPE_DELIVERED" => Some(Self::Delivered),
+            "FEEDBACK_TYPE_SOFT_BOUNCE" => Some(Self::SoftBounce),
+            "FEEDBACK_TYPE_HARD_BOUNCE" => Some(Self::HardBounce),
+            "FEEDBACK_TYPE_COMPLAINT" => Some(Self::Complaint),
+            _ => None,
+        }
+    }
+}
+/// Generated client implementations.
+pub mod notification_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+    /// The Notification service provides a way to send notifications to users.
+    #[derive(Debug, Clone)]
+    pub struct NotificationClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl NotificationClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> NotificationClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> NotificationClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + Send + Sync,
+        {
+            NotificationClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Send a notification to a user.
+        pub async fn send(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::SendRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SendResponse>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/notification.Notification/Send");
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "Send"));
+            self.inner.streaming(req, path, codec).await
+        }
+        /// Get the current status and state changes of a message.
+        pub async fn get_message_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetMessageStatusRequest>,
+        ) -> std::result::Result<tonic::Response<super::MessageStatusResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/GetMessageStatus");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "GetMessageStatus",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// List messages by recipient, sender and time range.
+        pub async fn list_messages(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListMessagesRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListMessagesResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/ListMessages");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "ListMessages"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// List scheduled messages which are not sent yet, the earliest first.
+        pub async fn list_scheduled(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListScheduledRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListMessagesResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/ListScheduled");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "ListScheduled",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Cancel a scheduled message which is not sent yet.
+        pub async fn cancel_scheduled(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelScheduledRequest>,
+        ) -> std::result::Result<tonic::Response<super::MessageStatusResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/CancelScheduled");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "CancelScheduled",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get the notification preferences of a user.
+        pub async fn get_preferences(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetPreferencesRequest>,
+        ) -> std::result::Result<tonic::Response<super::Preferences>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/GetPreferences");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "GetPreferences",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Replace the notification preferences of a user.
+        pub async fn update_preferences(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Preferences>,
+        ) -> std::result::Result<tonic::Response<super::Preferences>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/notification.Notification/UpdatePreferences",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "UpdatePreferences",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Unsubscribe the address of a signed unsubscribe token from every channel.
+        pub async fn unsubscribe(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UnsubscribeRequest>,
+        ) -> std::result::Result<tonic::Response<super::UnsubscribeResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/Unsubscribe");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "Unsubscribe"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// List the in-app messages of a device or user.
+        pub async fn list_inbox(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListInboxRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListInboxResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/notification.Notification/ListInbox");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "ListInbox"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Mark in-app messages as read or opened.
+        pub async fn mark_read(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MarkReadRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/notification.Notification/MarkRead");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "MarkRead"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Mark every in-app message of a device or user as read.
+        pub async fn mark_all_read(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MarkAllReadRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/MarkAllRead");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "MarkAllRead"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Delete an in-app message from an inbox.
+        pub async fn delete_message(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteMessageRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/DeleteMessage");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "DeleteMessage",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Receive the in-app messages of a device while they are sent, they are kept in the inbox as well.
+        pub async fn subscribe(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::InAppMessage>>,
+            tonic::Status,
+        > {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/notification.Notification/Subscribe");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "Subscribe"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Report a bounce, complaint or delivery receipt of a provider.
+        pub async fn report_feedback(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ProviderFeedback>,
+        ) -> std::result::Result<tonic::Response<super::FeedbackResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/ReportFeedback");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.Notification",
+                "ReportFeedback",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Create or replace a template.
+        pub async fn save_template(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Template>,
+        ) -> std::result::Result<tonic::Response<super::Template>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/SaveTemplate");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "SaveTemplate"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get a template by name.
+        pub async fn get_template(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetTemplateRequest>,
+        ) -> std::result::Result<tonic::Response<super::Template>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/GetTemplate");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "GetTemplate"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Render a template for every recipient of a batch and send the messages.
+        pub async fn send_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SendBatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::SendBatchResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/notification.Notification/SendBatch");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "SendBatch"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get the counters of the dispatch lanes.
+        pub async fn get_lane_stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetLaneStatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::LaneStatsResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/notification.Notification/GetLaneStats");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("notification.Notification", "GetLaneStats"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod notification_admin_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+    /// Operator tooling for the messages which could not be sent.
+    #[derive(Debug, Clone)]
+    pub struct NotificationAdminClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl NotificationAdminClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> NotificationAdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> NotificationAdminClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + Send + Sync,
+        {
+            NotificationAdminClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// List dead-lettered or stored messages by type, time and error.
+        pub async fn list_dead_letters(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListDeadLettersRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListDeadLettersResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/notification.NotificationAdmin/ListDeadLetters",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.NotificationAdmin",
+                "ListDeadLetters",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get the payload, attempt history and replays of a message.
+        pub async fn get_dead_letter(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetDeadLetterRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeadLetter>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/notification.NotificationAdmin/GetDeadLetter",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.NotificationAdmin",
+                "GetDeadLetter",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Send messages again, every replay is recorded in the audit.
+        pub async fn replay_dead_letters(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReplayRequest>,
+        ) -> std::result::Result<tonic::Response<super::ReplayResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/notification.NotificationAdmin/ReplayDeadLetters",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "notification.NotificationAdmin",
+                "ReplayDeadLetters",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod notification_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with NotificationServer.
+    #[async_trait]
+    pub trait Notification: Send + Sync + 'static {
+        /// Server streaming response type for the Send method.
+        type SendStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SendResponse, tonic::Status>,
+            > + Send
+            + 'static;
+        /// Send a notification to a user.
+        async fn send(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SendRequest>>,
+        ) -> std::result::Result<tonic::Response<Self::SendStream>, tonic::Status>;
+        /// Get the current status and state changes of a message.
+        async fn get_message_status(
+            &self,
+            request: tonic::Request<super::GetMessageStatusRequest>,
+        ) -> std::result::Result<tonic::Response<super::MessageStatusResponse>, tonic::Status>;
+        /// List messages by recipient, sender and time range.
+        async fn list_messages(
+            &self,
+            request: tonic::Request<super::ListMessagesRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListMessagesResponse>, tonic::Status>;
+        /// List scheduled messages which are not sent yet, the earliest first.
+        async fn list_scheduled(
+            &self,
+            request: tonic::Request<super::ListScheduledRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListMessagesResponse>, tonic::Status>;
+        /// Cancel a scheduled message which is not sent yet.
+        async fn cancel_scheduled(
+            &self,
+            request: tonic::Request<super::CancelScheduledRequest>,
+        ) -> std::result::Result<tonic::Response<super::MessageStatusResponse>, tonic::Status>;
+        /// Get the notification preferences of a user.
+        async fn get_preferences(
+            &self,
+            request: tonic::Request<super::GetPreferencesRequest>,
+        ) -> std::result::Result<tonic::Response<super::Preferences>, tonic::Status>;
+        /// Replace the notification preferences of a user.
+        async fn update_preferences(
+            &self,
+            request: tonic::Request<super::Preferences>,
+        ) -> std::result::Result<tonic::Response<super::Preferences>, tonic::Status>;
+        /// Unsubscribe the address of a signed unsubscribe token from every channel.
+        async fn unsubscribe(
+            &self,
+            request: tonic::Request<super::UnsubscribeRequest>,
+        ) -> std::result::Result<tonic::Response<super::UnsubscribeResponse>, tonic::Status>;
+        /// List the in-app messages of a device or user.
+        async fn list_inbox(
+            &self,
+            request: tonic::Request<super::ListInboxRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListInboxResponse>, tonic::Status>;
+        /// Mark in-app messages as read or opened.
+        async fn mark_read(
+            &self,
+            request: tonic::Request<super::MarkReadRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>;
+        /// Mark every in-app message of a device or user as read.
+        async fn mark_all_read(
+            &self,
+            request: tonic::Request<super::MarkAllReadRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>;
+        /// Delete an in-app message from an inbox.
+        async fn delete_message(
+            &self,
+            request: tonic::Request<super::DeleteMessageRequest>,
+        ) -> std::result::Result<tonic::Response<super::InboxUpdateResponse>, tonic::Status>;
+        /// Server streaming response type for the Subscribe method.
+        type SubscribeStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::InAppMessage, tonic::Status>,
+            > + Send
+            + 'static;
+        /// Receive the in-app messages of a device while they are sent, they are kept in the inbox as well.
+        async fn subscribe(
+            &self,
+            request: tonic::Request<super::SubscribeRequest>,
+        ) -> std::result::Result<tonic::Response<Self::SubscribeStream>, tonic::Status>;
+        /// Report a bounce, complaint or delivery receipt of a provider.
+        async fn report_feedback(
+            &self,
+            request: tonic::Request<super::ProviderFeedback>,
+        ) -> std::result::Result<tonic::Response<super::FeedbackResponse>, tonic::Status>;
+        /// Create or replace a template.
+        async fn save_template(
+            &self,
+            request: tonic::Request<super::Template>,
+        ) -> std::result::Result<tonic::Response<super::Template>, tonic::Status>;
+        /// Get a template by name.
+        async fn get_template(
+            &self,
+            request: tonic::Request<super::GetTemplateRequest>,
+        ) -> std::result::Result<tonic::Response<super::Template>, tonic::Status>;
+        /// Render a template for every recipient of a batch and send the messages.
+        async fn send_batch(
+            &self,
+            request: tonic::Request<super::SendBatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::SendBatchResponse>, tonic::Status>;
+        /// Get the counters of the dispatch lanes.
+        async fn get_lane_stats(
+            &self,
+            request: tonic::Request<super::GetLaneStatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::LaneStatsResponse>, tonic::Status>;
+    }
+    /// The Notification service provides a way to send notifications to users.
+    #[derive(Debug)]
+    pub struct NotificationServer<T: Notification> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Notification> NotificationServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for NotificationServer<T>
+    where
+        T: Notification,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/notification.Notification/Send" => {
+                    #[allow(non_camel_case_types)]
+                    struct SendSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::StreamingService<super::SendRequest> for SendSvc<T> {
+                        type Response = super::SendResponse;
+                        type ResponseStream = T::SendStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::SendRequest>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as Notification>::send(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SendSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/GetMessageStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMessageStatusSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification>
+                        tonic::server::UnaryService<super::GetMessageStatusRequest>
+                        for GetMessageStatusSvc<T>
+                    {
+                        type Response = super::MessageStatusResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetMessageStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::get_message_status(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetMessageStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/ListMessages" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListMessagesSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::ListMessagesRequest>
+                        for ListMessagesSvc<T>
+                    {
+                        type Response = super::ListMessagesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListMessagesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::list_messages(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListMessagesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/ListScheduled" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListScheduledSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::ListScheduledRequest>
+                        for ListScheduledSvc<T>
+                    {
+                        type Response = super::ListMessagesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListScheduledRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::list_scheduled(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListScheduledSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/CancelScheduled" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelScheduledSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::CancelScheduledRequest>
+                        for CancelScheduledSvc<T>
+                    {
+                        type Response = super::MessageStatusResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelScheduledRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::cancel_scheduled(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelScheduledSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/GetPreferences" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetPreferencesSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::GetPreferencesRequest>
+                        for GetPreferencesSvc<T>
+                    {
+                        type Response = super::Preferences;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetPreferencesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::get_preferences(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetPreferencesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/UpdatePreferences" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdatePreferencesSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::Preferences> for UpdatePreferencesSvc<T> {
+                        type Response = super::Preferences;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Preferences>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::update_preferences(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdatePreferencesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/Unsubscribe" => {
+                    #[allow(non_camel_case_types)]
+                    struct UnsubscribeSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::UnsubscribeRequest> for UnsubscribeSvc<T> {
+                        type Response = super::UnsubscribeResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UnsubscribeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::unsubscribe(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UnsubscribeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/ListInbox" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListInboxSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::ListInboxRequest> for ListInboxSvc<T> {
+                        type Response = super::ListInboxResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListInboxRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::list_inbox(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListInboxSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/MarkRead" => {
+                    #[allow(non_camel_case_types)]
+                    struct MarkReadSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::MarkReadRequest> for MarkReadSvc<T> {
+                        type Response = super::InboxUpdateResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MarkReadRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::mark_read(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MarkReadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/MarkAllRead" => {
+                    #[allow(non_camel_case_types)]
+                    struct MarkAllReadSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::MarkAllReadRequest> for MarkAllReadSvc<T> {
+                        type Response = super::InboxUpdateResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MarkAllReadRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::mark_all_read(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MarkAllReadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/DeleteMessage" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteMessageSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::DeleteMessageRequest>
+                        for DeleteMessageSvc<T>
+                    {
+                        type Response = super::InboxUpdateResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteMessageRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::delete_message(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DeleteMessageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/Subscribe" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification>
+                        tonic::server::ServerStreamingService<super::SubscribeRequest>
+                        for SubscribeSvc<T>
+                    {
+                        type Response = super::InAppMessage;
+                        type ResponseStream = T::SubscribeStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::subscribe(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/ReportFeedback" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReportFeedbackSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::ProviderFeedback>
+                        for ReportFeedbackSvc<T>
+                    {
+                        type Response = super::FeedbackResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ProviderFeedback>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::report_feedback(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReportFeedbackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/SaveTemplate" => {
+                    #[allow(non_camel_case_types)]
+                    struct SaveTemplateSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::Template> for SaveTemplateSvc<T> {
+                        type Response = super::Template;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Template>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::save_template(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SaveTemplateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/GetTemplate" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetTemplateSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::GetTemplateRequest> for GetTemplateSvc<T> {
+                        type Response = super::Template;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetTemplateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::get_template(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetTemplateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/SendBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct SendBatchSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::SendBatchRequest> for SendBatchSvc<T> {
+                        type Response = super::SendBatchResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SendBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::send_batch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SendBatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.Notification/GetLaneStats" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetLaneStatsSvc<T: Notification>(pub Arc<T>);
+                    impl<T: Notification> tonic::server::UnaryService<super::GetLaneStatsRequest>
+                        for GetLaneStatsSvc<T>
+                    {
+                        type Response = super::LaneStatsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetLaneStatsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::get_lane_stats(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetLaneStatsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: Notification> Clone for NotificationServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: Notification> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Notification> tonic::server::NamedService for NotificationServer<T> {
+        const NAME: &'static str = "notification.Notification";
+    }
+}
+/// Generated server implementations.
+pub mod notification_admin_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with NotificationAdminServer.
+    #[async_trait]
+    pub trait NotificationAdmin: Send + Sync + 'static {
+        /// List dead-lettered or stored messages by type, time and error.
+        async fn list_dead_letters(
+            &self,
+            request: tonic::Request<super::ListDeadLettersRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListDeadLettersResponse>, tonic::Status>;
+        /// Get the payload, attempt history and replays of a message.
+        async fn get_dead_letter(
+            &self,
+            request: tonic::Request<super::GetDeadLetterRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeadLetter>, tonic::Status>;
+        /// Send messages again, every replay is recorded in the audit.
+        async fn replay_dead_letters(
+            &self,
+            request: tonic::Request<super::ReplayRequest>,
+        ) -> std::result::Result<tonic::Response<super::ReplayResponse>, tonic::Status>;
+    }
+    /// Operator tooling for the messages which could not be sent.
+    #[derive(Debug)]
+    pub struct NotificationAdminServer<T: NotificationAdmin> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: NotificationAdmin> NotificationAdminServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for NotificationAdminServer<T>
+    where
+        T: NotificationAdmin,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/notification.NotificationAdmin/ListDeadLetters" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListDeadLettersSvc<T: NotificationAdmin>(pub Arc<T>);
+                    impl<T: NotificationAdmin>
+                        tonic::server::UnaryService<super::ListDeadLettersRequest>
+                        for ListDeadLettersSvc<T>
+                    {
+                        type Response = super::ListDeadLettersResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListDeadLettersRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NotificationAdmin>::list_dead_letters(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListDeadLettersSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.NotificationAdmin/GetDeadLetter" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetDeadLetterSvc<T: NotificationAdmin>(pub Arc<T>);
+                    impl<T: NotificationAdmin>
+                        tonic::server::UnaryService<super::GetDeadLetterRequest>
+                        for GetDeadLetterSvc<T>
+                    {
+                        type Response = super::DeadLetter;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetDeadLetterRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NotificationAdmin>::get_dead_letter(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetDeadLetterSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/notification.NotificationAdmin/ReplayDeadLetters" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReplayDeadLettersSvc<T: NotificationAdmin>(pub Arc<T>);
+                    impl<T: NotificationAdmin> tonic::server::UnaryService<super::ReplayRequest>
+                        for ReplayDeadLettersSvc<T>
+                    {
+                        type Response = super::ReplayResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReplayRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as NotificationAdmin>::replay_dead_letters(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReplayDeadLettersSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: NotificationAdmin> Clone for NotificationAdminServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: NotificationAdmin> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: NotificationAdmin> tonic::server::NamedService for NotificationAdminServer<T> {
+        const NAME: &'static str = "notification.NotificationAdmin";
+    }
+}
@@ -0,0 +1,110 @@
+use super::ServiceError;
+pub use crate::model::{
+    dead_letter::{DeadLetter, DeadLetterQuery, Replay, REPLAYABLE},
+    lifecycle::{MessageEvent, MessageStatus},
+    message::{Message, MessageError, MessageType},
+};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Error, Debug)]
+pub enum DeadLetterError {
+    #[error("Message {0} not found")]
+    NotFound(String),
+
+    #[error("Message {0} is {1}, only dead-lettered, stored or failed messages can be replayed")]
+    NotReplayable(String, MessageStatus),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// Messages which could not be sent, for operators to inspect and send again.
+#[async_trait]
+pub trait DeadLetters: Send + Sync + 'static {
+    async fn list(&self, query: DeadLetterQuery) -> Result<Vec<DeadLetter>, ServiceError>;
+
+    /// the message with its attempts and replays
+    async fn get(
+        &self,
+        id: &str,
+    ) -> Result<(DeadLetter, Vec<MessageEvent>, Vec<Replay>), ServiceError>;
+
+    /// Mark the message as being sent again, to the recipients of `replay` instead of the
+    /// original ones if given, and record the replay at the same time.
+    ///
+    /// Returns the message and the id of the replay, see `finish_replay`.
+    async fn start_replay(&self, replay: &Replay) -> Result<(Message, i64), ServiceError>;
+
+    /// record a replay which failed before it started
+    async fn record_replay(&self, replay: Replay) -> Result<(), ServiceError>;
+
+    /// set the outcome of a started replay
+    async fn finish_replay(&self, id: i64, outcome: &str) -> Result<(), ServiceError>;
+}
+
+pub struct DeadLettersPg {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl DeadLetters for DeadLettersPg {
+    async fn list(&self, query: DeadLetterQuery) -> Result<Vec<DeadLetter>, ServiceError> {
+        let messages = DeadLetter::list(&query, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?;
+        Ok(messages)
+    }
+
+    async fn get(
+        &self,
+        id: &str,
+    ) -> Result<(DeadLetter, Vec<MessageEvent>, Vec<Replay>), ServiceError> {
+        let message = DeadLetter::find(id, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?
+            .ok_or_else(|| DeadLetterError::NotFound(id.to_string()))?;
+        let events = MessageEvent::list_by_message(id, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?;
+        let replays = Replay::list_by_message(id, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?;
+        Ok((message, events, replays))
+    }
+
+    async fn start_replay(&self, replay: &Replay) -> Result<(Message, i64), ServiceError> {
+        if let Some(started) = DeadLetter::start_replay(replay, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?
+        {
+            return Ok(started);
+        }
+        let id = &replay.message_id;
+        let message = Message::find(id, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?
+            .ok_or_else(|| DeadLetterError::NotFound(id.to_string()))?;
+        Err(DeadLetterError::NotReplayable(id.to_string(), message.status).into())
+    }
+
+    async fn record_replay(&self, replay: Replay) -> Result<(), ServiceError> {
+        replay
+            .insert(&self.pool)
+            .await
+            .map_err(DeadLetterError::from)?;
+        Ok(())
+    }
+
+    async fn finish_replay(&self, id: i64, outcome: &str) -> Result<(), ServiceError> {
+        Replay::finish(id, outcome, &self.pool)
+            .await
+            .map_err(DeadLetterError::from)?;
+        Ok(())
+    }
+}
+
+pub fn dead_letters_pg(pool: PgPool) -> Box<dyn DeadLetters> {
+    Box::new(DeadLettersPg { pool })
+}
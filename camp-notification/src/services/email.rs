@@ -1,5 +1,8 @@
 use super::{SendResponse, ServiceError};
-pub use crate::model::message::{EmailMessage, Message, MessageError};
+use crate::config::EmailConfig;
+pub use crate::model::message::{
+    Attachment, AttachmentSource, EmailMessage, Message, MessageError,
+};
 use anyhow::Result;
 use chrono::Utc;
 use thiserror::Error;
@@ -15,7 +18,83 @@ pub enum EmailError {
     #[error("Sqlx error")]
     Model(#[from] MessageError),
     #[error("Failed to save email message {0}")]
-    FailedButSaved(Message),
+    FailedButSaved(Box<Message>),
+    #[error("Invalid email: {0}")]
+    Invalid(String),
+}
+
+/// headers built from the fields of the email which can not be overridden
+const RESERVED_HEADERS: [&str; 8] = [
+    "from",
+    "to",
+    "cc",
+    "bcc",
+    "subject",
+    "reply-to",
+    "content-type",
+    "mime-version",
+];
+
+/// reject emails the provider would refuse, before they are accepted
+pub fn check_email(email: &EmailMessage, config: &EmailConfig) -> Result<(), EmailError> {
+    // a line break would let the header add others of its own
+    if let Some(name) = email.headers.iter().find_map(|(name, value)| {
+        let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_graphic() && c != ':');
+        let valid_value = !value.contains(['\r', '\n']);
+        (!valid_name || !valid_value).then_some(name)
+    }) {
+        return Err(EmailError::Invalid(format!(
+            "header {:?} is malformed",
+            name
+        )));
+    }
+    if let Some(name) = email
+        .headers
+        .keys()
+        .find(|name| RESERVED_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+    {
+        return Err(EmailError::Invalid(format!(
+            "header {} can not be set directly",
+            name
+        )));
+    }
+    for attachment in &email.attachments {
+        if attachment.filename.is_empty() {
+            return Err(EmailError::Invalid(
+                "attachment filename is required".to_string(),
+            ));
+        }
+        match &attachment.source {
+            AttachmentSource::Content(content) if content.is_empty() => {
+                return Err(EmailError::Invalid(format!(
+                    "attachment {} has no content",
+                    attachment.filename
+                )));
+            }
+            AttachmentSource::Content(content)
+                if content.len() as u64 > config.max_inline_attachment_bytes =>
+            {
+                return Err(EmailError::Invalid(format!(
+                    "attachment {} exceeds {} bytes, reference it from blob storage instead",
+                    attachment.filename, config.max_inline_attachment_bytes
+                )));
+            }
+            AttachmentSource::Blob { url, .. } if url.is_empty() => {
+                return Err(EmailError::Invalid(format!(
+                    "attachment {} has no blob url",
+                    attachment.filename
+                )));
+            }
+            _ => {}
+        }
+    }
+    if email.attachments_size() > config.max_attachments_bytes {
+        return Err(EmailError::Invalid(format!(
+            "attachments exceed {} bytes",
+            config.max_attachments_bytes
+        )));
+    }
+    Ok(())
 }
 
 #[cfg_attr(feature = "test_utils", unimock(api=MockEmailInner))]
@@ -29,6 +108,9 @@ pub trait EmailInner: Send + Sync + 'static {
 
 #[async_trait]
 pub trait Email: Send + Sync + 'static {
+    /// whether the email can be sent, checked before it is accepted
+    async fn check(&self, email: &EmailMessage) -> Result<(), ServiceError>;
+
     async fn send_email(&self, email: EmailMessage) -> Result<SendResponse, ServiceError>;
 }
 
@@ -41,7 +123,7 @@ impl EmailInner for EmailFaker {
         email: EmailMessage,
     ) -> Result<SendResponse, (EmailMessage, EmailError)> {
         let random = rand::random::<u8>();
-        if random % 9 == 0 {
+        if random.is_multiple_of(9) {
             Ok(SendResponse {
                 id: email.id,
                 timestamp: Utc::now(),
@@ -52,11 +134,12 @@ impl EmailInner for EmailFaker {
     }
 }
 
-pub fn random_return_email(pool: sqlx::PgPool) -> Box<dyn Email> {
+pub fn random_return_email(pool: sqlx::PgPool, config: EmailConfig) -> Box<dyn Email> {
     let email = EmailFaker {};
     let email_fail_over = EmailFailOver::<EmailFaker> {
         sender: email,
         pool,
+        config,
     };
     Box::new(email_fail_over)
 }
@@ -64,19 +147,28 @@ pub fn random_return_email(pool: sqlx::PgPool) -> Box<dyn Email> {
 pub struct EmailFailOver<T: EmailInner> {
     pub sender: T,
     pub pool: sqlx::PgPool,
+    pub config: EmailConfig,
 }
 
 impl<T> EmailFailOver<T>
 where
     T: EmailInner,
 {
-    pub fn new(sender: T, pool: sqlx::PgPool) -> Self {
-        Self { sender, pool }
+    pub fn new(sender: T, pool: sqlx::PgPool, config: EmailConfig) -> Self {
+        Self {
+            sender,
+            pool,
+            config,
+        }
     }
 }
 
 #[async_trait]
 impl<T: EmailInner> Email for EmailFailOver<T> {
+    async fn check(&self, email: &EmailMessage) -> Result<(), ServiceError> {
+        Ok(check_email(email, &self.config)?)
+    }
+
     async fn send_email(&self, email: EmailMessage) -> Result<SendResponse, ServiceError> {
         //  try again if the first attempt fails
         let resp = match self.sender.send_email(email).await {
@@ -89,8 +181,8 @@ impl<T: EmailInner> Email for EmailFailOver<T> {
 
         match resp {
             Ok(send_response) => Ok(send_response),
-            Err((msg, _)) => match Message::insert_email(msg.clone(), &self.pool).await {
-                Ok(msg) => Err(EmailError::FailedButSaved(msg).into()),
+            Err((msg, _)) => match Message::store_email(msg.clone(), &self.pool).await {
+                Ok(msg) => Err(EmailError::FailedButSaved(Box::new(msg)).into()),
                 Err(err) => {
                     info!("Failed to save email message: {:?}", err);
                     Err(EmailError::Model(err).into())
@@ -101,6 +193,7 @@ impl<T: EmailInner> Email for EmailFailOver<T> {
 }
 
 #[cfg(test)]
+#[allow(clippy::result_large_err)]
 mod test {
     use unimock::{matching, MockFn as _, Unimock};
 
@@ -116,16 +209,67 @@ mod test {
                 sender: "Test".to_string(),
                 recipients: vec!["test1".to_string()],
                 body: "Test".to_string(),
+                ..Default::default()
             })
             .await
     }
 
+    #[test]
+    fn test_check_email() {
+        let config = EmailConfig {
+            max_inline_attachment_bytes: 4,
+            max_attachments_bytes: 10,
+        };
+        let attachment = |source| Attachment {
+            filename: "file.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            source,
+            content_id: None,
+        };
+        let blob = |size| AttachmentSource::Blob {
+            url: "s3://bucket/file.txt".to_string(),
+            size,
+        };
+        let mut email = EmailMessage {
+            attachments: vec![
+                attachment(AttachmentSource::Content(vec![1, 2, 3])),
+                attachment(blob(7)),
+            ],
+            headers: [("X-Campaign".to_string(), "welcome".to_string())].into(),
+            ..Default::default()
+        };
+        assert!(check_email(&email, &config).is_ok());
+
+        email.attachments[1] = attachment(blob(8));
+        assert!(check_email(&email, &config).is_err());
+        email.attachments[1] = attachment(AttachmentSource::Content(vec![0; 5]));
+        assert!(check_email(&email, &config).is_err());
+        email.attachments[1] = attachment(AttachmentSource::Content(vec![]));
+        assert!(check_email(&email, &config).is_err());
+        email.attachments.pop();
+        email
+            .headers
+            .insert("Reply-To".to_string(), "a@test.com".to_string());
+        assert!(check_email(&email, &config).is_err());
+        email.headers.remove("Reply-To");
+        assert!(check_email(&email, &config).is_ok());
+        for (name, value) in [
+            ("X-Campaign", "welcome\r\nBcc: a@test.com"),
+            ("X-Campaign\nBcc", "a@test.com"),
+            ("X-Campaign:", "welcome"),
+        ] {
+            let mut email = email.clone();
+            email.headers = [(name.to_string(), value.to_string())].into();
+            assert!(check_email(&email, &config).is_err());
+        }
+    }
+
     #[tokio::test]
     async fn test() {
         let mock_email = Unimock::new(MockEmailInner::send_email.each_call(matching!()).answers(
             &|_, email_msg| {
                 let random = rand::random::<u8>();
-                if random % 2 == 0 {
+                if random.is_multiple_of(2) {
                     Ok(SendResponse {
                         id: email_msg.id,
                         timestamp: Utc::now(),
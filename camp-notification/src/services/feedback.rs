@@ -0,0 +1,101 @@
+use super::{recipients::normalize_address, ServiceError};
+use crate::config::RecipientsConfig;
+pub use crate::model::{
+    feedback::{Feedback as ProviderFeedback, FeedbackType},
+    lifecycle::MessageStatus,
+    message::{Message, MessageError},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum FeedbackError {
+    #[error("Message {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid recipient {0}: {1}")]
+    InvalidRecipient(String, String),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// header carrying the hex hmac-sha256 of a webhook body, keyed with the shared secret
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// hex encoded signature of a webhook body
+pub fn sign_webhook(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify_webhook(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Bounces, complaints and delivery receipts reported by the providers after a send.
+#[async_trait]
+pub trait Feedback: Send + Sync + 'static {
+    /// returns the status of the message after the feedback
+    async fn record(&self, feedback: ProviderFeedback) -> Result<MessageStatus, ServiceError>;
+}
+
+pub struct FeedbackPg {
+    pub pool: PgPool,
+    pub config: RecipientsConfig,
+}
+
+#[async_trait]
+impl Feedback for FeedbackPg {
+    async fn record(&self, mut feedback: ProviderFeedback) -> Result<MessageStatus, ServiceError> {
+        // the recipient is suppressed and matched as the message was sent to it
+        let not_found = || FeedbackError::NotFound(feedback.message_id.clone());
+        let message = Message::find(&feedback.message_id, &self.pool)
+            .await
+            .map_err(FeedbackError::from)?
+            .ok_or_else(not_found)?;
+        feedback.recipient = normalize_address(
+            message.r#type,
+            self.config.default_region,
+            &feedback.recipient,
+        )
+        .map_err(|reason| FeedbackError::InvalidRecipient(feedback.recipient.clone(), reason))?;
+        let status = feedback
+            .record(&self.pool)
+            .await
+            .map_err(FeedbackError::from)?
+            .ok_or_else(|| FeedbackError::NotFound(feedback.message_id.clone()))?;
+        Ok(status)
+    }
+}
+
+pub fn feedback_pg(pool: PgPool, config: RecipientsConfig) -> Box<dyn Feedback> {
+    Box::new(FeedbackPg { pool, config })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_webhook_signature() {
+        let signature = sign_webhook("secret", b"{}");
+        assert!(verify_webhook("secret", b"{}", &signature));
+        assert!(!verify_webhook("other", b"{}", &signature));
+        assert!(!verify_webhook("secret", b"{ }", &signature));
+        assert!(!verify_webhook("secret", b"{}", "not hex"));
+    }
+}
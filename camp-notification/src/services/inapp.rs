@@ -1,8 +1,14 @@
-use super::{SendResponse, ServiceError};
-pub use crate::model::message::{InAppMessage, MessageError};
+use super::{push::Push, SendResponse, ServiceError};
+pub use crate::model::{
+    inbox::InboxMessage,
+    message::{InAppMessage, MessageError},
+};
 use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
 use thiserror::Error;
 use tonic::async_trait;
+use tracing::warn;
 #[cfg(feature = "test_utils")]
 use unimock::unimock;
 
@@ -27,7 +33,7 @@ pub struct InAppFaker;
 impl InApp for InAppFaker {
     async fn send_inapp(&self, msg: InAppMessage) -> Result<SendResponse, ServiceError> {
         let random = rand::random::<u8>();
-        if random % 9 == 0 {
+        if random.is_multiple_of(9) {
             Ok(SendResponse {
                 id: msg.id,
                 timestamp: Utc::now(),
@@ -41,3 +47,30 @@ impl InApp for InAppFaker {
 pub fn random_return_inapp() -> Box<dyn InApp> {
     Box::new(InAppFaker {})
 }
+
+/// delivers in-app messages to the inbox of their device and pushes them to its subscribers
+pub struct InAppInbox {
+    pub pool: PgPool,
+    pub push: Arc<Box<dyn Push>>,
+}
+
+#[async_trait]
+impl InApp for InAppInbox {
+    async fn send_inapp(&self, msg: InAppMessage) -> Result<SendResponse, ServiceError> {
+        let stored = InboxMessage::deliver(&msg, &self.pool)
+            .await
+            .map_err(InAppError::from)?;
+        // the message is in the inbox already, a failed push only delays it
+        if let Err(e) = self.push.publish(&msg).await {
+            warn!("failed to push message {}: {}", msg.id, e);
+        }
+        Ok(SendResponse {
+            id: stored.message_id,
+            timestamp: stored.created_at,
+        })
+    }
+}
+
+pub fn inbox_inapp(pool: PgPool, push: Arc<Box<dyn Push>>) -> Box<dyn InApp> {
+    Box::new(InAppInbox { pool, push })
+}
@@ -0,0 +1,103 @@
+use super::ServiceError;
+pub use crate::model::{
+    inbox::{InboxEvent, InboxMessage, InboxOwner},
+    message::MessageError,
+};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Error, Debug)]
+pub enum InboxError {
+    #[error("Message {0} not found in the inbox")]
+    NotFound(String),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// Stored in-app messages of devices and the engagement with them.
+#[async_trait]
+pub trait Inbox: Send + Sync + 'static {
+    async fn list(
+        &self,
+        owner: &InboxOwner,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<InboxMessage>, ServiceError>;
+
+    async fn unread_count(&self, owner: &InboxOwner) -> Result<i64, ServiceError>;
+
+    /// returns the number of messages changed
+    async fn mark_read(
+        &self,
+        owner: &InboxOwner,
+        ids: &[String],
+        opened: bool,
+    ) -> Result<u64, ServiceError>;
+
+    async fn mark_all_read(&self, owner: &InboxOwner) -> Result<u64, ServiceError>;
+
+    async fn delete(&self, owner: &InboxOwner, id: &str) -> Result<(), ServiceError>;
+}
+
+pub struct InboxPg {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl Inbox for InboxPg {
+    async fn list(
+        &self,
+        owner: &InboxOwner,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<InboxMessage>, ServiceError> {
+        let messages = InboxMessage::list(owner, unread_only, limit, offset, &self.pool)
+            .await
+            .map_err(InboxError::from)?;
+        Ok(messages)
+    }
+
+    async fn unread_count(&self, owner: &InboxOwner) -> Result<i64, ServiceError> {
+        let count = InboxMessage::unread_count(owner, &self.pool)
+            .await
+            .map_err(InboxError::from)?;
+        Ok(count)
+    }
+
+    async fn mark_read(
+        &self,
+        owner: &InboxOwner,
+        ids: &[String],
+        opened: bool,
+    ) -> Result<u64, ServiceError> {
+        let updated = InboxMessage::mark_read(owner, ids, opened, &self.pool)
+            .await
+            .map_err(InboxError::from)?;
+        Ok(updated)
+    }
+
+    async fn mark_all_read(&self, owner: &InboxOwner) -> Result<u64, ServiceError> {
+        let updated = InboxMessage::mark_all_read(owner, &self.pool)
+            .await
+            .map_err(InboxError::from)?;
+        Ok(updated)
+    }
+
+    async fn delete(&self, owner: &InboxOwner, id: &str) -> Result<(), ServiceError> {
+        let deleted = InboxMessage::delete(owner, id, &self.pool)
+            .await
+            .map_err(InboxError::from)?;
+        if deleted == 0 {
+            return Err(InboxError::NotFound(id.to_string()).into());
+        }
+        Ok(())
+    }
+}
+
+pub fn inbox_pg(pool: PgPool) -> Box<dyn Inbox> {
+    Box::new(InboxPg { pool })
+}
@@ -0,0 +1,121 @@
+use super::ServiceError;
+pub use crate::model::{
+    feedback::RecipientOutcome,
+    lifecycle::{MessageEvent, MessageQuery, MessageStatus},
+    message::{Message, MessageError},
+};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Error, Debug)]
+pub enum LifecycleError {
+    #[error("Message not found: {0}")]
+    NotFound(String),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+#[derive(Debug)]
+pub enum Accepted {
+    New(Message),
+    /// the message id was already accepted within the idempotency window
+    Duplicate(Message),
+}
+
+/// Records every message and the state changes it goes through.
+#[async_trait]
+pub trait Lifecycle: Send + Sync + 'static {
+    async fn accept(&self, msg: Message) -> Result<Accepted, ServiceError>;
+
+    async fn transition(
+        &self,
+        id: &str,
+        status: MessageStatus,
+        provider_response: Option<&str>,
+    ) -> Result<(), ServiceError>;
+
+    /// forget a message accepted but not sent, see [`Message::release`]
+    async fn release(&self, id: &str) -> Result<(), ServiceError>;
+
+    async fn get(&self, id: &str) -> Result<(Message, Vec<MessageEvent>), ServiceError>;
+
+    /// what the providers reported for every recipient of a message
+    async fn outcomes(&self, id: &str) -> Result<Vec<RecipientOutcome>, ServiceError>;
+
+    async fn list(&self, query: MessageQuery) -> Result<Vec<Message>, ServiceError>;
+}
+
+pub struct LifecyclePg {
+    pub pool: PgPool,
+    pub window: Duration,
+}
+
+#[async_trait]
+impl Lifecycle for LifecyclePg {
+    async fn accept(&self, msg: Message) -> Result<Accepted, ServiceError> {
+        let id = msg.id.clone();
+        let window_start = Utc::now() - self.window;
+        if let Some(msg) = msg
+            .accept(window_start, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?
+        {
+            return Ok(Accepted::New(msg));
+        }
+        let (msg, _) = self.get(&id).await?;
+        Ok(Accepted::Duplicate(msg))
+    }
+
+    async fn transition(
+        &self,
+        id: &str,
+        status: MessageStatus,
+        provider_response: Option<&str>,
+    ) -> Result<(), ServiceError> {
+        Message::transition(id, status, provider_response, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?;
+        Ok(())
+    }
+
+    async fn release(&self, id: &str) -> Result<(), ServiceError> {
+        Message::release(id, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<(Message, Vec<MessageEvent>), ServiceError> {
+        let Some(msg) = Message::find(id, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?
+        else {
+            return Err(LifecycleError::NotFound(id.to_string()).into());
+        };
+        let events = MessageEvent::list_by_message(id, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?;
+        Ok((msg, events))
+    }
+
+    async fn outcomes(&self, id: &str) -> Result<Vec<RecipientOutcome>, ServiceError> {
+        let outcomes = RecipientOutcome::list_by_message(id, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?;
+        Ok(outcomes)
+    }
+
+    async fn list(&self, query: MessageQuery) -> Result<Vec<Message>, ServiceError> {
+        let messages = Message::list(&query, &self.pool)
+            .await
+            .map_err(LifecycleError::from)?;
+        Ok(messages)
+    }
+}
+
+pub fn lifecycle_pg(pool: PgPool, window: Duration) -> Box<dyn Lifecycle> {
+    Box::new(LifecyclePg { pool, window })
+}
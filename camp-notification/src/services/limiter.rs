@@ -0,0 +1,342 @@
+use super::ServiceError;
+use crate::{
+    config::{RateLimitBackend, RateLimitConfig, RateLimitRule, ThrottleMode},
+    model::{
+        message::{Message, MessageError, MessageType},
+        rate_limit::Bucket,
+    },
+};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use thiserror::Error;
+use tokio::sync::watch;
+use tonic::async_trait;
+use tracing::{info, warn};
+
+#[derive(Error, Debug)]
+pub enum LimiterError {
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// name of the provider a message type is delivered through
+pub fn provider(r#type: MessageType) -> &'static str {
+    match r#type {
+        MessageType::Email => "smtp",
+        MessageType::Sms => "sms_gateway",
+        MessageType::Inapp => "inapp",
+        MessageType::Webhook => "webhook",
+        MessageType::Unknown => "unknown",
+    }
+}
+
+/// Token buckets identified by a key.
+#[async_trait]
+pub trait RateLimiter: Send + Sync + 'static {
+    /// take a token from the bucket of `key`, or return how long it takes until one is available
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<Option<Duration>, ServiceError>;
+
+    /// put back a token taken from the bucket of `key`
+    async fn refund(&self, key: &str, rule: &RateLimitRule) -> Result<(), ServiceError>;
+}
+
+#[derive(Default)]
+pub struct InMemoryLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryLimiter {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<Option<Duration>, ServiceError> {
+        let now = Utc::now();
+        let mut buckets = self.buckets.lock().expect("rate limit buckets poisoned");
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Bucket::full(rule, now));
+        Ok(bucket.take(rule, now))
+    }
+
+    async fn refund(&self, key: &str, rule: &RateLimitRule) -> Result<(), ServiceError> {
+        let mut buckets = self.buckets.lock().expect("rate limit buckets poisoned");
+        if let Some(bucket) = buckets.get_mut(key) {
+            bucket.refund(rule);
+        }
+        Ok(())
+    }
+}
+
+pub struct PgLimiter {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl RateLimiter for PgLimiter {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        rule: &RateLimitRule,
+    ) -> Result<Option<Duration>, ServiceError> {
+        let wait = Bucket::pg_take(key, rule, &self.pool)
+            .await
+            .map_err(LimiterError::from)?;
+        Ok(wait)
+    }
+
+    async fn refund(&self, key: &str, rule: &RateLimitRule) -> Result<(), ServiceError> {
+        Bucket::pg_refund(key, rule, &self.pool)
+            .await
+            .map_err(LimiterError::from)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission {
+    Admitted,
+    /// the bucket of the key ran out of tokens
+    Throttled(String),
+}
+
+#[async_trait]
+pub trait Throttle: Send + Sync + 'static {
+    /// wait for or reject a message according to the limits of its channel, provider and recipients
+    async fn admit(&self, msg: &Message) -> Result<Admission, ServiceError>;
+}
+
+pub struct TokenBucketThrottle {
+    pub limiter: Box<dyn RateLimiter>,
+    /// the limits are read for every message, the backend is the one at start
+    pub config: watch::Receiver<RateLimitConfig>,
+}
+
+impl TokenBucketThrottle {
+    fn rules(config: &RateLimitConfig, msg: &Message) -> Vec<(String, RateLimitRule)> {
+        let channel = msg.r#type.to_string();
+        let provider = provider(msg.r#type);
+        let mut rules = Vec::new();
+        if let Some(rule) = config.channels.get(&channel) {
+            rules.push((format!("channel:{}", channel), *rule));
+        }
+        if let Some(rule) = config.providers.get(provider) {
+            rules.push((format!("provider:{}", provider), *rule));
+        }
+        if let Some(rule) = config.lanes.get(&msg.priority.to_string()) {
+            rules.push((format!("lane:{}", msg.priority), *rule));
+        }
+        if let Some(rule) = config.recipients.get(&channel) {
+            for recipient in msg.addresses() {
+                rules.push((format!("recipient:{}:{}", channel, recipient), *rule));
+            }
+        }
+        rules
+    }
+
+    /// put back the tokens taken for a message which is not sent
+    async fn refund(&self, taken: &[(String, RateLimitRule)]) {
+        for (key, rule) in taken {
+            if let Err(e) = self.limiter.refund(key, rule).await {
+                warn!("failed to refund a token of {}: {}", key, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Throttle for TokenBucketThrottle {
+    async fn admit(&self, msg: &Message) -> Result<Admission, ServiceError> {
+        let config = self.config.borrow().clone();
+        let max_delay = Duration::from_millis(config.max_delay_ms);
+        // a message rejected by one bucket does not use up the tokens of the others
+        let mut taken = Vec::new();
+        for (key, rule) in Self::rules(&config, msg) {
+            let mut waited = Duration::ZERO;
+            loop {
+                let wait = match self.limiter.try_acquire(&key, &rule).await {
+                    Ok(None) => break,
+                    Ok(Some(wait)) => wait,
+                    Err(e) => {
+                        self.refund(&taken).await;
+                        return Err(e);
+                    }
+                };
+                waited += wait;
+                if config.mode == ThrottleMode::Reject || waited > max_delay {
+                    info!("message {:?} throttled by {}", msg.id, key);
+                    self.refund(&taken).await;
+                    return Ok(Admission::Throttled(key));
+                }
+                tokio::time::sleep(wait).await;
+            }
+            taken.push((key, rule));
+        }
+        Ok(Admission::Admitted)
+    }
+}
+
+pub fn token_bucket_throttle(
+    config: watch::Receiver<RateLimitConfig>,
+    pool: PgPool,
+) -> Box<dyn Throttle> {
+    let backend = config.borrow().backend;
+    let limiter: Box<dyn RateLimiter> = match backend {
+        RateLimitBackend::Memory => Box::<InMemoryLimiter>::default(),
+        RateLimitBackend::Postgres => Box::new(PgLimiter { pool }),
+    };
+    Box::new(TokenBucketThrottle { limiter, config })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::message::{EmailMessage, Priority};
+    use std::time::Instant;
+
+    fn email(recipient: &str) -> Message {
+        EmailMessage {
+            id: "id".to_string(),
+            subject: "subject".to_string(),
+            sender: "sender".to_string(),
+            recipients: vec![recipient.to_string()],
+            body: "body".to_string(),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// the throttle and the sender of its reloaded config
+    fn throttle(
+        mode: ThrottleMode,
+        channel: RateLimitRule,
+        recipient: RateLimitRule,
+    ) -> (TokenBucketThrottle, watch::Sender<RateLimitConfig>) {
+        let (tx, config) = watch::channel(RateLimitConfig {
+            backend: RateLimitBackend::Memory,
+            mode,
+            max_delay_ms: 1000,
+            channels: [("email".to_string(), channel)].into(),
+            providers: HashMap::new(),
+            recipients: [("email".to_string(), recipient)].into(),
+            lanes: HashMap::new(),
+        });
+        let throttle = TokenBucketThrottle {
+            limiter: Box::<InMemoryLimiter>::default(),
+            config,
+        };
+        (throttle, tx)
+    }
+
+    #[tokio::test]
+    async fn test_reject_per_recipient() {
+        let (throttle, _config) = throttle(
+            ThrottleMode::Reject,
+            RateLimitRule {
+                capacity: 100,
+                per_secs: 1,
+            },
+            RateLimitRule {
+                capacity: 1,
+                per_secs: 3600,
+            },
+        );
+        let admission = throttle.admit(&email("a@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+        let admission = throttle.admit(&email("a@test.com")).await.unwrap();
+        assert_eq!(
+            admission,
+            Admission::Throttled("recipient:email:a@test.com".to_string())
+        );
+        let admission = throttle.admit(&email("b@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+    }
+
+    #[tokio::test]
+    async fn test_reject_refunds_tokens_taken() {
+        let (throttle, _config) = throttle(
+            ThrottleMode::Reject,
+            RateLimitRule {
+                capacity: 2,
+                per_secs: 3600,
+            },
+            RateLimitRule {
+                capacity: 1,
+                per_secs: 3600,
+            },
+        );
+        let admission = throttle.admit(&email("a@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+        let admission = throttle.admit(&email("a@test.com")).await.unwrap();
+        assert_eq!(
+            admission,
+            Admission::Throttled("recipient:email:a@test.com".to_string())
+        );
+        // the channel token taken for the rejected message was put back
+        let admission = throttle.admit(&email("b@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+        let admission = throttle.admit(&email("c@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Throttled("channel:email".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_delay_per_channel() {
+        let (throttle, _config) = throttle(
+            ThrottleMode::Delay,
+            RateLimitRule {
+                capacity: 10,
+                per_secs: 1,
+            },
+            RateLimitRule {
+                capacity: 100,
+                per_secs: 1,
+            },
+        );
+        let start = Instant::now();
+        for i in 0..11 {
+            let admission = throttle
+                .admit(&email(&format!("{}@test.com", i)))
+                .await
+                .unwrap();
+            assert_eq!(admission, Admission::Admitted);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn test_reject_per_lane() {
+        let rule = RateLimitRule {
+            capacity: 100,
+            per_secs: 1,
+        };
+        let (throttle, config) = throttle(ThrottleMode::Reject, rule, rule);
+        // reloaded after the throttle was built
+        config.send_modify(|config| {
+            config.lanes = [(
+                "low".to_string(),
+                RateLimitRule {
+                    capacity: 1,
+                    per_secs: 3600,
+                },
+            )]
+            .into()
+        });
+        let low = |recipient: &str| Message {
+            priority: Priority::Low,
+            ..email(recipient)
+        };
+        let admission = throttle.admit(&low("a@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+        let admission = throttle.admit(&low("b@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Throttled("lane:low".to_string()));
+        // other lanes are not held up by the low lane
+        let admission = throttle.admit(&email("c@test.com")).await.unwrap();
+        assert_eq!(admission, Admission::Admitted);
+    }
+}
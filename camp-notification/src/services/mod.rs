@@ -1,9 +1,23 @@
-use chrono::{DateTime, Utc};
+use crate::config::AppConfig;
+use camp_core::config::section;
+use chrono::{DateTime, Duration, Utc};
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::watch;
+pub mod dead_letter;
 pub mod email;
+pub mod feedback;
 pub mod inapp;
+pub mod inbox;
+pub mod lifecycle;
+pub mod limiter;
+pub mod preferences;
+pub mod push;
+pub mod recipients;
+pub mod scheduler;
 pub mod sms;
+pub mod template;
+pub mod webhook;
 
 #[derive(Debug, Clone)]
 pub struct SendResponse {
@@ -15,6 +29,17 @@ pub trait ServicesFactory {
     fn email(&self) -> Arc<Box<dyn email::Email>>;
     fn inapp(&self) -> Arc<Box<dyn inapp::InApp>>;
     fn sms(&self) -> Arc<Box<dyn sms::Sms>>;
+    fn lifecycle(&self) -> Arc<Box<dyn lifecycle::Lifecycle>>;
+    fn throttle(&self) -> Arc<Box<dyn limiter::Throttle>>;
+    fn scheduler(&self) -> Arc<Box<dyn scheduler::Scheduler>>;
+    fn preferences(&self) -> Arc<Box<dyn preferences::Preferences>>;
+    fn inbox(&self) -> Arc<Box<dyn inbox::Inbox>>;
+    fn push(&self) -> Arc<Box<dyn push::Push>>;
+    fn recipients(&self) -> Arc<Box<dyn recipients::Recipients>>;
+    fn feedback(&self) -> Arc<Box<dyn feedback::Feedback>>;
+    fn templates(&self) -> Arc<Box<dyn template::Templates>>;
+    fn dead_letters(&self) -> Arc<Box<dyn dead_letter::DeadLetters>>;
+    fn webhook(&self) -> Arc<Box<dyn webhook::Webhook>>;
 }
 
 #[derive(Debug, Error)]
@@ -25,6 +50,28 @@ pub enum ServiceError {
     InApp(#[from] inapp::InAppError),
     #[error("Sms error: {0}")]
     Sms(#[from] sms::SmsError),
+    #[error("Lifecycle error: {0}")]
+    Lifecycle(#[from] lifecycle::LifecycleError),
+    #[error("Limiter error: {0}")]
+    Limiter(#[from] limiter::LimiterError),
+    #[error("Scheduler error: {0}")]
+    Scheduler(#[from] scheduler::SchedulerError),
+    #[error("Preferences error: {0}")]
+    Preferences(#[from] preferences::PreferencesError),
+    #[error("Inbox error: {0}")]
+    Inbox(#[from] inbox::InboxError),
+    #[error("Push error: {0}")]
+    Push(#[from] push::PushError),
+    #[error("Recipients error: {0}")]
+    Recipients(#[from] recipients::RecipientsError),
+    #[error("Feedback error: {0}")]
+    Feedback(#[from] feedback::FeedbackError),
+    #[error("Template error: {0}")]
+    Template(#[from] template::TemplateError),
+    #[error("Dead letter error: {0}")]
+    DeadLetter(#[from] dead_letter::DeadLetterError),
+    #[error("Webhook error: {0}")]
+    Webhook(#[from] webhook::WebhookError),
 }
 
 pub enum ServicesTypes {
@@ -35,6 +82,17 @@ pub struct ServicesFactoryImpl {
     pub email: Arc<Box<dyn email::Email>>,
     pub inapp: Arc<Box<dyn inapp::InApp>>,
     pub sms: Arc<Box<dyn sms::Sms>>,
+    pub lifecycle: Arc<Box<dyn lifecycle::Lifecycle>>,
+    pub throttle: Arc<Box<dyn limiter::Throttle>>,
+    pub scheduler: Arc<Box<dyn scheduler::Scheduler>>,
+    pub preferences: Arc<Box<dyn preferences::Preferences>>,
+    pub inbox: Arc<Box<dyn inbox::Inbox>>,
+    pub push: Arc<Box<dyn push::Push>>,
+    pub recipients: Arc<Box<dyn recipients::Recipients>>,
+    pub feedback: Arc<Box<dyn feedback::Feedback>>,
+    pub templates: Arc<Box<dyn template::Templates>>,
+    pub dead_letters: Arc<Box<dyn dead_letter::DeadLetters>>,
+    pub webhook: Arc<Box<dyn webhook::Webhook>>,
 }
 
 impl ServicesFactory for ServicesFactoryImpl {
@@ -47,19 +105,85 @@ impl ServicesFactory for ServicesFactoryImpl {
     fn sms(&self) -> Arc<Box<dyn sms::Sms>> {
         self.sms.clone()
     }
+    fn lifecycle(&self) -> Arc<Box<dyn lifecycle::Lifecycle>> {
+        self.lifecycle.clone()
+    }
+    fn throttle(&self) -> Arc<Box<dyn limiter::Throttle>> {
+        self.throttle.clone()
+    }
+    fn scheduler(&self) -> Arc<Box<dyn scheduler::Scheduler>> {
+        self.scheduler.clone()
+    }
+    fn preferences(&self) -> Arc<Box<dyn preferences::Preferences>> {
+        self.preferences.clone()
+    }
+    fn inbox(&self) -> Arc<Box<dyn inbox::Inbox>> {
+        self.inbox.clone()
+    }
+    fn push(&self) -> Arc<Box<dyn push::Push>> {
+        self.push.clone()
+    }
+    fn recipients(&self) -> Arc<Box<dyn recipients::Recipients>> {
+        self.recipients.clone()
+    }
+    fn feedback(&self) -> Arc<Box<dyn feedback::Feedback>> {
+        self.feedback.clone()
+    }
+    fn templates(&self) -> Arc<Box<dyn template::Templates>> {
+        self.templates.clone()
+    }
+    fn dead_letters(&self) -> Arc<Box<dyn dead_letter::DeadLetters>> {
+        self.dead_letters.clone()
+    }
+    fn webhook(&self) -> Arc<Box<dyn webhook::Webhook>> {
+        self.webhook.clone()
+    }
 }
 
 impl ServicesFactoryImpl {
-    pub fn new(r#type: ServicesTypes, pool: sqlx::PgPool) -> Self {
+    /// rate limits and webhooks follow the reloaded `config`, the rest keeps the one at start
+    pub fn new(
+        r#type: ServicesTypes,
+        pool: sqlx::PgPool,
+        live: &watch::Receiver<AppConfig>,
+    ) -> Self {
+        let config = live.borrow().clone();
         match r#type {
             ServicesTypes::AllUnimock => {
-                let email = email::random_return_email(pool.clone());
-                let inapp = inapp::random_return_inapp();
+                let email = email::random_return_email(pool.clone(), config.email.clone());
+                let push = Arc::new(push::push(&config.push, pool.clone()));
+                let inapp = inapp::inbox_inapp(pool.clone(), push.clone());
+                let inbox = inbox::inbox_pg(pool.clone());
                 let sms = sms::return_sms_mock();
+                let window = Duration::seconds(config.idempotency.window_secs as i64);
+                let lifecycle = lifecycle::lifecycle_pg(pool.clone(), window);
+                let scheduler = scheduler::scheduler_pg(pool.clone(), window);
+                let preferences =
+                    preferences::preferences_pg(pool.clone(), config.preferences.clone());
+                let recipients = recipients::recipients_pg(pool.clone(), config.recipients.clone());
+                let feedback = feedback::feedback_pg(pool.clone(), config.recipients.clone());
+                let templates = template::templates_pg(pool.clone());
+                let dead_letters = dead_letter::dead_letters_pg(pool.clone());
+                let webhook = webhook::webhook_http(section(live, |config| config.webhook.clone()));
+                let throttle = limiter::token_bucket_throttle(
+                    section(live, |config| config.rate_limit.clone()),
+                    pool,
+                );
                 Self {
                     email: Arc::new(email),
                     inapp: Arc::new(inapp),
                     sms: Arc::new(sms),
+                    lifecycle: Arc::new(lifecycle),
+                    throttle: Arc::new(throttle),
+                    scheduler: Arc::new(scheduler),
+                    preferences: Arc::new(preferences),
+                    inbox: Arc::new(inbox),
+                    push,
+                    recipients: Arc::new(recipients),
+                    feedback: Arc::new(feedback),
+                    templates: Arc::new(templates),
+                    dead_letters: Arc::new(dead_letters),
+                    webhook: Arc::new(webhook),
                 }
             }
         }
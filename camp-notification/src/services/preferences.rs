@@ -0,0 +1,196 @@
+use super::ServiceError;
+use crate::config::PreferencesConfig;
+pub use crate::model::{
+    message::{MessageError, MessageType},
+    preference::{ChannelPreference, UserPreferences},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum PreferencesError {
+    #[error("Preferences of user {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid unsubscribe token")]
+    InvalidToken,
+
+    #[error("Addresses {0:?} belong to another user")]
+    AddressTaken(Vec<String>),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+fn mac(secret: &str, address: &str, expires: i64) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(address.as_bytes());
+    mac.update(b".");
+    mac.update(expires.to_string().as_bytes());
+    mac
+}
+
+/// token naming the address to unsubscribe until `expires_at`, signed with `secret`
+pub fn sign_unsubscribe(secret: &str, address: &str, expires_at: DateTime<Utc>) -> String {
+    let expires = expires_at.timestamp();
+    let signature = mac(secret, address, expires).finalize().into_bytes();
+    format!(
+        "{}.{}.{}",
+        URL_SAFE_NO_PAD.encode(address),
+        expires,
+        URL_SAFE_NO_PAD.encode(signature)
+    )
+}
+
+/// the address of a token signed with `secret` which has not expired at `now`
+pub fn verify_unsubscribe(secret: &str, token: &str, now: DateTime<Utc>) -> Option<String> {
+    let mut parts = token.splitn(3, '.');
+    let (address, expires, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    let address = String::from_utf8(URL_SAFE_NO_PAD.decode(address).ok()?).ok()?;
+    let expires: i64 = expires.parse().ok()?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    mac(secret, &address, expires)
+        .verify_slice(&signature)
+        .ok()?;
+    (now.timestamp() < expires).then_some(address)
+}
+
+/// the recipients of a message who must not receive it right now
+#[derive(Debug, Default, PartialEq)]
+pub struct Suppressed {
+    /// unsubscribed or opted out of the channel, the message is not sent to them
+    pub addresses: Vec<String>,
+    /// the latest end of the quiet hours of the other recipients, the message waits until then
+    pub quiet_until: Option<DateTime<Utc>>,
+}
+
+/// Notification preferences of users, enforced before a message is dispatched.
+#[async_trait]
+pub trait Preferences: Send + Sync + 'static {
+    async fn get(&self, user_id: &str) -> Result<UserPreferences, ServiceError>;
+
+    async fn update(&self, prefs: UserPreferences) -> Result<UserPreferences, ServiceError>;
+
+    /// unsubscribe the address of a signed token, returns the address
+    async fn unsubscribe(&self, token: &str) -> Result<String, ServiceError>;
+
+    /// the addresses which must not receive a message of `channel` right now
+    async fn suppressed(
+        &self,
+        channel: MessageType,
+        addresses: &[&str],
+    ) -> Result<Suppressed, ServiceError>;
+
+    /// link for the owner of `address` to unsubscribe with
+    fn unsubscribe_link(&self, address: &str) -> String;
+}
+
+pub struct PreferencesPg {
+    pub pool: PgPool,
+    pub config: PreferencesConfig,
+}
+
+#[async_trait]
+impl Preferences for PreferencesPg {
+    async fn get(&self, user_id: &str) -> Result<UserPreferences, ServiceError> {
+        let prefs = UserPreferences::find(user_id, &self.pool)
+            .await
+            .map_err(PreferencesError::from)?
+            .ok_or_else(|| PreferencesError::NotFound(user_id.to_string()))?;
+        Ok(prefs)
+    }
+
+    async fn update(&self, prefs: UserPreferences) -> Result<UserPreferences, ServiceError> {
+        let taken = prefs
+            .save(&self.pool)
+            .await
+            .map_err(PreferencesError::from)?;
+        if !taken.is_empty() {
+            return Err(PreferencesError::AddressTaken(taken).into());
+        }
+        self.get(&prefs.user_id).await
+    }
+
+    async fn unsubscribe(&self, token: &str) -> Result<String, ServiceError> {
+        let address = verify_unsubscribe(&self.config.unsubscribe_secret, token, Utc::now())
+            .ok_or(PreferencesError::InvalidToken)?;
+        UserPreferences::unsubscribe(&address, &self.pool)
+            .await
+            .map_err(PreferencesError::from)?;
+        Ok(address)
+    }
+
+    async fn suppressed(
+        &self,
+        channel: MessageType,
+        addresses: &[&str],
+    ) -> Result<Suppressed, ServiceError> {
+        let now = Utc::now();
+        let found = UserPreferences::find_by_addresses(addresses, &self.pool)
+            .await
+            .map_err(PreferencesError::from)?;
+        let mut suppressed = Suppressed::default();
+        for (address, prefs) in found {
+            if !prefs.allows(channel) {
+                suppressed.addresses.push(address);
+            } else if let Some(until) = prefs.quiet_until(now) {
+                suppressed.quiet_until = suppressed.quiet_until.max(Some(until));
+            }
+        }
+        Ok(suppressed)
+    }
+
+    fn unsubscribe_link(&self, address: &str) -> String {
+        let expires_at = Utc::now() + Duration::seconds(self.config.unsubscribe_ttl_secs as i64);
+        format!(
+            "{}?token={}",
+            self.config.unsubscribe_url,
+            sign_unsubscribe(&self.config.unsubscribe_secret, address, expires_at)
+        )
+    }
+}
+
+pub fn preferences_pg(pool: PgPool, config: PreferencesConfig) -> Box<dyn Preferences> {
+    Box::new(PreferencesPg { pool, config })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unsubscribe_token() {
+        let now = Utc::now();
+        let expires_at = now + Duration::days(1);
+        let token = sign_unsubscribe("secret", "a@test.com", expires_at);
+        assert_eq!(
+            verify_unsubscribe("secret", &token, now).as_deref(),
+            Some("a@test.com")
+        );
+        assert_eq!(verify_unsubscribe("other", &token, now), None);
+        let (_, rest) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", URL_SAFE_NO_PAD.encode("b@test.com"), rest);
+        assert_eq!(verify_unsubscribe("secret", &forged, now), None);
+        assert_eq!(verify_unsubscribe("secret", "garbage", now), None);
+
+        // expired, or with the expiry pushed back
+        assert_eq!(verify_unsubscribe("secret", &token, expires_at), None);
+        let (address, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let extended = format!(
+            "{}.{}.{}",
+            address,
+            (expires_at + Duration::days(30)).timestamp(),
+            signature
+        );
+        assert_eq!(verify_unsubscribe("secret", &extended, expires_at), None);
+    }
+}
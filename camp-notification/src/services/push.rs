@@ -0,0 +1,196 @@
+use super::ServiceError;
+use crate::config::{PushBackend, PushConfig};
+pub use crate::model::{inbox::InboxMessage, message::InAppMessage};
+use sqlx::{postgres::PgListener, PgPool};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use thiserror::Error;
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+use tonic::async_trait;
+use tracing::{info, warn};
+
+#[derive(Error, Debug)]
+pub enum PushError {
+    #[error("sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Devices connected to this instance.
+#[derive(Default)]
+pub struct Subscribers {
+    buffer: usize,
+    devices: Mutex<HashMap<String, Vec<Sender<InAppMessage>>>>,
+}
+
+impl Subscribers {
+    pub fn new(buffer: usize) -> Self {
+        Self {
+            buffer: buffer.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn subscribe(&self, device_id: &str) -> Receiver<InAppMessage> {
+        let (tx, rx) = mpsc::channel(self.buffer);
+        let mut devices = self.devices.lock().expect("subscribers poisoned");
+        let senders = devices.entry(device_id.to_string()).or_default();
+        senders.retain(|s| !s.is_closed());
+        senders.push(tx);
+        rx
+    }
+
+    /// hand the message to the subscribers of its device, returns how many received it
+    pub fn deliver(&self, msg: &InAppMessage) -> usize {
+        let mut devices = self.devices.lock().expect("subscribers poisoned");
+        let Some(senders) = devices.get_mut(&msg.device_id) else {
+            return 0;
+        };
+        let mut delivered = 0;
+        senders.retain(|s| match s.try_send(msg.clone()) {
+            Ok(()) => {
+                delivered += 1;
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "subscriber of {} is behind, message {} is only in the inbox",
+                    msg.device_id, msg.id
+                );
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+        if senders.is_empty() {
+            devices.remove(&msg.device_id);
+        }
+        delivered
+    }
+}
+
+/// Pushes in-app messages to the devices subscribed to them, offline devices find them in the inbox.
+#[async_trait]
+pub trait Push: Send + Sync + 'static {
+    fn subscribe(&self, device_id: &str) -> Receiver<InAppMessage>;
+
+    /// the message must be in the inbox already
+    async fn publish(&self, msg: &InAppMessage) -> Result<(), ServiceError>;
+
+    /// receive the messages published by the other instances, runs until the task is aborted
+    async fn listen(&self) {}
+}
+
+pub struct InMemoryPush {
+    pub subscribers: Subscribers,
+}
+
+#[async_trait]
+impl Push for InMemoryPush {
+    fn subscribe(&self, device_id: &str) -> Receiver<InAppMessage> {
+        self.subscribers.subscribe(device_id)
+    }
+
+    async fn publish(&self, msg: &InAppMessage) -> Result<(), ServiceError> {
+        self.subscribers.deliver(msg);
+        Ok(())
+    }
+}
+
+/// publishes through postgres so that every instance delivers to its own subscribers
+pub struct PgPush {
+    pub pool: PgPool,
+    pub channel: String,
+    pub subscribers: Subscribers,
+}
+
+impl PgPush {
+    async fn listen_once(&self) -> Result<(), PushError> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(&self.channel).await?;
+        info!("push listener is listening on {}", self.channel);
+        loop {
+            // notifications are limited to 8000 bytes, only the id is sent
+            let notification = listener.recv().await?;
+            let id = notification.payload();
+            match InboxMessage::get(id, &self.pool).await {
+                Ok(Some(msg)) => {
+                    self.subscribers.deliver(&msg.into());
+                }
+                Ok(None) => info!("pushed message {} is not in the inbox anymore", id),
+                Err(e) => warn!("failed to load pushed message {}: {}", id, e),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Push for PgPush {
+    fn subscribe(&self, device_id: &str) -> Receiver<InAppMessage> {
+        self.subscribers.subscribe(device_id)
+    }
+
+    async fn publish(&self, msg: &InAppMessage) -> Result<(), ServiceError> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(&self.channel)
+            .bind(&msg.id)
+            .execute(&self.pool)
+            .await
+            .map_err(PushError::from)?;
+        Ok(())
+    }
+
+    async fn listen(&self) {
+        loop {
+            if let Err(e) = self.listen_once().await {
+                warn!("push listener on {} failed: {}", self.channel, e);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// the postgres backend only receives once its `listen` is running
+pub fn push(config: &PushConfig, pool: PgPool) -> Box<dyn Push> {
+    let subscribers = Subscribers::new(config.buffer);
+    match config.backend {
+        PushBackend::Memory => Box::new(InMemoryPush { subscribers }),
+        PushBackend::Postgres => Box::new(PgPush {
+            pool,
+            channel: config.channel.clone(),
+            subscribers,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn inapp(id: &str, device_id: &str) -> InAppMessage {
+        InAppMessage {
+            id: id.to_string(),
+            sender: "sender".to_string(),
+            body: "body".to_string(),
+            device_id: device_id.to_string(),
+            title: "title".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_deliver() {
+        let subscribers = Subscribers::new(1);
+        let mut first = subscribers.subscribe("device-1");
+        let mut second = subscribers.subscribe("device-1");
+        let mut other = subscribers.subscribe("device-2");
+
+        assert_eq!(subscribers.deliver(&inapp("m1", "device-1")), 2);
+        assert_eq!(first.recv().await.unwrap().id, "m1");
+        assert_eq!(second.recv().await.unwrap().id, "m1");
+        assert!(other.try_recv().is_err());
+
+        // a full subscriber misses the message, a closed one is dropped
+        assert_eq!(subscribers.deliver(&inapp("m2", "device-1")), 2);
+        drop(second);
+        assert_eq!(subscribers.deliver(&inapp("m3", "device-1")), 0);
+        assert_eq!(first.recv().await.unwrap().id, "m2");
+        assert_eq!(subscribers.deliver(&inapp("m4", "offline")), 0);
+    }
+}
@@ -0,0 +1,222 @@
+use super::ServiceError;
+use crate::config::RecipientsConfig;
+pub use crate::model::{
+    message::{MessageError, MessageType},
+    suppression::{Suppression, SuppressionReason},
+};
+use email_address::{EmailAddress, Options};
+use phonenumber::{country, Mode};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRecipient {
+    pub address: String,
+    pub reason: String,
+}
+
+#[derive(Error, Debug)]
+pub enum RecipientsError {
+    #[error("At least one recipient is required")]
+    Empty,
+
+    #[error("Invalid recipients: {}", .0.iter().map(|r| format!("{} ({})", r.address, r.reason)).collect::<Vec<_>>().join(", "))]
+    Invalid(Vec<InvalidRecipient>),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// lowercase the domain of an email address, the local part is case sensitive
+pub fn normalize_email(address: &str) -> Result<String, String> {
+    let options = Options::default()
+        .with_required_tld()
+        .without_display_text()
+        .without_domain_literal();
+    let email =
+        EmailAddress::parse_with_options(address.trim(), options).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "{}@{}",
+        email.local_part(),
+        email.domain().to_lowercase()
+    ))
+}
+
+/// format a phone number as E.164, numbers without a country code are read as numbers of `region`
+pub fn normalize_phone(number: &str, region: Option<country::Id>) -> Result<String, String> {
+    let number = phonenumber::parse(region, number.trim()).map_err(|e| e.to_string())?;
+    if !phonenumber::is_valid(&number) {
+        return Err("not a valid phone number".to_string());
+    }
+    Ok(number.format().mode(Mode::E164).to_string())
+}
+
+/// normalize an address the way the recipients of a `channel` message are, see [`Recipients::normalize`]
+pub fn normalize_address(
+    channel: MessageType,
+    region: Option<country::Id>,
+    address: &str,
+) -> Result<String, String> {
+    match channel {
+        MessageType::Email => normalize_email(address),
+        MessageType::Sms => normalize_phone(address, region),
+        MessageType::Inapp | MessageType::Webhook | MessageType::Unknown => Ok(address.to_string()),
+    }
+}
+
+/// normalize address groups in place, dropping duplicates across the groups
+pub fn normalize_groups(
+    channel: MessageType,
+    region: Option<country::Id>,
+    groups: &mut [&mut Vec<String>],
+) -> Result<(), RecipientsError> {
+    let mut seen = HashSet::new();
+    let mut invalid = Vec::new();
+    for group in groups.iter_mut() {
+        let mut normalized = Vec::with_capacity(group.len());
+        for address in group.drain(..) {
+            let result = match channel {
+                MessageType::Sms => normalize_phone(&address, region),
+                _ => normalize_email(&address),
+            };
+            match result {
+                Ok(a) if seen.insert(a.clone()) => normalized.push(a),
+                Ok(_) => {}
+                Err(reason) => invalid.push(InvalidRecipient { address, reason }),
+            }
+        }
+        **group = normalized;
+    }
+    if !invalid.is_empty() {
+        return Err(RecipientsError::Invalid(invalid));
+    }
+    if groups.first().is_some_and(|g| g.is_empty()) {
+        return Err(RecipientsError::Empty);
+    }
+    Ok(())
+}
+
+/// Validation of recipients before a message is accepted and the suppression list checked
+/// before it is dispatched.
+#[async_trait]
+pub trait Recipients: Send + Sync + 'static {
+    /// normalize the address groups of a message in place, e.g. to, cc and bcc of an email.
+    ///
+    /// Duplicates are dropped, an address is kept in the first group it appears in.
+    /// The first group must not end up empty.
+    fn normalize(
+        &self,
+        channel: MessageType,
+        groups: &mut [&mut Vec<String>],
+    ) -> Result<(), RecipientsError>;
+
+    /// the addresses which are on the suppression list
+    async fn suppressed(&self, addresses: &[&str]) -> Result<Vec<String>, ServiceError>;
+
+    async fn suppress(&self, address: &str, reason: SuppressionReason) -> Result<(), ServiceError>;
+
+    /// returns whether the address was suppressed
+    async fn unsuppress(&self, address: &str) -> Result<bool, ServiceError>;
+}
+
+pub struct RecipientsPg {
+    pub pool: PgPool,
+    pub config: RecipientsConfig,
+}
+
+#[async_trait]
+impl Recipients for RecipientsPg {
+    fn normalize(
+        &self,
+        channel: MessageType,
+        groups: &mut [&mut Vec<String>],
+    ) -> Result<(), RecipientsError> {
+        normalize_groups(channel, self.config.default_region, groups)
+    }
+
+    async fn suppressed(&self, addresses: &[&str]) -> Result<Vec<String>, ServiceError> {
+        if !self.config.check_suppressions {
+            return Ok(vec![]);
+        }
+        let found = Suppression::find(addresses, &self.pool)
+            .await
+            .map_err(RecipientsError::from)?;
+        Ok(found.into_iter().map(|s| s.address).collect())
+    }
+
+    async fn suppress(&self, address: &str, reason: SuppressionReason) -> Result<(), ServiceError> {
+        Suppression::insert(address, reason, &self.pool)
+            .await
+            .map_err(RecipientsError::from)?;
+        Ok(())
+    }
+
+    async fn unsuppress(&self, address: &str) -> Result<bool, ServiceError> {
+        let removed = Suppression::remove(address, &self.pool)
+            .await
+            .map_err(RecipientsError::from)?;
+        Ok(removed)
+    }
+}
+
+pub fn recipients_pg(pool: PgPool, config: RecipientsConfig) -> Box<dyn Recipients> {
+    Box::new(RecipientsPg { pool, config })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email(" Alice.Smith@Example.COM ").unwrap(),
+            "Alice.Smith@example.com"
+        );
+        assert!(normalize_email("alice").is_err());
+        assert!(normalize_email("alice@localhost").is_err());
+        assert!(normalize_email("Alice <alice@example.com>").is_err());
+    }
+
+    #[test]
+    fn test_normalize_phone() {
+        let cn = Some(country::Id::CN);
+        assert_eq!(
+            normalize_phone("138 0013 8000", cn).unwrap(),
+            "+8613800138000"
+        );
+        assert_eq!(
+            normalize_phone("+1 (650) 253-0000", cn).unwrap(),
+            "+16502530000"
+        );
+        assert!(normalize_phone("138 0013 8000", None).is_err());
+        assert!(normalize_phone("12345", cn).is_err());
+        assert!(normalize_phone("a@test.com", cn).is_err());
+    }
+
+    #[test]
+    fn test_normalize_groups() {
+        let cn = Some(country::Id::CN);
+        let mut to = vec!["a@test.com".to_string(), "A@TEST.com".to_string()];
+        let mut cc = vec!["a@Test.com".to_string(), "b@test.com".to_string()];
+        normalize_groups(MessageType::Email, cn, &mut [&mut to, &mut cc]).unwrap();
+        assert_eq!(to, vec!["a@test.com", "A@test.com"]);
+        assert_eq!(cc, vec!["b@test.com"]);
+
+        let mut to = vec!["13800138000".to_string(), "not a number".to_string()];
+        match normalize_groups(MessageType::Sms, cn, &mut [&mut to]) {
+            Err(RecipientsError::Invalid(invalid)) => {
+                assert_eq!(invalid.len(), 1);
+                assert_eq!(invalid[0].address, "not a number");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let mut to = vec![];
+        assert!(matches!(
+            normalize_groups(MessageType::Email, cn, &mut [&mut to]),
+            Err(RecipientsError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use super::{lifecycle::Accepted, ServiceError};
+pub use crate::model::{
+    lifecycle::{MessageQuery, MessageStatus},
+    message::{Message, MessageError},
+    schedule::{due_at, SendWindow},
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+    #[error("Message not found: {0}")]
+    NotFound(String),
+
+    #[error("Message {0} is {1} and can not be cancelled")]
+    NotCancellable(String, MessageStatus),
+
+    #[error("Invalid send window: {0}")]
+    InvalidWindow(String),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// Stores messages to be sent later and hands them out once they are due.
+#[async_trait]
+pub trait Scheduler: Send + Sync + 'static {
+    async fn schedule(
+        &self,
+        msg: Message,
+        send_at: DateTime<Utc>,
+    ) -> Result<Accepted, ServiceError>;
+
+    /// claim at most `limit` due messages, they are marked as sending. Messages claimed longer
+    /// than `lease` ago and still sending are claimed again
+    async fn claim_due(&self, limit: i64, lease: Duration) -> Result<Vec<Message>, ServiceError>;
+
+    async fn reschedule(
+        &self,
+        id: &str,
+        send_at: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<(), ServiceError>;
+
+    async fn cancel(&self, id: &str) -> Result<Message, ServiceError>;
+
+    async fn list(&self, query: MessageQuery) -> Result<Vec<Message>, ServiceError>;
+}
+
+pub struct SchedulerPg {
+    pub pool: PgPool,
+    pub window: Duration,
+}
+
+#[async_trait]
+impl Scheduler for SchedulerPg {
+    async fn schedule(
+        &self,
+        mut msg: Message,
+        send_at: DateTime<Utc>,
+    ) -> Result<Accepted, ServiceError> {
+        msg.status = MessageStatus::Scheduled;
+        msg.send_at = Some(send_at);
+        let id = msg.id.clone();
+        let window_start = Utc::now() - self.window;
+        if let Some(msg) = msg
+            .accept(window_start, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?
+        {
+            return Ok(Accepted::New(msg));
+        }
+        let msg = Message::find(&id, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?
+            .ok_or(SchedulerError::NotFound(id))?;
+        Ok(Accepted::Duplicate(msg))
+    }
+
+    async fn claim_due(&self, limit: i64, lease: Duration) -> Result<Vec<Message>, ServiceError> {
+        let messages = Message::claim_due(limit, lease, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?;
+        Ok(messages)
+    }
+
+    async fn reschedule(
+        &self,
+        id: &str,
+        send_at: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<(), ServiceError> {
+        Message::reschedule(id, send_at, reason, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?;
+        Ok(())
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Message, ServiceError> {
+        if let Some(msg) = Message::cancel(id, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?
+        {
+            return Ok(msg);
+        }
+        let err = match Message::find(id, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?
+        {
+            Some(msg) => SchedulerError::NotCancellable(msg.id, msg.status),
+            None => SchedulerError::NotFound(id.to_string()),
+        };
+        Err(err.into())
+    }
+
+    async fn list(&self, query: MessageQuery) -> Result<Vec<Message>, ServiceError> {
+        let messages = Message::list_scheduled(&query, &self.pool)
+            .await
+            .map_err(SchedulerError::from)?;
+        Ok(messages)
+    }
+}
+
+pub fn scheduler_pg(pool: PgPool, window: Duration) -> Box<dyn Scheduler> {
+    Box::new(SchedulerPg { pool, window })
+}
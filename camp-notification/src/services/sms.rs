@@ -28,7 +28,7 @@ pub struct SmsFaker;
 impl Sms for SmsFaker {
     async fn send_sms(&self, msg: SmsMessage) -> Result<SendResponse, ServiceError> {
         let random = rand::random::<u8>();
-        if random % 9 == 0 {
+        if random.is_multiple_of(9) {
             Ok(SendResponse {
                 id: msg.id,
                 timestamp: Utc::now(),
@@ -0,0 +1,114 @@
+use super::ServiceError;
+pub use crate::model::{
+    message::{MessageError, MessageType},
+    template::Template,
+};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::async_trait;
+
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("Template {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid template: {0}")]
+    Invalid(String),
+
+    #[error("Model error: {0}")]
+    Model(#[from] MessageError),
+}
+
+/// a template must name its channel, sender and body, an email its subject as well
+pub fn check_template(template: &Template) -> Result<(), TemplateError> {
+    let missing = if template.name.is_empty() {
+        Some("name")
+    } else if template.channel == MessageType::Unknown {
+        Some("channel")
+    } else if template.sender.is_empty() {
+        Some("sender")
+    } else if template.body.is_empty() {
+        Some("body")
+    } else if template.channel == MessageType::Email && template.subject.is_empty() {
+        Some("subject")
+    } else if template.channel == MessageType::Webhook && template.subject.is_empty() {
+        // the event of the webhook
+        Some("subject")
+    } else {
+        None
+    };
+    match missing {
+        Some(field) => Err(TemplateError::Invalid(format!("{} is required", field))),
+        None => Ok(()),
+    }
+}
+
+/// Templates of the messages sent to many recipients at once.
+#[async_trait]
+pub trait Templates: Send + Sync + 'static {
+    async fn get(&self, name: &str) -> Result<Template, ServiceError>;
+
+    /// create or replace the template of the same name
+    async fn save(&self, template: Template) -> Result<Template, ServiceError>;
+}
+
+pub struct TemplatesPg {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl Templates for TemplatesPg {
+    async fn get(&self, name: &str) -> Result<Template, ServiceError> {
+        let template = Template::find(name, &self.pool)
+            .await
+            .map_err(TemplateError::from)?
+            .ok_or_else(|| TemplateError::NotFound(name.to_string()))?;
+        Ok(template)
+    }
+
+    async fn save(&self, template: Template) -> Result<Template, ServiceError> {
+        check_template(&template)?;
+        let template = template
+            .save(&self.pool)
+            .await
+            .map_err(TemplateError::from)?;
+        Ok(template)
+    }
+}
+
+pub fn templates_pg(pool: PgPool) -> Box<dyn Templates> {
+    Box::new(TemplatesPg { pool })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_check_template() {
+        let template = Template {
+            name: "reset".to_string(),
+            channel: MessageType::Sms,
+            sender: "camp".to_string(),
+            subject: String::new(),
+            body: "your code is {{code}}".to_string(),
+            html_body: None,
+            updated_at: Utc::now(),
+        };
+        assert!(check_template(&template).is_ok());
+        let email = Template {
+            channel: MessageType::Email,
+            ..template.clone()
+        };
+        assert!(matches!(
+            check_template(&email),
+            Err(TemplateError::Invalid(e)) if e == "subject is required"
+        ));
+        let unknown = Template {
+            channel: MessageType::Unknown,
+            ..template
+        };
+        assert!(check_template(&unknown).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use super::{feedback::sign_webhook, SendResponse, ServiceError};
+use crate::config::WebhookConfig;
+pub use crate::{config::WebhookEndpoint, model::message::WebhookMessage};
+use chrono::Utc;
+use reqwest::StatusCode;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::watch;
+use tonic::async_trait;
+use tracing::warn;
+
+pub use super::feedback::SIGNATURE_HEADER;
+/// header carrying the event of the payload
+pub const EVENT_HEADER: &str = "x-webhook-event";
+/// header carrying the message id, the same for every retry so that partners can drop duplicates
+pub const MESSAGE_ID_HEADER: &str = "x-message-id";
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Invalid webhook: {0}")]
+    Invalid(String),
+
+    #[error("Webhook sending failed: {0}")]
+    Send(String),
+}
+
+/// reject webhooks to unregistered endpoints and payloads which are not json
+pub fn check_webhook<'a>(
+    msg: &WebhookMessage,
+    config: &'a WebhookConfig,
+) -> Result<&'a WebhookEndpoint, WebhookError> {
+    let endpoint = config.endpoints.get(&msg.endpoint).ok_or_else(|| {
+        WebhookError::Invalid(format!("endpoint {:?} is not registered", msg.endpoint))
+    })?;
+    if msg.event.is_empty() {
+        return Err(WebhookError::Invalid("event is required".to_string()));
+    }
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(&msg.payload) {
+        return Err(WebhookError::Invalid(format!("payload is not json: {}", e)));
+    }
+    Ok(endpoint)
+}
+
+/// JSON payloads posted to the endpoints partners registered.
+#[async_trait]
+pub trait Webhook: Send + Sync + 'static {
+    /// whether the webhook can be sent, checked before it is accepted
+    async fn check(&self, msg: &WebhookMessage) -> Result<(), ServiceError>;
+
+    async fn send_webhook(&self, msg: WebhookMessage) -> Result<SendResponse, ServiceError>;
+}
+
+pub struct WebhookHttp {
+    pub client: reqwest::Client,
+    /// read for every webhook, endpoints and retries may be reloaded
+    pub config: watch::Receiver<WebhookConfig>,
+}
+
+/// whether a failed attempt may succeed when tried again
+enum Attempt {
+    Retry(String),
+    GiveUp(String),
+}
+
+impl WebhookHttp {
+    async fn post(
+        &self,
+        msg: &WebhookMessage,
+        endpoint: &WebhookEndpoint,
+        timeout: Duration,
+    ) -> Result<(), Attempt> {
+        let resp = self
+            .client
+            .post(&endpoint.url)
+            .timeout(timeout)
+            .header("content-type", "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                sign_webhook(&endpoint.secret, msg.payload.as_bytes()),
+            )
+            .header(EVENT_HEADER, &msg.event)
+            .header(MESSAGE_ID_HEADER, &msg.id)
+            .body(msg.payload.clone())
+            .send()
+            .await
+            .map_err(|e| Attempt::Retry(e.to_string()))?;
+        match resp.status() {
+            status if status.is_success() => Ok(()),
+            status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Err(Attempt::Retry(status.to_string()))
+            }
+            status => Err(Attempt::GiveUp(status.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Webhook for WebhookHttp {
+    async fn check(&self, msg: &WebhookMessage) -> Result<(), ServiceError> {
+        check_webhook(msg, &self.config.borrow())?;
+        Ok(())
+    }
+
+    async fn send_webhook(&self, msg: WebhookMessage) -> Result<SendResponse, ServiceError> {
+        let config = self.config.borrow().clone();
+        let endpoint = check_webhook(&msg, &config)?;
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let mut backoff = Duration::from_millis(config.backoff_ms);
+        let mut attempt = 0;
+        loop {
+            let error = match self.post(&msg, endpoint, timeout).await {
+                Ok(()) => {
+                    return Ok(SendResponse {
+                        id: msg.id,
+                        timestamp: Utc::now(),
+                    })
+                }
+                Err(Attempt::Retry(e)) if attempt < config.retries => e,
+                Err(Attempt::Retry(e) | Attempt::GiveUp(e)) => {
+                    return Err(WebhookError::Send(format!("{}: {}", msg.endpoint, e)).into())
+                }
+            };
+            attempt += 1;
+            warn!(
+                "webhook {} to {} failed, retry {} in {:?}: {}",
+                msg.id, msg.endpoint, attempt, backoff, error
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+pub fn webhook_http(config: watch::Receiver<WebhookConfig>) -> Box<dyn Webhook> {
+    Box::new(WebhookHttp {
+        client: reqwest::Client::new(),
+        config,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::services::feedback::verify_webhook;
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+        Router,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    /// answers 503 to the first `failures` requests, records the rest
+    #[derive(Clone, Default)]
+    struct Partner {
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+        received: Arc<std::sync::Mutex<Vec<(HeaderMap, Bytes)>>>,
+    }
+
+    async fn receive(
+        State(partner): State<Partner>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        if partner.calls.fetch_add(1, Ordering::SeqCst) < partner.failures {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        partner.received.lock().unwrap().push((headers, body));
+        StatusCode::NO_CONTENT
+    }
+
+    async fn serve(partner: Partner) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(partner);
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        format!("http://{}/hook", addr)
+    }
+
+    fn webhook(url: &str, retries: u32) -> WebhookHttp {
+        let endpoint = WebhookEndpoint {
+            url: url.to_string(),
+            secret: "secret".to_string(),
+        };
+        WebhookHttp {
+            client: reqwest::Client::new(),
+            config: watch::channel(WebhookConfig {
+                timeout_ms: 1000,
+                retries,
+                backoff_ms: 1,
+                endpoints: HashMap::from([("partner".to_string(), endpoint)]),
+            })
+            .1,
+        }
+    }
+
+    fn msg(payload: &str) -> WebhookMessage {
+        WebhookMessage {
+            id: "hook-1".to_string(),
+            sender: "crm".to_string(),
+            endpoint: "partner".to_string(),
+            event: "campaign.sent".to_string(),
+            payload: payload.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_webhook_signed_with_retries() {
+        let partner = Partner {
+            failures: 2,
+            ..Default::default()
+        };
+        let url = serve(partner.clone()).await;
+        let resp = webhook(&url, 2)
+            .send_webhook(msg(r#"{"id":1}"#))
+            .await
+            .unwrap();
+        assert_eq!(resp.id, "hook-1");
+        assert_eq!(partner.calls.load(Ordering::SeqCst), 3);
+        let received = partner.received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(body.as_ref(), br#"{"id":1}"#);
+        assert_eq!(headers[EVENT_HEADER], "campaign.sent");
+        assert_eq!(headers[MESSAGE_ID_HEADER], "hook-1");
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        assert!(verify_webhook("secret", body, signature));
+    }
+
+    #[tokio::test]
+    async fn test_send_webhook_gives_up() {
+        let partner = Partner {
+            failures: 5,
+            ..Default::default()
+        };
+        let url = serve(partner.clone()).await;
+        let err = webhook(&url, 1).send_webhook(msg("{}")).await.unwrap_err();
+        assert!(matches!(err, ServiceError::Webhook(WebhookError::Send(_))));
+        assert_eq!(partner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_check_webhook() {
+        let webhook = webhook("http://localhost/hook", 0);
+        assert!(check_webhook(&msg("{}"), &webhook.config.borrow()).is_ok());
+        assert!(check_webhook(&msg("not json"), &webhook.config.borrow()).is_err());
+        let unknown = WebhookMessage {
+            endpoint: "unknown".to_string(),
+            ..msg("{}")
+        };
+        assert!(check_webhook(&unknown, &webhook.config.borrow()).is_err());
+    }
+}
@@ -1,34 +1,132 @@
 use anyhow::Result;
-use camp_core::core_fake::{UniqueEmail, VecRanger};
+use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+use camp_core::auth::{ClientAuth, ClientAuthConfig, ADMIN_SCOPE};
+use camp_core::core_fake::{UniqueEmail, UniquePhone, VecRanger};
+use camp_core::proto::utc_to_ts;
+use camp_core::telemetry;
+use camp_notification::config::{AppConfig, WebhookEndpoint};
+use camp_notification::fake::provider::FakeProvider;
+use camp_notification::model::suppression::{Suppression, SuppressionReason};
+use camp_notification::model::{self, lifecycle, message::Message};
+use camp_notification::pb::notification::{
+    email_attachment, notification_admin_client::NotificationAdminClient, send_request,
+    BatchRecipient, BlobReference, CancelScheduledRequest, ChannelPreference, DeleteMessageRequest,
+    EmailAttachment, EmailMessage, GetDeadLetterRequest, GetLaneStatsRequest,
+    GetMessageStatusRequest, GetPreferencesRequest, GetTemplateRequest, InAppMessage,
+    ListDeadLettersRequest, ListInboxRequest, ListMessagesRequest, ListScheduledRequest,
+    LocalWindow, MarkAllReadRequest, MarkReadRequest, MessageStatus, MessageType, Preferences,
+    Priority, ProviderFeedback, ReplayRequest, SendBatchRequest, SendBatchResponse,
+    SendResponseType, SmsMessage, SubscribeRequest, Template, UnsubscribeRequest, WebhookMessage,
+};
 use camp_notification::pb::notification::{notification_client::NotificationClient, SendRequest};
-use camp_notification::pb::notification::{send_request, EmailMessage, InAppMessage, SmsMessage};
-use camp_notification::AppState;
+use camp_notification::services::feedback::{verify_webhook, FeedbackType};
+use camp_notification::services::{
+    inapp::{self, InApp},
+    SendResponse as ServiceResponse, ServiceError,
+};
+use camp_notification::{abi::inapp::InAppGrpc, AppState, FEEDBACK_SCOPE};
+use chrono::{Timelike as _, Utc};
 use fake::faker::name::en::Name;
 use fake::Fake as _;
 use futures::StreamExt;
-use tokio::time::{sleep, Duration};
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+use sqlx_db_tester::TestPg;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    net::TcpListener,
+    sync::{oneshot, watch},
+    time::{sleep, Duration},
+};
+use tonic::{codegen::InterceptedService, transport::Channel};
+use tracing::info;
+
+/// a channel whose requests carry a token with every scope of the server
+type AuthChannel = InterceptedService<Channel, ClientAuth>;
+
+/// start a server with the config of notification.yml changed by `configure`, on a free port
+async fn start_server(
+    configure: impl FnOnce(&mut AppConfig),
+) -> Result<(TestPg, AppState, AuthChannel)> {
+    let (tdb, app_state) = AppState::new_for_test_with(configure).await?;
+    let channel = serve(app_state.clone()).await?;
+    Ok((tdb, app_state, channel))
+}
+
+/// serve `app_state` on a free port
+async fn serve(app_state: AppState) -> Result<AuthChannel> {
+    let auth = client_auth(&app_state.app_config, &[ADMIN_SCOPE, FEEDBACK_SCOPE])?;
+    Ok(InterceptedService::new(listen(app_state).await?, auth))
+}
+
+/// serve `app_state` on a free port, the requests of the channel carry no token
+async fn listen(app_state: AppState) -> Result<Channel> {
+    let listener = TcpListener::bind("[::1]:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { app_state.grpc_run_on(listener).await });
+    Ok(Channel::from_shared(url)?.connect().await?)
+}
+
+/// tokens with `scopes`, signed with the key the server verifies them with
+fn client_auth(config: &AppConfig, scopes: &[&str]) -> Result<ClientAuth> {
+    let auth = config
+        .grpc
+        .server
+        .auth
+        .as_ref()
+        .expect("auth is enabled in notification.yml");
+    Ok(ClientAuth::try_new(&ClientAuthConfig {
+        algorithm: auth.algorithm,
+        sk: Some(auth.pk.clone()),
+        token: None,
+        subject: "crm".to_string(),
+        issuer: auth.issuer.clone(),
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+        ttl_secs: 60,
+    })?)
+}
+
+/// the most messages an in-app provider sends at the same time
+#[derive(Default)]
+struct Overlap {
+    sending: AtomicUsize,
+    most: AtomicUsize,
+}
+
+/// an in-app provider which takes a while to send
+struct SlowInApp(Arc<Overlap>);
+
+#[tonic::async_trait]
+impl InApp for SlowInApp {
+    async fn send_inapp(&self, msg: inapp::InAppMessage) -> Result<ServiceResponse, ServiceError> {
+        let sending = self.0.sending.fetch_add(1, Ordering::SeqCst) + 1;
+        self.0.most.fetch_max(sending, Ordering::SeqCst);
+        sleep(Duration::from_millis(200)).await;
+        self.0.sending.fetch_sub(1, Ordering::SeqCst);
+        Ok(ServiceResponse {
+            id: msg.id,
+            timestamp: Utc::now(),
+        })
+    }
+}
 
 #[tokio::test]
 async fn send_email_should_work() -> Result<()> {
-    let layer = Layer::new().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
-    let (_tbd, app_state) = AppState::new_for_test().await?;
-    let config = app_state.app_config.clone();
+    let _telemetry = telemetry::init("notification")?;
     info!("Starting notification service ...");
-
-    tokio::spawn(async move { app_state.grpc_run().await });
-    sleep(Duration::from_millis(10)).await;
-
-    let grpc_url = format!("http://[::1]:{}", config.grpc.port);
-    let mut client = NotificationClient::connect(grpc_url).await?;
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
     let email = Some(send_request::Msg::Email(EmailMessage {
         message_id: "1".to_string(),
         subject: "test".to_string(),
         sender: "test".to_string(),
         recipients: vec!["test@163.com".to_string()],
         body: "test body 1".to_string(),
+        ..Default::default()
     }));
 
     let sms = Some(send_request::Msg::Sms(SmsMessage {
@@ -37,7 +135,7 @@ async fn send_email_should_work() -> Result<()> {
         recipients: VecRanger {
             lower: 1,
             upper: 10,
-            item: UniqueEmail,
+            item: UniquePhone,
         }
         .fake(),
         body: "test body 2".to_string(),
@@ -51,9 +149,18 @@ async fn send_email_should_work() -> Result<()> {
     }));
 
     let send_requests = vec![
-        SendRequest { msg: email },
-        SendRequest { msg: sms },
-        SendRequest { msg: inapp },
+        SendRequest {
+            msg: email,
+            ..Default::default()
+        },
+        SendRequest {
+            msg: sms,
+            ..Default::default()
+        },
+        SendRequest {
+            msg: inapp,
+            ..Default::default()
+        },
     ];
     let p = tokio_stream::iter(send_requests);
     let stream_resp = client.send(p).await?.into_inner();
@@ -61,3 +168,1244 @@ async fn send_email_should_work() -> Result<()> {
     info!("send_requests received : {:?}", ret);
     Ok(())
 }
+
+#[tokio::test]
+async fn get_message_status_should_work() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let email = Some(send_request::Msg::Email(EmailMessage {
+        message_id: "status-1".to_string(),
+        subject: "welcome".to_string(),
+        sender: "welcome".to_string(),
+        recipients: vec!["status@163.com".to_string()],
+        body: "welcome body".to_string(),
+        ..Default::default()
+    }));
+    let stream = tokio_stream::iter(vec![SendRequest {
+        msg: email,
+        ..Default::default()
+    }]);
+    let ret: Vec<_> = client.send(stream).await?.into_inner().collect().await;
+    assert_eq!(ret.len(), 1);
+
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "status-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.recipients, vec!["status@163.com".to_string()]);
+    assert_eq!(status.events.len(), 3);
+    assert_eq!(status.events[0].status(), MessageStatus::Accepted);
+    assert_eq!(status.events[1].status(), MessageStatus::Sending);
+    assert_ne!(status.status(), MessageStatus::Sending);
+
+    let list = client
+        .list_messages(ListMessagesRequest {
+            recipient: "status@163.com".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(list.messages.len(), 1);
+
+    let not_found = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "not-exists".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(not_found.code(), tonic::Code::NotFound);
+    Ok(())
+}
+
+#[tokio::test]
+async fn duplicate_message_should_not_be_sent_twice() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let inapp = SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: "dup-1".to_string(),
+            device_id: "device-1".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        ..Default::default()
+    };
+    // the duplicate in the same stream waits for the outcome of the first one
+    let stream = tokio_stream::iter(vec![inapp.clone(), inapp.clone()]);
+    let ret: Vec<_> = client.send(stream).await?.into_inner().collect().await;
+    let first = ret[0].as_ref().unwrap();
+    let second = ret[1].as_ref().unwrap();
+    assert_eq!(first.message_id, second.message_id);
+    assert_eq!(first.status(), SendResponseType::Success);
+    assert_eq!(second.status(), SendResponseType::Success);
+
+    // and in a stream of its own
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![inapp]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(ret[0].as_ref().unwrap().status(), SendResponseType::Success);
+
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "dup-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.events.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn scheduled_message_should_be_sent_when_due() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|config| config.scheduler.poll_interval_ms = 100).await?;
+    let mut client = NotificationClient::new(channel);
+    let scheduled = |id: &str, secs: i64| SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: id.to_string(),
+            device_id: "scheduled-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        send_at: Some(utc_to_ts(Utc::now() + chrono::Duration::seconds(secs))),
+        ..Default::default()
+    };
+    let stream = tokio_stream::iter(vec![scheduled("soon", 1), scheduled("later", 3600)]);
+    let ret: Vec<_> = client.send(stream).await?.into_inner().collect().await;
+    for resp in ret {
+        assert_eq!(resp?.status(), SendResponseType::Scheduled);
+    }
+
+    let list = client
+        .list_scheduled(ListScheduledRequest {
+            recipient: "scheduled-device".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    let ids: Vec<_> = list
+        .messages
+        .iter()
+        .map(|m| m.message_id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["soon", "later"]);
+
+    let cancelled = client
+        .cancel_scheduled(CancelScheduledRequest {
+            message_id: "later".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(cancelled.status(), MessageStatus::Cancelled);
+    let err = client
+        .cancel_scheduled(CancelScheduledRequest {
+            message_id: "later".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+
+    sleep(Duration::from_millis(1500)).await;
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "soon".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.events[0].status(), MessageStatus::Scheduled);
+    assert_eq!(status.events[1].status(), MessageStatus::Sending);
+    assert_ne!(status.status(), MessageStatus::Scheduled);
+    Ok(())
+}
+
+#[tokio::test]
+async fn scheduler_should_stop_on_shutdown() -> Result<()> {
+    let (_tdb, app_state) =
+        AppState::new_for_test_with(|config| config.scheduler.poll_interval_ms = 50).await?;
+    let config = watch::channel(app_state.app_config.scheduler.clone()).1;
+    let (stop, stopped) = oneshot::channel::<()>();
+    let scheduler = tokio::spawn(app_state.notification_grpc.run_scheduler(config, async {
+        let _ = stopped.await;
+    }));
+    sleep(Duration::from_millis(120)).await;
+    assert!(!scheduler.is_finished());
+    stop.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(1), scheduler).await??;
+    Ok(())
+}
+
+#[tokio::test]
+async fn opted_out_message_should_be_suppressed() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let prefs = client
+        .update_preferences(Preferences {
+            user_id: "user-1".to_string(),
+            addresses: vec!["opted-out-device".to_string()],
+            channels: vec![ChannelPreference {
+                channel: MessageType::InApp as i32,
+                enabled: false,
+            }],
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(prefs.channels.len(), 1);
+    let fetched = client
+        .get_preferences(GetPreferencesRequest {
+            user_id: "user-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(fetched, prefs);
+
+    let inapp = SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: "suppressed-1".to_string(),
+            device_id: "opted-out-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![inapp]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(
+        ret[0].as_ref().unwrap().status(),
+        SendResponseType::Suppressed
+    );
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "suppressed-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.status(), MessageStatus::Suppressed);
+
+    // the address of user-1 can not be taken over
+    let err = client
+        .update_preferences(Preferences {
+            user_id: "user-2".to_string(),
+            addresses: vec!["opted-out-device".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::AlreadyExists);
+
+    // a message during the quiet hours waits until they are over
+    let hour = Utc::now().hour();
+    client
+        .update_preferences(Preferences {
+            user_id: "user-3".to_string(),
+            addresses: vec!["sleeping-device".to_string()],
+            quiet_hours: Some(LocalWindow {
+                timezone: "UTC".to_string(),
+                start_hour: hour,
+                end_hour: (hour + 2) % 24,
+            }),
+            ..Default::default()
+        })
+        .await?;
+    let inapp = SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: "quiet-1".to_string(),
+            device_id: "sleeping-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![inapp]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    let resp = ret[0].as_ref().unwrap();
+    assert_eq!(resp.status(), SendResponseType::Scheduled);
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "quiet-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.status(), MessageStatus::Scheduled);
+    assert_eq!(status.send_at, resp.timestamp);
+
+    let err = client
+        .unsubscribe(UnsubscribeRequest {
+            token: "forged.token".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    Ok(())
+}
+
+#[tokio::test]
+async fn inapp_message_should_be_stored_in_inbox() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let inapp = |id: &str| SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: id.to_string(),
+            device_id: "inbox-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        ..Default::default()
+    };
+    let stream = tokio_stream::iter(vec![inapp("inbox-1"), inapp("inbox-2")]);
+    let ret: Vec<_> = client.send(stream).await?.into_inner().collect().await;
+    for resp in ret {
+        assert_eq!(resp?.status(), SendResponseType::Success);
+    }
+
+    let inbox = client
+        .list_inbox(ListInboxRequest {
+            device_id: "inbox-device".to_string(),
+            unread_only: true,
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(inbox.messages.len(), 2);
+    assert_eq!(inbox.unread_count, 2);
+
+    let resp = client
+        .mark_read(MarkReadRequest {
+            device_id: "inbox-device".to_string(),
+            message_ids: vec!["inbox-1".to_string()],
+            opened: true,
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!((resp.updated, resp.unread_count), (1, 1));
+    let resp = client
+        .mark_all_read(MarkAllReadRequest {
+            device_id: "inbox-device".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!((resp.updated, resp.unread_count), (1, 0));
+
+    client
+        .delete_message(DeleteMessageRequest {
+            device_id: "inbox-device".to_string(),
+            message_id: "inbox-1".to_string(),
+            ..Default::default()
+        })
+        .await?;
+    let inbox = client
+        .list_inbox(ListInboxRequest {
+            device_id: "inbox-device".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(inbox.messages.len(), 1);
+    assert_eq!(inbox.messages[0].message_id, "inbox-2");
+    assert!(inbox.messages[0].read_at.is_some());
+
+    let err = client
+        .list_inbox(ListInboxRequest::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribers_should_receive_their_inapp_messages() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    // give the push listener time to start listening
+    sleep(Duration::from_millis(200)).await;
+
+    let mut first = NotificationClient::new(channel.clone());
+    let mut second = NotificationClient::new(channel.clone());
+    let mut sender = NotificationClient::new(channel);
+    let mut first_stream = first
+        .subscribe(SubscribeRequest {
+            device_id: "push-device-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    let mut second_stream = second
+        .subscribe(SubscribeRequest {
+            device_id: "push-device-2".to_string(),
+        })
+        .await?
+        .into_inner();
+
+    let inapp = |id: &str, device_id: &str| SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: id.to_string(),
+            device_id: device_id.to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "sender".to_string(),
+        })),
+        ..Default::default()
+    };
+    let stream = tokio_stream::iter(vec![
+        inapp("push-1", "push-device-1"),
+        inapp("push-2", "push-device-2"),
+        inapp("push-3", "offline-device"),
+    ]);
+    let ret: Vec<_> = sender.send(stream).await?.into_inner().collect().await;
+    assert_eq!(ret.len(), 3);
+
+    let timeout = Duration::from_secs(5);
+    let received = tokio::time::timeout(timeout, first_stream.next()).await?;
+    assert_eq!(received.unwrap()?.message_id, "push-1");
+    let received = tokio::time::timeout(timeout, second_stream.next()).await?;
+    assert_eq!(received.unwrap()?.message_id, "push-2");
+
+    // the offline device finds its message in the inbox
+    let inbox = sender
+        .list_inbox(ListInboxRequest {
+            device_id: "offline-device".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(inbox.messages[0].message_id, "push-3");
+    Ok(())
+}
+
+#[tokio::test]
+async fn email_attachments_should_be_checked() -> Result<()> {
+    let (_tdb, app_state, channel) = start_server(|_| {}).await?;
+    let config = app_state.app_config;
+    let mut client = NotificationClient::new(channel);
+    let attachment = |name: &str, source| EmailAttachment {
+        filename: name.to_string(),
+        content_type: "application/octet-stream".to_string(),
+        source: Some(source),
+        ..Default::default()
+    };
+    let email = |id: &str, attachments: Vec<EmailAttachment>| SendRequest {
+        msg: Some(send_request::Msg::Email(EmailMessage {
+            message_id: id.to_string(),
+            subject: "report".to_string(),
+            sender: "reports".to_string(),
+            recipients: vec!["attachments@163.com".to_string()],
+            body: "report attached".to_string(),
+            html_body: "<p>report attached</p>".to_string(),
+            cc: vec!["attachments-cc@163.com".to_string()],
+            reply_to: "support@163.com".to_string(),
+            attachments,
+            ..Default::default()
+        })),
+        // keep the email from being sent, only the check matters here
+        send_at: Some(utc_to_ts(Utc::now() + chrono::Duration::hours(1))),
+        ..Default::default()
+    };
+    let max_inline = config.email.max_inline_attachment_bytes as usize;
+    let accepted = email(
+        "attachments-1",
+        vec![
+            attachment(
+                "small.bin",
+                email_attachment::Source::Content(vec![1; max_inline]),
+            ),
+            attachment(
+                "large.bin",
+                email_attachment::Source::Blob(BlobReference {
+                    url: "s3://reports/large.bin".to_string(),
+                    size: 1024 * 1024,
+                }),
+            ),
+        ],
+    );
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![accepted]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(
+        ret[0].as_ref().unwrap().status(),
+        SendResponseType::Scheduled
+    );
+
+    let too_large_inline = email(
+        "attachments-2",
+        vec![attachment(
+            "large.bin",
+            email_attachment::Source::Content(vec![1; max_inline + 1]),
+        )],
+    );
+    let too_large_total = email(
+        "attachments-3",
+        vec![attachment(
+            "huge.bin",
+            email_attachment::Source::Blob(BlobReference {
+                url: "s3://reports/huge.bin".to_string(),
+                size: config.email.max_attachments_bytes + 1,
+            }),
+        )],
+    );
+    // a rejected email is answered on its own, the stream goes on
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![
+            too_large_inline,
+            too_large_total,
+            email("attachments-4", vec![]),
+        ]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    let mut ret: Vec<_> = ret.into_iter().collect::<Result<_, _>>()?;
+    ret.sort_by(|a, b| a.message_id.cmp(&b.message_id));
+    let statuses: Vec<_> = ret
+        .iter()
+        .map(|r| (r.message_id.as_str(), r.status()))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("attachments-2", SendResponseType::Invalid),
+            ("attachments-3", SendResponseType::Invalid),
+            ("attachments-4", SendResponseType::Scheduled),
+        ]
+    );
+    assert!(ret[0].reason.contains("large.bin"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn recipients_should_be_validated_and_normalized() -> Result<()> {
+    let (_tdb, app_state, channel) = start_server(|_| {}).await?;
+    Suppression::insert(
+        "bounced@163.com",
+        SuppressionReason::Bounced,
+        &app_state.pool,
+    )
+    .await?;
+    let mut client = NotificationClient::new(channel);
+    let sms = |id: &str, recipients: &[&str]| SendRequest {
+        msg: Some(send_request::Msg::Sms(SmsMessage {
+            message_id: id.to_string(),
+            sender: "recipients".to_string(),
+            recipients: recipients.iter().map(|r| r.to_string()).collect(),
+            body: "body".to_string(),
+        })),
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![sms(
+            "recipients-1",
+            &["13800138000", "not-a-number", "a@163.com"],
+        )]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    let resp = ret[0].as_ref().unwrap();
+    assert_eq!(resp.message_id, "recipients-1");
+    assert_eq!(resp.status(), SendResponseType::Invalid);
+    let invalid: Vec<_> = resp
+        .invalid_recipients
+        .iter()
+        .map(|r| r.address.as_str())
+        .collect();
+    assert_eq!(invalid, vec!["not-a-number", "a@163.com"]);
+
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![sms(
+            "recipients-2",
+            &["138 0013 8000", "+8613800138000"],
+        )]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    // the fake provider fails most sends, the message is accepted either way
+    assert_eq!(ret.len(), 1);
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "recipients-2".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.recipients, vec!["+8613800138000"]);
+
+    // the only recipient bounced before, so nothing is sent
+    let email = SendRequest {
+        msg: Some(send_request::Msg::Email(EmailMessage {
+            message_id: "recipients-3".to_string(),
+            subject: "subject".to_string(),
+            sender: "recipients".to_string(),
+            recipients: vec!["bounced@163.COM".to_string()],
+            body: "body".to_string(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![email]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(
+        ret[0].as_ref().unwrap().status(),
+        SendResponseType::Suppressed
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn provider_feedback_should_suppress_bounced_recipients() -> Result<()> {
+    let (_tdb, app_state, channel) = start_server(|_| {}).await?;
+    let config = app_state.app_config.clone();
+    let listener = std::net::TcpListener::bind("[::1]:0")?;
+    let webhook_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { app_state.webhook_run_on(listener).await });
+    let mut client = NotificationClient::new(channel);
+    let email = |id: &str, recipients: &[&str]| SendRequest {
+        msg: Some(send_request::Msg::Email(EmailMessage {
+            message_id: id.to_string(),
+            subject: "subject".to_string(),
+            sender: "feedback".to_string(),
+            recipients: recipients.iter().map(|r| r.to_string()).collect(),
+            body: "body".to_string(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![email(
+            "feedback-1",
+            &["delivered@163.com", "bounced@163.com"],
+        )]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(ret.len(), 1);
+
+    let smtp = FakeProvider::new("smtp", &config.feedback.secrets["smtp"], &webhook_url);
+    let resp = smtp
+        .emit(
+            "feedback-1",
+            "delivered@163.com",
+            FeedbackType::Delivered,
+            "250 ok",
+        )
+        .await?;
+    assert_eq!(resp.status(), 200);
+    // providers may report the address in another case, it is matched as it was sent
+    let resp = smtp
+        .emit(
+            "feedback-1",
+            "bounced@163.COM",
+            FeedbackType::HardBounce,
+            "550 mailbox unavailable",
+        )
+        .await?;
+    assert_eq!(resp.status(), 200);
+    // a late delivery receipt does not undo the bounce
+    let resp = smtp
+        .emit(
+            "feedback-1",
+            "bounced@163.com",
+            FeedbackType::Delivered,
+            "250 ok",
+        )
+        .await?;
+    assert_eq!(resp.status(), 200);
+    let resp = smtp
+        .emit("feedback-1", "not an address", FeedbackType::Delivered, "")
+        .await?;
+    assert_eq!(resp.status(), 400);
+    let forged = FakeProvider::new("smtp", "wrong-secret", &webhook_url);
+    let resp = forged
+        .emit(
+            "feedback-1",
+            "delivered@163.com",
+            FeedbackType::Complaint,
+            "",
+        )
+        .await?;
+    assert_eq!(resp.status(), 401);
+
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "feedback-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.status(), MessageStatus::Bounced);
+    let outcomes: Vec<_> = status
+        .outcomes
+        .iter()
+        .map(|o| (o.recipient.as_str(), o.status()))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            ("bounced@163.com", MessageStatus::Bounced),
+            ("delivered@163.com", MessageStatus::Delivered),
+        ]
+    );
+
+    // the bounced address is left out of later sends
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![email(
+            "feedback-2",
+            &["bounced@163.com"],
+        )]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(
+        ret[0].as_ref().unwrap().status(),
+        SendResponseType::Suppressed
+    );
+
+    let resp = client
+        .report_feedback(ProviderFeedback {
+            message_id: "feedback-unknown".to_string(),
+            recipient: "delivered@163.com".to_string(),
+            r#type: camp_notification::pb::notification::FeedbackType::Complaint as i32,
+            provider: "smtp".to_string(),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().code(), tonic::Code::NotFound);
+    Ok(())
+}
+
+#[tokio::test]
+async fn batch_should_render_template_for_every_recipient() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let template = Template {
+        name: "promo".to_string(),
+        channel: MessageType::InApp as i32,
+        sender: "campaign".to_string(),
+        subject: "Hi {{name}}".to_string(),
+        body: "{{offer}} off today".to_string(),
+        ..Default::default()
+    };
+    client.save_template(template.clone()).await?;
+    let saved = client
+        .get_template(GetTemplateRequest {
+            name: "promo".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(saved, template);
+    let err = client
+        .save_template(Template {
+            name: "no-subject".to_string(),
+            channel: MessageType::Email as i32,
+            subject: String::new(),
+            ..template.clone()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+    let recipient = |device_id: &str, variables: &[(&str, &str)]| BatchRecipient {
+        address: device_id.to_string(),
+        variables: variables
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    };
+    let batch = SendBatchRequest {
+        batch_id: "promo-batch".to_string(),
+        template: "promo".to_string(),
+        recipients: vec![
+            recipient("batch-device-1", &[("name", "Alice"), ("offer", "20%")]),
+            recipient("batch-device-2", &[("name", "Bob")]),
+            recipient("batch-device-3", &[("name", "Carol"), ("offer", "10%")]),
+        ],
+        ..Default::default()
+    };
+    let resp = client.send_batch(batch.clone()).await?.into_inner();
+    assert_eq!((resp.accepted, resp.skipped, resp.failed), (2, 0, 1));
+    assert!(resp
+        .results
+        .iter()
+        .all(|r| r.message_id.starts_with("promo-batch-") && r.message_id.len() <= 64));
+    assert_eq!(resp.results[1].status(), SendResponseType::Failed);
+    assert!(resp.results[1].error.contains("offer"));
+
+    // the same batch again is not delivered twice, even with its recipients in another order
+    let mut again = batch;
+    again.recipients.reverse();
+    let resent = client.send_batch(again).await?.into_inner();
+    assert_eq!(resent.accepted, 2);
+    let ids = |resp: &SendBatchResponse| {
+        let mut ids: Vec<_> = resp.results.iter().map(|r| r.message_id.clone()).collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(&resent), ids(&resp));
+    let inbox = client
+        .list_inbox(ListInboxRequest {
+            device_id: "batch-device-1".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(inbox.messages.len(), 1);
+    assert_eq!(inbox.messages[0].title, "Hi Alice");
+    assert_eq!(inbox.messages[0].body, "20% off today");
+
+    let err = client
+        .send_batch(SendBatchRequest {
+            batch_id: "missing".to_string(),
+            template: "missing".to_string(),
+            recipients: vec![recipient("batch-device-1", &[])],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::NotFound);
+    Ok(())
+}
+
+#[tokio::test]
+async fn send_stream_should_be_answered_concurrently() -> Result<()> {
+    let (_tdb, mut app_state) =
+        AppState::new_for_test_with(|config| config.dispatch.stream_concurrency = 8).await?;
+    let overlap = Arc::new(Overlap::default());
+    app_state.notification_grpc.inapp = InAppGrpc(Arc::new(Box::new(SlowInApp(overlap.clone()))));
+    let mut client = NotificationClient::new(serve(app_state).await?);
+    let requests: Vec<_> = (0..16)
+        .map(|i| SendRequest {
+            msg: Some(send_request::Msg::InApp(InAppMessage {
+                message_id: format!("concurrent-{:02}", i),
+                device_id: "concurrent-device".to_string(),
+                title: "title".to_string(),
+                body: "body".to_string(),
+                sender: "concurrent".to_string(),
+            })),
+            ..Default::default()
+        })
+        .collect();
+    let start = std::time::Instant::now();
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(requests))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    // 16 sends of 200ms one after the other would take 3.2s
+    assert!(start.elapsed() < Duration::from_millis(1600));
+    assert!(overlap.most.load(Ordering::SeqCst) > 1);
+    assert!(overlap.most.load(Ordering::SeqCst) <= 8);
+    // responses may come back out of order, every message is answered once
+    let mut ids: Vec<_> = ret.into_iter().map(|r| r.unwrap().message_id).collect();
+    ids.sort();
+    let expected: Vec<_> = (0..16).map(|i| format!("concurrent-{:02}", i)).collect();
+    assert_eq!(ids, expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn priority_should_be_kept_and_counted_per_lane() -> Result<()> {
+    let (_tdb, _, channel) = start_server(|_| {}).await?;
+    let mut client = NotificationClient::new(channel);
+    let inapp = |id: &str, priority: Priority| SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: id.to_string(),
+            device_id: "lane-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "lanes".to_string(),
+        })),
+        priority: priority as i32,
+        ..Default::default()
+    };
+    let scheduled = SendRequest {
+        send_at: Some(utc_to_ts(Utc::now() + chrono::Duration::hours(1))),
+        ..inapp("lane-scheduled", Priority::High)
+    };
+    let requests = vec![
+        inapp("lane-high-1", Priority::High),
+        inapp("lane-low-1", Priority::Low),
+        inapp("lane-low-2", Priority::Low),
+        inapp("lane-high-2", Priority::High),
+        scheduled,
+    ];
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(requests))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(ret.len(), 5);
+
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "lane-scheduled".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.priority(), Priority::High);
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "lane-low-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.priority(), Priority::Low);
+
+    let stats = client
+        .get_lane_stats(GetLaneStatsRequest {})
+        .await?
+        .into_inner();
+    let dispatched: Vec<_> = stats
+        .lanes
+        .iter()
+        .map(|l| (l.priority(), l.dispatched, l.in_flight))
+        .collect();
+    assert_eq!(
+        dispatched,
+        vec![
+            (Priority::High, 2, 0),
+            (Priority::Normal, 0, 0),
+            (Priority::Low, 2, 0)
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn dead_letters_should_be_listed_and_replayed() -> Result<()> {
+    let (_tdb, app_state, channel) = start_server(|_| {}).await?;
+    let pool = app_state.pool;
+    let mut client = NotificationClient::new(channel.clone());
+    let mut admin = NotificationAdminClient::new(channel);
+    let inapp = |id: &str| SendRequest {
+        msg: Some(send_request::Msg::InApp(InAppMessage {
+            message_id: id.to_string(),
+            device_id: "lost-device".to_string(),
+            title: "title".to_string(),
+            body: "body".to_string(),
+            sender: "replay".to_string(),
+        })),
+        priority: Priority::High as i32,
+        ..Default::default()
+    };
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![inapp("dl-1"), inapp("dl-2")]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(ret.len(), 2);
+    // give the messages up as if the provider had failed
+    Message::transition(
+        "dl-1",
+        lifecycle::MessageStatus::DeadLettered,
+        Some("device gone"),
+        &pool,
+    )
+    .await?;
+    Message::transition(
+        "dl-2",
+        lifecycle::MessageStatus::DeadLettered,
+        Some("quota exceeded"),
+        &pool,
+    )
+    .await?;
+
+    let found = admin
+        .list_dead_letters(ListDeadLettersRequest {
+            r#type: MessageType::InApp as i32,
+            error: "GONE".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(found.messages.len(), 1);
+    assert_eq!(found.messages[0].error, "device gone");
+
+    let dead_letter = admin
+        .get_dead_letter(GetDeadLetterRequest {
+            message_id: "dl-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    let message = dead_letter.message.unwrap();
+    assert_eq!(message.status(), MessageStatus::DeadLettered);
+    assert!(message.events.len() >= 3);
+    let request = dead_letter.request.unwrap();
+    assert_eq!(request.priority(), Priority::High);
+    assert!(
+        matches!(request.msg, Some(send_request::Msg::InApp(m)) if m.device_id == "lost-device")
+    );
+
+    let invalid = admin
+        .replay_dead_letters(ReplayRequest {
+            message_ids: vec!["dl-1".to_string(), "dl-2".to_string()],
+            recipients: vec!["new-device".to_string()],
+            operator: "ops".to_string(),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(invalid.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+    let replayed = admin
+        .replay_dead_letters(ReplayRequest {
+            message_ids: vec!["dl-1".to_string()],
+            recipients: vec!["new-device".to_string()],
+            operator: "ops".to_string(),
+            reason: "device replaced".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(replayed.results.len(), 1);
+    assert_eq!(replayed.results[0].status(), SendResponseType::Success);
+    let inbox = client
+        .list_inbox(ListInboxRequest {
+            device_id: "new-device".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(inbox.messages.len(), 1);
+
+    // replay everything matching a filter, the sent message is not replayed again
+    let replayed = admin
+        .replay_dead_letters(ReplayRequest {
+            filter: Some(ListDeadLettersRequest {
+                r#type: MessageType::InApp as i32,
+                ..Default::default()
+            }),
+            operator: "ops".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    let ids: Vec<_> = replayed
+        .results
+        .iter()
+        .map(|r| r.message_id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["dl-2"]);
+    let again = admin
+        .replay_dead_letters(ReplayRequest {
+            message_ids: vec!["dl-1".to_string()],
+            operator: "ops".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    assert_eq!(again.results[0].status(), SendResponseType::Failed);
+    assert!(again.results[0].error.contains("can be replayed"));
+
+    let dead_letter = admin
+        .get_dead_letter(GetDeadLetterRequest {
+            message_id: "dl-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(dead_letter.message.unwrap().status(), MessageStatus::Sent);
+    let outcomes: Vec<_> = dead_letter
+        .replays
+        .iter()
+        .map(|r| (r.recipients.clone(), r.outcome.as_str()))
+        .collect();
+    assert_eq!(
+        outcomes[0],
+        (vec!["new-device".to_string()], "SEND_RESPONSE_TYPE_SUCCESS")
+    );
+    assert_eq!(dead_letter.replays.len(), 2);
+    assert_eq!(dead_letter.replays[0].reason, "device replaced");
+    Ok(())
+}
+
+#[tokio::test]
+async fn replay_by_filter_should_page_through_every_match() -> Result<()> {
+    let (_tdb, app_state, channel) = start_server(|_| {}).await?;
+    let pool = app_state.pool;
+    let mut admin = NotificationAdminClient::new(channel);
+    for i in 0..130 {
+        let message: Message = model::message::InAppMessage {
+            id: format!("bulk-{:03}", i),
+            sender: "replay".to_string(),
+            body: "body".to_string(),
+            device_id: "bulk-device".to_string(),
+            title: "title".to_string(),
+        }
+        .into();
+        message.accept(Utc::now(), &pool).await?;
+        Message::transition(
+            &format!("bulk-{:03}", i),
+            lifecycle::MessageStatus::DeadLettered,
+            Some("device gone"),
+            &pool,
+        )
+        .await?;
+    }
+    let replayed = admin
+        .replay_dead_letters(ReplayRequest {
+            filter: Some(ListDeadLettersRequest {
+                error: "device gone".to_string(),
+                ..Default::default()
+            }),
+            operator: "ops".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+    let mut ids: Vec<_> = replayed.results.into_iter().map(|r| r.message_id).collect();
+    ids.sort();
+    let expected: Vec<_> = (0..130).map(|i| format!("bulk-{:03}", i)).collect();
+    assert_eq!(ids, expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhook_should_be_posted_to_registered_endpoint() -> Result<()> {
+    type Received = std::sync::Arc<std::sync::Mutex<Vec<(HeaderMap, Bytes)>>>;
+    async fn receive(State(received): State<Received>, headers: HeaderMap, body: Bytes) {
+        received.lock().unwrap().push((headers, body));
+    }
+    let received = Received::default();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let partner_url = format!("http://{}/events", listener.local_addr()?);
+    let partner = Router::new()
+        .route("/events", post(receive))
+        .with_state(received.clone());
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(partner.into_make_service()));
+
+    let (_tdb, _, channel) = start_server(|config| {
+        let endpoint = WebhookEndpoint {
+            url: partner_url,
+            secret: "partner-secret".to_string(),
+        };
+        config.webhook.endpoints = HashMap::from([("partner".to_string(), endpoint)]);
+    })
+    .await?;
+    let mut client = NotificationClient::new(channel);
+    let webhook = |id: &str, endpoint: &str, payload: &str| SendRequest {
+        msg: Some(send_request::Msg::Webhook(WebhookMessage {
+            message_id: id.to_string(),
+            endpoint: endpoint.to_string(),
+            event: "campaign.sent".to_string(),
+            payload: payload.to_string(),
+            sender: "crm".to_string(),
+        })),
+        ..Default::default()
+    };
+    let payload = r#"{"campaign":"spring","sent":2}"#;
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(vec![webhook(
+            "hook-1", "partner", payload,
+        )]))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    let resp = ret.into_iter().next().unwrap().unwrap();
+    assert_eq!(resp.status(), SendResponseType::Success);
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        assert_eq!(body.as_ref(), payload.as_bytes());
+        assert_eq!(headers["x-webhook-event"], "campaign.sent");
+        let signature = headers["x-signature"].to_str()?;
+        assert!(verify_webhook("partner-secret", body, signature));
+    }
+    let status = client
+        .get_message_status(GetMessageStatusRequest {
+            message_id: "hook-1".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert_eq!(status.r#type(), MessageType::Webhook);
+    assert_eq!(status.recipients, vec!["partner".to_string()]);
+
+    // invalid webhooks are answered one by one, the stream goes on
+    let requests = vec![
+        webhook("hook-2", "unknown", payload),
+        webhook("hook-3", "partner", "not json"),
+        webhook("hook-4", "partner", payload),
+    ];
+    let ret: Vec<_> = client
+        .send(tokio_stream::iter(requests))
+        .await?
+        .into_inner()
+        .collect()
+        .await;
+    let mut statuses: Vec<_> = ret
+        .into_iter()
+        .map(|r| {
+            let r = r.unwrap();
+            (r.message_id.clone(), r.status(), r.reason.is_empty())
+        })
+        .collect();
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        vec![
+            ("hook-2".to_string(), SendResponseType::Invalid, false),
+            ("hook-3".to_string(), SendResponseType::Invalid, false),
+            ("hook-4".to_string(), SendResponseType::Success, true),
+        ]
+    );
+    assert_eq!(received.lock().unwrap().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn feedback_and_templates_should_require_their_scope() -> Result<()> {
+    let (_tdb, app_state) = AppState::new_for_test().await?;
+    let auth = client_auth(&app_state.app_config, &["campaign"])?;
+    let channel = listen(app_state).await?;
+    let feedback = ProviderFeedback {
+        message_id: "scoped".to_string(),
+        recipient: "delivered@163.com".to_string(),
+        provider: "smtp".to_string(),
+        ..Default::default()
+    };
+
+    let mut anonymous = NotificationClient::new(channel.clone());
+    let status = anonymous
+        .report_feedback(feedback.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    let mut campaign = NotificationClient::with_interceptor(channel, auth);
+    let status = campaign.report_feedback(feedback).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let status = campaign
+        .save_template(Template {
+            name: "scoped".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    Ok(())
+}
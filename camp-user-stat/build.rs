@@ -1,14 +1,19 @@
 use anyhow::Result;
 use proto_builder_trait::tonic::BuilderAttributes;
-use std::env::current_dir;
+use std::{
+    env::{self, current_dir},
+    path::PathBuf,
+};
 
 fn main() -> Result<()> {
     let dir = current_dir()?;
     println!("Current directory: {:?}", dir);
     std::fs::create_dir_all("src/pb")?;
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     let builder = tonic_build::configure();
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(out_dir.join("user_stat_descriptor.bin"))
         .with_serde(
             &["User"],
             true,
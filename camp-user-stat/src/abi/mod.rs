@@ -1,16 +1,28 @@
 use crate::pb::user_stat::{user_stat_server::UserStat, QueryRequest, RawQueryRequest, User};
 use crate::services::{IdQuery, Query, TimeQuery, UserStatService, UserStatVO};
+use camp_core::{
+    error::ClassifyError,
+    metrics::{counter_vec, IntCounterVec},
+};
 use chrono::{DateTime, TimeZone, Utc};
 use derive_builder::Builder;
 use futures::Stream;
 use prost_types::Timestamp;
 use std::collections::HashMap;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 use tonic::{Request, Response, Status};
 use tracing::info;
 
 type ServiceResult<T> = Result<Response<T>, Status>;
+
+static ROWS_STREAMED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter_vec(
+        "user_stat_rows_streamed_total",
+        "users streamed to the clients, by method",
+        &["method"],
+    )
+});
 type ResponseUserStream = Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>;
 
 impl From<UserStatVO> for User {
@@ -94,11 +106,14 @@ where
         match self.service.query(qr).await {
             Ok(users) => {
                 info!("user_stat query get users: {:?}", users.len());
+                ROWS_STREAMED
+                    .with_label_values(&["query"])
+                    .inc_by(users.len() as u64);
                 Ok(Response::new(Box::pin(futures::stream::iter(
-                    users.into_iter().map(|v| Ok(v.into())),
+                    users.into_iter().map(User::from).map(Ok),
                 ))))
             }
-            Err(e) => Err(Status::internal(e.to_string())),
+            Err(e) => Err(e.to_status()),
         }
     }
 
@@ -108,10 +123,15 @@ where
     ) -> ServiceResult<Self::RawQueryStream> {
         let rq = request.into_inner();
         match self.service.raw_query(rq).await {
-            Ok(users) => Ok(Response::new(Box::pin(futures::stream::iter(
-                users.into_iter().map(|v| Ok(v.into())),
-            )))),
-            Err(e) => Err(Status::internal(e.to_string())),
+            Ok(users) => {
+                ROWS_STREAMED
+                    .with_label_values(&["raw_query"])
+                    .inc_by(users.len() as u64);
+                Ok(Response::new(Box::pin(futures::stream::iter(
+                    users.into_iter().map(User::from).map(Ok),
+                ))))
+            }
+            Err(e) => Err(e.to_status()),
         }
     }
 }
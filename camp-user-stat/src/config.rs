@@ -1,5 +1,5 @@
 use anyhow::Result;
-use camp_core::config::config_load;
+use camp_core::{config::ConfigLoader, metrics::MetricsConfig, server::ServerConfig};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -7,11 +7,16 @@ pub struct AppConfig {
     pub grpc: GRPCConfig,
     pub http: HttpConfig,
     pub db: DBConfig,
+    /// the `/metrics` http endpoint, not served if unset
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GRPCConfig {
     pub port: u16,
+    #[serde(flatten)]
+    pub server: ServerConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,12 +45,15 @@ impl DBConfig {
 
 impl AppConfig {
     pub fn load() -> Result<Self> {
-        let config: Self = config_load(vec![
-            "./camp-user-stat/user_stat.yml".to_string(),
-            "../camp-user-stat/user_stat.yml".to_string(),
-            "./user_stat.yml".to_string(),
-            "/etc/config/user_stat.yml".to_string(),
-        ])?;
+        let config: Self = ConfigLoader::new("user_stat")
+            .from_process()
+            .paths([
+                "./camp-user-stat/user_stat.yml",
+                "../camp-user-stat/user_stat.yml",
+                "./user_stat.yml",
+                "/etc/config/user_stat.yml",
+            ])
+            .load()?;
         Ok(config)
     }
 }
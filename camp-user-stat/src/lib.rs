@@ -8,10 +8,9 @@ pub mod services;
 
 use crate::pb::user_stat::user_stat_server::{UserStat as UserStatGrpc, UserStatServer};
 use anyhow::Result;
+use camp_core::{auth::ADMIN_SCOPE, configure_service, server::GrpcServer};
 use config::AppConfig;
 use derive_builder::Builder;
-use std::net::ToSocketAddrs as _;
-use tracing::info;
 
 // AppState
 // AppState's fields are grpc-service、axum-service、config or any other stateless components,
@@ -32,11 +31,16 @@ where
     // 3. creat grpc service but not run
     // 4. TODO registry axum handler but not run
     pub async fn grpc_run(self) -> Result<()> {
-        let addr = format!("[::1]:{:?}", self.app_config.grpc.port);
-        info!("user_stat_grpc server is ready to running on {:?}", addr);
-        tonic::transport::Server::builder()
-            .add_service(UserStatServer::new(self.user_stat_grpc))
-            .serve(addr.to_socket_addrs()?.next().unwrap())
+        let config = &self.app_config.grpc;
+        GrpcServer::new("user_stat", config.port, &config.server)?
+            .require_scope("/user_stat.UserStat/RawQuery", ADMIN_SCOPE)
+            .add_service(configure_service!(
+                UserStatServer::new(self.user_stat_grpc),
+                &config.server
+            ))
+            .file_descriptor_set(pb::FILE_DESCRIPTOR_SET)
+            .metrics(self.app_config.metrics.as_ref())
+            .serve()
             .await?;
         Ok(())
     }
@@ -1,11 +1,12 @@
 use crate::model::UserStat;
+use camp_core::error::{ClassifyError, FieldViolation};
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
 use itertools::Itertools as _;
 use sqlx::FromRow;
 use std::collections::HashMap;
 use thiserror::Error;
-use tonic::async_trait;
+use tonic::{async_trait, Code};
 use tracing::info;
 
 #[derive(Debug, Error)]
@@ -20,6 +21,48 @@ pub enum UserStatError {
     Any(#[from] anyhow::Error),
 }
 
+impl ClassifyError for UserStatError {
+    fn domain(&self) -> &'static str {
+        "user_stat"
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            UserStatError::Sqlx(_) => "DATABASE_ERROR",
+            UserStatError::FieldNotFound(_) => "FIELD_NOT_FOUND",
+            UserStatError::Any(_) => "UNKNOWN",
+        }
+    }
+
+    fn code(&self) -> Code {
+        match self {
+            UserStatError::FieldNotFound(_) => Code::InvalidArgument,
+            UserStatError::Sqlx(sqlx::Error::RowNotFound) => Code::NotFound,
+            UserStatError::Sqlx(
+                sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_),
+            ) => Code::Unavailable,
+            // syntax errors, unknown columns and invalid values of a raw query
+            UserStatError::Sqlx(sqlx::Error::Database(e))
+                if e.code()
+                    .is_some_and(|code| code.starts_with("42") || code.starts_with("22")) =>
+            {
+                Code::InvalidArgument
+            }
+            _ => Code::Internal,
+        }
+    }
+
+    fn field_violations(&self) -> Vec<FieldViolation> {
+        match self {
+            UserStatError::FieldNotFound(e) => vec![
+                FieldViolation::new("timestamps", e),
+                FieldViolation::new("ids", e),
+            ],
+            _ => vec![],
+        }
+    }
+}
+
 pub struct TimeQuery {
     pub(crate) lower: Option<DateTime<Utc>>,
     pub(crate) upper: Option<DateTime<Utc>>,
@@ -106,7 +149,8 @@ impl IdQuery {
 
 impl Query {
     async fn try_to_string(&self) -> Result<String, UserStatError> {
-        const SELECT_FORMAT: &str = r#"SELECT email, name FROM user_stats WHERE "#;
+        const SELECT_FORMAT: &str =
+            r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE "#;
         let fields = UserStat::fields().await?;
         let mut query = String::from(SELECT_FORMAT);
         let time_condition = self
@@ -152,7 +196,11 @@ mod test {
             timestamps: HashMap::new(),
             ids: HashMap::new(),
         };
-        query.try_to_string().await.unwrap_err();
+        let status = query.try_to_string().await.unwrap_err().to_status();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let bad_request = camp_core::error::bad_request(&status).unwrap();
+        assert_eq!(bad_request.field_violations.len(), 2);
+        assert!(!camp_core::error::is_retryable(&status));
     }
 
     #[tokio::test]
@@ -174,7 +222,7 @@ mod test {
         let query = query.try_to_string().await.unwrap();
         assert_eq!(
             query,
-            r#"SELECT email, name FROM user_stats WHERE created_at BETWEEN '1970-01-01T00:00:00.000000010Z' AND '1970-01-01T00:00:00.000000020Z' AND array[1, 2, 3] <@ recent_watched"#
+            r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE created_at BETWEEN '1970-01-01T00:00:00.000000010Z' AND '1970-01-01T00:00:00.000000020Z' AND array[1, 2, 3] <@ recent_watched"#
         );
         println!("{}", query);
     }
@@ -205,7 +253,7 @@ mod test {
         let query = query.try_to_string().await.unwrap();
         assert_eq!(
             query,
-            r#"SELECT email, name FROM user_stats WHERE created_at BETWEEN '1970-01-01T00:00:00.000000010Z' AND '1970-01-01T00:00:00.000000020Z'"#
+            r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE created_at BETWEEN '1970-01-01T00:00:00.000000010Z' AND '1970-01-01T00:00:00.000000020Z'"#
         );
         println!("{}", query);
     }
@@ -224,7 +272,7 @@ mod test {
         let query = query.try_to_string().await.unwrap();
         assert_eq!(
             query,
-            r#"SELECT email, name FROM user_stats WHERE array[1, 2, 3] <@ recent_watched"#
+            r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE array[1, 2, 3] <@ recent_watched"#
         );
         println!("{}", query);
     }
@@ -247,9 +295,9 @@ mod test {
         let query = query.try_to_string().await.unwrap();
         assert!(
             query
-                == r#"SELECT email, name FROM user_stats WHERE true AND array[1, 2, 3] <@ recent_watched"#
+                == r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE true AND array[1, 2, 3] <@ recent_watched"#
                 || query
-                    == r#"SELECT email, name FROM user_stats WHERE array[1, 2, 3] <@ recent_watched AND true"#
+                    == r#"SELECT email, name, started_but_not_finished FROM user_stats WHERE array[1, 2, 3] <@ recent_watched AND true"#
         );
         println!("{}", query);
     }
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use camp_core::auth::{ClientAuth, ClientAuthConfig};
 use camp_user_stat::{
     ioc::UserStatGRPCV1,
     pb::user_stat::{user_stat_client::UserStatClient, IdQuery, QueryRequestBuilder},
@@ -6,18 +7,31 @@ use camp_user_stat::{
 };
 use futures::StreamExt;
 use tokio::time::{sleep, Duration};
+use tonic::transport::Channel;
 
 #[tokio::test]
 async fn query_should_work() -> Result<()> {
     let (_tdb, app_state) = AppState::<UserStatGRPCV1>::new_for_test().await?;
     //let app_state = AppState::<UserStatGRPCV1>::new().await?;
+    let auth = app_state.app_config.grpc.server.auth.as_ref();
+    let auth = auth.expect("auth is enabled in user_stat.yml");
+    let auth = ClientAuth::try_new(&ClientAuthConfig {
+        algorithm: auth.algorithm,
+        sk: Some(auth.pk.clone()),
+        token: None,
+        subject: "crm".to_string(),
+        issuer: auth.issuer.clone(),
+        scopes: vec![],
+        ttl_secs: 60,
+    })?;
     let app_state_clone = app_state.clone();
     tokio::spawn(async move {
         app_state_clone.grpc_run().await.unwrap();
     });
     sleep(Duration::from_secs(1)).await;
     println!("grpc server started");
-    let mut client = UserStatClient::connect("http://[::1]:50055").await?;
+    let channel = Channel::from_static("http://[::1]:50055").connect().await?;
+    let mut client = UserStatClient::with_interceptor(channel, auth);
     let req = QueryRequestBuilder::default()
         .id((
             "viewed_but_not_started".to_string(),
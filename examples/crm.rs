@@ -5,10 +5,7 @@ fn main() {
 
 async fn start_metadata() -> Result<()>{
     let server = MetadataGRPC {};
-    let config: AppConfig = config_load(vec![
-        "./metadata.yml".to_string(),
-        "/etc/config/metadata.yml".to_string(),
-    ])?;
+    let config = AppConfig::load()?;
     let addr = format!("[::1]:{:?}", config.server.port);
     Server::builder()
         .add_service(MetadataServer::new(server))